@@ -1,12 +1,32 @@
+use kreqo_core::learn::Deck;
 use kreqo_core::users::User;
-use kreqo_server::api::{cleanup_expired_sessions, current_user, login, logout};
+use kreqo_core::users::permissions::Permission;
+use kreqo_server::api::{
+    change_password, cleanup_expired_sessions, current_user, get_image, login, logout,
+    reset_password,
+};
 use kreqo_server::custom_client::request::save_cookies;
-use kreqo_ui::auth_forms::{AuthMessage, AuthRequest, UserLoginForm};
+use kreqo_ui::audit_log::{AuditLog, handle_audit_log_request};
+use kreqo_ui::auth_forms::{
+    AuthMessage, AuthRequest, ChangePasswordForm, ResetPasswordForm, UserLoginForm,
+};
+use kreqo_ui::card_content::decode_image;
 use kreqo_ui::component::list::ListRequest;
 use kreqo_ui::component::{AsyncList, Form, action_button, logo, user_profile_overview};
+use kreqo_ui::deck_export::{DeckExport, ExportMessage, export_deck_file};
+use kreqo_ui::deck_import::{
+    DeckImport, ImportMessage, ImportRequest, import_anki_file, import_delimited_file,
+};
+use kreqo_ui::deck_list::DeckStorage;
+use kreqo_ui::deleted_users::{DeletedUsers, handle_deleted_user_request};
+use kreqo_ui::offline;
+use kreqo_ui::review::{ReviewMessage, ReviewRequest, ReviewSession};
+use kreqo_ui::scheduler_settings::{SchedulerSettingsForm, handle_scheduler_request};
+use kreqo_ui::session_list::{SessionList, handle_session_request};
+use kreqo_ui::stats::{StudyStatsView, handle_stats_request};
 use kreqo_ui::theme::BACKGROUND_COLOR;
 use kreqo_ui::user_list::UserStorage;
-use xilem::core::one_of::OneOf3;
+use xilem::core::one_of::OneOf9;
 use xilem::core::{fork, lens, map_action, map_state};
 use xilem::masonry::layout::{AsUnit, Dim};
 use xilem::palette::css::GRAY;
@@ -16,7 +36,7 @@ use xilem::view::{
     FlexExt, MainAxisAlignment, flex_col, flex_row, label, portal, sized_box, split, text_button,
     worker,
 };
-use xilem::{WindowId, WindowView, window};
+use xilem::{WidgetView, WindowId, WindowView, window};
 
 #[derive(Default)]
 enum Page {
@@ -24,6 +44,12 @@ enum Page {
     Login,
     Signup,
     UserList,
+    Review,
+    Decks,
+    Stats,
+    Account,
+    ResetPassword,
+    AuditLog,
 }
 
 pub struct AppState {
@@ -32,8 +58,20 @@ pub struct AppState {
     page: Page,
     current_user: Option<User>,
     login_form: UserLoginForm,
+    change_password_form: ChangePasswordForm,
+    reset_password_form: ResetPasswordForm,
     auth_sender: Option<UnboundedSender<AuthRequest>>,
     user_list: AsyncList<User, UserStorage>,
+    user_sessions: SessionList,
+    deleted_users: DeletedUsers,
+    account_sessions: SessionList,
+    scheduler_settings: SchedulerSettingsForm,
+    review_session: ReviewSession,
+    deck_list: AsyncList<Deck, DeckStorage>,
+    deck_import: DeckImport,
+    deck_export: DeckExport,
+    study_stats: StudyStatsView,
+    audit_log: AuditLog,
 }
 
 impl Default for AppState {
@@ -44,8 +82,20 @@ impl Default for AppState {
             page: Page::default(),
             current_user: None,
             login_form: UserLoginForm::default(),
+            change_password_form: ChangePasswordForm::default(),
+            reset_password_form: ResetPasswordForm::default(),
             auth_sender: None,
-            user_list: AsyncList::new(true, true),
+            user_list: AsyncList::new(true, true).optimistic(),
+            user_sessions: SessionList::default(),
+            deleted_users: DeletedUsers::default(),
+            account_sessions: SessionList::default(),
+            scheduler_settings: SchedulerSettingsForm::default(),
+            review_session: ReviewSession::default(),
+            deck_list: AsyncList::new(false, false).optimistic(),
+            deck_import: DeckImport::default(),
+            deck_export: DeckExport::default(),
+            study_stats: StudyStatsView::default(),
+            audit_log: AuditLog::default(),
         }
     }
 }
@@ -57,6 +107,113 @@ impl xilem::AppState for AppState {
 }
 
 impl AppState {
+    fn with_sidebar<Content>(&self, content: Content) -> impl WidgetView<Self> + use<Content>
+    where
+        Content: WidgetView<Self>,
+    {
+        let user_profile = self.current_user.as_ref().map(|_| {
+            lens(user_profile_overview, move |state: &mut Self| {
+                &mut state.current_user.as_mut().unwrap().username
+            })
+        });
+        let navigation = flex_col((
+            text_button("Review", |state: &mut Self| state.page = Page::Review),
+            text_button("Decks", |state: &mut Self| state.page = Page::Decks),
+            text_button("Stats", |state: &mut Self| state.page = Page::Stats),
+            text_button("Users", |state: &mut Self| state.page = Page::UserList),
+            text_button("Account", |state: &mut Self| state.page = Page::Account),
+        ))
+        .gap(5.px());
+        let server_management = self.current_user.as_ref().and_then(|user| {
+            user.has_permission(Permission::ServerManage).then(|| {
+                flex_col((
+                    text_button("Audit log", |state: &mut Self| state.page = Page::AuditLog),
+                    action_button("Cleanup sessions", |state: &mut Self| {
+                        state.auth_sender.as_ref().inspect(|sender| {
+                            let _ = sender.send(AuthRequest::CleanupSessions);
+                        });
+                    }),
+                ))
+                .gap(5.px())
+            })
+        });
+        let logout_button = action_button("Log Out", |state: &mut Self| {
+            state.auth_sender.as_ref().inspect(|sender| {
+                let _ = sender.send(AuthRequest::Logout);
+            });
+        });
+        let sidebar = flex_col((
+            logo(),
+            user_profile,
+            navigation,
+            server_management,
+            logout_button,
+        ))
+        .gap(20.px())
+        .padding(15.);
+        let sidebar_worker = fork(
+            sidebar,
+            worker(
+                |proxy, mut rx| async move {
+                    while let Some(request) = rx.recv().await {
+                        match request {
+                            AuthRequest::CleanupSessions => {
+                                if let Ok(removed_session_ids) = cleanup_expired_sessions().await {
+                                    println!(
+                                        "Successfully removed {} sessions",
+                                        removed_session_ids.len()
+                                    );
+                                    drop(proxy.message(AuthMessage::SessionsCleanedUp));
+                                }
+                            }
+                            AuthRequest::Logout => {
+                                if logout().await.is_ok() {
+                                    println!("Successfully logged out");
+                                    drop(proxy.message(AuthMessage::UserRefreshed(None)));
+                                }
+                            }
+                            AuthRequest::ChangePassword(current_password, password) => {
+                                let message =
+                                    match change_password(current_password, password).await {
+                                        Ok(()) => AuthMessage::PasswordChanged,
+                                        Err(error) => AuthMessage::PasswordError(error),
+                                    };
+                                drop(proxy.message(message));
+                            }
+                            _ => (),
+                        }
+                    }
+                },
+                |state: &mut Self, sender| {
+                    state.auth_sender = Some(sender);
+                },
+                |state: &mut Self, message| match message {
+                    AuthMessage::UserRefreshed(user) => {
+                        state.current_user = user.clone();
+                        if let Some(user) = &user
+                            && user.id != 1
+                        {
+                            offline::set_user(user.id);
+                        }
+                        if user.is_none() {
+                            state.page = Page::default();
+                        } else {
+                            state.user_list.resubscribe();
+                            state.deck_list.resubscribe();
+                        }
+                    }
+                    message => state.change_password_form.handle_message(message),
+                },
+            ),
+        );
+
+        split(sidebar_worker, content)
+            .split_point_from_start(200.px())
+            .draggable(false)
+            .solid_bar(true)
+            .bar_thickness(2.px())
+    }
+
     pub fn logic(&mut self) -> impl Iterator<Item = WindowView<AppState>> + use<> {
         let page = match self.page {
             Page::Login => {
@@ -72,10 +229,15 @@ impl AppState {
                 let goto_signup =
                     text_button("Sign Up", |state: &mut Self| state.page = Page::Signup)
                         .corner_radius(100.);
+                let goto_reset_password = text_button("Reset Password", |state: &mut Self| {
+                    state.page = Page::ResetPassword;
+                })
+                .corner_radius(100.);
                 let content = flex_col((
                     sized_box(form).dims((600.px(), Dim::MinContent)),
                     separator,
                     goto_signup,
+                    goto_reset_password,
                 ))
                 .main_axis_alignment(MainAxisAlignment::Center);
                 let worker = fork(
@@ -85,8 +247,9 @@ impl AppState {
                             while let Some(request) = rx.recv().await {
                                 match request {
                                     AuthRequest::Login(username, password) => {
-                                        if login(username, password).await.is_err() {
-                                            return;
+                                        if let Err(error) = login(username, password).await {
+                                            drop(proxy.message(AuthMessage::LoginError(error)));
+                                            continue;
                                         }
                                         let user = current_user().await.ok();
                                         drop(proxy.message(AuthMessage::UserRefreshed(user)));
@@ -105,19 +268,23 @@ impl AppState {
                                 let _ = sender.send(AuthRequest::RefreshUser);
                             });
                         },
-                        |state: &mut Self, message| {
-                            if let AuthMessage::UserRefreshed(user) = message {
+                        |state: &mut Self, message| match message {
+                            AuthMessage::UserRefreshed(user) => {
                                 state.current_user = user.clone();
                                 if let Some(user) = user
                                     && user.id != 1
                                 {
+                                    offline::set_user(user.id);
+                                    state.user_list.resubscribe();
+                                    state.deck_list.resubscribe();
                                     state.page = Page::UserList;
                                 }
                             }
+                            message => state.login_form.handle_message(message),
                         },
                     ),
                 );
-                OneOf3::A(worker)
+                OneOf9::A(worker)
             }
             Page::Signup => {
                 let form = map_action(
@@ -142,98 +309,392 @@ impl AppState {
                     goto_login,
                 ))
                 .main_axis_alignment(MainAxisAlignment::Center);
-                OneOf3::B(content)
+                OneOf9::B(content)
             }
             Page::UserList => {
-                let user_profile = self.current_user.as_ref().map(|_| {
-                    lens(user_profile_overview, move |state: &mut Self| {
-                        &mut state.current_user.as_mut().unwrap().username
-                    })
+                let user_list = map_action(
+                    map_state(
+                        AsyncList::worker(self.user_list.view()),
+                        move |state: &mut Self| &mut state.user_list,
+                    ),
+                    |state: &mut Self, resolved| {
+                        if matches!(resolved, Some(ListRequest::Delete(_))) {
+                            state.deleted_users.refresh();
+                        }
+                    },
+                );
+                let can_manage_users = self
+                    .current_user
+                    .as_ref()
+                    .is_some_and(|user| user.has_permission(Permission::UsersManage));
+                let user_sessions = can_manage_users.then(|| {
+                    map_state(
+                        self.user_sessions.view(self.user_list.items()),
+                        |state: &mut Self| &mut state.user_sessions,
+                    )
                 });
-                let cleanup_sessions_button = self.current_user.as_ref().and_then(|user| {
-                    user.permissions
-                        .contains("Server::Manage")
-                        .then_some(action_button("Cleanup sessions", |state: &mut Self| {
-                            state.auth_sender.as_ref().inspect(|sender| {
-                                let _ = sender.send(AuthRequest::CleanupSessions);
-                            });
-                        }))
+                let deleted_users = can_manage_users.then(|| {
+                    fork(
+                        map_state(self.deleted_users.view(), |state: &mut Self| {
+                            &mut state.deleted_users
+                        }),
+                        worker(
+                            |proxy, mut rx| async move {
+                                while let Some(request) = rx.recv().await {
+                                    drop(proxy.message(handle_deleted_user_request(request).await));
+                                }
+                            },
+                            |state: &mut Self, sender| {
+                                state.deleted_users.set_sender(sender);
+                            },
+                            |state: &mut Self, message| {
+                                if state.deleted_users.handle_message(message).is_some() {
+                                    state.user_list.refresh();
+                                }
+                            },
+                        ),
+                    )
                 });
-                let logout_button = action_button("Log Out", |state: &mut Self| {
-                    state.auth_sender.as_ref().inspect(|sender| {
-                        let _ = sender.send(AuthRequest::Logout);
-                    });
+                let users = flex_row(
+                    sized_box(flex_col((user_list, user_sessions, deleted_users)).gap(20.px()))
+                        .width(600.px()),
+                )
+                .main_axis_alignment(MainAxisAlignment::Center)
+                .width(Dim::Stretch)
+                .padding(15.);
+                let user_list_error = self.user_list.error_view().map(|error_view| {
+                    map_state(
+                        flex_row(error_view)
+                            .main_axis_alignment(MainAxisAlignment::Center)
+                            .padding(15.),
+                        |state: &mut Self| &mut state.user_list,
+                    )
                 });
-                let sidebar =
-                    flex_col((logo(), user_profile, cleanup_sessions_button, logout_button))
-                        .gap(20.px())
-                        .padding(15.);
-                let sidebar_worker = fork(
-                    sidebar,
+                let portal = portal(users).flex(1.);
+                let content = flex_col((portal, user_list_error)).gap(0.px());
+                let worker = fork(
+                    content,
                     worker(
                         |proxy, mut rx| async move {
                             while let Some(request) = rx.recv().await {
-                                match request {
-                                    AuthRequest::CleanupSessions => {
-                                        if let Ok(removed_session_ids) =
-                                            cleanup_expired_sessions().await
+                                drop(proxy.message(handle_session_request(request).await));
+                            }
+                        },
+                        |state: &mut Self, sender| {
+                            state.user_sessions.set_sender(sender);
+                        },
+                        |state: &mut Self, message| {
+                            state.user_sessions.handle_message(message);
+                        },
+                    ),
+                );
+
+                OneOf9::C(self.with_sidebar(worker))
+            }
+            Page::Review => {
+                let review_session = flex_row(
+                    sized_box(map_state(self.review_session.view(), |state: &mut Self| {
+                        &mut state.review_session
+                    }))
+                    .width(600.px()),
+                )
+                .main_axis_alignment(MainAxisAlignment::Center)
+                .width(Dim::Stretch)
+                .padding(15.);
+                let worker = fork(
+                    portal(review_session),
+                    worker(
+                        |proxy, mut rx| async move {
+                            while let Some(request) = rx.recv().await {
+                                let message = match request {
+                                    ReviewRequest::FetchQueue => {
+                                        match offline::fetch_review_queue().await {
+                                            Ok(queue) => ReviewMessage::QueueFetched(queue),
+                                            Err(error) => ReviewMessage::Error(error),
+                                        }
+                                    }
+                                    ReviewRequest::Review(id, rating, time_taken_ms) => {
+                                        match offline::review_card(id, rating, time_taken_ms).await
                                         {
-                                            println!(
-                                                "Successfully removed {} sessions",
-                                                removed_session_ids.len()
-                                            );
-                                            drop(proxy.message(AuthMessage::SessionsCleanedUp));
+                                            Ok(card) => ReviewMessage::Reviewed(card),
+                                            Err(error) => ReviewMessage::Error(error),
                                         }
                                     }
-                                    AuthRequest::Logout => {
-                                        if logout().await.is_ok() {
-                                            println!("Successfully logged out");
-                                            drop(proxy.message(AuthMessage::UserRefreshed(None)));
+                                    ReviewRequest::FetchImage(id) => {
+                                        let image = get_image(id)
+                                            .await
+                                            .ok()
+                                            .and_then(|image| decode_image(&image.data));
+                                        ReviewMessage::ImageFetched(id, image)
+                                    }
+                                };
+                                drop(proxy.message(message));
+                            }
+                        },
+                        |state: &mut Self, sender| {
+                            state.review_session.set_sender(sender);
+                        },
+                        |state: &mut Self, message| {
+                            state.review_session.handle_message(message);
+                        },
+                    ),
+                );
+
+                OneOf9::D(self.with_sidebar(worker))
+            }
+            Page::Decks => {
+                let deck_import = map_state(self.deck_import.view(), |state: &mut Self| {
+                    &mut state.deck_import
+                });
+                let deck_export = map_state(
+                    self.deck_export.view(self.deck_list.items()),
+                    |state: &mut Self| &mut state.deck_export,
+                );
+                let deck_list = map_action(
+                    map_state(
+                        AsyncList::worker(
+                            flex_col((
+                                self.deck_list.create_view(),
+                                self.deck_list.view(),
+                                self.deck_list.error_view(),
+                            ))
+                            .gap(10.px()),
+                        ),
+                        |state: &mut Self| &mut state.deck_list,
+                    ),
+                    |_, _| (),
+                );
+                let decks = flex_row(
+                    sized_box(flex_col((deck_import, deck_export, deck_list)).gap(20.px()))
+                        .width(600.px()),
+                )
+                .main_axis_alignment(MainAxisAlignment::Center)
+                .width(Dim::Stretch)
+                .padding(15.);
+                let import_worker = fork(
+                    portal(decks),
+                    worker(
+                        |proxy, mut rx| async move {
+                            while let Some(request) = rx.recv().await {
+                                let message = match request {
+                                    ImportRequest::Anki(path) => {
+                                        match import_anki_file(path).await {
+                                            Ok(decks) => ImportMessage::Imported(decks),
+                                            Err(error) => ImportMessage::Error(error),
                                         }
                                     }
-                                    _ => (),
-                                }
+                                    ImportRequest::Delimited(path, import) => {
+                                        match import_delimited_file(path, import).await {
+                                            Ok(deck) => ImportMessage::Imported(vec![deck]),
+                                            Err(error) => ImportMessage::Error(error),
+                                        }
+                                    }
+                                };
+                                drop(proxy.message(message));
                             }
                         },
                         |state: &mut Self, sender| {
-                            state.auth_sender = Some(sender);
+                            state.deck_import.set_sender(sender);
                         },
                         |state: &mut Self, message| {
-                            if let AuthMessage::UserRefreshed(user) = message {
-                                state.current_user = user.clone();
-                                if user.is_none() {
-                                    state.page = Page::default();
-                                }
+                            if state.deck_import.handle_message(message) {
+                                state.deck_list.refresh();
+                            }
+                        },
+                    ),
+                );
+                let worker = fork(
+                    import_worker,
+                    worker(
+                        |proxy, mut rx| async move {
+                            while let Some(request) = rx.recv().await {
+                                let message = match export_deck_file(request).await {
+                                    Ok(path) => ExportMessage::Exported(path),
+                                    Err(error) => ExportMessage::Error(error),
+                                };
+                                drop(proxy.message(message));
                             }
                         },
+                        |state: &mut Self, sender| {
+                            state.deck_export.set_sender(sender);
+                        },
+                        |state: &mut Self, message| {
+                            state.deck_export.handle_message(message);
+                        },
                     ),
                 );
 
-                let user_list = flex_row(sized_box(self.user_list.view()).width(600.px()))
-                    .main_axis_alignment(MainAxisAlignment::Center)
-                    .width(Dim::Stretch)
-                    .padding(15.);
-                let user_list_error = self.user_list.error_view().map(|error_view| {
-                    flex_row(error_view)
-                        .main_axis_alignment(MainAxisAlignment::Center)
-                        .padding(15.)
-                });
-                let portal = portal(user_list).flex(1.);
-                let content = flex_col((portal, user_list_error)).gap(0.px());
-                let worker = map_action(
-                    map_state(AsyncList::worker(content), move |state: &mut Self| {
-                        &mut state.user_list
+                OneOf9::E(self.with_sidebar(worker))
+            }
+            Page::Account => {
+                let form = map_action(
+                    lens(Form::view, move |state: &mut Self| {
+                        &mut state.change_password_form
                     }),
-                    |_, _| (),
+                    |state: &mut Self, submit| {
+                        state
+                            .change_password_form
+                            .handle_submit(submit, state.auth_sender.as_ref());
+                    },
+                );
+                if let Some(user) = &self.current_user {
+                    self.account_sessions.select(user.id);
+                }
+                let account_sessions =
+                    map_state(self.account_sessions.view(&[]), |state: &mut Self| {
+                        &mut state.account_sessions
+                    });
+                let scheduler_settings =
+                    map_state(self.scheduler_settings.view(), |state: &mut Self| {
+                        &mut state.scheduler_settings
+                    });
+                let content = flex_row(
+                    sized_box(flex_col((form, scheduler_settings, account_sessions)).gap(20.px()))
+                        .width(600.px()),
+                )
+                .main_axis_alignment(MainAxisAlignment::Center)
+                .width(Dim::Stretch)
+                .padding(15.);
+                let scheduler_worker = fork(
+                    portal(content),
+                    worker(
+                        |proxy, mut rx| async move {
+                            while let Some(request) = rx.recv().await {
+                                drop(proxy.message(handle_scheduler_request(request).await));
+                            }
+                        },
+                        |state: &mut Self, sender| {
+                            state.scheduler_settings.set_sender(sender);
+                        },
+                        |state: &mut Self, message| {
+                            state.scheduler_settings.handle_message(message);
+                        },
+                    ),
+                );
+                let worker = fork(
+                    scheduler_worker,
+                    worker(
+                        |proxy, mut rx| async move {
+                            while let Some(request) = rx.recv().await {
+                                drop(proxy.message(handle_session_request(request).await));
+                            }
+                        },
+                        |state: &mut Self, sender| {
+                            state.account_sessions.set_sender(sender);
+                        },
+                        |state: &mut Self, message| {
+                            state.account_sessions.handle_message(message);
+                        },
+                    ),
+                );
+
+                OneOf9::F(self.with_sidebar(worker))
+            }
+            Page::ResetPassword => {
+                let form = map_action(
+                    lens(Form::view, move |state: &mut Self| {
+                        &mut state.reset_password_form
+                    }),
+                    |state: &mut Self, submit| {
+                        state
+                            .reset_password_form
+                            .handle_submit(submit, state.auth_sender.as_ref());
+                    },
+                );
+                let separator = label("OR").color(GRAY);
+                let goto_login = text_button("Log In", |state: &mut Self| {
+                    state.page = Page::Login;
+                })
+                .corner_radius(100.);
+                let content = flex_col((
+                    sized_box(form).dims((600.px(), Dim::MinContent)),
+                    separator,
+                    goto_login,
+                ))
+                .main_axis_alignment(MainAxisAlignment::Center);
+                let worker = fork(
+                    content,
+                    worker(
+                        |proxy, mut rx| async move {
+                            while let Some(request) = rx.recv().await {
+                                if let AuthRequest::ResetPassword(token, password) = request {
+                                    let message = match reset_password(token, password).await {
+                                        Ok(()) => AuthMessage::PasswordChanged,
+                                        Err(error) => AuthMessage::PasswordError(error),
+                                    };
+                                    drop(proxy.message(message));
+                                }
+                            }
+                        },
+                        |state: &mut Self, sender| {
+                            state.auth_sender = Some(sender);
+                        },
+                        |state: &mut Self, message| {
+                            if state.reset_password_form.handle_message(message) {
+                                state.page = Page::Login;
+                            }
+                        },
+                    ),
+                );
+                OneOf9::G(worker)
+            }
+            Page::AuditLog => {
+                let audit_log = flex_row(
+                    sized_box(map_state(self.audit_log.view(), |state: &mut Self| {
+                        &mut state.audit_log
+                    }))
+                    .width(600.px()),
+                )
+                .main_axis_alignment(MainAxisAlignment::Center)
+                .width(Dim::Stretch)
+                .padding(15.);
+                let worker = fork(
+                    portal(audit_log),
+                    worker(
+                        |proxy, mut rx| async move {
+                            while let Some(request) = rx.recv().await {
+                                drop(proxy.message(handle_audit_log_request(request).await));
+                            }
+                        },
+                        |state: &mut Self, sender| {
+                            state.audit_log.set_sender(sender);
+                        },
+                        |state: &mut Self, message| {
+                            state.audit_log.handle_message(message);
+                        },
+                    ),
                 );
 
-                OneOf3::C(
-                    split(sidebar_worker, worker)
-                        .split_point_from_start(200.px())
-                        .draggable(false)
-                        .solid_bar(true)
-                        .bar_thickness(2.px()),
+                OneOf9::H(self.with_sidebar(worker))
+            }
+            Page::Stats => {
+                let study_stats = flex_row(
+                    sized_box(map_state(self.study_stats.view(), |state: &mut Self| {
+                        &mut state.study_stats
+                    }))
+                    .width(600.px()),
                 )
+                .main_axis_alignment(MainAxisAlignment::Center)
+                .width(Dim::Stretch)
+                .padding(15.);
+                let worker = fork(
+                    portal(study_stats),
+                    worker(
+                        |proxy, mut rx| async move {
+                            while let Some(request) = rx.recv().await {
+                                drop(proxy.message(handle_stats_request(request).await));
+                            }
+                        },
+                        |state: &mut Self, sender| {
+                            state.study_stats.set_sender(sender);
+                        },
+                        |state: &mut Self, message| {
+                            state.study_stats.handle_message(message);
+                        },
+                    ),
+                );
+
+                OneOf9::I(self.with_sidebar(worker))
             }
         };
         std::iter::once(
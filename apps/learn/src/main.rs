@@ -6,7 +6,10 @@ use xilem::winit::error::EventLoopError;
 use xilem::{EventLoop, Xilem};
 
 fn main() -> Result<(), EventLoopError> {
-    server_fn::client::set_server_url(format!("http://{}", SERVER_ADDRESS).leak());
+    // Set to an `https://` URL for servers behind TLS
+    let server_url =
+        std::env::var("KREQO_SERVER_URL").unwrap_or_else(|_| format!("http://{}", SERVER_ADDRESS));
+    server_fn::client::set_server_url(server_url.trim_end_matches('/').to_string().leak());
 
     let mut def_props = default_property_set();
     apply_theme(&mut def_props);
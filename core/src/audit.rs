@@ -0,0 +1,242 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Largest page of audit entries the server returns at once.
+pub const MAX_AUDIT_PAGE_SIZE: i64 = 200;
+
+/// Privileged action recorded in the audit log, stored in the database by its name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AuditAction {
+    CleanupSessions,
+    ForcePasswordReset,
+    UpdateUsername,
+    DeleteUser,
+    RestoreUser,
+    RevokeSession,
+    RevokeOtherSessions,
+    GrantRole,
+    RevokeRole,
+    GrantPermission,
+    RevokePermission,
+    UpdateDeck,
+    DeleteDeck,
+    UpdateCard,
+    DeleteCard,
+    UpdateNote,
+    DeleteNote,
+    UpdateNoteType,
+    DeleteNoteType,
+    UpdateDeckPreset,
+    DeleteDeckPreset,
+    CreateCard,
+    CreateNote,
+    ImportDeck,
+    PurgeUser,
+}
+
+impl AuditAction {
+    pub const ALL: [AuditAction; 25] = [
+        AuditAction::CleanupSessions,
+        AuditAction::ForcePasswordReset,
+        AuditAction::UpdateUsername,
+        AuditAction::DeleteUser,
+        AuditAction::RestoreUser,
+        AuditAction::RevokeSession,
+        AuditAction::RevokeOtherSessions,
+        AuditAction::GrantRole,
+        AuditAction::RevokeRole,
+        AuditAction::GrantPermission,
+        AuditAction::RevokePermission,
+        AuditAction::UpdateDeck,
+        AuditAction::DeleteDeck,
+        AuditAction::UpdateCard,
+        AuditAction::DeleteCard,
+        AuditAction::UpdateNote,
+        AuditAction::DeleteNote,
+        AuditAction::UpdateNoteType,
+        AuditAction::DeleteNoteType,
+        AuditAction::UpdateDeckPreset,
+        AuditAction::DeleteDeckPreset,
+        AuditAction::CreateCard,
+        AuditAction::CreateNote,
+        AuditAction::ImportDeck,
+        AuditAction::PurgeUser,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AuditAction::CleanupSessions => "cleanup_sessions",
+            AuditAction::ForcePasswordReset => "force_password_reset",
+            AuditAction::UpdateUsername => "update_username",
+            AuditAction::DeleteUser => "delete_user",
+            AuditAction::RestoreUser => "restore_user",
+            AuditAction::RevokeSession => "revoke_session",
+            AuditAction::RevokeOtherSessions => "revoke_other_sessions",
+            AuditAction::GrantRole => "grant_role",
+            AuditAction::RevokeRole => "revoke_role",
+            AuditAction::GrantPermission => "grant_permission",
+            AuditAction::RevokePermission => "revoke_permission",
+            AuditAction::UpdateDeck => "update_deck",
+            AuditAction::DeleteDeck => "delete_deck",
+            AuditAction::UpdateCard => "update_card",
+            AuditAction::DeleteCard => "delete_card",
+            AuditAction::UpdateNote => "update_note",
+            AuditAction::DeleteNote => "delete_note",
+            AuditAction::UpdateNoteType => "update_note_type",
+            AuditAction::DeleteNoteType => "delete_note_type",
+            AuditAction::UpdateDeckPreset => "update_deck_preset",
+            AuditAction::DeleteDeckPreset => "delete_deck_preset",
+            AuditAction::CreateCard => "create_card",
+            AuditAction::CreateNote => "create_note",
+            AuditAction::ImportDeck => "import_deck",
+            AuditAction::PurgeUser => "purge_user",
+        }
+    }
+
+    /// Whether the target is gone after the action, leaving no state to record after it.
+    pub fn is_deletion(self) -> bool {
+        matches!(
+            self,
+            AuditAction::DeleteUser
+                | AuditAction::DeleteDeck
+                | AuditAction::DeleteCard
+                | AuditAction::DeleteNote
+                | AuditAction::DeleteNoteType
+                | AuditAction::DeleteDeckPreset
+                | AuditAction::PurgeUser
+        )
+    }
+}
+
+impl Display for AuditAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for AuditAction {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        AuditAction::ALL
+            .into_iter()
+            .find(|action| action.as_str() == s)
+            .ok_or(())
+    }
+}
+
+/// What an audited action was applied to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AuditTarget {
+    Server,
+    User(i64),
+    Deck(i64),
+    Card(i64),
+    Note(i64),
+    NoteType(i64),
+    DeckPreset(i64),
+}
+
+impl AuditTarget {
+    pub fn kind(self) -> &'static str {
+        match self {
+            AuditTarget::Server => "server",
+            AuditTarget::User(_) => "user",
+            AuditTarget::Deck(_) => "deck",
+            AuditTarget::Card(_) => "card",
+            AuditTarget::Note(_) => "note",
+            AuditTarget::NoteType(_) => "note_type",
+            AuditTarget::DeckPreset(_) => "deck_preset",
+        }
+    }
+
+    pub fn id(self) -> Option<i64> {
+        match self {
+            AuditTarget::Server => None,
+            AuditTarget::User(id)
+            | AuditTarget::Deck(id)
+            | AuditTarget::Card(id)
+            | AuditTarget::Note(id)
+            | AuditTarget::NoteType(id)
+            | AuditTarget::DeckPreset(id) => Some(id),
+        }
+    }
+
+    pub fn from_parts(kind: &str, id: Option<i64>) -> Option<Self> {
+        match (kind, id) {
+            ("server", None) => Some(AuditTarget::Server),
+            ("user", Some(id)) => Some(AuditTarget::User(id)),
+            ("deck", Some(id)) => Some(AuditTarget::Deck(id)),
+            ("card", Some(id)) => Some(AuditTarget::Card(id)),
+            ("note", Some(id)) => Some(AuditTarget::Note(id)),
+            ("note_type", Some(id)) => Some(AuditTarget::NoteType(id)),
+            ("deck_preset", Some(id)) => Some(AuditTarget::DeckPreset(id)),
+            _ => None,
+        }
+    }
+}
+
+impl Display for AuditTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.id() {
+            Some(id) => write!(f, "{} {id}", self.kind()),
+            None => write!(f, "{}", self.kind()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub id: i64,
+    /// `None` once the actor was deleted, or for actions of the server itself.
+    pub actor_id: Option<i64>,
+    pub actor_name: Option<String>,
+    pub action: AuditAction,
+    pub target: AuditTarget,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Page of audit entries, newest first, matching every filter that is set.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditQuery {
+    pub actor_id: Option<i64>,
+    pub action: Option<AuditAction>,
+    pub target: Option<AuditTarget>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub offset: i64,
+    pub limit: i64,
+}
+
+impl Default for AuditQuery {
+    fn default() -> Self {
+        Self {
+            actor_id: None,
+            action: None,
+            target: None,
+            since: None,
+            until: None,
+            offset: 0,
+            limit: 50,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct AuditPage {
+    pub entries: Vec<AuditEntry>,
+    /// Number of entries matching the query across all pages.
+    pub total: i64,
+}
+
+impl AuditPage {
+    pub fn has_more(&self, query: &AuditQuery) -> bool {
+        query.offset + (self.entries.len() as i64) < self.total
+    }
+}
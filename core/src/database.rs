@@ -1,14 +1,25 @@
-use argon2::Argon2;
-use argon2::password_hash::rand_core::OsRng;
-use argon2::password_hash::{PasswordHasher, SaltString};
-use chrono::{DateTime, Utc};
+pub mod audit;
+pub mod learn;
+pub mod login_attempts;
+pub mod notes;
+pub mod presets;
+pub mod roles;
+pub mod scheduler;
+pub mod sessions;
+pub mod stats;
+
+use chrono::{DateTime, Duration, Utc};
 use hashbrown::{HashMap, HashSet};
-use sqlx::{FromRow, PgPool};
+use sqlx::{Acquire, FromRow, PgExecutor, Postgres};
 
 use crate::errors::ServerError;
 use crate::users::User;
-use crate::users::permissions::UserPermission;
-use crate::users::roles::UserRole;
+use crate::users::passwords::{generate_secret, hash_password, needs_rehash, verify_password};
+use crate::users::permissions::{Permission, UserPermission};
+use crate::users::query::{MAX_USER_PAGE_SIZE, UserPage, UserQuery};
+
+/// Time during which a password reset token can be used.
+const PASSWORD_RESET_VALIDITY: Duration = Duration::hours(1);
 
 #[derive(FromRow, Clone, Debug)]
 pub struct SqlUser {
@@ -17,12 +28,22 @@ pub struct SqlUser {
     pub username: String,
     pub password: String,
     pub created_at: Option<DateTime<Utc>>,
+    pub password_reset_required: bool,
+    pub deleted_at: Option<DateTime<Utc>>,
 }
 
 impl SqlUser {
-    fn into_user(self, user_perms: Option<Vec<UserPermission>>) -> User {
+    fn into_user(
+        self,
+        user_roles: Option<Vec<String>>,
+        user_perms: Option<Vec<UserPermission>>,
+    ) -> User {
+        let roles = user_roles.unwrap_or_default().into_iter().collect();
         let permissions = if let Some(user_perms) = user_perms {
-            user_perms.into_iter().map(|perm| perm.token).collect()
+            user_perms
+                .into_iter()
+                .filter_map(|perm| perm.token.parse().ok())
+                .collect()
         } else {
             HashSet::new()
         };
@@ -31,13 +52,20 @@ impl SqlUser {
             anonymous: self.anonymous,
             username: self.username,
             created_at: self.created_at,
+            deleted_at: self.deleted_at,
+            roles,
             permissions,
         }
     }
 
-    pub async fn to_user(self, pool: &PgPool) -> Result<User, ServerError> {
-        let user_perms = get_user_perms(pool, self.id).await?;
-        Ok(self.into_user(Some(user_perms)))
+    pub async fn to_user(
+        self,
+        db: impl Acquire<'_, Database = Postgres>,
+    ) -> Result<User, ServerError> {
+        let mut conn = db.acquire().await?;
+        let user_roles = roles::get_user_roles(&mut *conn, self.id).await?;
+        let user_perms = get_user_perms(&mut *conn, self.id).await?;
+        Ok(self.into_user(Some(user_roles), Some(user_perms)))
     }
 }
 
@@ -54,144 +82,448 @@ impl From<SqlUserPermission> for UserPermission {
 }
 
 pub async fn get_user_perms(
-    pool: &PgPool,
+    executor: impl PgExecutor<'_>,
     user_id: i64,
 ) -> Result<Vec<UserPermission>, ServerError> {
     let user_perms = sqlx::query_as!(
         UserPermission,
-        "SELECT token FROM user_permissions WHERE user_id = $1",
+        r#"SELECT token AS "token!" FROM user_permissions WHERE user_id = $1
+            UNION
+            SELECT role_permissions.token FROM user_roles
+            JOIN role_permissions ON role_permissions.role_id = user_roles.role_id
+            WHERE user_roles.user_id = $1"#,
         user_id
     )
-    .fetch_all(pool)
+    .fetch_all(executor)
     .await?;
     Ok(user_perms)
 }
 
+/// Checks against the database whether a user has `permission`, either directly or through one
+/// of their roles. Deleted users have none.
+pub async fn has_permission(
+    executor: impl PgExecutor<'_>,
+    user_id: i64,
+    permission: Permission,
+) -> Result<bool, ServerError> {
+    Ok(sqlx::query_scalar!(
+        r#"SELECT EXISTS (
+            SELECT 1 FROM user_permissions WHERE user_id = $1 AND token = $2
+            UNION ALL
+            SELECT 1 FROM user_roles
+            JOIN role_permissions ON role_permissions.role_id = user_roles.role_id
+            WHERE user_roles.user_id = $1 AND role_permissions.token = $2
+        ) AND NOT EXISTS (
+            SELECT 1 FROM users WHERE id = $1 AND deleted_at IS NOT NULL
+        ) AS "exists!""#,
+        user_id,
+        permission.as_str()
+    )
+    .fetch_one(executor)
+    .await?)
+}
+
 pub async fn add_user_perms(
-    pool: &PgPool,
+    executor: impl PgExecutor<'_>,
     user_id: i64,
     user_perms: Vec<UserPermission>,
 ) -> Result<(), ServerError> {
-    for perm in user_perms {
-        sqlx::query!(
-            "INSERT INTO user_permissions (user_id, token) VALUES ($1, $2)",
-            user_id,
-            perm.token
-        )
-        .execute(pool)
-        .await?;
-    }
+    let tokens = user_perms
+        .into_iter()
+        .map(|perm| perm.token)
+        .collect::<Vec<_>>();
+    sqlx::query!(
+        "INSERT INTO user_permissions (user_id, token) SELECT $1, UNNEST($2::TEXT[])
+            ON CONFLICT (user_id, token) DO NOTHING",
+        user_id,
+        &tokens
+    )
+    .execute(executor)
+    .await?;
     Ok(())
 }
 
-pub async fn get_sql_users(pool: &PgPool) -> Result<Vec<SqlUser>, ServerError> {
-    Ok(
-        sqlx::query_as!(SqlUser, "SELECT * FROM users WHERE id <> 1")
-            .fetch_all(pool)
-            .await?,
+pub async fn get_sql_users(
+    executor: impl PgExecutor<'_>,
+    query: &UserQuery,
+) -> Result<Vec<SqlUser>, ServerError> {
+    Ok(sqlx::query_as!(
+        SqlUser,
+        "SELECT * FROM users
+            WHERE id <> 1 AND username ILIKE $1 AND (deleted_at IS NOT NULL) = $6
+            ORDER BY
+                CASE WHEN $2 = 'username' AND NOT $3 THEN LOWER(username) END ASC,
+                CASE WHEN $2 = 'username' AND $3 THEN LOWER(username) END DESC,
+                CASE WHEN $2 = 'created_at' AND NOT $3 THEN created_at END ASC,
+                CASE WHEN $2 = 'created_at' AND $3 THEN created_at END DESC,
+                CASE WHEN $3 THEN id END DESC,
+                id ASC
+            LIMIT $4 OFFSET $5",
+        search_pattern(&query.search),
+        query.sort_by.as_str(),
+        query.descending,
+        query.limit.clamp(1, MAX_USER_PAGE_SIZE),
+        query.offset.max(0),
+        query.deleted
+    )
+    .fetch_all(executor)
+    .await?)
+}
+
+pub async fn count_sql_users(
+    executor: impl PgExecutor<'_>,
+    query: &UserQuery,
+) -> Result<i64, ServerError> {
+    Ok(sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!" FROM users
+            WHERE id <> 1 AND username ILIKE $1 AND (deleted_at IS NOT NULL) = $2"#,
+        search_pattern(&query.search),
+        query.deleted
     )
+    .fetch_one(executor)
+    .await?)
+}
+
+/// `ILIKE` pattern matching any username containing `search`.
+fn search_pattern(search: &str) -> String {
+    let escaped = search
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{escaped}%")
 }
 
-pub async fn get_sql_user(pool: &PgPool, id: i64) -> Result<SqlUser, ServerError> {
-    Ok(
-        sqlx::query_as!(SqlUser, "SELECT * FROM users WHERE id = $1", id)
-            .fetch_one(pool)
-            .await?,
+pub async fn get_sql_user(executor: impl PgExecutor<'_>, id: i64) -> Result<SqlUser, ServerError> {
+    Ok(sqlx::query_as!(
+        SqlUser,
+        "SELECT * FROM users WHERE id = $1 AND deleted_at IS NULL",
+        id
     )
+    .fetch_one(executor)
+    .await?)
 }
 
 pub async fn get_sql_user_from_username(
-    pool: &PgPool,
+    executor: impl PgExecutor<'_>,
     username: String,
 ) -> Result<SqlUser, ServerError> {
-    Ok(
-        sqlx::query_as!(SqlUser, "SELECT * FROM users WHERE username = $1", username)
-            .fetch_one(pool)
-            .await?,
+    Ok(sqlx::query_as!(
+        SqlUser,
+        "SELECT * FROM users WHERE username = $1 AND deleted_at IS NULL",
+        username
     )
+    .fetch_one(executor)
+    .await?)
 }
 
-pub async fn get_users(pool: &PgPool) -> Result<Vec<User>, ServerError> {
-    let sql_users = get_sql_users(pool).await?;
+pub async fn get_users(
+    db: impl Acquire<'_, Database = Postgres>,
+    query: &UserQuery,
+) -> Result<UserPage, ServerError> {
+    let mut conn = db.acquire().await?;
+    let sql_users = get_sql_users(&mut *conn, query).await?;
+    let total = count_sql_users(&mut *conn, query).await?;
+    let user_ids = sql_users
+        .iter()
+        .map(|sql_user| sql_user.id)
+        .collect::<Vec<_>>();
+    let mut roles_map = HashMap::with_capacity(sql_users.len());
+    for user_role in sqlx::query!(
+        "SELECT user_roles.user_id, roles.name FROM user_roles
+            JOIN roles ON roles.id = user_roles.role_id
+            WHERE user_roles.user_id = ANY($1)",
+        &user_ids
+    )
+    .fetch_all(&mut *conn)
+    .await?
+    {
+        let entry = roles_map.entry(user_role.user_id).or_insert(Vec::new());
+        entry.push(user_role.name);
+    }
     let mut perms_map = HashMap::with_capacity(sql_users.len());
-    for sql_user_perm in sqlx::query_as!(SqlUserPermission, "SELECT * FROM user_permissions")
-        .fetch_all(pool)
-        .await?
+    for sql_user_perm in sqlx::query_as!(
+        SqlUserPermission,
+        r#"SELECT user_id AS "user_id!", token AS "token!" FROM user_permissions
+            WHERE user_id = ANY($1)
+            UNION
+            SELECT user_roles.user_id, role_permissions.token FROM user_roles
+            JOIN role_permissions ON role_permissions.role_id = user_roles.role_id
+            WHERE user_roles.user_id = ANY($1)"#,
+        &user_ids
+    )
+    .fetch_all(&mut *conn)
+    .await?
     {
         let entry = perms_map.entry(sql_user_perm.user_id).or_insert(Vec::new());
         entry.push(sql_user_perm.into());
     }
-    Ok(sql_users
+    let users = sql_users
         .iter()
         .map(|sql_user| {
-            sql_user
-                .clone()
-                .into_user(perms_map.get(&sql_user.id).cloned())
+            sql_user.clone().into_user(
+                roles_map.get(&sql_user.id).cloned(),
+                perms_map.get(&sql_user.id).cloned(),
+            )
         })
-        .collect())
+        .collect();
+    Ok(UserPage { users, total })
 }
 
-pub async fn get_user(pool: &PgPool, id: i64) -> Result<User, ServerError> {
-    get_sql_user(pool, id).await?.to_user(pool).await
+pub async fn get_user(
+    db: impl Acquire<'_, Database = Postgres>,
+    id: i64,
+) -> Result<User, ServerError> {
+    let mut conn = db.acquire().await?;
+    get_sql_user(&mut *conn, id)
+        .await?
+        .to_user(&mut *conn)
+        .await
 }
 
-pub async fn get_user_from_username(pool: &PgPool, username: String) -> Result<User, ServerError> {
-    get_sql_user_from_username(pool, username)
+pub async fn get_user_from_username(
+    db: impl Acquire<'_, Database = Postgres>,
+    username: String,
+) -> Result<User, ServerError> {
+    let mut conn = db.acquire().await?;
+    get_sql_user_from_username(&mut *conn, username)
         .await?
-        .to_user(pool)
+        .to_user(&mut *conn)
         .await
 }
 
+/// Creates a user with `role`, or nothing at all when any step fails.
+/// Reports the unique username constraint as [`ServerError::UsernameTaken`], deleted users keeping
+/// their username until they are purged so that they can be restored.
+fn username_error(error: sqlx::Error) -> ServerError {
+    match &error {
+        sqlx::Error::Database(database_error) if database_error.is_unique_violation() => {
+            ServerError::UsernameTaken
+        }
+        _ => error.into(),
+    }
+}
+
 pub async fn create_user(
-    pool: &PgPool,
+    db: impl Acquire<'_, Database = Postgres>,
     username: String,
     password: String,
-    role: UserRole,
+    role: &str,
 ) -> Result<User, ServerError> {
-    let salt = SaltString::generate(&mut OsRng);
-    let password_hashed = Argon2::default()
-        .hash_password(password.as_bytes(), &salt)?
-        .to_string();
+    let password_hashed = hash_password(&password)?;
 
+    let mut tx = db.begin().await?;
     let id = sqlx::query_scalar!(
         "INSERT INTO users (username, password, anonymous) VALUES ($1, $2, $3) RETURNING id",
         username.clone(),
         password_hashed,
         false
     )
-    .fetch_one(pool)
-    .await?;
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(username_error)?;
 
-    add_user_perms(pool, id, role.permissions()).await?;
+    roles::grant_role_by_name(&mut *tx, id, role).await?;
 
     // To check if the creation of the user was successfull
-    let user = get_user(pool, id).await?;
+    let user = get_user(&mut *tx, id).await?;
+    tx.commit().await?;
 
     Ok(user)
 }
 
 pub async fn update_user_username(
-    pool: &PgPool,
+    db: impl Acquire<'_, Database = Postgres>,
     id: i64,
     username: String,
 ) -> Result<User, ServerError> {
+    let mut conn = db.acquire().await?;
     let id = sqlx::query_scalar!(
-        "UPDATE users SET username = $2 WHERE id = $1 RETURNING id",
+        "UPDATE users SET username = $2 WHERE id = $1 AND deleted_at IS NULL RETURNING id",
         id,
         username.clone(),
     )
-    .fetch_one(pool)
-    .await?;
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(username_error)?;
 
-    let user = get_user(pool, id).await?;
+    let user = get_user(&mut *conn, id).await?;
 
     Ok(user)
 }
 
-pub async fn delete_user(pool: &PgPool, id: i64) -> Result<i64, ServerError> {
-    Ok(
-        sqlx::query_scalar!("DELETE FROM users WHERE id = $1 RETURNING id", id)
-            .fetch_one(pool)
-            .await?,
+/// Hashes and stores a new password, which also invalidates the pending password resets of the
+/// user.
+pub async fn update_user_password(
+    db: impl Acquire<'_, Database = Postgres>,
+    id: i64,
+    password: String,
+) -> Result<(), ServerError> {
+    let password_hashed = hash_password(&password)?;
+    let mut tx = db.begin().await?;
+    sqlx::query!(
+        "UPDATE users SET password = $2, password_reset_required = false WHERE id = $1",
+        id,
+        password_hashed
+    )
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query!("DELETE FROM password_resets WHERE user_id = $1", id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// User with `username` when `password` is theirs. Passwords hashed with other parameters than
+/// the current ones are hashed again.
+pub async fn verify_login(
+    db: impl Acquire<'_, Database = Postgres>,
+    username: String,
+    password: &str,
+) -> Result<Option<SqlUser>, ServerError> {
+    let mut conn = db.acquire().await?;
+    let Ok(sql_user) = get_sql_user_from_username(&mut *conn, username).await else {
+        return Ok(None);
+    };
+    if !verify_password(password, &sql_user.password).unwrap_or(false) {
+        return Ok(None);
+    }
+    if needs_rehash(&sql_user.password) {
+        rehash_user_password(&mut *conn, sql_user.id, password).await?;
+    }
+    Ok(Some(sql_user))
+}
+
+/// Stores a new hash of the current password, leaving pending password resets untouched.
+pub async fn rehash_user_password(
+    executor: impl PgExecutor<'_>,
+    id: i64,
+    password: &str,
+) -> Result<(), ServerError> {
+    sqlx::query!(
+        "UPDATE users SET password = $2 WHERE id = $1",
+        id,
+        hash_password(password)?
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+/// Creates a single-use token to reset the password of a user. When `required` is set, the user
+/// can't log in with their current password anymore and their sessions are revoked.
+pub async fn create_password_reset(
+    db: impl Acquire<'_, Database = Postgres>,
+    user_id: i64,
+    required: bool,
+) -> Result<String, ServerError> {
+    let secret = generate_secret();
+    let mut tx = db.begin().await?;
+    let id = sqlx::query_scalar!(
+        "INSERT INTO password_resets (user_id, secret, expires_at) VALUES ($1, $2, $3)
+            RETURNING id",
+        user_id,
+        hash_password(&secret)?,
+        Utc::now() + PASSWORD_RESET_VALIDITY
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    if required {
+        sqlx::query!(
+            "UPDATE users SET password_reset_required = true WHERE id = $1",
+            user_id
+        )
+        .execute(&mut *tx)
+        .await?;
+        sessions::revoke_user_sessions(&mut *tx, user_id).await?;
+    }
+    tx.commit().await?;
+    Ok(format!("{id}.{secret}"))
+}
+
+/// Sets a new password with a token from [`create_password_reset`], which can't be used again.
+/// The sessions of the user are revoked, since whoever knew the previous password could have
+/// opened them.
+pub async fn reset_password(
+    db: impl Acquire<'_, Database = Postgres>,
+    token: &str,
+    password: String,
+) -> Result<i64, ServerError> {
+    let Some((id, secret)) = token
+        .split_once('.')
+        .and_then(|(id, secret)| Some((id.parse::<i64>().ok()?, secret)))
+    else {
+        return Err(ServerError::InvalidResetToken);
+    };
+    let mut tx = db.begin().await?;
+    let password_reset = sqlx::query!(
+        "SELECT secret, expires_at FROM password_resets WHERE id = $1",
+        id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(ServerError::InvalidResetToken)?;
+    if password_reset.expires_at < Utc::now() || !verify_password(secret, &password_reset.secret)? {
+        return Err(ServerError::InvalidResetToken);
+    }
+    // Deleting the token first makes sure that concurrent uses of it can't both succeed
+    let user_id = sqlx::query_scalar!(
+        "DELETE FROM password_resets WHERE id = $1 RETURNING user_id",
+        id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(ServerError::InvalidResetToken)?;
+    update_user_password(&mut *tx, user_id, password).await?;
+    sessions::revoke_user_sessions(&mut *tx, user_id).await?;
+    tx.commit().await?;
+    Ok(user_id)
+}
+
+/// Marks a user as deleted and revokes their sessions. The user is kept, along with their decks,
+/// until [`purge_deleted_users`] removes them, and can be restored in the meantime.
+pub async fn delete_user(
+    db: impl Acquire<'_, Database = Postgres>,
+    id: i64,
+) -> Result<i64, ServerError> {
+    let mut tx = db.begin().await?;
+    let id = sqlx::query_scalar!(
+        "UPDATE users SET deleted_at = NOW() WHERE id = $1 AND deleted_at IS NULL RETURNING id",
+        id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    sessions::revoke_user_sessions(&mut *tx, id).await?;
+    sqlx::query!("DELETE FROM password_resets WHERE user_id = $1", id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(id)
+}
+
+pub async fn restore_user(
+    db: impl Acquire<'_, Database = Postgres>,
+    id: i64,
+) -> Result<User, ServerError> {
+    let mut conn = db.acquire().await?;
+    let id = sqlx::query_scalar!(
+        "UPDATE users SET deleted_at = NULL WHERE id = $1 AND deleted_at IS NOT NULL RETURNING id",
+        id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+    get_user(&mut *conn, id).await
+}
+
+/// Removes for good the users deleted before `deleted_before`, along with everything they own.
+pub async fn purge_deleted_users(
+    executor: impl PgExecutor<'_>,
+    deleted_before: DateTime<Utc>,
+) -> Result<Vec<i64>, ServerError> {
+    Ok(sqlx::query_scalar!(
+        "DELETE FROM users WHERE deleted_at < $1 RETURNING id",
+        deleted_before
     )
+    .fetch_all(executor)
+    .await?)
 }
@@ -0,0 +1,125 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::{Acquire, PgExecutor, Postgres};
+
+use crate::audit::{
+    AuditAction, AuditEntry, AuditPage, AuditQuery, AuditTarget, MAX_AUDIT_PAGE_SIZE,
+};
+use crate::errors::ServerError;
+
+struct SqlAuditEntry {
+    id: i64,
+    actor_id: Option<i64>,
+    actor_name: Option<String>,
+    action: String,
+    target_kind: String,
+    target_id: Option<i64>,
+    before: Option<Value>,
+    after: Option<Value>,
+    created_at: DateTime<Utc>,
+}
+
+impl SqlAuditEntry {
+    /// Entries written by a newer server with unknown actions or targets are skipped.
+    fn into_entry(self) -> Option<AuditEntry> {
+        Some(AuditEntry {
+            id: self.id,
+            actor_id: self.actor_id,
+            actor_name: self.actor_name,
+            action: self.action.parse().ok()?,
+            target: AuditTarget::from_parts(&self.target_kind, self.target_id)?,
+            before: self.before,
+            after: self.after,
+            created_at: self.created_at,
+        })
+    }
+}
+
+fn to_value(value: Option<impl Serialize>) -> Result<Option<Value>, ServerError> {
+    value
+        .map(serde_json::to_value)
+        .transpose()
+        .map_err(|error| ServerError::Database(error.to_string()))
+}
+
+/// Records that `actor_id` applied `action` to `target`, with the state of the target before and
+/// after the action when it has one. Actions of the server itself have no actor.
+pub async fn record(
+    executor: impl PgExecutor<'_>,
+    actor_id: impl Into<Option<i64>>,
+    action: AuditAction,
+    target: AuditTarget,
+    before: Option<impl Serialize>,
+    after: Option<impl Serialize>,
+) -> Result<(), ServerError> {
+    sqlx::query!(
+        "INSERT INTO audit_log (actor_id, action, target_kind, target_id, before, after)
+            VALUES ($1, $2, $3, $4, $5, $6)",
+        actor_id.into(),
+        action.as_str(),
+        target.kind(),
+        target.id(),
+        to_value(before)?,
+        to_value(after)?
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+pub async fn get_audit_log(
+    db: impl Acquire<'_, Database = Postgres>,
+    query: &AuditQuery,
+) -> Result<AuditPage, ServerError> {
+    let mut conn = db.acquire().await?;
+    let action = query.action.map(AuditAction::as_str);
+    let target_kind = query.target.map(AuditTarget::kind);
+    let target_id = query.target.and_then(AuditTarget::id);
+    let entries = sqlx::query_as!(
+        SqlAuditEntry,
+        r#"SELECT audit_log.id, actor_id, users.username AS "actor_name?", action, target_kind,
+                target_id, before, after, audit_log.created_at
+            FROM audit_log
+            LEFT JOIN users ON users.id = audit_log.actor_id
+            WHERE ($1::BIGINT IS NULL OR actor_id = $1)
+                AND ($2::TEXT IS NULL OR action = $2)
+                AND ($3::TEXT IS NULL OR target_kind = $3)
+                AND ($4::BIGINT IS NULL OR target_id = $4)
+                AND ($5::TIMESTAMPTZ IS NULL OR audit_log.created_at >= $5)
+                AND ($6::TIMESTAMPTZ IS NULL OR audit_log.created_at < $6)
+            ORDER BY audit_log.created_at DESC, audit_log.id DESC
+            LIMIT $7 OFFSET $8"#,
+        query.actor_id,
+        action,
+        target_kind,
+        target_id,
+        query.since,
+        query.until,
+        query.limit.clamp(1, MAX_AUDIT_PAGE_SIZE),
+        query.offset.max(0)
+    )
+    .fetch_all(&mut *conn)
+    .await?
+    .into_iter()
+    .filter_map(SqlAuditEntry::into_entry)
+    .collect();
+    let total = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!" FROM audit_log
+            WHERE ($1::BIGINT IS NULL OR actor_id = $1)
+                AND ($2::TEXT IS NULL OR action = $2)
+                AND ($3::TEXT IS NULL OR target_kind = $3)
+                AND ($4::BIGINT IS NULL OR target_id = $4)
+                AND ($5::TIMESTAMPTZ IS NULL OR created_at >= $5)
+                AND ($6::TIMESTAMPTZ IS NULL OR created_at < $6)"#,
+        query.actor_id,
+        action,
+        target_kind,
+        target_id,
+        query.since,
+        query.until
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+    Ok(AuditPage { entries, total })
+}
@@ -0,0 +1,592 @@
+use chrono::{DateTime, Duration, Utc};
+use hashbrown::HashMap;
+use sqlx::{Acquire, FromRow, PgExecutor, Postgres};
+
+use crate::database::presets;
+use crate::database::scheduler::{get_card_scheduler_settings, get_scheduler_settings};
+use crate::errors::ServerError;
+use crate::learn::content::{CardImage, cloze_cards};
+use crate::learn::presets::{QueueLimits, StudyPlan};
+use crate::learn::{
+    Card, Deck, ImportedDeck, MAX_SYNCED_REVIEWS, MAX_TIME_TAKEN_MS, OfflineReview, Rating,
+    ReviewLog, ReviewQueue, SchedulerSettings,
+};
+
+#[derive(FromRow, Clone, Debug)]
+pub struct SqlCard {
+    pub id: i64,
+    pub deck_id: i64,
+    pub front: String,
+    pub back: String,
+    pub tags: Vec<String>,
+    pub extra: Vec<String>,
+    pub due: DateTime<Utc>,
+    pub stability: f64,
+    pub difficulty: f64,
+    pub elapsed_days: i64,
+    pub scheduled_days: i64,
+    pub reps: i32,
+    pub lapses: i32,
+    pub state: i16,
+    pub last_review: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub note_id: Option<i64>,
+    pub template_ord: Option<i32>,
+    pub typed_answer: Option<String>,
+    pub learning_step: i32,
+}
+
+impl From<SqlCard> for Card {
+    fn from(val: SqlCard) -> Self {
+        Card {
+            id: val.id,
+            deck_id: val.deck_id,
+            front: val.front,
+            back: val.back,
+            tags: val.tags,
+            extra: val.extra,
+            due: val.due,
+            stability: val.stability,
+            difficulty: val.difficulty,
+            elapsed_days: val.elapsed_days,
+            scheduled_days: val.scheduled_days,
+            reps: val.reps,
+            lapses: val.lapses,
+            state: val.state.into(),
+            last_review: val.last_review,
+            created_at: val.created_at,
+            note_id: val.note_id,
+            template_ord: val.template_ord,
+            typed_answer: val.typed_answer,
+            learning_step: val.learning_step,
+        }
+    }
+}
+
+#[derive(FromRow)]
+pub struct SqlReviewLog {
+    pub id: i64,
+    pub card_id: i64,
+    pub rating: i16,
+    pub state: i16,
+    pub elapsed_days: i64,
+    pub scheduled_days: i64,
+    pub reviewed_at: DateTime<Utc>,
+    pub time_taken_ms: i64,
+}
+
+impl From<SqlReviewLog> for ReviewLog {
+    fn from(val: SqlReviewLog) -> Self {
+        ReviewLog {
+            id: val.id,
+            card_id: val.card_id,
+            rating: val.rating.into(),
+            state: val.state.into(),
+            elapsed_days: val.elapsed_days,
+            scheduled_days: val.scheduled_days,
+            reviewed_at: val.reviewed_at,
+            time_taken_ms: val.time_taken_ms,
+        }
+    }
+}
+
+pub async fn get_decks(
+    executor: impl PgExecutor<'_>,
+    owner_id: i64,
+) -> Result<Vec<Deck>, ServerError> {
+    Ok(sqlx::query_as!(
+        Deck,
+        "SELECT * FROM decks WHERE owner_id = $1 ORDER BY id",
+        owner_id
+    )
+    .fetch_all(executor)
+    .await?)
+}
+
+pub async fn get_deck(executor: impl PgExecutor<'_>, id: i64) -> Result<Deck, ServerError> {
+    Ok(
+        sqlx::query_as!(Deck, "SELECT * FROM decks WHERE id = $1", id)
+            .fetch_one(executor)
+            .await?,
+    )
+}
+
+/// Owner of a deck, `None` when it doesn't exist.
+pub async fn get_deck_owner(
+    executor: impl PgExecutor<'_>,
+    id: i64,
+) -> Result<Option<i64>, ServerError> {
+    Ok(
+        sqlx::query_scalar!("SELECT owner_id FROM decks WHERE id = $1", id)
+            .fetch_optional(executor)
+            .await?,
+    )
+}
+
+pub async fn create_deck(
+    executor: impl PgExecutor<'_>,
+    owner_id: i64,
+    name: String,
+    description: String,
+) -> Result<Deck, ServerError> {
+    Ok(sqlx::query_as!(
+        Deck,
+        "INSERT INTO decks (owner_id, name, description) VALUES ($1, $2, $3) RETURNING *",
+        owner_id,
+        name,
+        description
+    )
+    .fetch_one(executor)
+    .await?)
+}
+
+pub async fn update_deck(
+    executor: impl PgExecutor<'_>,
+    id: i64,
+    name: String,
+    description: String,
+) -> Result<Deck, ServerError> {
+    Ok(sqlx::query_as!(
+        Deck,
+        "UPDATE decks SET name = $2, description = $3 WHERE id = $1 RETURNING *",
+        id,
+        name,
+        description
+    )
+    .fetch_one(executor)
+    .await?)
+}
+
+pub async fn delete_deck(executor: impl PgExecutor<'_>, id: i64) -> Result<i64, ServerError> {
+    Ok(
+        sqlx::query_scalar!("DELETE FROM decks WHERE id = $1 RETURNING id", id)
+            .fetch_one(executor)
+            .await?,
+    )
+}
+
+pub async fn get_cards(
+    executor: impl PgExecutor<'_>,
+    deck_id: i64,
+) -> Result<Vec<Card>, ServerError> {
+    let sql_cards = sqlx::query_as!(
+        SqlCard,
+        "SELECT * FROM cards WHERE deck_id = $1 ORDER BY id",
+        deck_id
+    )
+    .fetch_all(executor)
+    .await?;
+    Ok(sql_cards.into_iter().map(Card::from).collect())
+}
+
+pub async fn get_card(executor: impl PgExecutor<'_>, id: i64) -> Result<Card, ServerError> {
+    let sql_card = sqlx::query_as!(SqlCard, "SELECT * FROM cards WHERE id = $1", id)
+        .fetch_one(executor)
+        .await?;
+    Ok(sql_card.into())
+}
+
+/// Owner of the deck of a card, `None` when it doesn't exist.
+pub async fn get_card_owner(
+    executor: impl PgExecutor<'_>,
+    id: i64,
+) -> Result<Option<i64>, ServerError> {
+    Ok(sqlx::query_scalar!(
+        "SELECT decks.owner_id FROM cards JOIN decks ON decks.id = cards.deck_id
+            WHERE cards.id = $1",
+        id
+    )
+    .fetch_optional(executor)
+    .await?)
+}
+
+/// Every card of the decks of a user.
+pub async fn get_owner_cards(
+    executor: impl PgExecutor<'_>,
+    owner_id: i64,
+) -> Result<Vec<Card>, ServerError> {
+    let sql_cards = sqlx::query_as!(
+        SqlCard,
+        "SELECT cards.* FROM cards
+            JOIN decks ON decks.id = cards.deck_id
+            WHERE decks.owner_id = $1
+            ORDER BY cards.id",
+        owner_id
+    )
+    .fetch_all(executor)
+    .await?;
+    Ok(sql_cards.into_iter().map(Card::from).collect())
+}
+
+pub async fn get_due_cards(
+    executor: impl PgExecutor<'_>,
+    owner_id: i64,
+    now: DateTime<Utc>,
+) -> Result<Vec<Card>, ServerError> {
+    let sql_cards = sqlx::query_as!(
+        SqlCard,
+        "SELECT cards.* FROM cards
+            JOIN decks ON decks.id = cards.deck_id
+            WHERE decks.owner_id = $1 AND cards.due <= $2
+            ORDER BY cards.due",
+        owner_id,
+        now
+    )
+    .fetch_all(executor)
+    .await?;
+    Ok(sql_cards.into_iter().map(Card::from).collect())
+}
+
+/// When the next card of a user that isn't due at `now` will be.
+pub async fn get_next_due(
+    executor: impl PgExecutor<'_>,
+    owner_id: i64,
+    now: DateTime<Utc>,
+) -> Result<Option<DateTime<Utc>>, ServerError> {
+    Ok(sqlx::query_scalar!(
+        "SELECT MIN(cards.due) FROM cards
+            JOIN decks ON decks.id = cards.deck_id
+            WHERE decks.owner_id = $1 AND cards.due > $2",
+        owner_id,
+        now
+    )
+    .fetch_one(executor)
+    .await?)
+}
+
+/// Options and what is left of the daily limits of each deck of a user at `now`.
+pub async fn get_study_plan(
+    db: impl Acquire<'_, Database = Postgres>,
+    owner_id: i64,
+    now: DateTime<Utc>,
+) -> Result<StudyPlan, ServerError> {
+    let mut conn = db.acquire().await?;
+    let settings = get_scheduler_settings(&mut *conn, owner_id).await?;
+    let day_start = settings.day_start(now);
+    let mut presets = presets::get_owner_deck_presets(&mut *conn, owner_id).await?;
+    let studied_counts = presets::get_studied_counts(&mut *conn, owner_id, day_start).await?;
+    let studied_notes = presets::get_studied_notes(&mut *conn, owner_id, day_start).await?;
+    let mut limits = HashMap::new();
+    for deck in get_decks(&mut *conn, owner_id).await? {
+        let preset = presets
+            .entry(deck.id)
+            .or_insert_with(|| settings.default_preset());
+        let (new_cards, reviews) = studied_counts.get(&deck.id).copied().unwrap_or_default();
+        limits.insert(deck.id, QueueLimits::remaining(preset, new_cards, reviews));
+    }
+    let next_day = settings.day_start(day_start + Duration::hours(36));
+    Ok(StudyPlan {
+        settings,
+        presets,
+        limits,
+        studied_notes,
+        next_day,
+    })
+}
+
+pub async fn get_review_queue(
+    db: impl Acquire<'_, Database = Postgres>,
+    owner_id: i64,
+    now: DateTime<Utc>,
+) -> Result<ReviewQueue, ServerError> {
+    let mut conn = db.acquire().await?;
+    let plan = get_study_plan(&mut *conn, owner_id, now).await?;
+    let cards = get_due_cards(&mut *conn, owner_id, now).await?;
+    let next_due = get_next_due(&mut *conn, owner_id, now).await?;
+    Ok(ReviewQueue::limited(
+        cards,
+        &plan.limits,
+        &plan.studied_notes,
+        next_due,
+        plan.next_day,
+    ))
+}
+
+pub async fn create_card(
+    executor: impl PgExecutor<'_>,
+    deck_id: i64,
+    front: String,
+    back: String,
+) -> Result<Card, ServerError> {
+    let sql_card = sqlx::query_as!(
+        SqlCard,
+        "INSERT INTO cards (deck_id, front, back) VALUES ($1, $2, $3) RETURNING *",
+        deck_id,
+        front,
+        back
+    )
+    .fetch_one(executor)
+    .await?;
+    Ok(sql_card.into())
+}
+
+/// Inserts a card along with its scheduling state, for cards that come with a review history.
+pub async fn create_card_with_state(
+    executor: impl PgExecutor<'_>,
+    deck_id: i64,
+    card: &Card,
+) -> Result<Card, ServerError> {
+    let sql_card = sqlx::query_as!(
+        SqlCard,
+        "INSERT INTO cards (
+                deck_id, front, back, tags, extra, due, stability, difficulty, elapsed_days,
+                scheduled_days, reps, lapses, state, last_review
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
+            RETURNING *",
+        deck_id,
+        card.front,
+        card.back,
+        &card.tags,
+        &card.extra,
+        card.due,
+        card.stability,
+        card.difficulty,
+        card.elapsed_days,
+        card.scheduled_days,
+        card.reps,
+        card.lapses,
+        i16::from(card.state),
+        card.last_review
+    )
+    .fetch_one(executor)
+    .await?;
+    Ok(sql_card.into())
+}
+
+pub async fn update_card(
+    executor: impl PgExecutor<'_>,
+    id: i64,
+    front: String,
+    back: String,
+) -> Result<Card, ServerError> {
+    let sql_card = sqlx::query_as!(
+        SqlCard,
+        "UPDATE cards SET front = $2, back = $3 WHERE id = $1 RETURNING *",
+        id,
+        front,
+        back
+    )
+    .fetch_one(executor)
+    .await?;
+    Ok(sql_card.into())
+}
+
+pub async fn delete_card(executor: impl PgExecutor<'_>, id: i64) -> Result<i64, ServerError> {
+    Ok(
+        sqlx::query_scalar!("DELETE FROM cards WHERE id = $1 RETURNING id", id)
+            .fetch_one(executor)
+            .await?,
+    )
+}
+
+/// Creates one card per cloze deletion of `text`, or none at all when any of them fails.
+pub async fn create_cloze_cards(
+    db: impl Acquire<'_, Database = Postgres>,
+    deck_id: i64,
+    text: &str,
+) -> Result<Vec<Card>, ServerError> {
+    let mut tx = db.begin().await?;
+    let mut cards = Vec::new();
+    for (front, back) in cloze_cards(text) {
+        cards.push(create_card(&mut *tx, deck_id, front, back).await?);
+    }
+    tx.commit().await?;
+    Ok(cards)
+}
+
+pub async fn create_image(
+    executor: impl PgExecutor<'_>,
+    owner_id: i64,
+    content_type: &str,
+    data: &[u8],
+) -> Result<CardImage, ServerError> {
+    Ok(sqlx::query_as!(
+        CardImage,
+        "INSERT INTO card_images (owner_id, content_type, data) VALUES ($1, $2, $3)
+            RETURNING id, owner_id, content_type, data",
+        owner_id,
+        content_type,
+        data
+    )
+    .fetch_one(executor)
+    .await?)
+}
+
+pub async fn get_image(executor: impl PgExecutor<'_>, id: i64) -> Result<CardImage, ServerError> {
+    Ok(sqlx::query_as!(
+        CardImage,
+        "SELECT id, owner_id, content_type, data FROM card_images WHERE id = $1",
+        id
+    )
+    .fetch_one(executor)
+    .await?)
+}
+
+pub async fn get_review_logs(
+    executor: impl PgExecutor<'_>,
+    card_id: i64,
+) -> Result<Vec<ReviewLog>, ServerError> {
+    let sql_review_logs = sqlx::query_as!(
+        SqlReviewLog,
+        "SELECT * FROM review_logs WHERE card_id = $1 ORDER BY reviewed_at",
+        card_id
+    )
+    .fetch_all(executor)
+    .await?;
+    Ok(sql_review_logs.into_iter().map(ReviewLog::from).collect())
+}
+
+pub async fn create_review_log(
+    executor: impl PgExecutor<'_>,
+    review_log: &ReviewLog,
+) -> Result<(), ServerError> {
+    sqlx::query!(
+        "INSERT INTO review_logs
+                (card_id, rating, state, elapsed_days, scheduled_days, reviewed_at, time_taken_ms)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)",
+        review_log.card_id,
+        i16::from(review_log.rating),
+        i16::from(review_log.state),
+        review_log.elapsed_days,
+        review_log.scheduled_days,
+        review_log.reviewed_at,
+        review_log.time_taken_ms
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+pub async fn review_card(
+    db: impl Acquire<'_, Database = Postgres>,
+    id: i64,
+    rating: Rating,
+    time_taken_ms: i64,
+    now: DateTime<Utc>,
+) -> Result<Card, ServerError> {
+    let mut tx = db.begin().await?;
+    // The card is locked so that concurrent reviews are applied one after the other
+    let mut card: Card =
+        sqlx::query_as!(SqlCard, "SELECT * FROM cards WHERE id = $1 FOR UPDATE", id)
+            .fetch_one(&mut *tx)
+            .await?
+            .into();
+    let settings = get_card_scheduler_settings(&mut *tx, id).await?;
+    let preset = presets::get_card_deck_preset(&mut *tx, id)
+        .await?
+        .unwrap_or_else(|| settings.default_preset());
+    let scheduler = SchedulerSettings {
+        desired_retention: preset.desired_retention,
+        ..settings
+    }
+    .scheduler();
+    let previous_state = card.state;
+    let review_log = ReviewLog {
+        time_taken_ms: time_taken_ms.clamp(0, MAX_TIME_TAKEN_MS),
+        ..card.review_with(&scheduler, rating, now)
+    };
+    preset.apply_steps(&mut card, previous_state, rating, now);
+
+    sqlx::query!(
+        "UPDATE cards
+            SET due = $2, stability = $3, difficulty = $4, elapsed_days = $5,
+                scheduled_days = $6, reps = $7, lapses = $8, state = $9, last_review = $10,
+                learning_step = $11, tags = $12
+            WHERE id = $1",
+        card.id,
+        card.due,
+        card.stability,
+        card.difficulty,
+        card.elapsed_days,
+        card.scheduled_days,
+        card.reps,
+        card.lapses,
+        i16::from(card.state),
+        card.last_review,
+        card.learning_step,
+        &card.tags
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    create_review_log(&mut *tx, &review_log).await?;
+    tx.commit().await?;
+
+    Ok(card)
+}
+
+/// Applies reviews made offline in the order they were made, returning the reviewed cards. A
+/// review is skipped when its card was deleted, isn't owned by `owner_id`, or was reviewed again
+/// after it, e.g. from another device. Review times come from the client, so they are kept
+/// between the creation of the card and `now`.
+pub async fn sync_reviews(
+    db: impl Acquire<'_, Database = Postgres>,
+    owner_id: i64,
+    mut reviews: Vec<OfflineReview>,
+    now: DateTime<Utc>,
+) -> Result<Vec<Card>, ServerError> {
+    if reviews.len() > MAX_SYNCED_REVIEWS {
+        return Err(ServerError::TooManyReviews {
+            limit: MAX_SYNCED_REVIEWS,
+        });
+    }
+    reviews.sort_by_key(|review| review.reviewed_at);
+    let mut tx = db.begin().await?;
+    let mut cards = Vec::with_capacity(reviews.len());
+    for review in reviews {
+        let card = sqlx::query!(
+            "SELECT cards.created_at, cards.last_review FROM cards
+                JOIN decks ON decks.id = cards.deck_id
+                WHERE cards.id = $1 AND decks.owner_id = $2
+                FOR UPDATE OF cards",
+            review.card_id,
+            owner_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(card) = card else {
+            continue;
+        };
+        let mut reviewed_at = review.reviewed_at.min(now);
+        if let Some(created_at) = card.created_at {
+            reviewed_at = reviewed_at.max(created_at);
+        }
+        if card
+            .last_review
+            .is_some_and(|last_review| last_review >= reviewed_at)
+        {
+            continue;
+        }
+        let card = review_card(
+            &mut *tx,
+            review.card_id,
+            review.rating,
+            review.time_taken_ms,
+            reviewed_at,
+        )
+        .await?;
+        cards.push(card);
+    }
+    tx.commit().await?;
+    Ok(cards)
+}
+
+/// Imports a deck with all of its cards, or nothing at all when any of them fails.
+pub async fn import_deck(
+    db: impl Acquire<'_, Database = Postgres>,
+    owner_id: i64,
+    imported_deck: ImportedDeck,
+) -> Result<Deck, ServerError> {
+    let mut tx = db.begin().await?;
+    let deck = create_deck(&mut *tx, owner_id, imported_deck.name, String::new()).await?;
+    for imported_card in imported_deck.cards {
+        let card = create_card_with_state(&mut *tx, deck.id, &imported_card.card).await?;
+        for mut review_log in imported_card.review_logs {
+            review_log.card_id = card.id;
+            create_review_log(&mut *tx, &review_log).await?;
+        }
+    }
+    tx.commit().await?;
+    Ok(deck)
+}
@@ -0,0 +1,98 @@
+use chrono::{Duration, Utc};
+use sqlx::{Acquire, PgExecutor, Postgres};
+
+use crate::errors::ServerError;
+
+/// Failed attempts allowed before logins start being delayed.
+const FREE_ATTEMPTS: i32 = 3;
+/// Failed attempts after which logins are locked for [`LOCKOUT_DURATION`].
+const LOCKOUT_ATTEMPTS: i32 = 10;
+const LOCKOUT_DURATION: Duration = Duration::hours(1);
+/// Failures older than this are forgotten on the next failure.
+const ATTEMPT_WINDOW: Duration = Duration::hours(24);
+
+/// Delay before the next attempt is allowed, doubling with each failure past [`FREE_ATTEMPTS`].
+fn backoff(failures: i32) -> Option<Duration> {
+    if failures >= LOCKOUT_ATTEMPTS {
+        return Some(LOCKOUT_DURATION);
+    }
+    let exponent = failures.checked_sub(FREE_ATTEMPTS)?;
+    Some(Duration::seconds(1 << exponent).min(LOCKOUT_DURATION))
+}
+
+/// Counts an attempt as a failure for each of the `keys` before the password is verified, so that
+/// concurrent attempts can't get past the backoff. Fails with [`ServerError::LoginLocked`] without
+/// counting anything when any of the `keys` is locked.
+pub async fn reserve_login_attempt(
+    db: impl Acquire<'_, Database = Postgres>,
+    keys: &[String],
+) -> Result<(), ServerError> {
+    let now = Utc::now();
+    let mut tx = db.begin().await?;
+    for key in keys {
+        // The conflicting row stays locked until the end of the transaction, even when it isn't
+        // updated, so its lock can't change before it is read
+        let failures = sqlx::query_scalar!(
+            "INSERT INTO login_attempts (key, failures, last_failure_at) VALUES ($1, 1, $2)
+                ON CONFLICT (key) DO UPDATE SET
+                    failures = CASE
+                        WHEN login_attempts.last_failure_at < $3 THEN 1
+                        ELSE login_attempts.failures + 1
+                    END,
+                    last_failure_at = $2
+                WHERE login_attempts.locked_until IS NULL OR login_attempts.locked_until <= $2
+                RETURNING failures",
+            key,
+            now,
+            now - ATTEMPT_WINDOW
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(failures) = failures else {
+            let locked_until = sqlx::query_scalar!(
+                r#"SELECT locked_until AS "locked_until!" FROM login_attempts WHERE key = $1"#,
+                key
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+            let retry_after = (locked_until - now).num_seconds().max(0);
+            return Err(ServerError::LoginLocked {
+                retry_after: retry_after as u64 + 1,
+            });
+        };
+        sqlx::query!(
+            "UPDATE login_attempts SET locked_until = $2 WHERE key = $1",
+            key,
+            backoff(failures).map(|backoff| now + backoff)
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+pub async fn clear_login_failures(
+    executor: impl PgExecutor<'_>,
+    key: &str,
+) -> Result<(), ServerError> {
+    sqlx::query!("DELETE FROM login_attempts WHERE key = $1", key)
+        .execute(executor)
+        .await?;
+    Ok(())
+}
+
+/// Forgets the successful attempt and one earlier failure, so that the failures of a key shared
+/// by several users wear off without a single successful login resetting them.
+pub async fn decay_login_failures(
+    executor: impl PgExecutor<'_>,
+    key: &str,
+) -> Result<(), ServerError> {
+    sqlx::query!(
+        "UPDATE login_attempts SET failures = GREATEST(failures - 2, 0) WHERE key = $1",
+        key
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,303 @@
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use sqlx::{Acquire, PgConnection, PgExecutor, Postgres};
+
+use crate::database::learn::SqlCard;
+use crate::errors::ServerError;
+use crate::learn::Card;
+use crate::learn::notes::{Note, NoteError, NoteType, Template};
+
+struct SqlNoteType {
+    id: i64,
+    owner_id: i64,
+    name: String,
+    cloze: bool,
+    fields: Vec<String>,
+    templates: Value,
+    created_at: Option<DateTime<Utc>>,
+}
+
+impl TryFrom<SqlNoteType> for NoteType {
+    type Error = ServerError;
+
+    fn try_from(val: SqlNoteType) -> Result<Self, Self::Error> {
+        let templates = serde_json::from_value(val.templates)
+            .map_err(|error| ServerError::Database(error.to_string()))?;
+        Ok(NoteType {
+            id: val.id,
+            owner_id: val.owner_id,
+            name: val.name,
+            cloze: val.cloze,
+            fields: val.fields,
+            templates,
+            created_at: val.created_at,
+        })
+    }
+}
+
+fn templates_value(templates: &[Template]) -> Result<Value, ServerError> {
+    serde_json::to_value(templates).map_err(|error| ServerError::Database(error.to_string()))
+}
+
+pub async fn get_note_types(
+    executor: impl PgExecutor<'_>,
+    owner_id: i64,
+) -> Result<Vec<NoteType>, ServerError> {
+    sqlx::query_as!(
+        SqlNoteType,
+        "SELECT * FROM note_types WHERE owner_id = $1 ORDER BY id",
+        owner_id
+    )
+    .fetch_all(executor)
+    .await?
+    .into_iter()
+    .map(NoteType::try_from)
+    .collect()
+}
+
+pub async fn get_note_type(
+    executor: impl PgExecutor<'_>,
+    id: i64,
+) -> Result<NoteType, ServerError> {
+    sqlx::query_as!(SqlNoteType, "SELECT * FROM note_types WHERE id = $1", id)
+        .fetch_one(executor)
+        .await?
+        .try_into()
+}
+
+/// Owner of a note type, `None` when it doesn't exist.
+pub async fn get_note_type_owner(
+    executor: impl PgExecutor<'_>,
+    id: i64,
+) -> Result<Option<i64>, ServerError> {
+    Ok(
+        sqlx::query_scalar!("SELECT owner_id FROM note_types WHERE id = $1", id)
+            .fetch_optional(executor)
+            .await?,
+    )
+}
+
+pub async fn create_note_type(
+    executor: impl PgExecutor<'_>,
+    owner_id: i64,
+    note_type: &NoteType,
+) -> Result<NoteType, ServerError> {
+    sqlx::query_as!(
+        SqlNoteType,
+        "INSERT INTO note_types (owner_id, name, cloze, fields, templates)
+            VALUES ($1, $2, $3, $4, $5) RETURNING *",
+        owner_id,
+        note_type.name,
+        note_type.cloze,
+        &note_type.fields,
+        templates_value(&note_type.templates)?
+    )
+    .fetch_one(executor)
+    .await?
+    .try_into()
+}
+
+/// Updates a note type and regenerates the cards of its notes. Fields can only be renamed once
+/// notes use the note type, and whether it is a cloze note type can't change.
+pub async fn update_note_type(
+    db: impl Acquire<'_, Database = Postgres>,
+    id: i64,
+    note_type: &NoteType,
+) -> Result<NoteType, ServerError> {
+    let mut tx = db.begin().await?;
+    let previous = get_note_type(&mut *tx, id).await?;
+    let notes = sqlx::query_as!(
+        Note,
+        "SELECT * FROM notes WHERE note_type_id = $1 ORDER BY id",
+        id
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    if previous.cloze != note_type.cloze {
+        return Err(NoteError::ClozeChanged.into());
+    }
+    if !notes.is_empty() && previous.fields.len() != note_type.fields.len() {
+        return Err(NoteError::FieldCount {
+            expected: previous.fields.len(),
+            actual: note_type.fields.len(),
+        }
+        .into());
+    }
+    let updated_note_type: NoteType = sqlx::query_as!(
+        SqlNoteType,
+        "UPDATE note_types SET name = $2, fields = $3, templates = $4 WHERE id = $1 RETURNING *",
+        id,
+        note_type.name,
+        &note_type.fields,
+        templates_value(&note_type.templates)?
+    )
+    .fetch_one(&mut *tx)
+    .await?
+    .try_into()?;
+    for note in notes {
+        generate_cards(&mut tx, &updated_note_type, &note).await?;
+    }
+    tx.commit().await?;
+    Ok(updated_note_type)
+}
+
+pub async fn delete_note_type(executor: impl PgExecutor<'_>, id: i64) -> Result<i64, ServerError> {
+    Ok(
+        sqlx::query_scalar!("DELETE FROM note_types WHERE id = $1 RETURNING id", id)
+            .fetch_one(executor)
+            .await?,
+    )
+}
+
+pub async fn get_notes(
+    executor: impl PgExecutor<'_>,
+    deck_id: i64,
+) -> Result<Vec<Note>, ServerError> {
+    Ok(sqlx::query_as!(
+        Note,
+        "SELECT * FROM notes WHERE deck_id = $1 ORDER BY id",
+        deck_id
+    )
+    .fetch_all(executor)
+    .await?)
+}
+
+pub async fn get_note(executor: impl PgExecutor<'_>, id: i64) -> Result<Note, ServerError> {
+    Ok(
+        sqlx::query_as!(Note, "SELECT * FROM notes WHERE id = $1", id)
+            .fetch_one(executor)
+            .await?,
+    )
+}
+
+/// Owner of the deck of a note, `None` when it doesn't exist.
+pub async fn get_note_owner(
+    executor: impl PgExecutor<'_>,
+    id: i64,
+) -> Result<Option<i64>, ServerError> {
+    Ok(sqlx::query_scalar!(
+        "SELECT decks.owner_id FROM notes JOIN decks ON decks.id = notes.deck_id
+            WHERE notes.id = $1",
+        id
+    )
+    .fetch_optional(executor)
+    .await?)
+}
+
+/// Brings the cards of a note in line with its fields and note type. Cards are matched to their
+/// template by ordinal and updated in place, keeping their review history, while cards whose
+/// template no longer generates anything are deleted.
+async fn generate_cards(
+    conn: &mut PgConnection,
+    note_type: &NoteType,
+    note: &Note,
+) -> Result<Vec<Card>, ServerError> {
+    let rendered_cards = note_type.render_cards(&note.fields);
+    let ords = rendered_cards
+        .iter()
+        .map(|rendered_card| rendered_card.ord)
+        .collect::<Vec<_>>();
+    sqlx::query!(
+        "DELETE FROM cards WHERE note_id = $1 AND NOT (template_ord = ANY($2))",
+        note.id,
+        &ords
+    )
+    .execute(&mut *conn)
+    .await?;
+    let mut cards = Vec::with_capacity(rendered_cards.len());
+    for rendered_card in rendered_cards {
+        let updated_card = sqlx::query_as!(
+            SqlCard,
+            "UPDATE cards SET front = $3, back = $4, typed_answer = $5, tags = $6
+                WHERE note_id = $1 AND template_ord = $2 RETURNING *",
+            note.id,
+            rendered_card.ord,
+            rendered_card.front,
+            rendered_card.back,
+            rendered_card.typed_answer,
+            &note.tags
+        )
+        .fetch_optional(&mut *conn)
+        .await?;
+        let sql_card = match updated_card {
+            Some(sql_card) => sql_card,
+            None => {
+                sqlx::query_as!(
+                    SqlCard,
+                    "INSERT INTO cards (deck_id, front, back, tags, note_id, template_ord, typed_answer)
+                        VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING *",
+                    note.deck_id,
+                    rendered_card.front,
+                    rendered_card.back,
+                    &note.tags,
+                    note.id,
+                    rendered_card.ord,
+                    rendered_card.typed_answer
+                )
+                .fetch_one(&mut *conn)
+                .await?
+            }
+        };
+        cards.push(Card::from(sql_card));
+    }
+    Ok(cards)
+}
+
+/// Creates a note along with its cards, after checking its fields against its note type.
+pub async fn create_note(
+    db: impl Acquire<'_, Database = Postgres>,
+    deck_id: i64,
+    note_type: &NoteType,
+    fields: Vec<String>,
+    tags: Vec<String>,
+) -> Result<(Note, Vec<Card>), ServerError> {
+    note_type.validate_fields(&fields)?;
+    let mut tx = db.begin().await?;
+    let note = sqlx::query_as!(
+        Note,
+        "INSERT INTO notes (deck_id, note_type_id, fields, tags) VALUES ($1, $2, $3, $4)
+            RETURNING *",
+        deck_id,
+        note_type.id,
+        &fields,
+        &tags
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    let cards = generate_cards(&mut tx, note_type, &note).await?;
+    tx.commit().await?;
+    Ok((note, cards))
+}
+
+/// Updates the fields of a note and regenerates its cards.
+pub async fn update_note(
+    db: impl Acquire<'_, Database = Postgres>,
+    id: i64,
+    note_type: &NoteType,
+    fields: Vec<String>,
+    tags: Vec<String>,
+) -> Result<(Note, Vec<Card>), ServerError> {
+    note_type.validate_fields(&fields)?;
+    let mut tx = db.begin().await?;
+    let note = sqlx::query_as!(
+        Note,
+        "UPDATE notes SET fields = $2, tags = $3 WHERE id = $1 RETURNING *",
+        id,
+        &fields,
+        &tags
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    let cards = generate_cards(&mut tx, note_type, &note).await?;
+    tx.commit().await?;
+    Ok((note, cards))
+}
+
+/// Deletes a note along with its cards.
+pub async fn delete_note(executor: impl PgExecutor<'_>, id: i64) -> Result<i64, ServerError> {
+    Ok(
+        sqlx::query_scalar!("DELETE FROM notes WHERE id = $1 RETURNING id", id)
+            .fetch_one(executor)
+            .await?,
+    )
+}
@@ -0,0 +1,223 @@
+use chrono::{DateTime, Utc};
+use hashbrown::{HashMap, HashSet};
+use sqlx::PgExecutor;
+
+use crate::errors::ServerError;
+use crate::learn::Deck;
+use crate::learn::presets::DeckPreset;
+
+pub async fn get_deck_presets(
+    executor: impl PgExecutor<'_>,
+    owner_id: i64,
+) -> Result<Vec<DeckPreset>, ServerError> {
+    Ok(sqlx::query_as!(
+        DeckPreset,
+        "SELECT * FROM deck_presets WHERE owner_id = $1 ORDER BY id",
+        owner_id
+    )
+    .fetch_all(executor)
+    .await?)
+}
+
+pub async fn get_deck_preset(
+    executor: impl PgExecutor<'_>,
+    id: i64,
+) -> Result<DeckPreset, ServerError> {
+    Ok(
+        sqlx::query_as!(DeckPreset, "SELECT * FROM deck_presets WHERE id = $1", id)
+            .fetch_one(executor)
+            .await?,
+    )
+}
+
+/// Preset of the deck of a card, or `None` when the deck doesn't have one.
+/// Owner of a preset, `None` when it doesn't exist.
+pub async fn get_deck_preset_owner(
+    executor: impl PgExecutor<'_>,
+    id: i64,
+) -> Result<Option<i64>, ServerError> {
+    Ok(
+        sqlx::query_scalar!("SELECT owner_id FROM deck_presets WHERE id = $1", id)
+            .fetch_optional(executor)
+            .await?,
+    )
+}
+
+pub async fn get_card_deck_preset(
+    executor: impl PgExecutor<'_>,
+    card_id: i64,
+) -> Result<Option<DeckPreset>, ServerError> {
+    Ok(sqlx::query_as!(
+        DeckPreset,
+        "SELECT deck_presets.* FROM deck_presets
+            JOIN decks ON decks.preset_id = deck_presets.id
+            JOIN cards ON cards.deck_id = decks.id
+            WHERE cards.id = $1",
+        card_id
+    )
+    .fetch_optional(executor)
+    .await?)
+}
+
+pub async fn create_deck_preset(
+    executor: impl PgExecutor<'_>,
+    owner_id: i64,
+    preset: &DeckPreset,
+) -> Result<DeckPreset, ServerError> {
+    Ok(sqlx::query_as!(
+        DeckPreset,
+        "INSERT INTO deck_presets (owner_id, name, new_per_day, reviews_per_day, learning_steps,
+                relearning_steps, desired_retention, leech_threshold, bury_siblings)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) RETURNING *",
+        owner_id,
+        preset.name,
+        preset.new_per_day,
+        preset.reviews_per_day,
+        &preset.learning_steps,
+        &preset.relearning_steps,
+        preset.desired_retention,
+        preset.leech_threshold,
+        preset.bury_siblings
+    )
+    .fetch_one(executor)
+    .await?)
+}
+
+pub async fn update_deck_preset(
+    executor: impl PgExecutor<'_>,
+    id: i64,
+    preset: &DeckPreset,
+) -> Result<DeckPreset, ServerError> {
+    Ok(sqlx::query_as!(
+        DeckPreset,
+        "UPDATE deck_presets
+            SET name = $2, new_per_day = $3, reviews_per_day = $4, learning_steps = $5,
+                relearning_steps = $6, desired_retention = $7, leech_threshold = $8,
+                bury_siblings = $9
+            WHERE id = $1 RETURNING *",
+        id,
+        preset.name,
+        preset.new_per_day,
+        preset.reviews_per_day,
+        &preset.learning_steps,
+        &preset.relearning_steps,
+        preset.desired_retention,
+        preset.leech_threshold,
+        preset.bury_siblings
+    )
+    .fetch_one(executor)
+    .await?)
+}
+
+/// Deletes a preset, the decks using it going back to the default options.
+pub async fn delete_deck_preset(
+    executor: impl PgExecutor<'_>,
+    id: i64,
+) -> Result<i64, ServerError> {
+    Ok(
+        sqlx::query_scalar!("DELETE FROM deck_presets WHERE id = $1 RETURNING id", id)
+            .fetch_one(executor)
+            .await?,
+    )
+}
+
+pub async fn set_deck_preset(
+    executor: impl PgExecutor<'_>,
+    deck_id: i64,
+    preset_id: Option<i64>,
+) -> Result<Deck, ServerError> {
+    Ok(sqlx::query_as!(
+        Deck,
+        "UPDATE decks SET preset_id = $2 WHERE id = $1 RETURNING *",
+        deck_id,
+        preset_id
+    )
+    .fetch_one(executor)
+    .await?)
+}
+
+/// Presets of the decks of a user, by deck. Decks without preset are left out.
+pub async fn get_owner_deck_presets(
+    executor: impl PgExecutor<'_>,
+    owner_id: i64,
+) -> Result<HashMap<i64, DeckPreset>, ServerError> {
+    let rows = sqlx::query!(
+        r#"SELECT decks.id AS "deck_id!", deck_presets.* FROM decks
+            JOIN deck_presets ON deck_presets.id = decks.preset_id
+            WHERE decks.owner_id = $1"#,
+        owner_id
+    )
+    .fetch_all(executor)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            let preset = DeckPreset {
+                id: row.id,
+                owner_id: row.owner_id,
+                name: row.name,
+                new_per_day: row.new_per_day,
+                reviews_per_day: row.reviews_per_day,
+                learning_steps: row.learning_steps,
+                relearning_steps: row.relearning_steps,
+                desired_retention: row.desired_retention,
+                leech_threshold: row.leech_threshold,
+                bury_siblings: row.bury_siblings,
+                created_at: row.created_at,
+            };
+            (row.deck_id, preset)
+        })
+        .collect())
+}
+
+/// New cards and reviews studied in each deck of a user since `since`, by deck.
+pub async fn get_studied_counts(
+    executor: impl PgExecutor<'_>,
+    owner_id: i64,
+    since: DateTime<Utc>,
+) -> Result<HashMap<i64, (usize, usize)>, ServerError> {
+    let rows = sqlx::query!(
+        r#"SELECT cards.deck_id,
+                COUNT(*) FILTER (WHERE review_logs.state = 0) AS "new_cards!",
+                COUNT(*) FILTER (WHERE review_logs.state = 2) AS "reviews!"
+            FROM review_logs
+            JOIN cards ON cards.id = review_logs.card_id
+            JOIN decks ON decks.id = cards.deck_id
+            WHERE decks.owner_id = $1 AND review_logs.reviewed_at >= $2
+            GROUP BY cards.deck_id"#,
+        owner_id,
+        since
+    )
+    .fetch_all(executor)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            let counts = (
+                usize::try_from(row.new_cards).unwrap_or_default(),
+                usize::try_from(row.reviews).unwrap_or_default(),
+            );
+            (row.deck_id, counts)
+        })
+        .collect())
+}
+
+/// Notes of a user with a card studied since `since`.
+pub async fn get_studied_notes(
+    executor: impl PgExecutor<'_>,
+    owner_id: i64,
+    since: DateTime<Utc>,
+) -> Result<HashSet<i64>, ServerError> {
+    let note_ids = sqlx::query_scalar!(
+        r#"SELECT DISTINCT cards.note_id AS "note_id!" FROM review_logs
+            JOIN cards ON cards.id = review_logs.card_id
+            JOIN decks ON decks.id = cards.deck_id
+            WHERE decks.owner_id = $1 AND review_logs.reviewed_at >= $2
+                AND cards.note_id IS NOT NULL"#,
+        owner_id,
+        since
+    )
+    .fetch_all(executor)
+    .await?;
+    Ok(note_ids.into_iter().collect())
+}
@@ -0,0 +1,164 @@
+use hashbrown::HashSet;
+use sqlx::{Acquire, FromRow, PgExecutor, Postgres};
+
+use crate::errors::ServerError;
+use crate::users::permissions::Permission;
+use crate::users::roles::Role;
+
+#[derive(FromRow)]
+pub struct SqlRole {
+    pub id: i64,
+    pub name: String,
+}
+
+#[derive(FromRow)]
+pub struct SqlRolePermission {
+    pub role_id: i64,
+    pub token: String,
+}
+
+pub async fn get_roles(
+    db: impl Acquire<'_, Database = Postgres>,
+) -> Result<Vec<Role>, ServerError> {
+    let mut conn = db.acquire().await?;
+    let mut roles = sqlx::query_as!(SqlRole, "SELECT * FROM roles ORDER BY id")
+        .fetch_all(&mut *conn)
+        .await?
+        .into_iter()
+        .map(|sql_role| Role {
+            id: sql_role.id,
+            name: sql_role.name,
+            permissions: HashSet::new(),
+        })
+        .collect::<Vec<_>>();
+    for sql_role_perm in sqlx::query_as!(SqlRolePermission, "SELECT * FROM role_permissions")
+        .fetch_all(&mut *conn)
+        .await?
+    {
+        let Ok(permission) = sql_role_perm.token.parse() else {
+            continue;
+        };
+        if let Some(role) = roles
+            .iter_mut()
+            .find(|role| role.id == sql_role_perm.role_id)
+        {
+            role.permissions.insert(permission);
+        }
+    }
+    Ok(roles)
+}
+
+/// Permissions a role grants, empty when it doesn't exist.
+pub async fn get_role_permissions(
+    executor: impl PgExecutor<'_>,
+    role_id: i64,
+) -> Result<Vec<Permission>, ServerError> {
+    Ok(sqlx::query_scalar!(
+        "SELECT token FROM role_permissions WHERE role_id = $1",
+        role_id
+    )
+    .fetch_all(executor)
+    .await?
+    .into_iter()
+    .filter_map(|token| token.parse().ok())
+    .collect())
+}
+
+pub async fn get_user_roles(
+    executor: impl PgExecutor<'_>,
+    user_id: i64,
+) -> Result<Vec<String>, ServerError> {
+    Ok(sqlx::query_scalar!(
+        "SELECT roles.name FROM user_roles
+            JOIN roles ON roles.id = user_roles.role_id
+            WHERE user_roles.user_id = $1",
+        user_id
+    )
+    .fetch_all(executor)
+    .await?)
+}
+
+pub async fn grant_role(
+    executor: impl PgExecutor<'_>,
+    user_id: i64,
+    role_id: i64,
+) -> Result<(), ServerError> {
+    sqlx::query!(
+        "INSERT INTO user_roles (user_id, role_id) VALUES ($1, $2)
+            ON CONFLICT (user_id, role_id) DO NOTHING",
+        user_id,
+        role_id
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+/// Fails when no role is named `name`.
+pub async fn grant_role_by_name(
+    executor: impl PgExecutor<'_>,
+    user_id: i64,
+    name: &str,
+) -> Result<(), ServerError> {
+    sqlx::query_scalar!(
+        r#"WITH role AS (SELECT id FROM roles WHERE name = $2),
+            granted AS (
+                INSERT INTO user_roles (user_id, role_id) SELECT $1, id FROM role
+                ON CONFLICT (user_id, role_id) DO NOTHING
+            )
+            SELECT id AS "id!" FROM role"#,
+        user_id,
+        name
+    )
+    .fetch_one(executor)
+    .await?;
+    Ok(())
+}
+
+pub async fn revoke_role(
+    executor: impl PgExecutor<'_>,
+    user_id: i64,
+    role_id: i64,
+) -> Result<(), ServerError> {
+    sqlx::query!(
+        "DELETE FROM user_roles WHERE user_id = $1 AND role_id = $2",
+        user_id,
+        role_id
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+pub async fn grant_permission(
+    executor: impl PgExecutor<'_>,
+    user_id: i64,
+    permission: Permission,
+) -> Result<(), ServerError> {
+    sqlx::query!(
+        "INSERT INTO user_permissions (user_id, token) VALUES ($1, $2)
+            ON CONFLICT (user_id, token) DO NOTHING",
+        user_id,
+        permission.as_str()
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+/// Revokes a permission granted directly to a user. Permissions granted by the roles of the user
+/// are kept, the role itself has to be revoked for them to be removed.
+pub async fn revoke_permission(
+    executor: impl PgExecutor<'_>,
+    user_id: i64,
+    permission: Permission,
+) -> Result<(), ServerError> {
+    sqlx::query!(
+        "DELETE FROM user_permissions WHERE user_id = $1 AND token = $2",
+        user_id,
+        permission.as_str()
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,195 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgExecutor;
+
+use crate::database::learn::SqlReviewLog;
+use crate::errors::ServerError;
+use crate::learn::optimizer::OptimizationResult;
+use crate::learn::{ReviewLog, SchedulerSettings};
+
+struct SqlSchedulerSettings {
+    desired_retention: f64,
+    weights: Option<Vec<f64>>,
+    review_count: Option<i64>,
+    log_loss_before: Option<f64>,
+    log_loss_after: Option<f64>,
+    optimized_at: Option<DateTime<Utc>>,
+    timezone: String,
+    day_start_hour: i16,
+}
+
+impl From<SqlSchedulerSettings> for SchedulerSettings {
+    fn from(val: SqlSchedulerSettings) -> Self {
+        let optimization = match (
+            val.weights,
+            val.review_count,
+            val.log_loss_before,
+            val.log_loss_after,
+            val.optimized_at,
+        ) {
+            (
+                Some(weights),
+                Some(review_count),
+                Some(log_loss_before),
+                Some(log_loss_after),
+                Some(optimized_at),
+            ) => Some(OptimizationResult {
+                weights,
+                review_count: review_count as usize,
+                log_loss_before,
+                log_loss_after,
+                optimized_at,
+            }),
+            _ => None,
+        };
+        SchedulerSettings {
+            desired_retention: val.desired_retention,
+            optimization,
+            timezone: val.timezone,
+            day_start_hour: u32::try_from(val.day_start_hour).unwrap_or_default(),
+        }
+    }
+}
+
+pub async fn get_scheduler_settings(
+    executor: impl PgExecutor<'_>,
+    user_id: i64,
+) -> Result<SchedulerSettings, ServerError> {
+    let settings = sqlx::query_as!(
+        SqlSchedulerSettings,
+        "SELECT desired_retention, weights, review_count, log_loss_before, log_loss_after,
+                optimized_at, timezone, day_start_hour
+            FROM scheduler_settings WHERE user_id = $1",
+        user_id
+    )
+    .fetch_optional(executor)
+    .await?;
+    Ok(settings.map(SchedulerSettings::from).unwrap_or_default())
+}
+
+/// Settings of the owner of a card, used when reviewing it.
+pub async fn get_card_scheduler_settings(
+    executor: impl PgExecutor<'_>,
+    card_id: i64,
+) -> Result<SchedulerSettings, ServerError> {
+    let settings = sqlx::query_as!(
+        SqlSchedulerSettings,
+        "SELECT desired_retention, weights, review_count, log_loss_before, log_loss_after,
+                optimized_at, timezone, day_start_hour
+            FROM scheduler_settings
+            JOIN decks ON decks.owner_id = scheduler_settings.user_id
+            JOIN cards ON cards.deck_id = decks.id
+            WHERE cards.id = $1",
+        card_id
+    )
+    .fetch_optional(executor)
+    .await?;
+    Ok(settings.map(SchedulerSettings::from).unwrap_or_default())
+}
+
+pub async fn update_desired_retention(
+    executor: impl PgExecutor<'_>,
+    user_id: i64,
+    desired_retention: f64,
+) -> Result<SchedulerSettings, ServerError> {
+    Ok(sqlx::query_as!(
+        SqlSchedulerSettings,
+        "INSERT INTO scheduler_settings (user_id, desired_retention) VALUES ($1, $2)
+            ON CONFLICT (user_id) DO UPDATE SET desired_retention = EXCLUDED.desired_retention
+            RETURNING desired_retention, weights, review_count, log_loss_before, log_loss_after,
+                optimized_at, timezone, day_start_hour",
+        user_id,
+        desired_retention
+    )
+    .fetch_one(executor)
+    .await?
+    .into())
+}
+
+/// Sets the time zone of a user, and the local hour at which their study days start.
+pub async fn update_day_boundary(
+    executor: impl PgExecutor<'_>,
+    user_id: i64,
+    timezone: &str,
+    day_start_hour: u32,
+) -> Result<SchedulerSettings, ServerError> {
+    Ok(sqlx::query_as!(
+        SqlSchedulerSettings,
+        "INSERT INTO scheduler_settings (user_id, timezone, day_start_hour) VALUES ($1, $2, $3)
+            ON CONFLICT (user_id) DO UPDATE SET
+                timezone = EXCLUDED.timezone,
+                day_start_hour = EXCLUDED.day_start_hour
+            RETURNING desired_retention, weights, review_count, log_loss_before, log_loss_after,
+                optimized_at, timezone, day_start_hour",
+        user_id,
+        timezone,
+        i16::try_from(day_start_hour).unwrap_or_default()
+    )
+    .fetch_one(executor)
+    .await?
+    .into())
+}
+
+pub async fn save_optimization(
+    executor: impl PgExecutor<'_>,
+    user_id: i64,
+    optimization: &OptimizationResult,
+) -> Result<SchedulerSettings, ServerError> {
+    Ok(sqlx::query_as!(
+        SqlSchedulerSettings,
+        "INSERT INTO scheduler_settings
+                (user_id, weights, review_count, log_loss_before, log_loss_after, optimized_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (user_id) DO UPDATE SET
+                weights = EXCLUDED.weights,
+                review_count = EXCLUDED.review_count,
+                log_loss_before = EXCLUDED.log_loss_before,
+                log_loss_after = EXCLUDED.log_loss_after,
+                optimized_at = EXCLUDED.optimized_at
+            RETURNING desired_retention, weights, review_count, log_loss_before, log_loss_after,
+                optimized_at, timezone, day_start_hour",
+        user_id,
+        &optimization.weights,
+        optimization.review_count as i64,
+        optimization.log_loss_before,
+        optimization.log_loss_after,
+        optimization.optimized_at
+    )
+    .fetch_one(executor)
+    .await?
+    .into())
+}
+
+/// The `limit` latest review logs of the cards of a user, grouped by card and sorted by date.
+pub async fn get_review_histories(
+    executor: impl PgExecutor<'_>,
+    owner_id: i64,
+    limit: i64,
+) -> Result<Vec<Vec<ReviewLog>>, ServerError> {
+    let sql_review_logs = sqlx::query_as!(
+        SqlReviewLog,
+        r#"SELECT id AS "id!", card_id AS "card_id!", rating AS "rating!", state AS "state!",
+                elapsed_days AS "elapsed_days!", scheduled_days AS "scheduled_days!",
+                reviewed_at AS "reviewed_at!", time_taken_ms AS "time_taken_ms!"
+            FROM (
+                SELECT review_logs.* FROM review_logs
+                    JOIN cards ON cards.id = review_logs.card_id
+                    JOIN decks ON decks.id = cards.deck_id
+                    WHERE decks.owner_id = $1
+                    ORDER BY review_logs.reviewed_at DESC
+                    LIMIT $2
+            ) AS latest_review_logs
+            ORDER BY card_id, reviewed_at"#,
+        owner_id,
+        limit
+    )
+    .fetch_all(executor)
+    .await?;
+    let mut histories: Vec<Vec<ReviewLog>> = Vec::new();
+    for review_log in sql_review_logs.into_iter().map(ReviewLog::from) {
+        match histories.last_mut() {
+            Some(history) if history[0].card_id == review_log.card_id => history.push(review_log),
+            _ => histories.push(vec![review_log]),
+        }
+    }
+    Ok(histories)
+}
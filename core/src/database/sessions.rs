@@ -0,0 +1,174 @@
+use chrono::{Duration, Utc};
+use sqlx::{Acquire, PgExecutor, Postgres};
+
+use crate::errors::ServerError;
+use crate::users::sessions::UserSession;
+
+/// Time during which further requests of a session aren't recorded, so that sessions aren't
+/// written on every request.
+const LAST_SEEN_INTERVAL: Duration = Duration::minutes(1);
+
+/// Records a request made by a logged in session and returns whether the session is still
+/// active, i.e. it wasn't revoked. The time it was last seen at is only updated once per
+/// [`LAST_SEEN_INTERVAL`], unless its address changed. Sessions missing from the session store
+/// aren't recorded again, so that removing the record of a revoked session doesn't restore it.
+pub async fn touch_session(
+    db: impl Acquire<'_, Database = Postgres>,
+    session_id: &str,
+    user_id: i64,
+    client_label: &str,
+    client_address: Option<String>,
+) -> Result<bool, ServerError> {
+    let mut conn = db.acquire().await?;
+    let active = sqlx::query_scalar!(
+        r#"INSERT INTO user_sessions (session_id, user_id, client_label, client_address)
+            SELECT $1, $2, $3, $4
+            WHERE EXISTS (SELECT 1 FROM axum_sessions WHERE axum_sessions.id = $1)
+            ON CONFLICT (session_id) DO UPDATE SET
+                last_seen_at = NOW(),
+                client_address = EXCLUDED.client_address
+            WHERE user_sessions.last_seen_at < $5
+                OR user_sessions.client_address IS DISTINCT FROM EXCLUDED.client_address
+            RETURNING revoked_at IS NULL AS "active!""#,
+        session_id,
+        user_id,
+        client_label,
+        client_address,
+        Utc::now() - LAST_SEEN_INTERVAL
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+    match active {
+        Some(active) => Ok(active),
+        // The session was seen recently, so it is left untouched, or it isn't stored anymore
+        None => is_session_active(&mut *conn, session_id).await,
+    }
+}
+
+/// Whether the session is logged in and wasn't revoked.
+pub async fn is_session_active(
+    executor: impl PgExecutor<'_>,
+    session_id: &str,
+) -> Result<bool, ServerError> {
+    Ok(sqlx::query_scalar!(
+        r#"SELECT EXISTS (
+                SELECT 1 FROM user_sessions WHERE session_id = $1 AND revoked_at IS NULL
+            ) AS "active!""#,
+        session_id
+    )
+    .fetch_one(executor)
+    .await?)
+}
+
+/// Sessions of a user that are still stored by the session store, i.e. that didn't expire, and
+/// weren't revoked.
+pub async fn get_user_sessions(
+    executor: impl PgExecutor<'_>,
+    user_id: i64,
+    current_session_id: &str,
+) -> Result<Vec<UserSession>, ServerError> {
+    Ok(sqlx::query_as!(
+        UserSession,
+        r#"SELECT user_sessions.id, client_label, client_address, created_at, last_seen_at,
+                session_id = $2 AS "current!"
+            FROM user_sessions
+            JOIN axum_sessions ON axum_sessions.id = user_sessions.session_id
+            WHERE user_id = $1 AND revoked_at IS NULL
+                AND (axum_sessions.expires IS NULL
+                    OR axum_sessions.expires > EXTRACT(EPOCH FROM NOW()))
+            ORDER BY last_seen_at DESC"#,
+        user_id,
+        current_session_id
+    )
+    .fetch_all(executor)
+    .await?)
+}
+
+/// Revoked sessions are logged out on their next request.
+pub async fn revoke_session(
+    executor: impl PgExecutor<'_>,
+    user_id: i64,
+    id: i64,
+) -> Result<i64, ServerError> {
+    Ok(sqlx::query_scalar!(
+        "UPDATE user_sessions SET revoked_at = NOW()
+            WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL
+            RETURNING id",
+        id,
+        user_id
+    )
+    .fetch_one(executor)
+    .await?)
+}
+
+/// Revokes every session of a user, e.g. once their password can't be trusted anymore.
+pub async fn revoke_user_sessions(
+    executor: impl PgExecutor<'_>,
+    user_id: i64,
+) -> Result<Vec<i64>, ServerError> {
+    Ok(sqlx::query_scalar!(
+        "UPDATE user_sessions SET revoked_at = NOW()
+            WHERE user_id = $1 AND revoked_at IS NULL
+            RETURNING id",
+        user_id
+    )
+    .fetch_all(executor)
+    .await?)
+}
+
+pub async fn revoke_other_sessions(
+    executor: impl PgExecutor<'_>,
+    user_id: i64,
+    current_session_id: &str,
+) -> Result<Vec<i64>, ServerError> {
+    Ok(sqlx::query_scalar!(
+        "UPDATE user_sessions SET revoked_at = NOW()
+            WHERE user_id = $1 AND session_id <> $2 AND revoked_at IS NULL
+            RETURNING id",
+        user_id,
+        current_session_id
+    )
+    .fetch_all(executor)
+    .await?)
+}
+
+/// Removes the records of revoked sessions along with the sessions themselves, and the records
+/// of sessions that expired, returning the ids of the removed sessions.
+pub async fn delete_stale_sessions(
+    db: impl Acquire<'_, Database = Postgres>,
+) -> Result<Vec<String>, ServerError> {
+    let mut tx = db.begin().await?;
+    sqlx::query!(
+        "DELETE FROM axum_sessions
+            WHERE id IN (SELECT session_id FROM user_sessions WHERE revoked_at IS NOT NULL)"
+    )
+    .execute(&mut *tx)
+    .await?;
+    let session_ids = sqlx::query_scalar!(
+        "DELETE FROM user_sessions
+            WHERE revoked_at IS NOT NULL OR NOT EXISTS (
+                SELECT 1 FROM axum_sessions
+                WHERE axum_sessions.id = user_sessions.session_id
+                    AND (axum_sessions.expires IS NULL
+                        OR axum_sessions.expires > EXTRACT(EPOCH FROM NOW()))
+            )
+            RETURNING session_id"
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(session_ids)
+}
+
+pub async fn delete_sessions(
+    executor: impl PgExecutor<'_>,
+    session_ids: &[String],
+) -> Result<(), ServerError> {
+    sqlx::query!(
+        "DELETE FROM user_sessions WHERE session_id = ANY($1)",
+        session_ids
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,100 @@
+use chrono::{DateTime, Duration, Utc};
+use sqlx::{Acquire, Postgres};
+
+use crate::database::scheduler::get_scheduler_settings;
+use crate::errors::ServerError;
+use crate::learn::stats::{CardMaturity, DayReviews, DueForecast, MATURE_INTERVAL, StudyStats};
+
+/// Reviews of a user over the `day_count` study days up to `now`, days starting at the day
+/// boundary of the user in their time zone.
+pub async fn get_study_stats(
+    db: impl Acquire<'_, Database = Postgres>,
+    owner_id: i64,
+    now: DateTime<Utc>,
+    day_count: u32,
+) -> Result<StudyStats, ServerError> {
+    let mut conn = db.acquire().await?;
+    let settings = get_scheduler_settings(&mut *conn, owner_id).await?;
+    let today = settings.study_date(now);
+    // Some margin is kept for days shortened or lengthened by a clock change
+    let since = settings.day_start(now) - Duration::days(i64::from(day_count)) - Duration::hours(2);
+
+    let reviews = sqlx::query_as!(
+        DayReviews,
+        r#"SELECT date AS "date!",
+                COUNT(*) AS "reviews!",
+                COUNT(*) FILTER (WHERE state = 2) AS "graded!",
+                COUNT(*) FILTER (WHERE state = 2 AND rating > 1) AS "passed!",
+                SUM(time_taken_ms)::BIGINT AS "time_taken_ms!"
+            FROM (
+                SELECT ((review_logs.reviewed_at AT TIME ZONE $2) - make_interval(hours => $3))::date
+                        AS date,
+                    review_logs.rating, review_logs.state, review_logs.time_taken_ms
+                FROM review_logs
+                JOIN cards ON cards.id = review_logs.card_id
+                JOIN decks ON decks.id = cards.deck_id
+                WHERE decks.owner_id = $1 AND review_logs.reviewed_at >= $4
+            ) AS reviews
+            GROUP BY date ORDER BY date"#,
+        owner_id,
+        settings.tz().name(),
+        i32::try_from(settings.day_start_hour).unwrap_or_default(),
+        since
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let maturity = sqlx::query_as!(
+        CardMaturity,
+        r#"SELECT COUNT(*) FILTER (WHERE cards.state = 0) AS "new!",
+                COUNT(*) FILTER (WHERE cards.state IN (1, 3)) AS "learning!",
+                COUNT(*) FILTER (WHERE cards.state = 2 AND cards.scheduled_days < $2) AS "young!",
+                COUNT(*) FILTER (WHERE cards.state = 2 AND cards.scheduled_days >= $2) AS "mature!"
+            FROM cards
+            JOIN decks ON decks.id = cards.deck_id
+            WHERE decks.owner_id = $1"#,
+        owner_id,
+        MATURE_INTERVAL
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(StudyStats::new(today, day_count, reviews, maturity))
+}
+
+/// Cards of a user due on each of the `day_count` study days from `now`. New cards are left out,
+/// since when they are studied depends on the daily limits rather than on their due date.
+pub async fn get_due_forecast(
+    db: impl Acquire<'_, Database = Postgres>,
+    owner_id: i64,
+    now: DateTime<Utc>,
+    day_count: u32,
+) -> Result<Vec<DueForecast>, ServerError> {
+    let mut conn = db.acquire().await?;
+    let settings = get_scheduler_settings(&mut *conn, owner_id).await?;
+    let today = settings.study_date(now);
+    let until = settings.day_start(now) + Duration::days(i64::from(day_count)) + Duration::hours(2);
+
+    let due = sqlx::query!(
+        r#"SELECT ((GREATEST(cards.due, $2) AT TIME ZONE $3) - make_interval(hours => $4))::date
+                    AS "date!",
+                COUNT(*) AS "due!"
+            FROM cards
+            JOIN decks ON decks.id = cards.deck_id
+            WHERE decks.owner_id = $1 AND cards.state <> 0 AND cards.due < $5
+            GROUP BY 1"#,
+        owner_id,
+        now,
+        settings.tz().name(),
+        i32::try_from(settings.day_start_hour).unwrap_or_default(),
+        until
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(DueForecast::days(
+        today,
+        day_count,
+        due.into_iter().map(|row| (row.date, row.due)),
+    ))
+}
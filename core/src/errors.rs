@@ -6,6 +6,12 @@ use server_fn::error::{FromServerFnError, ServerFnErrorErr};
 use sqlx::Error as SqlxError;
 use thiserror::Error;
 
+use crate::learn::anki::AnkiError;
+use crate::learn::delimited::DelimitedError;
+use crate::learn::notes::NoteError;
+use crate::learn::optimizer::OptimizationError;
+use crate::learn::presets::PresetError;
+
 #[derive(Error, Debug, Serialize, Deserialize)]
 #[non_exhaustive]
 pub enum ServerError {
@@ -19,8 +25,34 @@ pub enum ServerError {
     PasswordHash(String),
     #[error("wrong username or password")]
     WrongLogin,
+    #[error("username is already taken")]
+    UsernameTaken,
+    #[error("too many failed login attempts, retry in {retry_after} seconds")]
+    LoginLocked { retry_after: u64 },
+    #[error("wrong password")]
+    WrongPassword,
+    #[error("a password reset is required for this account")]
+    PasswordResetRequired,
+    #[error("invalid or expired password reset token")]
+    InvalidResetToken,
     #[error("authentication required or missing permissions")]
     Unauthorized,
+    #[error("import failed: {0}")]
+    Import(String),
+    #[error("invalid image: {0}")]
+    InvalidImage(String),
+    #[error("invalid note: {0}")]
+    InvalidNote(String),
+    #[error("optimization failed: {0}")]
+    Optimization(String),
+    #[error("invalid deck preset: {0}")]
+    InvalidPreset(String),
+    #[error("too many reviews to sync at once, at most {limit} can be")]
+    TooManyReviews { limit: usize },
+    #[error("field `{name}` is larger than {limit} bytes")]
+    FieldTooLarge { name: String, limit: usize },
+    #[error("request is larger than {limit} bytes")]
+    RequestTooLarge { limit: usize },
 }
 
 impl FromServerFnError for ServerError {
@@ -48,3 +80,33 @@ impl From<PasswordHashError> for ServerError {
         Self::PasswordHash(value.to_string())
     }
 }
+
+impl From<AnkiError> for ServerError {
+    fn from(value: AnkiError) -> Self {
+        Self::Import(value.to_string())
+    }
+}
+
+impl From<DelimitedError> for ServerError {
+    fn from(value: DelimitedError) -> Self {
+        Self::Import(value.to_string())
+    }
+}
+
+impl From<NoteError> for ServerError {
+    fn from(value: NoteError) -> Self {
+        Self::InvalidNote(value.to_string())
+    }
+}
+
+impl From<OptimizationError> for ServerError {
+    fn from(value: OptimizationError) -> Self {
+        Self::Optimization(value.to_string())
+    }
+}
+
+impl From<PresetError> for ServerError {
+    fn from(value: PresetError) -> Self {
+        Self::InvalidPreset(value.to_string())
+    }
+}
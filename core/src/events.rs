@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+
+use crate::learn::Deck;
+use crate::users::User;
+use crate::users::permissions::Permission;
+
+/// Change pushed by the server to the clients subscribed to its events.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ServerEvent {
+    User(ItemEvent<User>),
+    Deck {
+        owner_id: i64,
+        event: ItemEvent<Deck>,
+    },
+    /// Some events were missed, so whatever the subscriber shows has to be fetched again.
+    Lagged,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ItemEvent<T> {
+    Created(T),
+    Updated(T),
+    Deleted(i64),
+}
+
+impl ServerEvent {
+    pub fn deck(owner_id: i64, event: ItemEvent<Deck>) -> Self {
+        ServerEvent::Deck { owner_id, event }
+    }
+
+    /// Whether `user` is allowed to receive the event, following the permissions of the server
+    /// functions fetching the same items.
+    pub fn is_visible_to(&self, user: &User) -> bool {
+        match self {
+            ServerEvent::User(_) => user.has_permission(Permission::UsersView),
+            ServerEvent::Deck { owner_id, .. } => *owner_id == user.id,
+            ServerEvent::Lagged => true,
+        }
+    }
+}
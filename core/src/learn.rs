@@ -0,0 +1,459 @@
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Timelike, Utc};
+use chrono_tz::Tz;
+use hashbrown::{HashMap, HashSet};
+use rs_fsrs::{FSRS, Parameters};
+use serde::{Deserialize, Serialize};
+
+use crate::learn::optimizer::OptimizationResult;
+use crate::learn::presets::{DeckPreset, QueueLimits};
+
+pub mod anki;
+pub mod content;
+pub mod delimited;
+pub mod fsrs;
+pub mod notes;
+pub mod optimizer;
+pub mod presets;
+pub mod stats;
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Deck {
+    pub id: i64,
+    pub owner_id: i64,
+    pub name: String,
+    pub description: String,
+    pub created_at: Option<DateTime<Utc>>,
+    /// Preset whose options apply to the deck, or `None` to use the default options.
+    #[serde(default)]
+    pub preset_id: Option<i64>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CardState {
+    #[default]
+    New,
+    Learning,
+    Review,
+    Relearning,
+}
+
+impl From<i16> for CardState {
+    fn from(value: i16) -> Self {
+        match value {
+            1 => CardState::Learning,
+            2 => CardState::Review,
+            3 => CardState::Relearning,
+            _ => CardState::New,
+        }
+    }
+}
+
+impl From<CardState> for i16 {
+    fn from(value: CardState) -> Self {
+        match value {
+            CardState::New => 0,
+            CardState::Learning => 1,
+            CardState::Review => 2,
+            CardState::Relearning => 3,
+        }
+    }
+}
+
+impl From<rs_fsrs::State> for CardState {
+    fn from(value: rs_fsrs::State) -> Self {
+        match value {
+            rs_fsrs::State::New => CardState::New,
+            rs_fsrs::State::Learning => CardState::Learning,
+            rs_fsrs::State::Review => CardState::Review,
+            rs_fsrs::State::Relearning => CardState::Relearning,
+        }
+    }
+}
+
+impl From<CardState> for rs_fsrs::State {
+    fn from(value: CardState) -> Self {
+        match value {
+            CardState::New => rs_fsrs::State::New,
+            CardState::Learning => rs_fsrs::State::Learning,
+            CardState::Review => rs_fsrs::State::Review,
+            CardState::Relearning => rs_fsrs::State::Relearning,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Rating {
+    Again,
+    Hard,
+    Good,
+    Easy,
+}
+
+impl From<i16> for Rating {
+    fn from(value: i16) -> Self {
+        match value {
+            2 => Rating::Hard,
+            3 => Rating::Good,
+            4 => Rating::Easy,
+            _ => Rating::Again,
+        }
+    }
+}
+
+impl From<Rating> for i16 {
+    fn from(value: Rating) -> Self {
+        match value {
+            Rating::Again => 1,
+            Rating::Hard => 2,
+            Rating::Good => 3,
+            Rating::Easy => 4,
+        }
+    }
+}
+
+impl From<Rating> for rs_fsrs::Rating {
+    fn from(value: Rating) -> Self {
+        match value {
+            Rating::Again => rs_fsrs::Rating::Again,
+            Rating::Hard => rs_fsrs::Rating::Hard,
+            Rating::Good => rs_fsrs::Rating::Good,
+            Rating::Easy => rs_fsrs::Rating::Easy,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Card {
+    pub id: i64,
+    pub deck_id: i64,
+    pub front: String,
+    pub back: String,
+    pub tags: Vec<String>,
+    pub extra: Vec<String>,
+    pub due: DateTime<Utc>,
+    pub stability: f64,
+    pub difficulty: f64,
+    pub elapsed_days: i64,
+    pub scheduled_days: i64,
+    pub reps: i32,
+    pub lapses: i32,
+    pub state: CardState,
+    pub last_review: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+    /// Note the card was generated from along with the ordinal of its template, or `None` for
+    /// cards written directly.
+    #[serde(default)]
+    pub note_id: Option<i64>,
+    #[serde(default)]
+    pub template_ord: Option<i32>,
+    #[serde(default)]
+    pub typed_answer: Option<String>,
+    /// Learning or relearning step the card is at, see [`presets::DeckPreset::apply_steps`].
+    #[serde(default)]
+    pub learning_step: i32,
+}
+
+impl Card {
+    pub fn new(front: String, back: String, tags: Vec<String>, now: DateTime<Utc>) -> Self {
+        Self {
+            id: 0,
+            deck_id: 0,
+            front,
+            back,
+            tags,
+            extra: Vec::new(),
+            due: now,
+            stability: 0.,
+            difficulty: 0.,
+            elapsed_days: 0,
+            scheduled_days: 0,
+            reps: 0,
+            lapses: 0,
+            state: CardState::New,
+            last_review: None,
+            created_at: None,
+            note_id: None,
+            template_ord: None,
+            typed_answer: None,
+            learning_step: 0,
+        }
+    }
+
+    fn to_fsrs(&self) -> rs_fsrs::Card {
+        rs_fsrs::Card {
+            due: self.due,
+            stability: self.stability,
+            difficulty: self.difficulty,
+            elapsed_days: self.elapsed_days,
+            scheduled_days: self.scheduled_days,
+            reps: self.reps,
+            lapses: self.lapses,
+            state: self.state.into(),
+            last_review: self.last_review.unwrap_or(self.due),
+        }
+    }
+
+    /// Computes the next FSRS state of this card for the given rating and returns the review log
+    /// describing the card as it was before the review. The log isn't persisted yet, so its `id`
+    /// is left at `0`.
+    pub fn review(&mut self, rating: Rating, now: DateTime<Utc>) -> ReviewLog {
+        self.review_with(&scheduler(), rating, now)
+    }
+
+    /// Same as [`Card::review`], scheduling the card with the settings of its owner.
+    pub fn review_with(
+        &mut self,
+        scheduler: &FSRS,
+        rating: Rating,
+        now: DateTime<Utc>,
+    ) -> ReviewLog {
+        let scheduling_info = scheduler.next(self.to_fsrs(), now, rating.into());
+        let next = scheduling_info.card;
+        self.due = next.due;
+        self.stability = next.stability;
+        self.difficulty = next.difficulty;
+        self.elapsed_days = next.elapsed_days;
+        self.scheduled_days = next.scheduled_days;
+        self.reps = next.reps;
+        self.lapses = next.lapses;
+        self.state = next.state.into();
+        self.last_review = Some(now);
+
+        let log = scheduling_info.review_log;
+        ReviewLog {
+            id: 0,
+            card_id: self.id,
+            rating,
+            state: log.state.into(),
+            elapsed_days: log.elapsed_days,
+            scheduled_days: log.scheduled_days,
+            reviewed_at: log.reviewed_date,
+            time_taken_ms: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewLog {
+    pub id: i64,
+    pub card_id: i64,
+    pub rating: Rating,
+    pub state: CardState,
+    pub elapsed_days: i64,
+    pub scheduled_days: i64,
+    pub reviewed_at: DateTime<Utc>,
+    /// Time spent answering the card, `0` when unknown.
+    #[serde(default)]
+    pub time_taken_ms: i64,
+}
+
+/// Review made while the server was unreachable, sent to it once it is reachable again.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct OfflineReview {
+    pub card_id: i64,
+    pub rating: Rating,
+    pub reviewed_at: DateTime<Utc>,
+    #[serde(default)]
+    pub time_taken_ms: i64,
+}
+
+/// A card read from an imported file, along with the reviews it already went through.
+#[derive(Debug, Clone)]
+pub struct ImportedCard {
+    pub card: Card,
+    pub review_logs: Vec<ReviewLog>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ImportedDeck {
+    pub name: String,
+    pub cards: Vec<ImportedCard>,
+}
+
+/// The cards a user has to study right now, along with how many of them are new, in (re)learning
+/// or in review. When nothing is due, `next_due` tells when the next card will be.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ReviewQueue {
+    pub cards: Vec<Card>,
+    pub new_count: usize,
+    pub learning_count: usize,
+    pub review_count: usize,
+    pub next_due: Option<DateTime<Utc>>,
+}
+
+impl ReviewQueue {
+    pub fn new(cards: Vec<Card>, next_due: Option<DateTime<Utc>>) -> Self {
+        let mut queue = Self {
+            next_due,
+            ..Default::default()
+        };
+        for card in &cards {
+            *queue.count_mut(card.state) += 1;
+        }
+        queue.cards = cards;
+        queue
+    }
+
+    fn count_mut(&mut self, state: CardState) -> &mut usize {
+        match state {
+            CardState::New => &mut self.new_count,
+            CardState::Learning | CardState::Relearning => &mut self.learning_count,
+            CardState::Review => &mut self.review_count,
+        }
+    }
+
+    pub fn current(&self) -> Option<&Card> {
+        self.cards.first()
+    }
+
+    /// Queue of the due cards of a user, sorted by due date, within what is left of the daily
+    /// limits of their decks. Cards in (re)learning aren't limited. Decks burying siblings only
+    /// show one card per note a day, notes in `studied_notes` being already studied today. When
+    /// cards are held back, they become due again on `next_day`.
+    pub fn limited(
+        due_cards: Vec<Card>,
+        limits: &HashMap<i64, QueueLimits>,
+        studied_notes: &HashSet<i64>,
+        next_due: Option<DateTime<Utc>>,
+        next_day: DateTime<Utc>,
+    ) -> Self {
+        let mut limits = limits.clone();
+        let mut studied_notes = studied_notes.clone();
+        let mut held_back = false;
+        let mut cards = Vec::with_capacity(due_cards.len());
+        for card in due_cards {
+            if let Some(deck_limits) = limits.get_mut(&card.deck_id) {
+                let buried = deck_limits.bury_siblings
+                    && matches!(card.state, CardState::New | CardState::Review)
+                    && card.note_id.is_some_and(|id| studied_notes.contains(&id));
+                let remaining = match card.state {
+                    CardState::New => Some(&mut deck_limits.new_cards),
+                    CardState::Review => Some(&mut deck_limits.reviews),
+                    CardState::Learning | CardState::Relearning => None,
+                };
+                if buried || remaining.as_ref().is_some_and(|remaining| **remaining == 0) {
+                    held_back = true;
+                    continue;
+                }
+                if let Some(remaining) = remaining {
+                    *remaining -= 1;
+                }
+            }
+            if let Some(note_id) = card.note_id {
+                studied_notes.insert(note_id);
+            }
+            cards.push(card);
+        }
+        let next_due = match (cards.is_empty(), held_back) {
+            (false, _) => None,
+            (true, true) => Some(next_due.map_or(next_day, |next_due| next_due.min(next_day))),
+            (true, false) => next_due,
+        };
+        Self::new(cards, next_due)
+    }
+
+    pub fn remove(&mut self, id: i64) -> Option<Card> {
+        let index = self.cards.iter().position(|card| card.id == id)?;
+        let card = self.cards.remove(index);
+        let count = self.count_mut(card.state);
+        *count = count.saturating_sub(1);
+        Some(card)
+    }
+}
+
+/// Longest time counted for answering a card, so that leaving the app open doesn't skew stats.
+pub const MAX_TIME_TAKEN_MS: i64 = 60_000;
+
+/// Most reviews made offline that can be synced at once, larger backlogs being sent in batches.
+pub const MAX_SYNCED_REVIEWS: usize = 1000;
+
+/// Lowest and highest share of due cards a user can ask to recall.
+pub const MIN_DESIRED_RETENTION: f64 = 0.7;
+pub const MAX_DESIRED_RETENTION: f64 = 0.99;
+
+/// Hour at which a new study day starts by default, so late reviews count for the day before.
+pub const DEFAULT_DAY_START_HOUR: u32 = 4;
+
+/// How the cards of a user are scheduled. The default FSRS weights are used until they are fitted
+/// to the review history of the user, and the desired retention applies to decks without preset.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SchedulerSettings {
+    pub desired_retention: f64,
+    pub optimization: Option<OptimizationResult>,
+    /// IANA name of the time zone of the user, such as `Europe/Paris`.
+    pub timezone: String,
+    pub day_start_hour: u32,
+}
+
+impl Default for SchedulerSettings {
+    fn default() -> Self {
+        Self {
+            desired_retention: Parameters::default().request_retention,
+            optimization: None,
+            timezone: Tz::UTC.name().to_string(),
+            day_start_hour: DEFAULT_DAY_START_HOUR,
+        }
+    }
+}
+
+impl SchedulerSettings {
+    /// Options of the decks without preset.
+    pub fn default_preset(&self) -> DeckPreset {
+        DeckPreset {
+            desired_retention: self.desired_retention,
+            ..Default::default()
+        }
+    }
+
+    /// Time zone of the user, UTC when it isn't known.
+    pub fn tz(&self) -> Tz {
+        self.timezone.parse::<Tz>().unwrap_or(Tz::UTC)
+    }
+
+    /// Start of the study day of the user containing `now`, which daily limits are counted from.
+    pub fn day_start(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        let timezone = self.tz();
+        let local = now.with_timezone(&timezone);
+        let mut date = local.date_naive();
+        if local.hour() < self.day_start_hour {
+            date = date.pred_opt().unwrap_or(date);
+        }
+        let start = date
+            .and_hms_opt(self.day_start_hour.min(23), 0, 0)
+            .unwrap_or_default();
+        // The day start falls in a gap when clocks move forward at that hour
+        timezone
+            .from_local_datetime(&start)
+            .earliest()
+            .or_else(|| {
+                timezone
+                    .from_local_datetime(&(start + Duration::hours(1)))
+                    .earliest()
+            })
+            .map_or(now, |start| start.with_timezone(&Utc))
+    }
+
+    /// Date of the study day of the user containing `now`.
+    pub fn study_date(&self, now: DateTime<Utc>) -> NaiveDate {
+        self.day_start(now).with_timezone(&self.tz()).date_naive()
+    }
+
+    pub fn scheduler(&self) -> FSRS {
+        let mut parameters = Parameters {
+            request_retention: self.desired_retention,
+            ..Default::default()
+        };
+        let weights = self
+            .optimization
+            .as_ref()
+            .and_then(|optimization| optimization.weights.as_slice().try_into().ok());
+        if let Some(weights) = weights {
+            parameters.w = weights;
+        }
+        FSRS::new(parameters)
+    }
+}
+
+pub fn scheduler() -> FSRS {
+    SchedulerSettings::default().scheduler()
+}
@@ -0,0 +1,584 @@
+use std::io::{Cursor, Read, Write};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use hashbrown::HashMap;
+use rusqlite::Connection;
+use serde::Deserialize;
+use thiserror::Error;
+use zip::ZipArchive;
+
+use crate::learn::content::render_cloze;
+use crate::learn::fsrs::MAX_STABILITY;
+use crate::learn::{
+    Card, CardState, ImportedCard, ImportedDeck, MAX_TIME_TAKEN_MS, Rating, ReviewLog,
+};
+
+/// Collection files by order of preference: `anki21b` is the zstd-compressed collection written
+/// by recent Anki versions, the others are only present in packages exported for older ones.
+const COLLECTION_NAMES: [&str; 3] = [
+    "collection.anki21b",
+    "collection.anki21",
+    "collection.anki2",
+];
+const DEFAULT_DECK_NAME: &str = "Imported deck";
+
+/// Largest collection read from a package once decompressed, so that a small archive can't
+/// expand to fill the memory of the server.
+pub const MAX_COLLECTION_SIZE: u64 = 512 * 1024 * 1024;
+/// Largest package uploaded, media included.
+pub const MAX_PACKAGE_SIZE: usize = 256 * 1024 * 1024;
+
+/// Note types and deck names of a collection, by id.
+type Metadata = (HashMap<i64, NoteType>, HashMap<i64, String>);
+
+#[derive(Error, Debug)]
+pub enum AnkiError {
+    #[error("invalid package archive: {0}")]
+    Archive(#[from] zip::result::ZipError),
+    #[error("no collection found in package")]
+    MissingCollection,
+    #[error("collection is larger than {} MiB", MAX_COLLECTION_SIZE / 1024 / 1024)]
+    CollectionTooLarge,
+    #[error("failed to read collection: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid collection: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("invalid collection metadata: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+#[derive(Default)]
+struct Template {
+    question: String,
+    answer: String,
+}
+
+#[derive(Default)]
+struct NoteType {
+    cloze: bool,
+    fields: Vec<String>,
+    templates: Vec<Template>,
+}
+
+struct Note {
+    note_type_id: i64,
+    fields: Vec<String>,
+    tags: Vec<String>,
+}
+
+struct AnkiCard {
+    id: i64,
+    note_id: i64,
+    deck_id: i64,
+    ord: i64,
+    kind: i64,
+    due: i64,
+    interval: i64,
+}
+
+struct AnkiReview {
+    rating: Rating,
+    reviewed_at: DateTime<Utc>,
+    time_taken_ms: i64,
+}
+
+#[derive(Deserialize)]
+struct LegacyNoteType {
+    #[serde(rename = "type", default)]
+    kind: i64,
+    flds: Vec<LegacyField>,
+    tmpls: Vec<LegacyTemplate>,
+}
+
+#[derive(Deserialize)]
+struct LegacyField {
+    name: String,
+    ord: usize,
+}
+
+#[derive(Deserialize)]
+struct LegacyTemplate {
+    ord: usize,
+    qfmt: String,
+    afmt: String,
+}
+
+#[derive(Deserialize)]
+struct LegacyDeck {
+    name: String,
+}
+
+impl From<LegacyNoteType> for NoteType {
+    fn from(mut val: LegacyNoteType) -> Self {
+        val.flds.sort_by_key(|field| field.ord);
+        val.tmpls.sort_by_key(|template| template.ord);
+        NoteType {
+            cloze: val.kind == 1,
+            fields: val.flds.into_iter().map(|field| field.name).collect(),
+            templates: val
+                .tmpls
+                .into_iter()
+                .map(|template| Template {
+                    question: template.qfmt,
+                    answer: template.afmt,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl NoteType {
+    /// Renders the front and back of the card generated from `note` by the template `ord`. Cloze
+    /// note types have a single template and generate one card per cloze number instead.
+    fn render(&self, note: &Note, ord: i64) -> Option<(String, String)> {
+        let ord = usize::try_from(ord).ok()?;
+        let fields: HashMap<&str, &str> = self
+            .fields
+            .iter()
+            .map(String::as_str)
+            .zip(note.fields.iter().map(String::as_str))
+            .collect();
+        let (template, cloze) = if self.cloze {
+            (self.templates.first()?, ord + 1)
+        } else {
+            (self.templates.get(ord)?, 0)
+        };
+        let front = render_template(&template.question, &fields, cloze, true);
+        let back = render_template(&template.answer, &fields, cloze, false);
+        Some((html_to_text(&front), html_to_text(&back)))
+    }
+}
+
+/// Parses an Anki `.apkg` or `.colpkg` package, returning its decks with their cards. The review
+/// history of each card is replayed through the FSRS scheduler so that imported cards keep their
+/// progress.
+pub fn parse_package(package: &[u8]) -> Result<Vec<ImportedDeck>, AnkiError> {
+    let collection = read_collection(package)?;
+    // SQLite can only open collections from disk
+    let mut file = tempfile::NamedTempFile::new()?;
+    file.write_all(&collection)?;
+    file.flush()?;
+    let connection = Connection::open(file.path())?;
+    parse_collection(&connection)
+}
+
+fn read_collection(package: &[u8]) -> Result<Vec<u8>, AnkiError> {
+    let mut archive = ZipArchive::new(Cursor::new(package))?;
+    for name in COLLECTION_NAMES {
+        let Ok(mut file) = archive.by_name(name) else {
+            continue;
+        };
+        let mut data = read_limited(&mut file)?;
+        if name.ends_with("anki21b") {
+            data = read_limited(zstd::Decoder::new(data.as_slice())?)?;
+        }
+        return Ok(data);
+    }
+    Err(AnkiError::MissingCollection)
+}
+
+/// Reads up to [`MAX_COLLECTION_SIZE`] bytes, failing when there are more to read.
+fn read_limited(reader: impl Read) -> Result<Vec<u8>, AnkiError> {
+    let mut data = Vec::new();
+    reader
+        .take(MAX_COLLECTION_SIZE + 1)
+        .read_to_end(&mut data)?;
+    if data.len() as u64 > MAX_COLLECTION_SIZE {
+        return Err(AnkiError::CollectionTooLarge);
+    }
+    Ok(data)
+}
+
+fn parse_collection(connection: &Connection) -> Result<Vec<ImportedDeck>, AnkiError> {
+    let collection_created_at: i64 =
+        connection.query_row("SELECT crt FROM col", [], |row| row.get(0))?;
+    let (note_types, deck_names) = if has_table(connection, "notetypes")? {
+        (read_note_types(connection)?, read_deck_names(connection)?)
+    } else {
+        read_legacy_metadata(connection)?
+    };
+    let notes = read_notes(connection)?;
+    let mut reviews = read_reviews(connection)?;
+
+    let mut decks: HashMap<i64, ImportedDeck> = HashMap::new();
+    // Cards in filtered decks keep their original deck and due date in `odid` and `odue`
+    let mut statement = connection.prepare(
+        "SELECT id, nid, CASE WHEN odid <> 0 THEN odid ELSE did END, ord, type,
+            CASE WHEN odid <> 0 THEN odue ELSE due END, ivl
+            FROM cards ORDER BY id",
+    )?;
+    let anki_cards = statement.query_map([], |row| {
+        Ok(AnkiCard {
+            id: row.get(0)?,
+            note_id: row.get(1)?,
+            deck_id: row.get(2)?,
+            ord: row.get(3)?,
+            kind: row.get(4)?,
+            due: row.get(5)?,
+            interval: row.get(6)?,
+        })
+    })?;
+    for anki_card in anki_cards {
+        let anki_card = anki_card?;
+        let Some(note) = notes.get(&anki_card.note_id) else {
+            continue;
+        };
+        let Some(note_type) = note_types.get(&note.note_type_id) else {
+            continue;
+        };
+        let Some((front, back)) = note_type.render(note, anki_card.ord) else {
+            continue;
+        };
+        let card = Card::new(front, back, note.tags.clone(), Utc::now());
+        let card_reviews = reviews.remove(&anki_card.id).unwrap_or_default();
+        let imported_card = import_card(card, &anki_card, card_reviews, collection_created_at);
+        decks
+            .entry(anki_card.deck_id)
+            .or_insert_with(|| ImportedDeck {
+                name: deck_names
+                    .get(&anki_card.deck_id)
+                    .cloned()
+                    .unwrap_or_else(|| DEFAULT_DECK_NAME.to_string()),
+                cards: Vec::new(),
+            })
+            .cards
+            .push(imported_card);
+    }
+    Ok(decks.into_values().collect())
+}
+
+fn import_card(
+    mut card: Card,
+    anki_card: &AnkiCard,
+    reviews: Vec<AnkiReview>,
+    collection_created_at: i64,
+) -> ImportedCard {
+    if let Some(first_review) = reviews.first() {
+        card.due = first_review.reviewed_at;
+    }
+    let review_logs: Vec<ReviewLog> = reviews
+        .into_iter()
+        .map(|review| ReviewLog {
+            time_taken_ms: review.time_taken_ms.clamp(0, MAX_TIME_TAKEN_MS),
+            ..card.review(review.rating, review.reviewed_at)
+        })
+        .collect();
+
+    // Without any history to replay, review cards are approximated from their SM-2 interval.
+    // Their due date is stored as a number of days since the collection was created, cards whose
+    // dates are out of range staying new.
+    if review_logs.is_empty() && anki_card.kind == 2 && anki_card.interval > 0 {
+        let collection_created_at =
+            DateTime::from_timestamp(collection_created_at, 0).unwrap_or_default();
+        let interval = anki_card.interval.min(MAX_STABILITY as i64);
+        let due = TimeDelta::try_days(anki_card.due)
+            .and_then(|due| collection_created_at.checked_add_signed(due));
+        let last_review = due.and_then(|due| due.checked_sub_signed(TimeDelta::days(interval)));
+        if let (Some(due), Some(last_review)) = (due, last_review) {
+            card.state = CardState::Review;
+            card.stability = interval as f64;
+            card.difficulty = 5.;
+            card.scheduled_days = interval;
+            card.reps = 1;
+            card.due = due;
+            card.last_review = Some(last_review);
+        }
+    }
+    ImportedCard { card, review_logs }
+}
+
+fn has_table(connection: &Connection, name: &str) -> Result<bool, AnkiError> {
+    Ok(connection.query_row(
+        "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = ?1",
+        [name],
+        |row| row.get(0),
+    )?)
+}
+
+fn read_note_types(connection: &Connection) -> Result<HashMap<i64, NoteType>, AnkiError> {
+    let mut note_types = HashMap::new();
+    let mut statement = connection.prepare("SELECT id, config FROM notetypes")?;
+    let rows = statement.query_map([], |row| {
+        Ok((row.get::<_, i64>(0)?, row.get::<_, Vec<u8>>(1)?))
+    })?;
+    for row in rows {
+        let (id, config) = row?;
+        // The kind of note type is the first field of its config, `1` meaning cloze
+        let cloze = proto_fields(&config)
+            .any(|(number, value)| number == 1 && matches!(value, ProtoValue::Varint(1)));
+        note_types.insert(
+            id,
+            NoteType {
+                cloze,
+                ..Default::default()
+            },
+        );
+    }
+
+    let mut statement = connection.prepare("SELECT ntid, name FROM fields ORDER BY ntid, ord")?;
+    let rows = statement.query_map([], |row| {
+        Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+    })?;
+    for row in rows {
+        let (id, name) = row?;
+        if let Some(note_type) = note_types.get_mut(&id) {
+            note_type.fields.push(name);
+        }
+    }
+
+    let mut statement =
+        connection.prepare("SELECT ntid, config FROM templates ORDER BY ntid, ord")?;
+    let rows = statement.query_map([], |row| {
+        Ok((row.get::<_, i64>(0)?, row.get::<_, Vec<u8>>(1)?))
+    })?;
+    for row in rows {
+        let (id, config) = row?;
+        let mut template = Template::default();
+        for (number, value) in proto_fields(&config) {
+            let ProtoValue::Bytes(bytes) = value else {
+                continue;
+            };
+            match number {
+                1 => template.question = String::from_utf8_lossy(bytes).into_owned(),
+                2 => template.answer = String::from_utf8_lossy(bytes).into_owned(),
+                _ => (),
+            }
+        }
+        if let Some(note_type) = note_types.get_mut(&id) {
+            note_type.templates.push(template);
+        }
+    }
+    Ok(note_types)
+}
+
+fn read_deck_names(connection: &Connection) -> Result<HashMap<i64, String>, AnkiError> {
+    let mut statement = connection.prepare("SELECT id, name FROM decks")?;
+    let rows = statement.query_map([], |row| {
+        Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+    })?;
+    let mut deck_names = HashMap::new();
+    for row in rows {
+        let (id, name) = row?;
+        // Nested deck names are separated by `\x1f` instead of `::` in the newer schema
+        deck_names.insert(id, name.replace('\x1f', "::"));
+    }
+    Ok(deck_names)
+}
+
+fn read_legacy_metadata(connection: &Connection) -> Result<Metadata, AnkiError> {
+    let (models, decks): (String, String) =
+        connection.query_row("SELECT models, decks FROM col", [], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?;
+    let models: HashMap<String, LegacyNoteType> = serde_json::from_str(&models)?;
+    let decks: HashMap<String, LegacyDeck> = serde_json::from_str(&decks)?;
+    let note_types = models
+        .into_iter()
+        .filter_map(|(id, model)| Some((id.parse().ok()?, model.into())))
+        .collect();
+    let deck_names = decks
+        .into_iter()
+        .filter_map(|(id, deck)| Some((id.parse().ok()?, deck.name)))
+        .collect();
+    Ok((note_types, deck_names))
+}
+
+fn read_notes(connection: &Connection) -> Result<HashMap<i64, Note>, AnkiError> {
+    let mut statement = connection.prepare("SELECT id, mid, flds, tags FROM notes")?;
+    let rows = statement.query_map([], |row| {
+        let fields: String = row.get(2)?;
+        let tags: String = row.get(3)?;
+        Ok((
+            row.get::<_, i64>(0)?,
+            Note {
+                note_type_id: row.get(1)?,
+                fields: fields.split('\x1f').map(String::from).collect(),
+                tags: tags.split_whitespace().map(String::from).collect(),
+            },
+        ))
+    })?;
+    Ok(rows.collect::<Result<_, rusqlite::Error>>()?)
+}
+
+fn read_reviews(connection: &Connection) -> Result<HashMap<i64, Vec<AnkiReview>>, AnkiError> {
+    // Only learning, review and relearning entries are actual reviews, the others are manual
+    // rescheduling or cram sessions which FSRS doesn't account for
+    let mut statement = connection.prepare(
+        "SELECT id, cid, ease, time FROM revlog
+            WHERE ease BETWEEN 1 AND 4 AND type IN (0, 1, 2)
+            ORDER BY id",
+    )?;
+    let rows = statement.query_map([], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, i64>(1)?,
+            row.get::<_, i16>(2)?,
+            row.get::<_, i64>(3)?,
+        ))
+    })?;
+    let mut reviews: HashMap<i64, Vec<AnkiReview>> = HashMap::new();
+    for row in rows {
+        let (id, card_id, ease, time_taken_ms) = row?;
+        // Review ids are the timestamp of the review in milliseconds
+        let Some(reviewed_at) = DateTime::from_timestamp_millis(id) else {
+            continue;
+        };
+        reviews.entry(card_id).or_default().push(AnkiReview {
+            rating: Rating::from(ease),
+            reviewed_at,
+            time_taken_ms,
+        });
+    }
+    Ok(reviews)
+}
+
+fn render_template(
+    template: &str,
+    fields: &HashMap<&str, &str>,
+    cloze: usize,
+    question: bool,
+) -> String {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        output.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find("}}") else {
+            rest = &rest[start..];
+            break;
+        };
+        let tag = rest[start + 2..start + end].trim();
+        rest = &rest[start + end + 2..];
+
+        let section = tag.strip_prefix('#').or_else(|| tag.strip_prefix('^'));
+        if let Some(name) = section.map(str::trim) {
+            let closing = format!("{{{{/{name}}}}}");
+            let (inner, after) = match rest.find(&closing) {
+                Some(index) => (&rest[..index], &rest[index + closing.len()..]),
+                None => (rest, ""),
+            };
+            let non_empty = fields
+                .get(name)
+                .is_some_and(|value| !html_to_text(value).is_empty());
+            if non_empty == tag.starts_with('#') {
+                output.push_str(&render_template(inner, fields, cloze, question));
+            }
+            rest = after;
+        } else {
+            output.push_str(&render_field(tag, fields, cloze, question));
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+fn render_field(tag: &str, fields: &HashMap<&str, &str>, cloze: usize, question: bool) -> String {
+    // Filters are chained before the field name, e.g. `{{text:cloze:Text}}`
+    let mut parts = tag.rsplit(':');
+    let name = parts.next().unwrap_or_default().trim();
+    // The front side is already shown above the back when reviewing
+    if name == "FrontSide" {
+        return String::new();
+    }
+    let Some(value) = fields.get(name) else {
+        return String::new();
+    };
+    let mut value = value.to_string();
+    for filter in parts {
+        value = match filter.trim() {
+            "cloze" => render_cloze(&value, cloze, question),
+            "text" => html_to_text(&value),
+            "type" => String::new(),
+            _ => value,
+        };
+    }
+    value
+}
+
+fn html_to_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('>') else {
+            rest = &rest[start..];
+            break;
+        };
+        let tag: String = rest[start + 1..start + end]
+            .trim_start_matches('/')
+            .chars()
+            .take_while(char::is_ascii_alphanumeric)
+            .collect();
+        if matches!(
+            tag.to_ascii_lowercase().as_str(),
+            "br" | "div" | "p" | "hr" | "li" | "tr"
+        ) && !text.ends_with('\n')
+        {
+            text.push('\n');
+        }
+        rest = &rest[start + end + 1..];
+    }
+    text.push_str(rest);
+
+    // Media files aren't imported, so sound references are dropped
+    while let Some(start) = text.find("[sound:") {
+        let end = text[start..]
+            .find(']')
+            .map_or(text.len(), |end| start + end + 1);
+        text.replace_range(start..end, "");
+    }
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+        .trim()
+        .to_string()
+}
+
+enum ProtoValue<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+    Fixed,
+}
+
+fn read_varint(bytes: &[u8], position: &mut usize) -> Option<u64> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let byte = *bytes.get(*position)?;
+        *position += 1;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+/// Iterates over the top-level fields of a protobuf message. The newer schema stores note type
+/// and template configs as protobuf, and only a couple of their fields are needed here.
+fn proto_fields(bytes: &[u8]) -> impl Iterator<Item = (u64, ProtoValue<'_>)> {
+    let mut position = 0;
+    std::iter::from_fn(move || {
+        let key = read_varint(bytes, &mut position)?;
+        let value = match key & 0x7 {
+            0 => ProtoValue::Varint(read_varint(bytes, &mut position)?),
+            1 => {
+                position += 8;
+                ProtoValue::Fixed
+            }
+            2 => {
+                let length = read_varint(bytes, &mut position)? as usize;
+                let value = bytes.get(position..position.checked_add(length)?)?;
+                position += length;
+                ProtoValue::Bytes(value)
+            }
+            5 => {
+                position += 4;
+                ProtoValue::Fixed
+            }
+            _ => return None,
+        };
+        Some((key >> 3, value))
+    })
+}
@@ -0,0 +1,367 @@
+use std::ops::Range;
+
+use pulldown_cmark::{Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+use serde::{Deserialize, Serialize};
+
+/// Scheme of the links to images stored by the server, e.g. `![diagram](image:42)`.
+pub const IMAGE_SCHEME: &str = "image:";
+/// Largest image accepted by the server, in bytes.
+pub const MAX_IMAGE_SIZE: usize = 2 * 1024 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CardImage {
+    pub id: i64,
+    pub owner_id: i64,
+    pub content_type: String,
+    pub data: Vec<u8>,
+}
+
+/// Content type of an image from its first bytes, or `None` when it isn't a supported format.
+pub fn image_content_type(data: &[u8]) -> Option<&'static str> {
+    if data.starts_with(b"\x89PNG") {
+        Some("image/png")
+    } else if data.starts_with(&[0xff, 0xd8, 0xff]) {
+        Some("image/jpeg")
+    } else if data.starts_with(b"GIF8") {
+        Some("image/gif")
+    } else if data.starts_with(b"RIFF") && data.get(8..12) == Some(b"WEBP".as_slice()) {
+        Some("image/webp")
+    } else {
+        None
+    }
+}
+
+/// Markdown referencing an image stored by the server.
+pub fn image_markdown(id: i64) -> String {
+    format!("![]({IMAGE_SCHEME}{id})")
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SpanStyle {
+    pub bold: bool,
+    pub italic: bool,
+    pub strikethrough: bool,
+    pub code: bool,
+}
+
+/// Text along with the byte ranges of its styled spans.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct StyledText {
+    pub text: String,
+    pub spans: Vec<(Range<usize>, SpanStyle)>,
+}
+
+impl StyledText {
+    fn push(&mut self, text: &str, style: SpanStyle) {
+        let start = self.text.len();
+        self.text.push_str(text);
+        if style != SpanStyle::default() {
+            self.spans.push((start..self.text.len(), style));
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.text.trim().is_empty()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Block {
+    Heading(u8, StyledText),
+    Paragraph(StyledText),
+    /// Item of a list, with its marker, e.g. `•` or `1.`, and its nesting depth starting at `0`.
+    ListItem {
+        marker: String,
+        depth: usize,
+        text: StyledText,
+    },
+    Quote(StyledText),
+    Code(String),
+    Image {
+        id: i64,
+        alt: String,
+    },
+    Rule,
+}
+
+/// Formatted content of a side of a card, parsed from markdown.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CardContent {
+    pub blocks: Vec<Block>,
+}
+
+/// Block being read along with its text so far.
+enum OpenBlock {
+    Heading(u8),
+    Paragraph,
+    ListItem { marker: String, depth: usize },
+    Code,
+}
+
+impl CardContent {
+    /// Parses the CommonMark subset shown by the clients. Images are only kept when they are
+    /// stored by the server, other images are replaced by their alternative text.
+    pub fn parse(markdown: &str) -> Self {
+        let mut blocks = Vec::new();
+        let mut open_block = None;
+        let mut text = StyledText::default();
+        let mut style = SpanStyle::default();
+        // Next number of each open list, `None` for bullet lists
+        let mut lists: Vec<Option<u64>> = Vec::new();
+        let mut quote_depth = 0;
+        let mut image: Option<(Option<i64>, String)> = None;
+
+        let flush = |blocks: &mut Vec<Block>,
+                     open_block: &mut Option<OpenBlock>,
+                     text: &mut StyledText,
+                     quote_depth: usize| {
+            let text = std::mem::take(text);
+            let block = match open_block.take() {
+                Some(OpenBlock::Code) => Block::Code(text.text.trim_end().to_string()),
+                _ if text.is_empty() => return,
+                Some(OpenBlock::Heading(level)) => Block::Heading(level, text),
+                Some(OpenBlock::ListItem { marker, depth }) => Block::ListItem {
+                    marker,
+                    depth,
+                    text,
+                },
+                Some(OpenBlock::Paragraph) | None if quote_depth > 0 => Block::Quote(text),
+                Some(OpenBlock::Paragraph) | None => Block::Paragraph(text),
+            };
+            blocks.push(block);
+        };
+
+        let options = Options::ENABLE_STRIKETHROUGH;
+        for event in Parser::new_ext(markdown, options) {
+            if let Some((_, alt)) = &mut image {
+                match event {
+                    Event::End(TagEnd::Image) => {
+                        let (id, alt) = image.take().unwrap_or_default();
+                        match id {
+                            Some(id) => {
+                                let reopened = match &open_block {
+                                    Some(OpenBlock::ListItem { marker, depth }) => {
+                                        Some(OpenBlock::ListItem {
+                                            marker: marker.clone(),
+                                            depth: *depth,
+                                        })
+                                    }
+                                    Some(_) => Some(OpenBlock::Paragraph),
+                                    None => None,
+                                };
+                                flush(&mut blocks, &mut open_block, &mut text, quote_depth);
+                                blocks.push(Block::Image { id, alt });
+                                open_block = reopened;
+                            }
+                            None => text.push(&alt, style),
+                        }
+                    }
+                    Event::Text(content) | Event::Code(content) => alt.push_str(&content),
+                    _ => (),
+                }
+                continue;
+            }
+            match event {
+                Event::Start(Tag::Heading { level, .. }) => {
+                    flush(&mut blocks, &mut open_block, &mut text, quote_depth);
+                    open_block = Some(OpenBlock::Heading(heading_level(level)));
+                }
+                Event::Start(Tag::Paragraph) => {
+                    // Paragraphs of a list item continue it
+                    if !matches!(open_block, Some(OpenBlock::ListItem { .. })) {
+                        flush(&mut blocks, &mut open_block, &mut text, quote_depth);
+                        open_block = Some(OpenBlock::Paragraph);
+                    } else if !text.is_empty() {
+                        text.push(" ", SpanStyle::default());
+                    }
+                }
+                Event::Start(Tag::CodeBlock(_)) => {
+                    flush(&mut blocks, &mut open_block, &mut text, quote_depth);
+                    open_block = Some(OpenBlock::Code);
+                }
+                Event::Start(Tag::BlockQuote(_)) => {
+                    flush(&mut blocks, &mut open_block, &mut text, quote_depth);
+                    quote_depth += 1;
+                }
+                Event::Start(Tag::List(start)) => {
+                    flush(&mut blocks, &mut open_block, &mut text, quote_depth);
+                    lists.push(start);
+                }
+                Event::Start(Tag::Item) => {
+                    flush(&mut blocks, &mut open_block, &mut text, quote_depth);
+                    let marker = match lists.last_mut() {
+                        Some(Some(number)) => {
+                            *number += 1;
+                            format!("{}.", *number - 1)
+                        }
+                        _ => "•".to_string(),
+                    };
+                    let depth = lists.len().saturating_sub(1);
+                    open_block = Some(OpenBlock::ListItem { marker, depth });
+                }
+                Event::Start(Tag::Strong) => style.bold = true,
+                Event::Start(Tag::Emphasis) => style.italic = true,
+                Event::Start(Tag::Strikethrough) => style.strikethrough = true,
+                Event::Start(Tag::Image { dest_url, .. }) => {
+                    let id = dest_url
+                        .strip_prefix(IMAGE_SCHEME)
+                        .and_then(|id| id.parse().ok());
+                    image = Some((id, String::new()));
+                }
+                Event::End(
+                    TagEnd::Heading(_) | TagEnd::Paragraph | TagEnd::CodeBlock | TagEnd::Item,
+                ) => {
+                    if !matches!(open_block, Some(OpenBlock::ListItem { .. }))
+                        || matches!(event, Event::End(TagEnd::Item))
+                    {
+                        flush(&mut blocks, &mut open_block, &mut text, quote_depth);
+                    }
+                }
+                Event::End(TagEnd::BlockQuote(_)) => {
+                    flush(&mut blocks, &mut open_block, &mut text, quote_depth);
+                    quote_depth = quote_depth.saturating_sub(1);
+                }
+                Event::End(TagEnd::List(_)) => {
+                    flush(&mut blocks, &mut open_block, &mut text, quote_depth);
+                    lists.pop();
+                }
+                Event::End(TagEnd::Strong) => style.bold = false,
+                Event::End(TagEnd::Emphasis) => style.italic = false,
+                Event::End(TagEnd::Strikethrough) => style.strikethrough = false,
+                Event::Text(content) => text.push(&content, style),
+                Event::Code(content) => text.push(
+                    &content,
+                    SpanStyle {
+                        code: true,
+                        ..style
+                    },
+                ),
+                Event::SoftBreak => text.push(" ", style),
+                Event::HardBreak => text.push("\n", style),
+                Event::Html(content) | Event::InlineHtml(content) => text.push(&content, style),
+                Event::Rule => {
+                    flush(&mut blocks, &mut open_block, &mut text, quote_depth);
+                    blocks.push(Block::Rule);
+                }
+                _ => (),
+            }
+        }
+        flush(&mut blocks, &mut open_block, &mut text, quote_depth);
+        Self { blocks }
+    }
+
+    /// Ids of the images stored by the server shown in this content.
+    pub fn image_ids(&self) -> impl Iterator<Item = i64> + '_ {
+        self.blocks.iter().filter_map(|block| match block {
+            Block::Image { id, .. } => Some(*id),
+            _ => None,
+        })
+    }
+}
+
+fn heading_level(level: HeadingLevel) -> u8 {
+    match level {
+        HeadingLevel::H1 => 1,
+        HeadingLevel::H2 => 2,
+        HeadingLevel::H3 => 3,
+        HeadingLevel::H4 => 4,
+        HeadingLevel::H5 => 5,
+        HeadingLevel::H6 => 6,
+    }
+}
+
+/// Piece of a text containing `{{cN::answer::hint}}` cloze deletions.
+enum ClozePart<'a> {
+    Text(&'a str),
+    Deletion {
+        number: usize,
+        answer: &'a str,
+        hint: Option<&'a str>,
+    },
+}
+
+/// Splits a text around its cloze deletions. Malformed deletions are kept as text.
+fn cloze_parts(text: &str) -> impl Iterator<Item = ClozePart<'_>> {
+    let mut rest = text;
+    std::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+        let Some(start) = rest.find("{{c") else {
+            return Some(ClozePart::Text(std::mem::take(&mut rest)));
+        };
+        if start > 0 {
+            let (text, after) = rest.split_at(start);
+            rest = after;
+            return Some(ClozePart::Text(text));
+        }
+        let number_start = 3;
+        let Some(separator) = rest[number_start..].find("::") else {
+            return Some(ClozePart::Text(std::mem::take(&mut rest)));
+        };
+        let Ok(number) = rest[number_start..number_start + separator].parse::<usize>() else {
+            let (text, after) = rest.split_at(number_start);
+            rest = after;
+            return Some(ClozePart::Text(text));
+        };
+        let content_start = number_start + separator + 2;
+        let Some(end) = rest[content_start..].find("}}") else {
+            return Some(ClozePart::Text(std::mem::take(&mut rest)));
+        };
+        let content = &rest[content_start..content_start + end];
+        rest = &rest[content_start + end + 2..];
+        let (answer, hint) = match content.split_once("::") {
+            Some((answer, hint)) => (answer, Some(hint)),
+            None => (content, None),
+        };
+        Some(ClozePart::Deletion {
+            number,
+            answer,
+            hint,
+        })
+    })
+}
+
+/// Numbers of the cloze deletions of a text, sorted and without duplicates.
+pub fn cloze_numbers(text: &str) -> Vec<usize> {
+    let mut numbers = cloze_parts(text)
+        .filter_map(|part| match part {
+            ClozePart::Deletion { number, .. } => Some(number),
+            ClozePart::Text(_) => None,
+        })
+        .collect::<Vec<_>>();
+    numbers.sort_unstable();
+    numbers.dedup();
+    numbers
+}
+
+/// Replaces every `{{cN::answer::hint}}` deletion by its answer, except for the deletion `cloze`
+/// on the question side which is hidden behind its hint.
+pub fn render_cloze(text: &str, cloze: usize, question: bool) -> String {
+    let mut output = String::with_capacity(text.len());
+    for part in cloze_parts(text) {
+        match part {
+            ClozePart::Text(text) => output.push_str(text),
+            ClozePart::Deletion { number, hint, .. } if question && number == cloze => {
+                output.push('[');
+                output.push_str(hint.unwrap_or("..."));
+                output.push(']');
+            }
+            ClozePart::Deletion { answer, .. } => output.push_str(answer),
+        }
+    }
+    output
+}
+
+/// Front and back of the card of each cloze deletion of a text, in the order of their numbers.
+pub fn cloze_cards(text: &str) -> Vec<(String, String)> {
+    cloze_numbers(text)
+        .into_iter()
+        .map(|cloze| {
+            (
+                render_cloze(text, cloze, true),
+                render_cloze(text, cloze, false),
+            )
+        })
+        .collect()
+}
@@ -0,0 +1,264 @@
+use chrono::{DateTime, Utc};
+use csv::{ReaderBuilder, StringRecord, WriterBuilder};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::learn::fsrs::{MAX_DIFFICULTY, MAX_STABILITY, MIN_DIFFICULTY, MIN_STABILITY};
+use crate::learn::{Card, CardState, ImportedCard, ImportedDeck};
+
+/// Columns written after the card content when exporting with the scheduling state. Files with
+/// a header containing all of them get their scheduling state restored when imported.
+const SCHEDULING_COLUMNS: [&str; 9] = [
+    "due",
+    "stability",
+    "difficulty",
+    "elapsed_days",
+    "scheduled_days",
+    "reps",
+    "lapses",
+    "state",
+    "last_review",
+];
+
+/// Largest delimited file imported.
+pub const MAX_FILE_SIZE: usize = 32 * 1024 * 1024;
+/// Largest column mapping sent along with a delimited file.
+pub const MAX_IMPORT_SIZE: usize = 64 * 1024;
+
+#[derive(Error, Debug)]
+pub enum DelimitedError {
+    #[error("invalid delimited file: {0}")]
+    Csv(#[from] csv::Error),
+    #[error("line {line} has no column {column}")]
+    MissingColumn { line: u64, column: usize },
+    #[error("line {line} has an invalid `{column}` value")]
+    InvalidValue { line: u64, column: &'static str },
+    #[error("failed to write file: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Delimiter {
+    #[default]
+    Comma,
+    Semicolon,
+    Tab,
+}
+
+impl std::fmt::Display for Delimiter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Delimiter::Comma => write!(f, "Comma"),
+            Delimiter::Semicolon => write!(f, "Semicolon"),
+            Delimiter::Tab => write!(f, "Tab"),
+        }
+    }
+}
+
+impl Delimiter {
+    pub fn byte(self) -> u8 {
+        match self {
+            Delimiter::Comma => b',',
+            Delimiter::Semicolon => b';',
+            Delimiter::Tab => b'\t',
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Delimiter::Comma => Delimiter::Semicolon,
+            Delimiter::Semicolon => Delimiter::Tab,
+            Delimiter::Tab => Delimiter::Comma,
+        }
+    }
+
+    /// Guesses the delimiter from a file extension, `.tsv` and `.txt` files being tab-separated.
+    pub fn from_extension(extension: &str) -> Self {
+        match extension.to_ascii_lowercase().as_str() {
+            "tsv" | "txt" => Delimiter::Tab,
+            _ => Delimiter::Comma,
+        }
+    }
+}
+
+/// Columns holding each part of the cards, starting from `0`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ColumnMapping {
+    pub front: usize,
+    pub back: usize,
+    pub tags: Option<usize>,
+    pub extra: Vec<usize>,
+}
+
+impl Default for ColumnMapping {
+    /// The layout written by [`export_cards`], without extra fields.
+    fn default() -> Self {
+        Self {
+            front: 0,
+            back: 1,
+            tags: Some(2),
+            extra: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DelimitedImport {
+    pub deck_name: String,
+    pub delimiter: Delimiter,
+    pub has_header: bool,
+    pub mapping: ColumnMapping,
+}
+
+/// Reads every row of a delimited file as a card, according to the column mapping of `import`.
+pub fn parse_cards(data: &[u8], import: &DelimitedImport) -> Result<ImportedDeck, DelimitedError> {
+    let mut reader = ReaderBuilder::new()
+        .delimiter(import.delimiter.byte())
+        .has_headers(import.has_header)
+        .flexible(true)
+        .from_reader(data);
+    let scheduling_columns = if import.has_header {
+        let headers = reader.headers()?;
+        SCHEDULING_COLUMNS
+            .iter()
+            .map(|name| headers.iter().position(|header| header.trim() == *name))
+            .collect::<Option<Vec<_>>>()
+    } else {
+        None
+    };
+
+    let now = Utc::now();
+    let mut cards = Vec::new();
+    for record in reader.records() {
+        let record = record?;
+        let line = record.position().map_or(0, |position| position.line());
+        let column = |index: usize| {
+            record.get(index).ok_or(DelimitedError::MissingColumn {
+                line,
+                column: index + 1,
+            })
+        };
+        let front = column(import.mapping.front)?.to_string();
+        let back = column(import.mapping.back)?.to_string();
+        let tags = match import.mapping.tags {
+            Some(index) => column(index)?
+                .split_whitespace()
+                .map(String::from)
+                .collect(),
+            None => Vec::new(),
+        };
+        let extra = import
+            .mapping
+            .extra
+            .iter()
+            .map(|&index| column(index).map(String::from))
+            .collect::<Result<_, _>>()?;
+
+        let mut card = Card::new(front, back, tags, now);
+        card.extra = extra;
+        if let Some(columns) = &scheduling_columns {
+            read_scheduling(&mut card, &record, columns, line)?;
+        }
+        cards.push(ImportedCard {
+            card,
+            review_logs: Vec::new(),
+        });
+    }
+    Ok(ImportedDeck {
+        name: import.deck_name.clone(),
+        cards,
+    })
+}
+
+fn read_scheduling(
+    card: &mut Card,
+    record: &StringRecord,
+    columns: &[usize],
+    line: u64,
+) -> Result<(), DelimitedError> {
+    let value = |position: usize| record.get(columns[position]).unwrap_or_default().trim();
+    let invalid = |position: usize| DelimitedError::InvalidValue {
+        line,
+        column: SCHEDULING_COLUMNS[position],
+    };
+    let finite = |position: usize| {
+        value(position)
+            .parse::<f64>()
+            .ok()
+            .filter(|number| number.is_finite())
+            .ok_or_else(|| invalid(position))
+    };
+    card.due = value(0).parse().map_err(|_| invalid(0))?;
+    card.stability = finite(1)?;
+    card.difficulty = finite(2)?;
+    card.elapsed_days = value(3).parse().map_err(|_| invalid(3))?;
+    card.scheduled_days = value(4).parse().map_err(|_| invalid(4))?;
+    card.reps = value(5).parse().map_err(|_| invalid(5))?;
+    card.lapses = value(6).parse().map_err(|_| invalid(6))?;
+    card.state = CardState::from(value(7).parse::<i16>().map_err(|_| invalid(7))?);
+    card.last_review = match value(8) {
+        "" => None,
+        last_review => Some(
+            last_review
+                .parse::<DateTime<Utc>>()
+                .map_err(|_| invalid(8))?,
+        ),
+    };
+    // New cards have no memory state yet, the others are kept within what the scheduler handles
+    if card.state != CardState::New {
+        card.stability = card.stability.clamp(MIN_STABILITY, MAX_STABILITY);
+        card.difficulty = card.difficulty.clamp(MIN_DIFFICULTY, MAX_DIFFICULTY);
+    }
+    Ok(())
+}
+
+/// Writes cards as a delimited file with a header, which [`parse_cards`] reads back with the
+/// default mapping plus one extra column per extra field.
+pub fn export_cards(
+    cards: &[Card],
+    delimiter: Delimiter,
+    include_scheduling: bool,
+) -> Result<String, DelimitedError> {
+    let extra_count = cards
+        .iter()
+        .map(|card| card.extra.len())
+        .max()
+        .unwrap_or_default();
+    let mut writer = WriterBuilder::new()
+        .delimiter(delimiter.byte())
+        .from_writer(Vec::new());
+
+    let mut header = vec!["front".to_string(), "back".to_string(), "tags".to_string()];
+    header.extend((1..=extra_count).map(|number| format!("extra_{number}")));
+    if include_scheduling {
+        header.extend(SCHEDULING_COLUMNS.map(String::from));
+    }
+    writer.write_record(&header)?;
+
+    for card in cards {
+        let mut record = vec![card.front.clone(), card.back.clone(), card.tags.join(" ")];
+        record.extend(
+            (0..extra_count).map(|index| card.extra.get(index).cloned().unwrap_or_default()),
+        );
+        if include_scheduling {
+            record.extend([
+                card.due.to_rfc3339(),
+                card.stability.to_string(),
+                card.difficulty.to_string(),
+                card.elapsed_days.to_string(),
+                card.scheduled_days.to_string(),
+                card.reps.to_string(),
+                card.lapses.to_string(),
+                i16::from(card.state).to_string(),
+                card.last_review
+                    .map(|last_review| last_review.to_rfc3339())
+                    .unwrap_or_default(),
+            ]);
+        }
+        writer.write_record(&record)?;
+    }
+    let data = writer
+        .into_inner()
+        .map_err(|error| DelimitedError::Io(error.into_error()))?;
+    Ok(String::from_utf8_lossy(&data).into_owned())
+}
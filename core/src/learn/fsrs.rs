@@ -0,0 +1,21 @@
+/// Shape of the forgetting curve, fixed in the FSRS versions whose weights are used.
+pub const DECAY: f64 = -0.5;
+/// Makes the recall probability of a card exactly 90% once `stability` days have passed.
+pub const FACTOR: f64 = 19. / 81.;
+pub const MIN_STABILITY: f64 = 0.01;
+/// Highest stability of a card, and longest interval it can be scheduled with, in days.
+pub const MAX_STABILITY: f64 = 36500.;
+/// Range of the difficulty of a card, from the easiest to the hardest.
+pub const MIN_DIFFICULTY: f64 = 1.;
+pub const MAX_DIFFICULTY: f64 = 10.;
+
+/// Probability of recalling a card of `stability` after `elapsed_days`.
+pub fn retrievability(elapsed_days: f64, stability: f64) -> f64 {
+    (1. + FACTOR * elapsed_days / stability).powf(DECAY)
+}
+
+/// Days until the recall probability of a card of `stability` drops to `desired_retention`.
+pub fn interval_days(stability: f64, desired_retention: f64) -> i64 {
+    let interval = stability / FACTOR * (desired_retention.powf(1. / DECAY) - 1.);
+    (interval.round() as i64).clamp(1, MAX_STABILITY as i64)
+}
@@ -0,0 +1,341 @@
+use chrono::{DateTime, Utc};
+use hashbrown::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::learn::content::{cloze_numbers, render_cloze};
+
+/// Field standing for the rendered front of the card on the back side.
+const FRONT_SIDE: &str = "FrontSide";
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum NoteError {
+    #[error("the note type needs a name")]
+    MissingName,
+    #[error("the note type needs at least one field")]
+    MissingFields,
+    #[error("invalid or duplicate field name \"{0}\"")]
+    InvalidFieldName(String),
+    #[error("the note type needs at least one template")]
+    MissingTemplates,
+    #[error("cloze note types have a single template using a cloze field")]
+    InvalidClozeTemplate,
+    #[error("a note type can't be turned into a cloze note type or back")]
+    ClozeChanged,
+    #[error("template \"{template}\" uses unknown field \"{field}\"")]
+    UnknownField { template: String, field: String },
+    #[error("expected {expected} fields, got {actual}")]
+    FieldCount { expected: usize, actual: usize },
+    #[error("the first field can't be empty")]
+    EmptyFirstField,
+    #[error("the note doesn't generate any card")]
+    NoCards,
+}
+
+/// Front and back of the cards generated from each note of a note type.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Template {
+    pub name: String,
+    pub front: String,
+    pub back: String,
+}
+
+/// Fields of a kind of note, and the templates making cards out of them. Cloze note types have a
+/// single template and make one card per cloze deletion instead.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct NoteType {
+    pub id: i64,
+    pub owner_id: i64,
+    pub name: String,
+    pub cloze: bool,
+    pub fields: Vec<String>,
+    pub templates: Vec<Template>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Note {
+    pub id: i64,
+    pub deck_id: i64,
+    pub note_type_id: i64,
+    pub fields: Vec<String>,
+    pub tags: Vec<String>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+/// Card generated from a note by the template `ord`, or by the cloze deletion `ord` for cloze note
+/// types.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderedCard {
+    pub ord: i32,
+    pub front: String,
+    pub back: String,
+    /// Answer to type before showing the back, asked by a `{{type:Field}}` tag on the front.
+    pub typed_answer: Option<String>,
+}
+
+enum TemplatePart<'a> {
+    Text(&'a str),
+    /// Field tag such as `{{Field}}` or `{{text:cloze:Field}}`, with its filters from the one
+    /// applied first.
+    Field {
+        name: &'a str,
+        filters: Vec<&'a str>,
+    },
+    /// `{{#Field}}...{{/Field}}` section, only rendered when the field isn't empty, or the
+    /// opposite for `{{^Field}}` sections.
+    Section {
+        name: &'a str,
+        inverted: bool,
+        inner: &'a str,
+    },
+}
+
+fn template_parts(template: &str) -> Vec<TemplatePart<'_>> {
+    let mut parts = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        parts.push(TemplatePart::Text(&rest[..start]));
+        let Some(end) = rest[start..].find("}}") else {
+            rest = &rest[start..];
+            break;
+        };
+        let tag = rest[start + 2..start + end].trim();
+        rest = &rest[start + end + 2..];
+
+        let section = tag.strip_prefix('#').or_else(|| tag.strip_prefix('^'));
+        if let Some(name) = section.map(str::trim) {
+            let closing = format!("{{{{/{name}}}}}");
+            let (inner, after) = match rest.find(&closing) {
+                Some(index) => (&rest[..index], &rest[index + closing.len()..]),
+                None => (rest, ""),
+            };
+            parts.push(TemplatePart::Section {
+                name,
+                inverted: tag.starts_with('^'),
+                inner,
+            });
+            rest = after;
+        } else {
+            let mut filters = tag.rsplit(':').map(str::trim);
+            let name = filters.next().unwrap_or_default();
+            parts.push(TemplatePart::Field {
+                name,
+                filters: filters.collect(),
+            });
+        }
+    }
+    parts.push(TemplatePart::Text(rest));
+    parts
+}
+
+/// Names of the fields used by a template, along with whether they go through a cloze filter.
+fn template_fields<'a>(template: &'a str, fields: &mut Vec<(&'a str, bool)>) {
+    for part in template_parts(template) {
+        match part {
+            TemplatePart::Text(_) => (),
+            TemplatePart::Field { name, filters } => {
+                fields.push((name, filters.contains(&"cloze")));
+            }
+            TemplatePart::Section { name, inner, .. } => {
+                fields.push((name, false));
+                template_fields(inner, fields);
+            }
+        }
+    }
+}
+
+struct RenderContext<'a> {
+    fields: &'a HashMap<&'a str, &'a str>,
+    cloze: usize,
+    question: bool,
+    front_side: &'a str,
+}
+
+#[derive(Default)]
+struct Rendered {
+    text: String,
+    /// Whether a non-empty field was rendered, cards whose front has none aren't generated.
+    has_content: bool,
+    typed_answer: Option<String>,
+    /// Whether a `{{type:Field}}` tag showed an empty field, leaving nothing to type.
+    empty_typed_answer: bool,
+}
+
+fn render_template(template: &str, context: &RenderContext, output: &mut Rendered) {
+    for part in template_parts(template) {
+        match part {
+            TemplatePart::Text(text) => output.text.push_str(text),
+            TemplatePart::Field { name, .. } if name == FRONT_SIDE => {
+                output.text.push_str(context.front_side);
+            }
+            TemplatePart::Field { name, filters } => {
+                let Some(value) = context.fields.get(name) else {
+                    continue;
+                };
+                let mut value = value.to_string();
+                for filter in filters {
+                    value = match filter {
+                        "cloze" => render_cloze(&value, context.cloze, context.question),
+                        "type" if context.question => {
+                            if value.trim().is_empty() {
+                                output.empty_typed_answer = true;
+                            } else {
+                                output.typed_answer.get_or_insert(value);
+                            }
+                            String::new()
+                        }
+                        "type" => String::new(),
+                        _ => value,
+                    };
+                }
+                output.has_content |= !value.trim().is_empty();
+                output.text.push_str(&value);
+            }
+            TemplatePart::Section {
+                name,
+                inverted,
+                inner,
+            } => {
+                let non_empty = context
+                    .fields
+                    .get(name)
+                    .is_some_and(|value| !value.trim().is_empty());
+                if non_empty != inverted {
+                    render_template(inner, context, output);
+                }
+            }
+        }
+    }
+}
+
+impl Template {
+    fn render(&self, fields: &HashMap<&str, &str>, cloze: usize) -> Option<RenderedCard> {
+        let mut context = RenderContext {
+            fields,
+            cloze,
+            question: true,
+            front_side: "",
+        };
+        let mut front = Rendered::default();
+        render_template(&self.front, &context, &mut front);
+        if front.empty_typed_answer || (!front.has_content && front.typed_answer.is_none()) {
+            return None;
+        }
+        let front_text = front.text.trim().to_string();
+        context.question = false;
+        context.front_side = &front_text;
+        let mut back = Rendered::default();
+        render_template(&self.back, &context, &mut back);
+        Some(RenderedCard {
+            ord: 0,
+            front: front_text,
+            back: back.text.trim().to_string(),
+            typed_answer: front.typed_answer,
+        })
+    }
+}
+
+impl NoteType {
+    pub fn validate(&self) -> Result<(), NoteError> {
+        if self.name.trim().is_empty() {
+            return Err(NoteError::MissingName);
+        }
+        if self.fields.is_empty() {
+            return Err(NoteError::MissingFields);
+        }
+        let mut names = HashSet::new();
+        for field in &self.fields {
+            let invalid = field.trim().is_empty()
+                || field == FRONT_SIDE
+                || field.contains(['{', '}', ':', '#', '^', '/'])
+                || !names.insert(field.as_str());
+            if invalid {
+                return Err(NoteError::InvalidFieldName(field.clone()));
+            }
+        }
+        if self.templates.is_empty() {
+            return Err(NoteError::MissingTemplates);
+        }
+        if self.cloze && self.templates.len() > 1 {
+            return Err(NoteError::InvalidClozeTemplate);
+        }
+        for template in &self.templates {
+            let mut used_fields = Vec::new();
+            template_fields(&template.front, &mut used_fields);
+            if self.cloze && !used_fields.iter().any(|(_, cloze)| *cloze) {
+                return Err(NoteError::InvalidClozeTemplate);
+            }
+            template_fields(&template.back, &mut used_fields);
+            let unknown_field = used_fields
+                .into_iter()
+                .find(|(name, _)| *name != FRONT_SIDE && !names.contains(name));
+            if let Some((field, _)) = unknown_field {
+                return Err(NoteError::UnknownField {
+                    template: template.name.clone(),
+                    field: field.to_string(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks the fields of a note of this type, which must generate at least one card.
+    pub fn validate_fields(&self, fields: &[String]) -> Result<(), NoteError> {
+        if fields.len() != self.fields.len() {
+            return Err(NoteError::FieldCount {
+                expected: self.fields.len(),
+                actual: fields.len(),
+            });
+        }
+        if fields.first().is_none_or(|field| field.trim().is_empty()) {
+            return Err(NoteError::EmptyFirstField);
+        }
+        if self.render_cards(fields).is_empty() {
+            return Err(NoteError::NoCards);
+        }
+        Ok(())
+    }
+
+    /// Cards generated from the fields of a note, by order of template or cloze number. Templates
+    /// whose front only shows empty fields don't generate any card.
+    pub fn render_cards(&self, fields: &[String]) -> Vec<RenderedCard> {
+        let values: HashMap<&str, &str> = self
+            .fields
+            .iter()
+            .map(String::as_str)
+            .zip(fields.iter().map(String::as_str))
+            .collect();
+        if self.cloze {
+            let Some(template) = self.templates.first() else {
+                return Vec::new();
+            };
+            let mut cloze_fields = Vec::new();
+            template_fields(&template.front, &mut cloze_fields);
+            let mut numbers = cloze_fields
+                .into_iter()
+                .filter(|(_, cloze)| *cloze)
+                .filter_map(|(name, _)| values.get(name))
+                .flat_map(|value| cloze_numbers(value))
+                .collect::<Vec<_>>();
+            numbers.sort_unstable();
+            numbers.dedup();
+            return numbers
+                .into_iter()
+                .filter_map(|number| {
+                    let card = template.render(&values, number)?;
+                    let ord = i32::try_from(number).ok()?;
+                    Some(RenderedCard { ord, ..card })
+                })
+                .collect();
+        }
+        self.templates
+            .iter()
+            .zip(0..)
+            .filter_map(|(template, ord)| {
+                let card = template.render(&values, 0)?;
+                Some(RenderedCard { ord, ..card })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,222 @@
+use chrono::{DateTime, Utc};
+use rs_fsrs::Parameters;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::learn::fsrs::{
+    MAX_DIFFICULTY, MAX_STABILITY, MIN_DIFFICULTY, MIN_STABILITY, retrievability,
+};
+use crate::learn::{CardState, Rating, ReviewLog};
+
+/// Reviews made at least a day after the previous one needed to fit the weights.
+pub const MIN_REVIEWS: usize = 100;
+
+/// Most recent reviews the weights are fitted to, which bounds the time and memory it takes.
+pub const MAX_REVIEWS: i64 = 100_000;
+
+const ITERATIONS: usize = 250;
+const LEARNING_RATE: f64 = 0.04;
+const GRADIENT_STEP: f64 = 1e-4;
+
+/// Range each weight is kept in while fitting, as in the reference FSRS optimizer.
+const WEIGHT_BOUNDS: [(f64, f64); 19] = [
+    (0.001, 100.),
+    (0.001, 100.),
+    (0.001, 100.),
+    (0.001, 100.),
+    (1., 10.),
+    (0.001, 4.),
+    (0.001, 4.),
+    (0.001, 0.75),
+    (0., 4.5),
+    (0., 0.8),
+    (0.001, 3.5),
+    (0.001, 5.),
+    (0.001, 0.25),
+    (0.001, 0.9),
+    (0., 4.),
+    (0., 1.),
+    (1., 6.),
+    (0., 2.),
+    (0., 2.),
+];
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum OptimizationError {
+    #[error("not enough reviews to optimize, {actual} out of {required}")]
+    NotEnoughReviews { required: usize, actual: usize },
+}
+
+/// Weights fitted to the review history of a user, along with how well the default weights and
+/// the fitted ones predicted whether cards were recalled.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OptimizationResult {
+    pub weights: Vec<f64>,
+    pub review_count: usize,
+    pub log_loss_before: f64,
+    pub log_loss_after: f64,
+    pub optimized_at: DateTime<Utc>,
+}
+
+/// Progress of the optimization job of a user.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub enum OptimizationStatus {
+    #[default]
+    Idle,
+    Running {
+        started_at: DateTime<Utc>,
+    },
+    Finished(OptimizationResult),
+    Failed(String),
+}
+
+fn clamp_weights(weights: &mut [f64]) {
+    for (weight, (min, max)) in weights.iter_mut().zip(WEIGHT_BOUNDS) {
+        *weight = weight.clamp(min, max);
+    }
+}
+
+fn grade(rating: Rating) -> f64 {
+    match rating {
+        Rating::Again => 1.,
+        Rating::Hard => 2.,
+        Rating::Good => 3.,
+        Rating::Easy => 4.,
+    }
+}
+
+fn initial_difficulty(w: &[f64], grade: f64) -> f64 {
+    w[4] - (w[5] * (grade - 1.)).exp() + 1.
+}
+
+fn next_difficulty(w: &[f64], difficulty: f64, grade: f64) -> f64 {
+    let delta = -w[6] * (grade - 3.);
+    let difficulty = difficulty + delta * (10. - difficulty) / 9.;
+    let target = initial_difficulty(w, 4.).clamp(MIN_DIFFICULTY, MAX_DIFFICULTY);
+    (w[7] * target + (1. - w[7]) * difficulty).clamp(MIN_DIFFICULTY, MAX_DIFFICULTY)
+}
+
+fn next_stability(w: &[f64], stability: f64, difficulty: f64, r: f64, grade: f64) -> f64 {
+    let stability = if grade == 1. {
+        let forget = w[11]
+            * difficulty.powf(-w[12])
+            * ((stability + 1.).powf(w[13]) - 1.)
+            * (w[14] * (1. - r)).exp();
+        forget.min(stability / (w[17] * w[18]).exp())
+    } else {
+        let hard_penalty = if grade == 2. { w[15] } else { 1. };
+        let easy_bonus = if grade == 4. { w[16] } else { 1. };
+        stability
+            * (w[8].exp()
+                * (11. - difficulty)
+                * stability.powf(-w[9])
+                * ((w[10] * (1. - r)).exp() - 1.)
+                * hard_penalty
+                * easy_bonus
+                + 1.)
+    };
+    stability.clamp(MIN_STABILITY, MAX_STABILITY)
+}
+
+/// Total log loss of the recall predictions made by `w` over the reviews made at least a day
+/// after the previous one, along with how many of them there were.
+fn log_loss(w: &[f64], histories: &[Vec<ReviewLog>]) -> (f64, usize) {
+    let mut loss = 0.;
+    let mut count = 0;
+    for history in histories {
+        let Some((first, reviews)) = history.split_first() else {
+            continue;
+        };
+        let first_grade = grade(first.rating);
+        let mut stability = w[first_grade as usize - 1].clamp(MIN_STABILITY, MAX_STABILITY);
+        let mut difficulty =
+            initial_difficulty(w, first_grade).clamp(MIN_DIFFICULTY, MAX_DIFFICULTY);
+        let mut last_review = first.reviewed_at;
+        for review in reviews {
+            let grade = grade(review.rating);
+            let elapsed_days = (review.reviewed_at - last_review).num_seconds() as f64 / 86400.;
+            if elapsed_days >= 1. {
+                let r = retrievability(elapsed_days, stability).clamp(1e-6, 1. - 1e-6);
+                loss -= if grade > 1. { r.ln() } else { (1. - r).ln() };
+                count += 1;
+                stability = next_stability(w, stability, difficulty, r, grade);
+            } else {
+                let short_term = stability * (w[17] * (grade - 3. + w[18])).exp();
+                stability = short_term.clamp(MIN_STABILITY, MAX_STABILITY);
+            }
+            difficulty = next_difficulty(w, difficulty, grade);
+            last_review = review.reviewed_at;
+        }
+    }
+    (loss, count)
+}
+
+/// Fits the FSRS weights to the review histories of the cards of a user, each sorted by date.
+/// Histories that don't start from a new card are ignored since their first reviews are missing.
+/// The default weights are kept when fitting doesn't improve on them.
+pub fn optimize(
+    histories: &[Vec<ReviewLog>],
+    now: DateTime<Utc>,
+) -> Result<OptimizationResult, OptimizationError> {
+    let histories = histories
+        .iter()
+        .filter(|history| {
+            history
+                .first()
+                .is_some_and(|log| log.state == CardState::New)
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+    let default_weights = Parameters::default().w.to_vec();
+    let (loss_before, review_count) = log_loss(&default_weights, &histories);
+    if review_count < MIN_REVIEWS {
+        return Err(OptimizationError::NotEnoughReviews {
+            required: MIN_REVIEWS,
+            actual: review_count,
+        });
+    }
+    let mean_loss = |weights: &[f64]| log_loss(weights, &histories).0 / review_count as f64;
+
+    // Adam with gradients estimated by central differences, which is enough for 19 weights
+    let mut weights = default_weights.clone();
+    let mut first_moment = vec![0.; weights.len()];
+    let mut second_moment = vec![0.; weights.len()];
+    let (beta1, beta2) = (0.9_f64, 0.999_f64);
+    for iteration in 1..=ITERATIONS {
+        let gradients = (0..weights.len())
+            .map(|index| {
+                let mut shifted = weights.clone();
+                shifted[index] += GRADIENT_STEP;
+                let loss_up = mean_loss(&shifted);
+                shifted[index] -= 2. * GRADIENT_STEP;
+                let loss_down = mean_loss(&shifted);
+                (loss_up - loss_down) / (2. * GRADIENT_STEP)
+            })
+            .collect::<Vec<_>>();
+        let step = iteration as i32;
+        let moments = first_moment.iter_mut().zip(&mut second_moment);
+        for ((weight, gradient), (first, second)) in weights.iter_mut().zip(gradients).zip(moments)
+        {
+            *first = beta1 * *first + (1. - beta1) * gradient;
+            *second = beta2 * *second + (1. - beta2) * gradient * gradient;
+            let first_corrected = *first / (1. - beta1.powi(step));
+            let second_corrected = *second / (1. - beta2.powi(step));
+            *weight -= LEARNING_RATE * first_corrected / (second_corrected.sqrt() + 1e-8);
+        }
+        clamp_weights(&mut weights);
+    }
+
+    let log_loss_before = loss_before / review_count as f64;
+    let mut log_loss_after = mean_loss(&weights);
+    if !log_loss_after.is_finite() || log_loss_after > log_loss_before {
+        weights = default_weights;
+        log_loss_after = log_loss_before;
+    }
+    Ok(OptimizationResult {
+        weights,
+        review_count,
+        log_loss_before,
+        log_loss_after,
+        optimized_at: now,
+    })
+}
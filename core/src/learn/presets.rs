@@ -0,0 +1,259 @@
+use chrono::{DateTime, Duration, Utc};
+use hashbrown::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::learn::fsrs::interval_days;
+use crate::learn::{
+    Card, CardState, MAX_DESIRED_RETENTION, MIN_DESIRED_RETENTION, Rating, ReviewLog, ReviewQueue,
+    SchedulerSettings,
+};
+
+/// Tag added to cards forgotten as many times as the leech threshold of their deck.
+pub const LEECH_TAG: &str = "leech";
+
+/// Longest learning step, in minutes.
+pub const MAX_STEP: i32 = 24 * 60;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum PresetError {
+    #[error("the preset needs a name")]
+    MissingName,
+    #[error("daily limits can't be negative")]
+    NegativeLimit,
+    #[error("learning steps must be between 1 and {MAX_STEP} minutes")]
+    InvalidStep,
+    #[error(
+        "desired retention must be between {MIN_DESIRED_RETENTION} and {MAX_DESIRED_RETENTION}"
+    )]
+    InvalidRetention,
+    #[error("the leech threshold can't be negative")]
+    InvalidLeechThreshold,
+}
+
+/// Options shared by the decks referencing the preset. Decks without one use the defaults.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeckPreset {
+    pub id: i64,
+    pub owner_id: i64,
+    pub name: String,
+    pub new_per_day: i32,
+    pub reviews_per_day: i32,
+    /// Delays in minutes before showing new cards again until they graduate to review.
+    pub learning_steps: Vec<i32>,
+    /// Delays in minutes before showing forgotten cards again until they go back to review.
+    pub relearning_steps: Vec<i32>,
+    pub desired_retention: f64,
+    /// Lapses after which a card is tagged as a leech, or `0` to never tag them.
+    pub leech_threshold: i32,
+    /// Whether a single card of each note is studied per day.
+    pub bury_siblings: bool,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+impl Default for DeckPreset {
+    fn default() -> Self {
+        Self {
+            id: 0,
+            owner_id: 0,
+            name: "Default".to_string(),
+            new_per_day: 20,
+            reviews_per_day: 200,
+            learning_steps: vec![1, 10],
+            relearning_steps: vec![10],
+            desired_retention: 0.9,
+            leech_threshold: 8,
+            bury_siblings: false,
+            created_at: None,
+        }
+    }
+}
+
+impl DeckPreset {
+    pub fn validate(&self) -> Result<(), PresetError> {
+        if self.name.trim().is_empty() {
+            return Err(PresetError::MissingName);
+        }
+        if self.new_per_day < 0 || self.reviews_per_day < 0 {
+            return Err(PresetError::NegativeLimit);
+        }
+        let invalid_step = self
+            .learning_steps
+            .iter()
+            .chain(&self.relearning_steps)
+            .any(|step| !(1..=MAX_STEP).contains(step));
+        if invalid_step {
+            return Err(PresetError::InvalidStep);
+        }
+        if !(MIN_DESIRED_RETENTION..=MAX_DESIRED_RETENTION).contains(&self.desired_retention) {
+            return Err(PresetError::InvalidRetention);
+        }
+        if self.leech_threshold < 0 {
+            return Err(PresetError::InvalidLeechThreshold);
+        }
+        Ok(())
+    }
+
+    /// Moves a card just reviewed through the learning or relearning steps, graduating it to
+    /// review once past the last one or rated easy. Cards in review that weren't forgotten keep
+    /// the schedule FSRS gave them.
+    pub fn apply_steps(
+        &self,
+        card: &mut Card,
+        previous_state: CardState,
+        rating: Rating,
+        now: DateTime<Utc>,
+    ) {
+        let (steps, state) = match (previous_state, rating) {
+            (CardState::New | CardState::Learning, _) => {
+                (&self.learning_steps, CardState::Learning)
+            }
+            (CardState::Relearning, _) | (CardState::Review, Rating::Again) => {
+                (&self.relearning_steps, CardState::Relearning)
+            }
+            (CardState::Review, _) => return,
+        };
+        let previous_step = if previous_state == CardState::Review {
+            0
+        } else {
+            card.learning_step
+        };
+        let step = match rating {
+            Rating::Again => Some(0),
+            Rating::Hard => Some(previous_step),
+            Rating::Good => Some(previous_step + 1),
+            Rating::Easy => None,
+        };
+        let delay = step.and_then(|step| Some((step, *steps.get(usize::try_from(step).ok()?)?)));
+        match delay {
+            Some((step, minutes)) => {
+                card.learning_step = step;
+                card.state = state;
+                card.scheduled_days = 0;
+                card.due = now + Duration::minutes(i64::from(minutes));
+            }
+            None => {
+                let days = interval_days(card.stability, self.desired_retention);
+                card.learning_step = 0;
+                card.state = CardState::Review;
+                card.scheduled_days = days;
+                card.due = now + Duration::days(days);
+            }
+        }
+        self.tag_leech(card, previous_state, rating);
+    }
+
+    fn tag_leech(&self, card: &mut Card, previous_state: CardState, rating: Rating) {
+        let lapsed = previous_state == CardState::Review && rating == Rating::Again;
+        if lapsed
+            && self.leech_threshold > 0
+            && card.lapses >= self.leech_threshold
+            && !card.tags.iter().any(|tag| tag == LEECH_TAG)
+        {
+            card.tags.push(LEECH_TAG.to_string());
+        }
+    }
+}
+
+/// What is left of the daily limits of a deck, along with whether it buries siblings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueueLimits {
+    pub new_cards: usize,
+    pub reviews: usize,
+    pub bury_siblings: bool,
+}
+
+impl QueueLimits {
+    /// Limits of a deck using `preset`, once `new_cards` and `reviews` were studied today.
+    pub fn remaining(preset: &DeckPreset, new_cards: usize, reviews: usize) -> Self {
+        Self {
+            new_cards: usize::try_from(preset.new_per_day)
+                .unwrap_or_default()
+                .saturating_sub(new_cards),
+            reviews: usize::try_from(preset.reviews_per_day)
+                .unwrap_or_default()
+                .saturating_sub(reviews),
+            bury_siblings: preset.bury_siblings,
+        }
+    }
+}
+
+/// Options and daily limits of the decks of a user at some point of their study day, kept along
+/// with the offline copy of their cards so that they are studied as the server would.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct StudyPlan {
+    pub settings: SchedulerSettings,
+    /// Preset of each deck, decks without one using the default options.
+    pub presets: HashMap<i64, DeckPreset>,
+    pub limits: HashMap<i64, QueueLimits>,
+    pub studied_notes: HashSet<i64>,
+    /// Start of the next study day, when the limits are reset.
+    pub next_day: DateTime<Utc>,
+}
+
+impl StudyPlan {
+    pub fn preset(&self, deck_id: i64) -> DeckPreset {
+        self.presets
+            .get(&deck_id)
+            .cloned()
+            .unwrap_or_else(|| self.settings.default_preset())
+    }
+
+    /// Resets the limits once the next study day started.
+    fn roll_over(&mut self, now: DateTime<Utc>) {
+        if now < self.next_day {
+            return;
+        }
+        self.limits = self
+            .presets
+            .iter()
+            .map(|(deck_id, preset)| (*deck_id, QueueLimits::remaining(preset, 0, 0)))
+            .collect();
+        self.studied_notes.clear();
+        let day_start = self.settings.day_start(now);
+        self.next_day = self.settings.day_start(day_start + Duration::hours(36));
+    }
+
+    /// Queue of the cards due at `now` among every card of the user, within the daily limits.
+    pub fn queue(&mut self, cards: &[Card], now: DateTime<Utc>) -> ReviewQueue {
+        self.roll_over(now);
+        let (mut due_cards, later_cards) = cards
+            .iter()
+            .cloned()
+            .partition::<Vec<_>, _>(|card| card.due <= now);
+        due_cards.sort_by_key(|card| card.due);
+        let next_due = later_cards.iter().map(|card| card.due).min();
+        ReviewQueue::limited(
+            due_cards,
+            &self.limits,
+            &self.studied_notes,
+            next_due,
+            self.next_day,
+        )
+    }
+
+    /// Reviews a card with the options of its deck, counting it against the daily limits.
+    pub fn review(&mut self, card: &mut Card, rating: Rating, now: DateTime<Utc>) -> ReviewLog {
+        self.roll_over(now);
+        let preset = self.preset(card.deck_id);
+        let scheduler = SchedulerSettings {
+            desired_retention: preset.desired_retention,
+            ..self.settings.clone()
+        }
+        .scheduler();
+        let previous_state = card.state;
+        let review_log = card.review_with(&scheduler, rating, now);
+        preset.apply_steps(card, previous_state, rating, now);
+        if let Some(limits) = self.limits.get_mut(&card.deck_id) {
+            match previous_state {
+                CardState::New => limits.new_cards = limits.new_cards.saturating_sub(1),
+                CardState::Review => limits.reviews = limits.reviews.saturating_sub(1),
+                CardState::Learning | CardState::Relearning => (),
+            }
+        }
+        if let Some(note_id) = card.note_id {
+            self.studied_notes.insert(note_id);
+        }
+        review_log
+    }
+}
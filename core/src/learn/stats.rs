@@ -0,0 +1,129 @@
+use chrono::{Days, NaiveDate};
+use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
+
+/// Interval in days from which cards in review are considered mature.
+pub const MATURE_INTERVAL: i64 = 21;
+
+/// Most days stats and forecasts can cover.
+pub const MAX_STATS_DAYS: u32 = 365;
+
+/// Reviews made during a study day of a user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DayReviews {
+    pub date: NaiveDate,
+    pub reviews: i64,
+    /// Reviews of cards in review, which retention is measured on.
+    pub graded: i64,
+    /// Reviews of cards in review that weren't forgotten.
+    pub passed: i64,
+    pub time_taken_ms: i64,
+}
+
+impl DayReviews {
+    pub fn new(date: NaiveDate) -> Self {
+        Self {
+            date,
+            reviews: 0,
+            graded: 0,
+            passed: 0,
+            time_taken_ms: 0,
+        }
+    }
+
+    /// Share of cards in review recalled this day, or `None` when none were reviewed.
+    pub fn retention(&self) -> Option<f64> {
+        (self.graded > 0).then(|| self.passed as f64 / self.graded as f64)
+    }
+}
+
+/// How many cards of a user are at each stage of learning.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CardMaturity {
+    pub new: i64,
+    /// Cards in learning or relearning.
+    pub learning: i64,
+    /// Cards in review with an interval shorter than [`MATURE_INTERVAL`].
+    pub young: i64,
+    pub mature: i64,
+}
+
+impl CardMaturity {
+    pub fn total(&self) -> i64 {
+        self.new + self.learning + self.young + self.mature
+    }
+}
+
+/// Reviews of a user over their last study days, oldest first, and the maturity of their cards.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StudyStats {
+    pub days: Vec<DayReviews>,
+    pub maturity: CardMaturity,
+}
+
+impl StudyStats {
+    /// Stats of the `day_count` days up to `today`, days without reviews being left at zero.
+    pub fn new(
+        today: NaiveDate,
+        day_count: u32,
+        reviews: impl IntoIterator<Item = DayReviews>,
+        maturity: CardMaturity,
+    ) -> Self {
+        let mut reviews = reviews
+            .into_iter()
+            .map(|day| (day.date, day))
+            .collect::<HashMap<_, _>>();
+        let start = today - Days::new(u64::from(day_count.saturating_sub(1)));
+        let days = dates(start, day_count)
+            .map(|date| {
+                reviews
+                    .remove(&date)
+                    .unwrap_or_else(|| DayReviews::new(date))
+            })
+            .collect();
+        Self { days, maturity }
+    }
+
+    pub fn reviews(&self) -> i64 {
+        self.days.iter().map(|day| day.reviews).sum()
+    }
+
+    pub fn time_taken_ms(&self) -> i64 {
+        self.days.iter().map(|day| day.time_taken_ms).sum()
+    }
+
+    /// Share of cards in review recalled over the whole period.
+    pub fn retention(&self) -> Option<f64> {
+        let graded = self.days.iter().map(|day| day.graded).sum::<i64>();
+        let passed = self.days.iter().map(|day| day.passed).sum::<i64>();
+        (graded > 0).then(|| passed as f64 / graded as f64)
+    }
+}
+
+/// Cards of a user due on a study day, overdue cards being due today.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DueForecast {
+    pub date: NaiveDate,
+    pub due: i64,
+}
+
+impl DueForecast {
+    /// Forecast of the `day_count` days from `today`, from the number of cards due by date.
+    pub fn days(
+        today: NaiveDate,
+        day_count: u32,
+        due: impl IntoIterator<Item = (NaiveDate, i64)>,
+    ) -> Vec<Self> {
+        let due = due.into_iter().collect::<HashMap<_, _>>();
+        dates(today, day_count)
+            .map(|date| Self {
+                date,
+                due: due.get(&date).copied().unwrap_or_default(),
+            })
+            .collect()
+    }
+}
+
+fn dates(start: NaiveDate, day_count: u32) -> impl Iterator<Item = NaiveDate> {
+    start.iter_days().take(day_count as usize)
+}
@@ -3,8 +3,11 @@ use std::sync::LazyLock;
 
 use directories::ProjectDirs;
 
+pub mod audit;
 pub mod database;
 pub mod errors;
+pub mod events;
+pub mod learn;
 pub mod users;
 
 pub static PROJECT_DIRS: LazyLock<Option<ProjectDirs>> =
@@ -15,6 +18,12 @@ pub fn cookies_path() -> PathBuf {
     project_dirs.cache_dir().with_file_name("cookies.json")
 }
 
+/// File keeping the decks, cards and pending changes of the learn client while offline.
+pub fn offline_store_path() -> PathBuf {
+    let project_dirs = PROJECT_DIRS.clone().unwrap();
+    project_dirs.data_dir().join("offline.json")
+}
+
 pub trait ExternMethod
 where
     Self: Sized,
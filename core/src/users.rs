@@ -1,5 +1,8 @@
+pub mod passwords;
 pub mod permissions;
+pub mod query;
 pub mod roles;
+pub mod sessions;
 
 use anyhow::anyhow;
 use async_trait::async_trait;
@@ -9,7 +12,8 @@ use hashbrown::HashSet;
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 
-use crate::database::get_user;
+use crate::database::{get_user, has_permission};
+use crate::users::permissions::Permission;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct User {
@@ -17,7 +21,17 @@ pub struct User {
     pub anonymous: bool,
     pub username: String,
     pub created_at: Option<DateTime<Utc>>,
-    pub permissions: HashSet<String>,
+    /// Set when the user was deleted, until they are restored or purged for good.
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub roles: HashSet<String>,
+    /// Permissions granted by the roles of the user along with the ones granted directly.
+    pub permissions: HashSet<Permission>,
+}
+
+impl User {
+    pub fn has_permission(&self, permission: Permission) -> bool {
+        self.permissions.contains(&permission)
+    }
 }
 
 impl Default for User {
@@ -27,6 +41,8 @@ impl Default for User {
             anonymous: true,
             username: "Guest".into(),
             created_at: None,
+            deleted_at: None,
+            roles: HashSet::new(),
             permissions: HashSet::new(),
         }
     }
@@ -54,7 +70,17 @@ impl Authentication<User, i64, PgPool> for User {
 
 #[async_trait]
 impl HasPermission<PgPool> for User {
-    async fn has(&self, perm: &str, _pool: &Option<&PgPool>) -> bool {
-        self.permissions.contains(perm)
+    /// Checks the permission against the database when a pool is given, so that changes made
+    /// to the roles of a logged in user apply right away.
+    async fn has(&self, perm: &str, pool: &Option<&PgPool>) -> bool {
+        let Ok(permission) = perm.parse() else {
+            return false;
+        };
+        match pool {
+            Some(pool) => has_permission(pool, self.id, permission)
+                .await
+                .unwrap_or(false),
+            None => self.has_permission(permission),
+        }
     }
 }
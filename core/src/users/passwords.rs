@@ -0,0 +1,106 @@
+use std::sync::OnceLock;
+
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::{Algorithm, Argon2, Params, Version};
+use serde::Deserialize;
+
+use crate::errors::ServerError;
+
+const ALGORITHM: Algorithm = Algorithm::Argon2id;
+const VERSION: Version = Version::V0x13;
+
+/// Parameters passwords are hashed with, set once when the server starts.
+static PASSWORD_PARAMS: OnceLock<PasswordParams> = OnceLock::new();
+
+/// Cost of the Argon2 hashes of passwords. Raising it makes passwords harder to crack but logging
+/// in slower.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PasswordParams {
+    /// Memory used by a hash, in KiB.
+    pub memory_cost: u32,
+    /// Passes over the memory.
+    pub time_cost: u32,
+    /// Lanes hashed in parallel.
+    pub parallelism: u32,
+}
+
+impl Default for PasswordParams {
+    fn default() -> Self {
+        Self {
+            memory_cost: Params::DEFAULT_M_COST,
+            time_cost: Params::DEFAULT_T_COST,
+            parallelism: Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+impl PasswordParams {
+    /// Parameters set when the server started, or the defaults.
+    pub fn current() -> Self {
+        PASSWORD_PARAMS.get().copied().unwrap_or_default()
+    }
+
+    /// Uses these parameters to hash passwords from now on. They can only be set once.
+    pub fn set_current(self) -> Result<(), ServerError> {
+        self.hasher()?;
+        let _ = PASSWORD_PARAMS.set(self);
+        Ok(())
+    }
+
+    /// Hasher with these parameters, failing when Argon2 doesn't accept them.
+    pub fn hasher(&self) -> Result<Argon2<'static>, ServerError> {
+        let params = Params::new(self.memory_cost, self.time_cost, self.parallelism, None)
+            .map_err(|error| ServerError::PasswordHash(error.to_string()))?;
+        Ok(Argon2::new(ALGORITHM, VERSION, params))
+    }
+
+    pub fn hash_password(&self, password: &str) -> Result<String, ServerError> {
+        let salt = SaltString::generate(&mut OsRng);
+        Ok(self
+            .hasher()?
+            .hash_password(password.as_bytes(), &salt)?
+            .to_string())
+    }
+
+    /// Whether `hash` was made with other parameters, in which case the password is hashed again
+    /// the next time its user logs in.
+    pub fn needs_rehash(&self, hash: &str) -> bool {
+        let Ok(parsed_hash) = PasswordHash::new(hash) else {
+            return true;
+        };
+        let Ok(params) = Params::try_from(&parsed_hash) else {
+            return true;
+        };
+        parsed_hash.algorithm != ALGORITHM.ident()
+            || parsed_hash.version != Some(VERSION.into())
+            || params.m_cost() != self.memory_cost
+            || params.t_cost() != self.time_cost
+            || params.p_cost() != self.parallelism
+    }
+}
+
+pub fn hash_password(password: &str) -> Result<String, ServerError> {
+    PasswordParams::current().hash_password(password)
+}
+
+/// Verifies `password` with the parameters stored in `hash`, which may differ from the current
+/// ones.
+pub fn verify_password(password: &str, hash: &str) -> Result<bool, ServerError> {
+    let parsed_hash = PasswordHash::new(hash)?;
+    Ok(Argon2::default()
+        .verify_password(password.as_bytes(), &parsed_hash)
+        .is_ok())
+}
+
+pub fn needs_rehash(hash: &str) -> bool {
+    PasswordParams::current().needs_rehash(hash)
+}
+
+/// Random secret of 256 bits, hex encoded.
+pub fn generate_secret() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
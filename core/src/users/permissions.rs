@@ -1,4 +1,10 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use axum_session_auth::Rights;
+use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
+use thiserror::Error;
 
 #[derive(FromRow, Default, Debug, Clone)]
 pub struct UserPermission {
@@ -12,3 +18,66 @@ impl UserPermission {
         }
     }
 }
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("unknown permission `{0}`")]
+pub struct UnknownPermission(pub String);
+
+/// Every permission known to the server, stored in the database by their token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Permission {
+    ServerManage,
+    UsersView,
+    UsersManage,
+    CurrentUserManage,
+    DecksManage,
+}
+
+impl Permission {
+    pub const ALL: [Permission; 5] = [
+        Permission::ServerManage,
+        Permission::UsersView,
+        Permission::UsersManage,
+        Permission::CurrentUserManage,
+        Permission::DecksManage,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Permission::ServerManage => "Server::Manage",
+            Permission::UsersView => "Users::View",
+            Permission::UsersManage => "Users::Manage",
+            Permission::CurrentUserManage => "CurrentUser::Manage",
+            Permission::DecksManage => "Decks::Manage",
+        }
+    }
+}
+
+impl Display for Permission {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for Permission {
+    type Err = UnknownPermission;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Permission::ALL
+            .into_iter()
+            .find(|permission| permission.as_str() == s)
+            .ok_or_else(|| UnknownPermission(s.to_string()))
+    }
+}
+
+impl From<Permission> for UserPermission {
+    fn from(value: Permission) -> Self {
+        UserPermission::new(value.as_str())
+    }
+}
+
+impl From<Permission> for Rights {
+    fn from(value: Permission) -> Self {
+        Rights::permission(value.as_str())
+    }
+}
@@ -0,0 +1,73 @@
+use serde::{Deserialize, Serialize};
+
+use crate::users::User;
+
+/// Largest page of users the server returns at once.
+pub const MAX_USER_PAGE_SIZE: i64 = 200;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UserSortKey {
+    #[default]
+    Id,
+    Username,
+    CreatedAt,
+}
+
+impl UserSortKey {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            UserSortKey::Id => "id",
+            UserSortKey::Username => "username",
+            UserSortKey::CreatedAt => "created_at",
+        }
+    }
+}
+
+/// Page of users matching `search`, a case insensitive part of their username.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UserQuery {
+    pub search: String,
+    /// Lists the deleted users awaiting their purge instead of the active ones.
+    pub deleted: bool,
+    pub sort_by: UserSortKey,
+    pub descending: bool,
+    pub offset: i64,
+    pub limit: i64,
+}
+
+impl Default for UserQuery {
+    fn default() -> Self {
+        Self {
+            search: String::new(),
+            deleted: false,
+            sort_by: UserSortKey::default(),
+            descending: false,
+            offset: 0,
+            limit: 50,
+        }
+    }
+}
+
+impl UserQuery {
+    /// Whether the user would be listed by this query, whatever page they would be on.
+    pub fn matches(&self, user: &User) -> bool {
+        user.deleted_at.is_some() == self.deleted
+            && user
+                .username
+                .to_lowercase()
+                .contains(&self.search.to_lowercase())
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct UserPage {
+    pub users: Vec<User>,
+    /// Number of users matching the query across all pages.
+    pub total: i64,
+}
+
+impl UserPage {
+    pub fn has_more(&self, query: &UserQuery) -> bool {
+        query.offset + (self.users.len() as i64) < self.total
+    }
+}
@@ -1,26 +1,14 @@
-use crate::users::permissions::UserPermission;
+use hashbrown::HashSet;
+use serde::{Deserialize, Serialize};
 
-#[derive(Default)]
-pub enum UserRole {
-    #[default]
-    Guest,
-    Normal,
-    Admin,
-}
+use crate::users::permissions::Permission;
+
+/// Role given to users when they sign up.
+pub const DEFAULT_ROLE: &str = "Normal";
 
-impl UserRole {
-    pub fn permissions(&self) -> Vec<UserPermission> {
-        match self {
-            UserRole::Guest => Vec::new(),
-            UserRole::Normal => vec![
-                UserPermission::new("Users::View"),
-                UserPermission::new("CurrentUser::Manage"),
-            ],
-            UserRole::Admin => vec![
-                UserPermission::new("Server::Manage"),
-                UserPermission::new("Users::View"),
-                UserPermission::new("Users::Manage"),
-            ],
-        }
-    }
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Role {
+    pub id: i64,
+    pub name: String,
+    pub permissions: HashSet<Permission>,
 }
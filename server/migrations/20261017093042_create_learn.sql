@@ -0,0 +1,40 @@
+CREATE TABLE IF NOT EXISTS decks (
+    id          BIGSERIAL PRIMARY KEY,
+    owner_id    BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name        TEXT NOT NULL CHECK (name <> ''),
+    description TEXT NOT NULL DEFAULT '',
+    created_at  TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS decks_owner_id_idx ON decks (owner_id);
+
+CREATE TABLE IF NOT EXISTS cards (
+    id             BIGSERIAL PRIMARY KEY,
+    deck_id        BIGINT NOT NULL REFERENCES decks(id) ON DELETE CASCADE,
+    front          TEXT NOT NULL,
+    back           TEXT NOT NULL,
+    due            TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    stability      DOUBLE PRECISION NOT NULL DEFAULT 0,
+    difficulty     DOUBLE PRECISION NOT NULL DEFAULT 0,
+    elapsed_days   BIGINT NOT NULL DEFAULT 0,
+    scheduled_days BIGINT NOT NULL DEFAULT 0,
+    reps           INTEGER NOT NULL DEFAULT 0,
+    lapses         INTEGER NOT NULL DEFAULT 0,
+    state          SMALLINT NOT NULL DEFAULT 0 CHECK (state BETWEEN 0 AND 3),
+    last_review    TIMESTAMPTZ,
+    created_at     TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS cards_deck_id_due_idx ON cards (deck_id, due);
+
+CREATE TABLE IF NOT EXISTS review_logs (
+    id             BIGSERIAL PRIMARY KEY,
+    card_id        BIGINT NOT NULL REFERENCES cards(id) ON DELETE CASCADE,
+    rating         SMALLINT NOT NULL CHECK (rating BETWEEN 1 AND 4),
+    state          SMALLINT NOT NULL CHECK (state BETWEEN 0 AND 3),
+    elapsed_days   BIGINT NOT NULL,
+    scheduled_days BIGINT NOT NULL,
+    reviewed_at    TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS review_logs_card_id_idx ON review_logs (card_id);
@@ -0,0 +1,4 @@
+INSERT INTO user_permissions (user_id, token)
+    SELECT user_id, 'Decks::Manage' FROM user_permissions
+    WHERE token = 'Users::Manage'
+    AND user_id NOT IN (SELECT user_id FROM user_permissions WHERE token = 'Decks::Manage');
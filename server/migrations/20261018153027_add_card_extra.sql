@@ -0,0 +1 @@
+ALTER TABLE cards ADD COLUMN IF NOT EXISTS extra TEXT[] NOT NULL DEFAULT '{}';
@@ -0,0 +1,65 @@
+CREATE TABLE IF NOT EXISTS roles (
+    id   BIGSERIAL PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE CHECK (name <> '')
+);
+
+CREATE TABLE IF NOT EXISTS role_permissions (
+    role_id BIGINT NOT NULL REFERENCES roles(id) ON DELETE CASCADE,
+    token   TEXT NOT NULL,
+    PRIMARY KEY (role_id, token)
+);
+
+CREATE TABLE IF NOT EXISTS user_roles (
+    user_id BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    role_id BIGINT NOT NULL REFERENCES roles(id) ON DELETE CASCADE,
+    PRIMARY KEY (user_id, role_id)
+);
+
+INSERT INTO roles (name)
+    VALUES ('Normal'), ('Admin')
+    ON CONFLICT (name)
+    DO NOTHING;
+
+INSERT INTO role_permissions (role_id, token)
+    SELECT roles.id, defaults.token FROM roles
+    JOIN (VALUES
+        ('Normal', 'Users::View'),
+        ('Normal', 'CurrentUser::Manage'),
+        ('Admin', 'Server::Manage'),
+        ('Admin', 'Users::View'),
+        ('Admin', 'Users::Manage'),
+        ('Admin', 'Decks::Manage')
+    ) AS defaults (role, token) ON defaults.role = roles.name
+    ON CONFLICT (role_id, token)
+    DO NOTHING;
+
+-- Existing users get the role they were created with, then only the permissions that role
+-- doesn't cover are kept as direct grants
+INSERT INTO user_roles (user_id, role_id)
+    SELECT DISTINCT user_permissions.user_id, roles.id FROM user_permissions
+    JOIN roles ON roles.name = 'Admin'
+    WHERE user_permissions.token = 'Users::Manage'
+    ON CONFLICT (user_id, role_id)
+    DO NOTHING;
+
+INSERT INTO user_roles (user_id, role_id)
+    SELECT DISTINCT user_permissions.user_id, roles.id FROM user_permissions
+    JOIN roles ON roles.name = 'Normal'
+    WHERE user_permissions.token = 'CurrentUser::Manage'
+    ON CONFLICT (user_id, role_id)
+    DO NOTHING;
+
+DELETE FROM user_permissions
+    USING user_roles, role_permissions
+    WHERE user_roles.user_id = user_permissions.user_id
+    AND role_permissions.role_id = user_roles.role_id
+    AND role_permissions.token = user_permissions.token;
+
+DELETE FROM user_permissions duplicate
+    USING user_permissions original
+    WHERE duplicate.ctid > original.ctid
+    AND duplicate.user_id = original.user_id
+    AND duplicate.token = original.token;
+
+CREATE UNIQUE INDEX IF NOT EXISTS user_permissions_user_id_token_idx
+    ON user_permissions (user_id, token);
@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS card_images (
+    id           BIGSERIAL PRIMARY KEY,
+    owner_id     BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    content_type TEXT NOT NULL,
+    data         BYTEA NOT NULL,
+    created_at   TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS card_images_owner_id_idx ON card_images (owner_id);
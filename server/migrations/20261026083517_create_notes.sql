@@ -0,0 +1,31 @@
+CREATE TABLE IF NOT EXISTS note_types (
+    id         BIGSERIAL PRIMARY KEY,
+    owner_id   BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name       TEXT NOT NULL CHECK (name <> ''),
+    cloze      BOOLEAN NOT NULL DEFAULT FALSE,
+    fields     TEXT[] NOT NULL,
+    templates  JSONB NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS note_types_owner_id_idx ON note_types (owner_id);
+
+-- Note types can't be deleted while notes use them
+CREATE TABLE IF NOT EXISTS notes (
+    id           BIGSERIAL PRIMARY KEY,
+    deck_id      BIGINT NOT NULL REFERENCES decks(id) ON DELETE CASCADE,
+    note_type_id BIGINT NOT NULL REFERENCES note_types(id),
+    fields       TEXT[] NOT NULL,
+    tags         TEXT[] NOT NULL DEFAULT '{}',
+    created_at   TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS notes_deck_id_idx ON notes (deck_id);
+CREATE INDEX IF NOT EXISTS notes_note_type_id_idx ON notes (note_type_id);
+
+ALTER TABLE cards ADD COLUMN IF NOT EXISTS note_id BIGINT REFERENCES notes(id) ON DELETE CASCADE;
+ALTER TABLE cards ADD COLUMN IF NOT EXISTS template_ord INTEGER;
+ALTER TABLE cards ADD COLUMN IF NOT EXISTS typed_answer TEXT;
+
+CREATE UNIQUE INDEX IF NOT EXISTS cards_note_id_template_ord_idx
+    ON cards (note_id, template_ord) WHERE note_id IS NOT NULL;
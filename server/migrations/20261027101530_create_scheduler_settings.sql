@@ -0,0 +1,11 @@
+-- Weights are only set once fitted to the review history of the user
+CREATE TABLE IF NOT EXISTS scheduler_settings (
+    user_id           BIGINT PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    desired_retention DOUBLE PRECISION NOT NULL DEFAULT 0.9
+        CHECK (desired_retention BETWEEN 0.7 AND 0.99),
+    weights           DOUBLE PRECISION[],
+    review_count      BIGINT,
+    log_loss_before   DOUBLE PRECISION,
+    log_loss_after    DOUBLE PRECISION,
+    optimized_at      TIMESTAMPTZ
+);
@@ -0,0 +1,28 @@
+CREATE TABLE IF NOT EXISTS deck_presets (
+    id                BIGSERIAL PRIMARY KEY,
+    owner_id          BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name              TEXT NOT NULL CHECK (name <> ''),
+    new_per_day       INTEGER NOT NULL DEFAULT 20 CHECK (new_per_day >= 0),
+    reviews_per_day   INTEGER NOT NULL DEFAULT 200 CHECK (reviews_per_day >= 0),
+    learning_steps    INTEGER[] NOT NULL DEFAULT '{1, 10}',
+    relearning_steps  INTEGER[] NOT NULL DEFAULT '{10}',
+    desired_retention DOUBLE PRECISION NOT NULL DEFAULT 0.9
+        CHECK (desired_retention BETWEEN 0.7 AND 0.99),
+    leech_threshold   INTEGER NOT NULL DEFAULT 8 CHECK (leech_threshold >= 0),
+    bury_siblings     BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at        TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS deck_presets_owner_id_idx ON deck_presets (owner_id);
+
+-- Decks whose preset is deleted go back to the default options
+ALTER TABLE decks ADD COLUMN IF NOT EXISTS preset_id BIGINT
+    REFERENCES deck_presets(id) ON DELETE SET NULL;
+
+ALTER TABLE cards ADD COLUMN IF NOT EXISTS learning_step INTEGER NOT NULL DEFAULT 0;
+
+ALTER TABLE scheduler_settings ADD COLUMN IF NOT EXISTS timezone TEXT NOT NULL DEFAULT 'UTC';
+ALTER TABLE scheduler_settings ADD COLUMN IF NOT EXISTS day_start_hour SMALLINT NOT NULL DEFAULT 4
+    CHECK (day_start_hour BETWEEN 0 AND 23);
+
+CREATE INDEX IF NOT EXISTS review_logs_reviewed_at_idx ON review_logs (reviewed_at);
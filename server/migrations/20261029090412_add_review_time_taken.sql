@@ -0,0 +1 @@
+ALTER TABLE review_logs ADD COLUMN IF NOT EXISTS time_taken_ms BIGINT NOT NULL DEFAULT 0;
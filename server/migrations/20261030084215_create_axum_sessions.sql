@@ -0,0 +1,7 @@
+-- Created by the session store when the server starts, and here as well so that the sessions of
+-- users can be joined to it
+CREATE TABLE IF NOT EXISTS axum_sessions (
+    id      VARCHAR(128) NOT NULL PRIMARY KEY,
+    expires INTEGER NULL,
+    session TEXT NOT NULL
+);
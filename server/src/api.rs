@@ -1,41 +1,197 @@
+use kreqo_core::audit::{AuditPage, AuditQuery};
 use kreqo_core::errors::ServerError;
+use kreqo_core::events::ServerEvent;
+use kreqo_core::learn::content::CardImage;
+use kreqo_core::learn::delimited::Delimiter;
+use kreqo_core::learn::notes::{Note, NoteType, Template};
+use kreqo_core::learn::optimizer::OptimizationStatus;
+use kreqo_core::learn::presets::{DeckPreset, StudyPlan};
+use kreqo_core::learn::stats::{DueForecast, StudyStats};
+use kreqo_core::learn::{Card, Deck, OfflineReview, Rating, ReviewQueue, SchedulerSettings};
 use kreqo_core::users::User;
+use kreqo_core::users::permissions::Permission;
+use kreqo_core::users::query::{UserPage, UserQuery};
+use kreqo_core::users::roles::Role;
+use kreqo_core::users::sessions::UserSession;
+use server_fn::codec::JsonEncoding;
+use server_fn::{BoxedStream, Websocket};
 use server_fn_macro_default::server;
 
 use crate::custom_client::client::CustomClient;
+use crate::custom_client::multipart::{Multipart, MultipartBody, multipart_args};
 
 cfg_if::cfg_if! {
     if #[cfg(feature = "ssr")] {
-        use argon2::{Argon2, PasswordHash, PasswordVerifier};
+        use chrono::Utc;
+        use futures::{StreamExt, future};
         use axum_session_auth::{Auth, Rights};
+        use kreqo_core::audit::{AuditAction, AuditTarget};
         use kreqo_core::database;
-        use kreqo_core::database::get_sql_user_from_username;
-        use kreqo_core::users::roles::UserRole;
+        use kreqo_core::events::ItemEvent;
+        use kreqo_core::learn::{anki, content, delimited};
+        use kreqo_core::learn::delimited::DelimitedImport;
+        use kreqo_core::learn::{MAX_DESIRED_RETENTION, MIN_DESIRED_RETENTION};
+        use kreqo_core::learn::stats::MAX_STATS_DAYS;
+        use server_fn::error::ServerFnErrorErr;
+        use kreqo_core::users::passwords::verify_password;
+        use kreqo_core::users::roles::DEFAULT_ROLE;
         use reqwest::Method;
-        use sqlx::PgPool;
+        use serde::Serialize;
+        use sqlx::{Acquire, PgConnection, PgPool};
 
-        use crate::context::{auth, pool, context};
+        use crate::context::{auth, client_address, context, pool};
+        use crate::events;
+        use crate::optimization;
 
-        async fn require_perms(user: User, rights: Rights) -> Result<(), ServerError> {
+        async fn require_perms(
+            pool: &PgPool,
+            user: User,
+            rights: Rights,
+        ) -> Result<(), ServerError> {
             if !Auth::<User, i64, PgPool>::build([Method::POST], true)
                 .requires(rights)
-                .validate(&user, &Method::POST, None)
+                .validate(&user, &Method::POST, Some(pool))
                 .await
             {
                 return Err(ServerError::Unauthorized);
             }
             Ok(())
         }
+
+        async fn require_user_access(
+            pool: &PgPool,
+            user: User,
+            user_id: i64,
+        ) -> Result<(), ServerError> {
+            if user_id == 1 {
+                return Err(ServerError::Unauthorized);
+            }
+            if user_id == user.id {
+                require_perms(
+                    pool,
+                    user,
+                    Rights::any([
+                        Permission::UsersManage.into(),
+                        Permission::CurrentUserManage.into(),
+                    ]),
+                )
+                .await
+            } else {
+                require_perms(pool, user, Permission::UsersManage.into()).await
+            }
+        }
+
+        /// Roles and permissions of a user can only be changed by someone else, who holds every
+        /// permission at stake, so that managing users doesn't allow to become an admin.
+        async fn require_grant_access(
+            pool: &PgPool,
+            user: User,
+            user_id: i64,
+            permissions: &[Permission],
+        ) -> Result<(), ServerError> {
+            if user_id == 1 || user_id == user.id {
+                return Err(ServerError::Unauthorized);
+            }
+            if !permissions
+                .iter()
+                .all(|permission| user.has_permission(*permission))
+            {
+                return Err(ServerError::Unauthorized);
+            }
+            require_perms(pool, user, Permission::UsersManage.into()).await
+        }
+
+        async fn require_deck_access(
+            pool: &PgPool,
+            user: User,
+            owner_id: i64,
+        ) -> Result<(), ServerError> {
+            if owner_id == user.id {
+                require_perms(
+                    pool,
+                    user,
+                    Rights::any([
+                        Permission::DecksManage.into(),
+                        Permission::CurrentUserManage.into(),
+                    ]),
+                )
+                .await
+            } else {
+                require_perms(pool, user, Permission::DecksManage.into()).await
+            }
+        }
+
+        /// Checks the rights on decks before the owner of an item is looked up, an item which
+        /// doesn't exist being refused like one of another user so that ids can't be probed.
+        async fn require_deck_item_access(
+            pool: &PgPool,
+            user: User,
+            owner_id: Option<i64>,
+        ) -> Result<i64, ServerError> {
+            require_perms(
+                pool,
+                user.clone(),
+                Rights::any([
+                    Permission::DecksManage.into(),
+                    Permission::CurrentUserManage.into(),
+                ]),
+            )
+            .await?;
+            let owner_id = owner_id.ok_or(ServerError::Unauthorized)?;
+            require_deck_access(pool, user, owner_id).await?;
+            Ok(owner_id)
+        }
+
+        /// Applies `change` to an item of `owner_id`, recording it in the audit log along with the
+        /// state of the item `before` and after it when the actor isn't the owner.
+        async fn audited<B: Serialize, T: Serialize>(
+            pool: &PgPool,
+            actor_id: i64,
+            owner_id: i64,
+            action: AuditAction,
+            target: impl FnOnce(&T) -> AuditTarget,
+            before: Option<B>,
+            change: impl AsyncFnOnce(&mut PgConnection) -> Result<T, ServerError>,
+        ) -> Result<T, ServerError> {
+            if owner_id == actor_id {
+                return change(&mut *pool.acquire().await?).await;
+            }
+            let mut tx = pool.begin().await?;
+            let value = change(&mut *tx).await?;
+            let after = (!action.is_deletion()).then_some(&value);
+            database::audit::record(&mut *tx, actor_id, action, target(&value), before, after)
+                .await?;
+            tx.commit().await?;
+            Ok(value)
+        }
     }
 }
 
 #[server]
 pub async fn cleanup_expired_sessions() -> Result<Vec<String>, ServerError> {
-    let auth = auth()?;
+    let (pool, auth) = context();
+    let auth = auth?;
     let current_user = auth.current_user.unwrap_or_default();
-    require_perms(current_user, Rights::permission("Server::Manage")).await?;
+    let actor_id = current_user.id;
+    require_perms(pool, current_user, Permission::ServerManage.into()).await?;
 
-    let removed_session_ids = auth.session.get_store().cleanup().await?;
+    let mut removed_session_ids = auth.session.get_store().cleanup().await?;
+    let mut tx = pool.begin().await?;
+    for session_id in database::sessions::delete_stale_sessions(&mut *tx).await? {
+        if !removed_session_ids.contains(&session_id) {
+            removed_session_ids.push(session_id);
+        }
+    }
+    database::audit::record(
+        &mut *tx,
+        actor_id,
+        AuditAction::CleanupSessions,
+        AuditTarget::Server,
+        None::<()>,
+        Some(removed_session_ids.len()),
+    )
+    .await?;
+    tx.commit().await?;
     Ok(removed_session_ids)
 }
 
@@ -45,28 +201,68 @@ pub async fn current_user() -> Result<User, ServerError> {
     Ok(auth.current_user.unwrap_or_default())
 }
 
+/// Pushes the changes made by any client that the current user is allowed to see, for as long as
+/// the websocket stays open. Nothing has to be sent by the client.
+#[server(protocol = Websocket<JsonEncoding, JsonEncoding>)]
+pub async fn subscribe_events(
+    input: BoxedStream<(), ServerError>,
+) -> Result<BoxedStream<ServerEvent, ServerError>, ServerError> {
+    let (pool, auth) = context();
+    let auth = auth?;
+    let current_user = auth.current_user.unwrap_or_default();
+    if current_user.anonymous {
+        return Err(ServerError::Unauthorized);
+    }
+    let session_id = auth.session.get_session_id().to_string();
+
+    // Draining the input keeps the websocket open until the client closes it, or until the
+    // events end because the session is no longer valid
+    let closed = input.for_each(|_| future::ready(()));
+    let events = events::subscribe(pool, current_user, session_id);
+    Ok(events.take_until(closed).into())
+}
+
+/// Failed attempts are tracked per username and per client address, each failure past a few
+/// delaying the next attempt exponentially until the login is temporarily locked.
 #[server]
 pub async fn login(username: String, password: String) -> Result<(), ServerError> {
     let (pool, auth) = context();
 
-    let sql_user = get_sql_user_from_username(pool, username)
-        .await
-        .map_err(|_| ServerError::WrongLogin)?;
+    let username_key = format!("username:{}", username.to_lowercase());
+    let address_key = client_address().map(|address| format!("address:{address}"));
+    let keys = [Some(username_key.clone()), address_key.clone()]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+    database::login_attempts::reserve_login_attempt(pool, &keys).await?;
+
+    let Some(sql_user) = database::verify_login(pool, username, &password).await? else {
+        return Err(ServerError::WrongLogin);
+    };
+    database::login_attempts::clear_login_failures(pool, &username_key).await?;
+    if let Some(address_key) = address_key {
+        database::login_attempts::decay_login_failures(pool, &address_key).await?;
+    }
 
-    let parsed_hash = PasswordHash::new(&sql_user.password)?;
-    Argon2::default()
-        .verify_password(password.as_bytes(), &parsed_hash)
-        .map_err(|_| ServerError::WrongLogin)?;
+    if sql_user.password_reset_required {
+        return Err(ServerError::PasswordResetRequired);
+    }
 
-    auth?.login_user(sql_user.id);
+    let auth = auth?;
+    let session_id = auth.session.get_session_id().to_string();
+    database::sessions::delete_sessions(pool, &[session_id]).await?;
+    auth.login_user(sql_user.id);
 
     Ok(())
 }
 
 #[server]
 pub async fn logout() -> Result<(), ServerError> {
-    let auth = auth();
-    auth?.logout_user();
+    let (pool, auth) = context();
+    let auth = auth?;
+    let session_id = auth.session.get_session_id().to_string();
+    database::sessions::delete_sessions(pool, &[session_id]).await?;
+    auth.logout_user();
     Ok(())
 }
 
@@ -76,25 +272,102 @@ pub async fn signup(username: String, password: String) -> Result<User, ServerEr
 
     #[cfg(debug_assertions)]
     std::thread::sleep(std::time::Duration::from_millis(500));
-    database::create_user(pool, username, password, UserRole::Normal).await
+    let user = database::create_user(pool, username, password, DEFAULT_ROLE).await?;
+    events::publish(ServerEvent::User(ItemEvent::Created(user.clone())));
+    Ok(user)
+}
+
+#[server]
+pub async fn change_password(
+    old_password: String,
+    new_password: String,
+) -> Result<(), ServerError> {
+    let (pool, auth) = context();
+    let auth = auth?;
+    let session_id = auth.session.get_session_id().to_string();
+    let current_user = auth.current_user.unwrap_or_default();
+
+    if current_user.id == 1 {
+        return Err(ServerError::Unauthorized);
+    }
+    let id = current_user.id;
+    require_perms(pool, current_user, Permission::CurrentUserManage.into()).await?;
+
+    let sql_user = database::get_sql_user(pool, id).await?;
+    if !verify_password(&old_password, &sql_user.password)? {
+        return Err(ServerError::WrongPassword);
+    }
+
+    #[cfg(debug_assertions)]
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    // Other sessions may have been opened by whoever knew the old password
+    let mut tx = pool.begin().await?;
+    database::update_user_password(&mut *tx, id, new_password).await?;
+    database::sessions::revoke_other_sessions(&mut *tx, id, &session_id).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Prevents the user from logging in until their password is reset with the returned token.
+#[server]
+pub async fn force_password_reset(user_id: i64) -> Result<String, ServerError> {
+    let (pool, auth) = context();
+    let current_user = auth?.current_user.unwrap_or_default();
+
+    if user_id == 1 {
+        return Err(ServerError::Unauthorized);
+    }
+    let actor_id = current_user.id;
+    require_perms(pool, current_user, Permission::UsersManage.into()).await?;
+
+    #[cfg(debug_assertions)]
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    let mut tx = pool.begin().await?;
+    let token = database::create_password_reset(&mut *tx, user_id, true).await?;
+    database::audit::record(
+        &mut *tx,
+        actor_id,
+        AuditAction::ForcePasswordReset,
+        AuditTarget::User(user_id),
+        None::<()>,
+        None::<()>,
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(token)
+}
+
+#[server]
+pub async fn reset_password(token: String, new_password: String) -> Result<(), ServerError> {
+    let pool = pool();
+
+    #[cfg(debug_assertions)]
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    database::reset_password(pool, &token, new_password).await?;
+    Ok(())
 }
 
 #[server]
-pub async fn get_users() -> Result<Vec<User>, ServerError> {
+pub async fn get_users(query: UserQuery) -> Result<UserPage, ServerError> {
     let (pool, auth) = context();
     let current_user = auth?.current_user.unwrap_or_default();
-    require_perms(current_user, Rights::permission("Users::View")).await?;
+    let permission = if query.deleted {
+        Permission::UsersManage
+    } else {
+        Permission::UsersView
+    };
+    require_perms(pool, current_user, permission.into()).await?;
 
     #[cfg(debug_assertions)]
     std::thread::sleep(std::time::Duration::from_millis(500));
-    database::get_users(pool).await
+    database::get_users(pool, &query).await
 }
 
 #[server]
 pub async fn get_user(id: i64) -> Result<User, ServerError> {
     let (pool, auth) = context();
     let current_user = auth?.current_user.unwrap_or_default();
-    require_perms(current_user, Rights::permission("Users::View")).await?;
+    require_perms(pool, current_user, Permission::UsersView.into()).await?;
 
     #[cfg(debug_assertions)]
     std::thread::sleep(std::time::Duration::from_millis(500));
@@ -105,7 +378,7 @@ pub async fn get_user(id: i64) -> Result<User, ServerError> {
 pub async fn get_user_from_username(username: String) -> Result<User, ServerError> {
     let (pool, auth) = context();
     let current_user = auth?.current_user.unwrap_or_default();
-    require_perms(current_user, Rights::permission("Users::View")).await?;
+    require_perms(pool, current_user, Permission::UsersView.into()).await?;
 
     #[cfg(debug_assertions)]
     std::thread::sleep(std::time::Duration::from_millis(500));
@@ -117,51 +390,1070 @@ pub async fn update_user_username(id: i64, username: String) -> Result<User, Ser
     let (pool, auth) = context();
     let current_user = auth?.current_user.unwrap_or_default();
 
-    if id == 1 {
+    let actor_id = current_user.id;
+    require_user_access(pool, current_user, id).await?;
+
+    #[cfg(debug_assertions)]
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    let before = database::get_user(pool, id).await?;
+    let user = audited(
+        pool,
+        actor_id,
+        id,
+        AuditAction::UpdateUsername,
+        |_| AuditTarget::User(id),
+        Some(before),
+        async |conn| database::update_user_username(conn, id, username).await,
+    )
+    .await?;
+    events::publish(ServerEvent::User(ItemEvent::Updated(user.clone())));
+    Ok(user)
+}
+
+#[server]
+pub async fn delete_user(id: i64) -> Result<i64, ServerError> {
+    let (pool, auth) = context();
+    let current_user = auth?.current_user.unwrap_or_default();
+
+    let actor_id = current_user.id;
+    require_user_access(pool, current_user, id).await?;
+
+    #[cfg(debug_assertions)]
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    let before = database::get_user(pool, id).await?;
+    audited(
+        pool,
+        actor_id,
+        id,
+        AuditAction::DeleteUser,
+        |_| AuditTarget::User(id),
+        Some(before),
+        async |conn| database::delete_user(conn, id).await,
+    )
+    .await?;
+    events::publish(ServerEvent::User(ItemEvent::Deleted(id)));
+    Ok(id)
+}
+
+/// Restores a deleted user that wasn't purged yet.
+#[server]
+pub async fn restore_user(id: i64) -> Result<User, ServerError> {
+    let (pool, auth) = context();
+    let current_user = auth?.current_user.unwrap_or_default();
+    let actor_id = current_user.id;
+    require_perms(pool, current_user, Permission::UsersManage.into()).await?;
+
+    #[cfg(debug_assertions)]
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    let mut tx = pool.begin().await?;
+    let user = database::restore_user(&mut *tx, id).await?;
+    database::audit::record(
+        &mut *tx,
+        actor_id,
+        AuditAction::RestoreUser,
+        AuditTarget::User(id),
+        None::<()>,
+        Some(&user),
+    )
+    .await?;
+    tx.commit().await?;
+    events::publish(ServerEvent::User(ItemEvent::Created(user.clone())));
+    Ok(user)
+}
+
+#[server]
+pub async fn get_sessions(user_id: i64) -> Result<Vec<UserSession>, ServerError> {
+    let (pool, auth) = context();
+    let auth = auth?;
+    let current_user = auth.current_user.clone().unwrap_or_default();
+    require_user_access(pool, current_user, user_id).await?;
+
+    #[cfg(debug_assertions)]
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    let session_id = auth.session.get_session_id().to_string();
+    database::sessions::get_user_sessions(pool, user_id, &session_id).await
+}
+
+#[server]
+pub async fn revoke_session(user_id: i64, id: i64) -> Result<i64, ServerError> {
+    let (pool, auth) = context();
+    let current_user = auth?.current_user.unwrap_or_default();
+    let actor_id = current_user.id;
+    require_user_access(pool, current_user, user_id).await?;
+
+    #[cfg(debug_assertions)]
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    audited(
+        pool,
+        actor_id,
+        user_id,
+        AuditAction::RevokeSession,
+        |_| AuditTarget::User(user_id),
+        None::<()>,
+        async |conn| database::sessions::revoke_session(conn, user_id, id).await,
+    )
+    .await
+}
+
+/// Revokes every session of the user except the one making the request.
+#[server]
+pub async fn revoke_other_sessions(user_id: i64) -> Result<Vec<i64>, ServerError> {
+    let (pool, auth) = context();
+    let auth = auth?;
+    let current_user = auth.current_user.clone().unwrap_or_default();
+    let actor_id = current_user.id;
+    require_user_access(pool, current_user, user_id).await?;
+
+    #[cfg(debug_assertions)]
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    let session_id = auth.session.get_session_id().to_string();
+    audited(
+        pool,
+        actor_id,
+        user_id,
+        AuditAction::RevokeOtherSessions,
+        |_| AuditTarget::User(user_id),
+        None::<()>,
+        async |conn| database::sessions::revoke_other_sessions(conn, user_id, &session_id).await,
+    )
+    .await
+}
+
+#[server]
+pub async fn get_roles() -> Result<Vec<Role>, ServerError> {
+    let (pool, auth) = context();
+    let current_user = auth?.current_user.unwrap_or_default();
+    require_perms(pool, current_user, Permission::UsersView.into()).await?;
+
+    #[cfg(debug_assertions)]
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    database::roles::get_roles(pool).await
+}
+
+#[server]
+pub async fn grant_role(user_id: i64, role_id: i64) -> Result<User, ServerError> {
+    let (pool, auth) = context();
+    let current_user = auth?.current_user.unwrap_or_default();
+
+    let actor_id = current_user.id;
+    let permissions = database::roles::get_role_permissions(pool, role_id).await?;
+    require_grant_access(pool, current_user, user_id, &permissions).await?;
+
+    #[cfg(debug_assertions)]
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    let mut tx = pool.begin().await?;
+    let before = database::get_user(&mut *tx, user_id).await?;
+    database::roles::grant_role(&mut *tx, user_id, role_id).await?;
+    let user = database::get_user(&mut *tx, user_id).await?;
+    database::audit::record(
+        &mut *tx,
+        actor_id,
+        AuditAction::GrantRole,
+        AuditTarget::User(user_id),
+        Some(&before),
+        Some(&user),
+    )
+    .await?;
+    tx.commit().await?;
+    events::publish(ServerEvent::User(ItemEvent::Updated(user.clone())));
+    Ok(user)
+}
+
+#[server]
+pub async fn revoke_role(user_id: i64, role_id: i64) -> Result<User, ServerError> {
+    let (pool, auth) = context();
+    let current_user = auth?.current_user.unwrap_or_default();
+
+    let actor_id = current_user.id;
+    let permissions = database::roles::get_role_permissions(pool, role_id).await?;
+    require_grant_access(pool, current_user, user_id, &permissions).await?;
+
+    #[cfg(debug_assertions)]
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    let mut tx = pool.begin().await?;
+    let before = database::get_user(&mut *tx, user_id).await?;
+    database::roles::revoke_role(&mut *tx, user_id, role_id).await?;
+    let user = database::get_user(&mut *tx, user_id).await?;
+    database::audit::record(
+        &mut *tx,
+        actor_id,
+        AuditAction::RevokeRole,
+        AuditTarget::User(user_id),
+        Some(&before),
+        Some(&user),
+    )
+    .await?;
+    tx.commit().await?;
+    events::publish(ServerEvent::User(ItemEvent::Updated(user.clone())));
+    Ok(user)
+}
+
+#[server]
+pub async fn grant_permission(user_id: i64, permission: Permission) -> Result<User, ServerError> {
+    let (pool, auth) = context();
+    let current_user = auth?.current_user.unwrap_or_default();
+
+    let actor_id = current_user.id;
+    require_grant_access(pool, current_user, user_id, &[permission]).await?;
+
+    #[cfg(debug_assertions)]
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    let mut tx = pool.begin().await?;
+    let before = database::get_user(&mut *tx, user_id).await?;
+    database::roles::grant_permission(&mut *tx, user_id, permission).await?;
+    let user = database::get_user(&mut *tx, user_id).await?;
+    database::audit::record(
+        &mut *tx,
+        actor_id,
+        AuditAction::GrantPermission,
+        AuditTarget::User(user_id),
+        Some(&before),
+        Some(&user),
+    )
+    .await?;
+    tx.commit().await?;
+    events::publish(ServerEvent::User(ItemEvent::Updated(user.clone())));
+    Ok(user)
+}
+
+/// Only revokes a permission granted directly, the user keeps it if one of their roles grants it.
+#[server]
+pub async fn revoke_permission(user_id: i64, permission: Permission) -> Result<User, ServerError> {
+    let (pool, auth) = context();
+    let current_user = auth?.current_user.unwrap_or_default();
+
+    let actor_id = current_user.id;
+    require_grant_access(pool, current_user, user_id, &[permission]).await?;
+
+    #[cfg(debug_assertions)]
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    let mut tx = pool.begin().await?;
+    let before = database::get_user(&mut *tx, user_id).await?;
+    database::roles::revoke_permission(&mut *tx, user_id, permission).await?;
+    let user = database::get_user(&mut *tx, user_id).await?;
+    database::audit::record(
+        &mut *tx,
+        actor_id,
+        AuditAction::RevokePermission,
+        AuditTarget::User(user_id),
+        Some(&before),
+        Some(&user),
+    )
+    .await?;
+    tx.commit().await?;
+    events::publish(ServerEvent::User(ItemEvent::Updated(user.clone())));
+    Ok(user)
+}
+
+#[server]
+pub async fn get_audit_log(query: AuditQuery) -> Result<AuditPage, ServerError> {
+    let (pool, auth) = context();
+    let current_user = auth?.current_user.unwrap_or_default();
+    require_perms(pool, current_user, Permission::ServerManage.into()).await?;
+
+    #[cfg(debug_assertions)]
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    database::audit::get_audit_log(pool, &query).await
+}
+
+#[server]
+pub async fn get_decks() -> Result<Vec<Deck>, ServerError> {
+    let (pool, auth) = context();
+    let current_user = auth?.current_user.unwrap_or_default();
+    let owner_id = current_user.id;
+    require_deck_access(pool, current_user, owner_id).await?;
+
+    #[cfg(debug_assertions)]
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    database::learn::get_decks(pool, owner_id).await
+}
+
+#[server]
+pub async fn get_user_decks(owner_id: i64) -> Result<Vec<Deck>, ServerError> {
+    let (pool, auth) = context();
+    let current_user = auth?.current_user.unwrap_or_default();
+    require_deck_access(pool, current_user, owner_id).await?;
+
+    #[cfg(debug_assertions)]
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    database::learn::get_decks(pool, owner_id).await
+}
+
+#[server]
+pub async fn create_deck(name: String, description: String) -> Result<Deck, ServerError> {
+    let (pool, auth) = context();
+    let current_user = auth?.current_user.unwrap_or_default();
+    let owner_id = current_user.id;
+    require_deck_access(pool, current_user, owner_id).await?;
+
+    #[cfg(debug_assertions)]
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    let deck = database::learn::create_deck(pool, owner_id, name, description).await?;
+    events::publish(ServerEvent::deck(
+        owner_id,
+        ItemEvent::Created(deck.clone()),
+    ));
+    Ok(deck)
+}
+
+#[server]
+pub async fn update_deck(id: i64, name: String, description: String) -> Result<Deck, ServerError> {
+    let (pool, auth) = context();
+    let current_user = auth?.current_user.unwrap_or_default();
+    let actor_id = current_user.id;
+    let owner_id = database::learn::get_deck_owner(pool, id).await?;
+    let owner_id = require_deck_item_access(pool, current_user, owner_id).await?;
+    let deck = database::learn::get_deck(pool, id).await?;
+
+    #[cfg(debug_assertions)]
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    let updated_deck = audited(
+        pool,
+        actor_id,
+        owner_id,
+        AuditAction::UpdateDeck,
+        |_| AuditTarget::Deck(id),
+        Some(deck),
+        async |conn| database::learn::update_deck(conn, id, name, description).await,
+    )
+    .await?;
+    events::publish(ServerEvent::deck(
+        owner_id,
+        ItemEvent::Updated(updated_deck.clone()),
+    ));
+    Ok(updated_deck)
+}
+
+#[server]
+pub async fn delete_deck(id: i64) -> Result<i64, ServerError> {
+    let (pool, auth) = context();
+    let current_user = auth?.current_user.unwrap_or_default();
+    let actor_id = current_user.id;
+    let owner_id = database::learn::get_deck_owner(pool, id).await?;
+    let owner_id = require_deck_item_access(pool, current_user, owner_id).await?;
+    let deck = database::learn::get_deck(pool, id).await?;
+
+    #[cfg(debug_assertions)]
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    audited(
+        pool,
+        actor_id,
+        owner_id,
+        AuditAction::DeleteDeck,
+        |_| AuditTarget::Deck(id),
+        Some(deck),
+        async |conn| database::learn::delete_deck(conn, id).await,
+    )
+    .await?;
+    events::publish(ServerEvent::deck(owner_id, ItemEvent::Deleted(id)));
+    Ok(id)
+}
+
+#[server]
+pub async fn get_cards(deck_id: i64) -> Result<Vec<Card>, ServerError> {
+    let (pool, auth) = context();
+    let current_user = auth?.current_user.unwrap_or_default();
+    let owner_id = database::learn::get_deck_owner(pool, deck_id).await?;
+    require_deck_item_access(pool, current_user, owner_id).await?;
+
+    #[cfg(debug_assertions)]
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    database::learn::get_cards(pool, deck_id).await
+}
+
+#[server]
+pub async fn create_card(deck_id: i64, front: String, back: String) -> Result<Card, ServerError> {
+    let (pool, auth) = context();
+    let current_user = auth?.current_user.unwrap_or_default();
+    let actor_id = current_user.id;
+    let owner_id = database::learn::get_deck_owner(pool, deck_id).await?;
+    let owner_id = require_deck_item_access(pool, current_user, owner_id).await?;
+
+    #[cfg(debug_assertions)]
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    audited(
+        pool,
+        actor_id,
+        owner_id,
+        AuditAction::CreateCard,
+        |card: &Card| AuditTarget::Card(card.id),
+        None::<()>,
+        async |conn| database::learn::create_card(conn, deck_id, front, back).await,
+    )
+    .await
+}
+
+#[server]
+pub async fn update_card(id: i64, front: String, back: String) -> Result<Card, ServerError> {
+    let (pool, auth) = context();
+    let current_user = auth?.current_user.unwrap_or_default();
+    let actor_id = current_user.id;
+    let owner_id = database::learn::get_card_owner(pool, id).await?;
+    let owner_id = require_deck_item_access(pool, current_user, owner_id).await?;
+    let card = database::learn::get_card(pool, id).await?;
+
+    #[cfg(debug_assertions)]
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    audited(
+        pool,
+        actor_id,
+        owner_id,
+        AuditAction::UpdateCard,
+        |_| AuditTarget::Card(id),
+        Some(card),
+        async |conn| database::learn::update_card(conn, id, front, back).await,
+    )
+    .await
+}
+
+#[server]
+pub async fn delete_card(id: i64) -> Result<i64, ServerError> {
+    let (pool, auth) = context();
+    let current_user = auth?.current_user.unwrap_or_default();
+    let actor_id = current_user.id;
+    let owner_id = database::learn::get_card_owner(pool, id).await?;
+    let owner_id = require_deck_item_access(pool, current_user, owner_id).await?;
+    let card = database::learn::get_card(pool, id).await?;
+
+    #[cfg(debug_assertions)]
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    audited(
+        pool,
+        actor_id,
+        owner_id,
+        AuditAction::DeleteCard,
+        |_| AuditTarget::Card(id),
+        Some(card),
+        async |conn| database::learn::delete_card(conn, id).await,
+    )
+    .await
+}
+
+#[server]
+pub async fn get_review_queue() -> Result<ReviewQueue, ServerError> {
+    let (pool, auth) = context();
+    let current_user = auth?.current_user.unwrap_or_default();
+    let owner_id = current_user.id;
+    require_deck_access(pool, current_user, owner_id).await?;
+
+    database::learn::get_review_queue(pool, owner_id, Utc::now()).await
+}
+
+/// Options and daily limits of the decks of the current user, so that their offline copy of the
+/// cards is studied as the server would.
+#[server]
+pub async fn get_study_plan() -> Result<StudyPlan, ServerError> {
+    let (pool, auth) = context();
+    let current_user = auth?.current_user.unwrap_or_default();
+    let owner_id = current_user.id;
+    require_deck_access(pool, current_user, owner_id).await?;
+
+    database::learn::get_study_plan(pool, owner_id, Utc::now()).await
+}
+
+/// Creates one card per `{{cN::...}}` cloze deletion of `text`.
+#[server]
+pub async fn create_cloze_cards(deck_id: i64, text: String) -> Result<Vec<Card>, ServerError> {
+    let (pool, auth) = context();
+    let current_user = auth?.current_user.unwrap_or_default();
+    let actor_id = current_user.id;
+    let owner_id = database::learn::get_deck_owner(pool, deck_id).await?;
+    let owner_id = require_deck_item_access(pool, current_user, owner_id).await?;
+
+    if content::cloze_numbers(&text).is_empty() {
+        return Err(ServerError::API(ServerFnErrorErr::Args(
+            "text has no cloze deletion".to_string(),
+        )));
+    }
+    #[cfg(debug_assertions)]
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    audited(
+        pool,
+        actor_id,
+        owner_id,
+        AuditAction::CreateCard,
+        |_| AuditTarget::Deck(deck_id),
+        None::<()>,
+        async |conn| database::learn::create_cloze_cards(conn, deck_id, &text).await,
+    )
+    .await
+}
+
+#[server]
+pub async fn get_note_types() -> Result<Vec<NoteType>, ServerError> {
+    let (pool, auth) = context();
+    let current_user = auth?.current_user.unwrap_or_default();
+    let owner_id = current_user.id;
+    require_deck_access(pool, current_user, owner_id).await?;
+
+    #[cfg(debug_assertions)]
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    database::notes::get_note_types(pool, owner_id).await
+}
+
+#[server]
+pub async fn create_note_type(
+    name: String,
+    cloze: bool,
+    fields: Vec<String>,
+    templates: Vec<Template>,
+) -> Result<NoteType, ServerError> {
+    let (pool, auth) = context();
+    let current_user = auth?.current_user.unwrap_or_default();
+    let owner_id = current_user.id;
+    require_deck_access(pool, current_user, owner_id).await?;
+
+    let note_type = NoteType {
+        name,
+        cloze,
+        fields,
+        templates,
+        ..Default::default()
+    };
+    note_type.validate()?;
+    #[cfg(debug_assertions)]
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    database::notes::create_note_type(pool, owner_id, &note_type).await
+}
+
+/// Updates a note type, regenerating the cards of its notes.
+#[server]
+pub async fn update_note_type(
+    id: i64,
+    name: String,
+    fields: Vec<String>,
+    templates: Vec<Template>,
+    cloze: bool,
+) -> Result<NoteType, ServerError> {
+    let (pool, auth) = context();
+    let current_user = auth?.current_user.unwrap_or_default();
+    let actor_id = current_user.id;
+    let owner_id = database::notes::get_note_type_owner(pool, id).await?;
+    let owner_id = require_deck_item_access(pool, current_user, owner_id).await?;
+    let previous = database::notes::get_note_type(pool, id).await?;
+
+    let note_type = NoteType {
+        name,
+        fields,
+        templates,
+        cloze,
+        ..previous.clone()
+    };
+    note_type.validate()?;
+    #[cfg(debug_assertions)]
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    audited(
+        pool,
+        actor_id,
+        owner_id,
+        AuditAction::UpdateNoteType,
+        |_| AuditTarget::NoteType(id),
+        Some(previous),
+        async |conn| database::notes::update_note_type(conn, id, &note_type).await,
+    )
+    .await
+}
+
+/// Deletes a note type, which fails while notes still use it.
+#[server]
+pub async fn delete_note_type(id: i64) -> Result<i64, ServerError> {
+    let (pool, auth) = context();
+    let current_user = auth?.current_user.unwrap_or_default();
+    let actor_id = current_user.id;
+    let owner_id = database::notes::get_note_type_owner(pool, id).await?;
+    let owner_id = require_deck_item_access(pool, current_user, owner_id).await?;
+    let note_type = database::notes::get_note_type(pool, id).await?;
+
+    #[cfg(debug_assertions)]
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    audited(
+        pool,
+        actor_id,
+        owner_id,
+        AuditAction::DeleteNoteType,
+        |_| AuditTarget::NoteType(id),
+        Some(note_type),
+        async |conn| database::notes::delete_note_type(conn, id).await,
+    )
+    .await
+}
+
+#[server]
+pub async fn get_notes(deck_id: i64) -> Result<Vec<Note>, ServerError> {
+    let (pool, auth) = context();
+    let current_user = auth?.current_user.unwrap_or_default();
+    let owner_id = database::learn::get_deck_owner(pool, deck_id).await?;
+    require_deck_item_access(pool, current_user, owner_id).await?;
+
+    #[cfg(debug_assertions)]
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    database::notes::get_notes(pool, deck_id).await
+}
+
+/// Creates a note in a deck along with the cards generated by its note type, which must belong
+/// to the owner of the deck.
+#[server]
+pub async fn create_note(
+    deck_id: i64,
+    note_type_id: i64,
+    fields: Vec<String>,
+    tags: Vec<String>,
+) -> Result<(Note, Vec<Card>), ServerError> {
+    let (pool, auth) = context();
+    let current_user = auth?.current_user.unwrap_or_default();
+    let actor_id = current_user.id;
+    let owner_id = database::learn::get_deck_owner(pool, deck_id).await?;
+    let owner_id = require_deck_item_access(pool, current_user, owner_id).await?;
+    let note_type = database::notes::get_note_type(pool, note_type_id).await?;
+    if note_type.owner_id != owner_id {
         return Err(ServerError::Unauthorized);
     }
 
-    if id == current_user.id {
-        require_perms(
-            current_user,
-            Rights::any([
-                Rights::permission("Users::Manage"),
-                Rights::permission("CurrentUser::Manage"),
-            ]),
-        )
-        .await?;
-    } else {
-        require_perms(current_user, Rights::permission("Users::Manage")).await?;
+    #[cfg(debug_assertions)]
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    audited(
+        pool,
+        actor_id,
+        owner_id,
+        AuditAction::CreateNote,
+        |(note, _): &(Note, Vec<Card>)| AuditTarget::Note(note.id),
+        None::<()>,
+        async |conn| database::notes::create_note(conn, deck_id, &note_type, fields, tags).await,
+    )
+    .await
+}
+
+/// Updates the fields of a note. Cards whose content didn't change keep their review history.
+#[server]
+pub async fn update_note(
+    id: i64,
+    fields: Vec<String>,
+    tags: Vec<String>,
+) -> Result<(Note, Vec<Card>), ServerError> {
+    let (pool, auth) = context();
+    let current_user = auth?.current_user.unwrap_or_default();
+    let actor_id = current_user.id;
+    let owner_id = database::notes::get_note_owner(pool, id).await?;
+    let owner_id = require_deck_item_access(pool, current_user, owner_id).await?;
+    let note = database::notes::get_note(pool, id).await?;
+    let note_type = database::notes::get_note_type(pool, note.note_type_id).await?;
+
+    #[cfg(debug_assertions)]
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    audited(
+        pool,
+        actor_id,
+        owner_id,
+        AuditAction::UpdateNote,
+        |_| AuditTarget::Note(id),
+        Some(note),
+        async |conn| database::notes::update_note(conn, id, &note_type, fields, tags).await,
+    )
+    .await
+}
+
+/// Deletes a note along with its cards.
+#[server]
+pub async fn delete_note(id: i64) -> Result<i64, ServerError> {
+    let (pool, auth) = context();
+    let current_user = auth?.current_user.unwrap_or_default();
+    let actor_id = current_user.id;
+    let owner_id = database::notes::get_note_owner(pool, id).await?;
+    let owner_id = require_deck_item_access(pool, current_user, owner_id).await?;
+    let note = database::notes::get_note(pool, id).await?;
+
+    #[cfg(debug_assertions)]
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    audited(
+        pool,
+        actor_id,
+        owner_id,
+        AuditAction::DeleteNote,
+        |_| AuditTarget::Note(id),
+        Some(note),
+        async |conn| database::notes::delete_note(conn, id).await,
+    )
+    .await
+}
+
+/// Stores an image sent in the `image` field, returning it to be referenced from cards with
+/// [`kreqo_core::learn::content::image_markdown`].
+#[server(input = Multipart)]
+pub async fn upload_image(data: MultipartBody) -> Result<CardImage, ServerError> {
+    let (pool, auth) = context();
+    let current_user = auth?.current_user.unwrap_or_default();
+    let owner_id = current_user.id;
+    require_deck_access(pool, current_user, owner_id).await?;
+
+    // The size is checked while the image is received rather than once it is in memory
+    let image = match data.read_field("image", content::MAX_IMAGE_SIZE).await {
+        Ok(image) => image,
+        Err(ServerError::FieldTooLarge { limit, .. } | ServerError::RequestTooLarge { limit }) => {
+            return Err(ServerError::InvalidImage(format!(
+                "larger than {limit} bytes"
+            )));
+        }
+        Err(error) => return Err(error),
+    };
+    let Some(content_type) = content::image_content_type(&image) else {
+        return Err(ServerError::InvalidImage("unsupported format".to_string()));
+    };
+    database::learn::create_image(pool, owner_id, content_type, &image).await
+}
+
+multipart_args!(UploadImage);
+
+#[server]
+pub async fn get_image(id: i64) -> Result<CardImage, ServerError> {
+    let (pool, auth) = context();
+    let current_user = auth?.current_user.unwrap_or_default();
+    let image = database::learn::get_image(pool, id).await?;
+    require_deck_access(pool, current_user, image.owner_id).await?;
+
+    Ok(image)
+}
+
+#[server]
+pub async fn review_card(id: i64, rating: Rating, time_taken_ms: i64) -> Result<Card, ServerError> {
+    let (pool, auth) = context();
+    let current_user = auth?.current_user.unwrap_or_default();
+    let owner_id = database::learn::get_card_owner(pool, id).await?;
+    require_deck_item_access(pool, current_user, owner_id).await?;
+
+    database::learn::review_card(pool, id, rating, time_taken_ms, Utc::now()).await
+}
+
+/// Applies the reviews made while offline, skipping the ones of cards that were deleted or
+/// reviewed again since.
+#[server]
+pub async fn sync_reviews(reviews: Vec<OfflineReview>) -> Result<Vec<Card>, ServerError> {
+    let (pool, auth) = context();
+    let current_user = auth?.current_user.unwrap_or_default();
+    let owner_id = current_user.id;
+    require_deck_access(pool, current_user, owner_id).await?;
+
+    database::learn::sync_reviews(pool, owner_id, reviews, Utc::now()).await
+}
+
+#[server]
+pub async fn get_scheduler_settings() -> Result<SchedulerSettings, ServerError> {
+    let (pool, auth) = context();
+    let current_user = auth?.current_user.unwrap_or_default();
+    let owner_id = current_user.id;
+    require_deck_access(pool, current_user, owner_id).await?;
+
+    database::scheduler::get_scheduler_settings(pool, owner_id).await
+}
+
+/// Sets the share of due cards the current user wants to recall.
+#[server]
+pub async fn update_desired_retention(
+    desired_retention: f64,
+) -> Result<SchedulerSettings, ServerError> {
+    let (pool, auth) = context();
+    let current_user = auth?.current_user.unwrap_or_default();
+    let owner_id = current_user.id;
+    require_deck_access(pool, current_user, owner_id).await?;
+
+    if !(MIN_DESIRED_RETENTION..=MAX_DESIRED_RETENTION).contains(&desired_retention) {
+        return Err(ServerError::API(ServerFnErrorErr::Args(format!(
+            "desired retention must be between {MIN_DESIRED_RETENTION} and {MAX_DESIRED_RETENTION}"
+        ))));
+    }
+    #[cfg(debug_assertions)]
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    database::scheduler::update_desired_retention(pool, owner_id, desired_retention).await
+}
+
+/// Starts fitting the scheduler weights of the current user to their review history, whose
+/// progress is then polled with [`get_optimization_status`].
+#[server]
+pub async fn optimize_scheduler() -> Result<OptimizationStatus, ServerError> {
+    let (pool, auth) = context();
+    let current_user = auth?.current_user.unwrap_or_default();
+    let owner_id = current_user.id;
+    require_deck_access(pool, current_user, owner_id).await?;
+
+    Ok(optimization::start(pool.clone(), owner_id))
+}
+
+#[server]
+pub async fn get_optimization_status() -> Result<OptimizationStatus, ServerError> {
+    let (pool, auth) = context();
+    let current_user = auth?.current_user.unwrap_or_default();
+    let owner_id = current_user.id;
+    require_deck_access(pool, current_user, owner_id).await?;
+
+    Ok(optimization::status(owner_id))
+}
+
+/// Sets the time zone of the current user, and the local hour at which their study days start.
+#[server]
+pub async fn update_day_boundary(
+    timezone: String,
+    day_start_hour: u32,
+) -> Result<SchedulerSettings, ServerError> {
+    let (pool, auth) = context();
+    let current_user = auth?.current_user.unwrap_or_default();
+    let owner_id = current_user.id;
+    require_deck_access(pool, current_user, owner_id).await?;
+
+    if timezone.parse::<chrono_tz::Tz>().is_err() || day_start_hour > 23 {
+        return Err(ServerError::API(ServerFnErrorErr::Args(
+            "invalid time zone or day start hour".to_string(),
+        )));
     }
+    #[cfg(debug_assertions)]
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    database::scheduler::update_day_boundary(pool, owner_id, &timezone, day_start_hour).await
+}
+
+#[server]
+pub async fn get_deck_presets() -> Result<Vec<DeckPreset>, ServerError> {
+    let (pool, auth) = context();
+    let current_user = auth?.current_user.unwrap_or_default();
+    let owner_id = current_user.id;
+    require_deck_access(pool, current_user, owner_id).await?;
 
     #[cfg(debug_assertions)]
     std::thread::sleep(std::time::Duration::from_millis(500));
-    database::update_user_username(pool, id, username).await
+    database::presets::get_deck_presets(pool, owner_id).await
 }
 
 #[server]
-pub async fn delete_user(id: i64) -> Result<i64, ServerError> {
+pub async fn create_deck_preset(preset: DeckPreset) -> Result<DeckPreset, ServerError> {
+    let (pool, auth) = context();
+    let current_user = auth?.current_user.unwrap_or_default();
+    let owner_id = current_user.id;
+    require_deck_access(pool, current_user, owner_id).await?;
+
+    preset.validate()?;
+    #[cfg(debug_assertions)]
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    database::presets::create_deck_preset(pool, owner_id, &preset).await
+}
+
+#[server]
+pub async fn update_deck_preset(preset: DeckPreset) -> Result<DeckPreset, ServerError> {
     let (pool, auth) = context();
     let current_user = auth?.current_user.unwrap_or_default();
+    let actor_id = current_user.id;
+    let owner_id = database::presets::get_deck_preset_owner(pool, preset.id).await?;
+    let owner_id = require_deck_item_access(pool, current_user, owner_id).await?;
+
+    preset.validate()?;
+    #[cfg(debug_assertions)]
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    let previous = database::presets::get_deck_preset(pool, preset.id).await?;
+    audited(
+        pool,
+        actor_id,
+        owner_id,
+        AuditAction::UpdateDeckPreset,
+        |_| AuditTarget::DeckPreset(preset.id),
+        Some(previous),
+        async |conn| database::presets::update_deck_preset(conn, preset.id, &preset).await,
+    )
+    .await
+}
 
-    if id == 1 {
+#[server]
+pub async fn delete_deck_preset(id: i64) -> Result<i64, ServerError> {
+    let (pool, auth) = context();
+    let current_user = auth?.current_user.unwrap_or_default();
+    let actor_id = current_user.id;
+    let owner_id = database::presets::get_deck_preset_owner(pool, id).await?;
+    let owner_id = require_deck_item_access(pool, current_user, owner_id).await?;
+
+    #[cfg(debug_assertions)]
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    let preset = database::presets::get_deck_preset(pool, id).await?;
+    audited(
+        pool,
+        actor_id,
+        owner_id,
+        AuditAction::DeleteDeckPreset,
+        |_| AuditTarget::DeckPreset(id),
+        Some(preset),
+        async |conn| database::presets::delete_deck_preset(conn, id).await,
+    )
+    .await
+}
+
+/// Makes a deck use the options of a preset of its owner, or the default options with `None`.
+#[server]
+pub async fn set_deck_preset(deck_id: i64, preset_id: Option<i64>) -> Result<Deck, ServerError> {
+    let (pool, auth) = context();
+    let current_user = auth?.current_user.unwrap_or_default();
+    let actor_id = current_user.id;
+    let owner_id = database::learn::get_deck_owner(pool, deck_id).await?;
+    let owner_id = require_deck_item_access(pool, current_user, owner_id).await?;
+    let deck = database::learn::get_deck(pool, deck_id).await?;
+    if let Some(preset_id) = preset_id
+        && database::presets::get_deck_preset_owner(pool, preset_id).await? != Some(owner_id)
+    {
         return Err(ServerError::Unauthorized);
     }
 
-    if id == current_user.id {
-        require_perms(
-            current_user,
-            Rights::any([
-                Rights::permission("Users::Manage"),
-                Rights::permission("CurrentUser::Manage"),
-            ]),
-        )
-        .await?;
-    } else {
-        require_perms(current_user, Rights::permission("Users::Manage")).await?;
-    }
+    #[cfg(debug_assertions)]
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    let updated_deck = audited(
+        pool,
+        actor_id,
+        owner_id,
+        AuditAction::UpdateDeck,
+        |_| AuditTarget::Deck(deck_id),
+        Some(deck),
+        async |conn| database::presets::set_deck_preset(conn, deck_id, preset_id).await,
+    )
+    .await?;
+    events::publish(ServerEvent::deck(
+        owner_id,
+        ItemEvent::Updated(updated_deck.clone()),
+    ));
+    Ok(updated_deck)
+}
+
+/// Reviews of the current user over their last `days` study days, and the maturity of their
+/// cards.
+#[server]
+pub async fn get_study_stats(days: u32) -> Result<StudyStats, ServerError> {
+    let (pool, auth) = context();
+    let current_user = auth?.current_user.unwrap_or_default();
+    let owner_id = current_user.id;
+    require_deck_access(pool, current_user, owner_id).await?;
+
+    let days = days.clamp(1, MAX_STATS_DAYS);
+    database::stats::get_study_stats(pool, owner_id, Utc::now(), days).await
+}
+
+/// Cards of the current user due on each of their next `days` study days.
+#[server]
+pub async fn get_due_forecast(days: u32) -> Result<Vec<DueForecast>, ServerError> {
+    let (pool, auth) = context();
+    let current_user = auth?.current_user.unwrap_or_default();
+    let owner_id = current_user.id;
+    require_deck_access(pool, current_user, owner_id).await?;
+
+    let days = days.clamp(1, MAX_STATS_DAYS);
+    database::stats::get_due_forecast(pool, owner_id, Utc::now(), days).await
+}
+
+/// Every card of the decks of the current user, kept by the client to review while offline.
+#[server]
+pub async fn get_all_cards() -> Result<Vec<Card>, ServerError> {
+    let (pool, auth) = context();
+    let current_user = auth?.current_user.unwrap_or_default();
+    let owner_id = current_user.id;
+    require_deck_access(pool, current_user, owner_id).await?;
 
     #[cfg(debug_assertions)]
     std::thread::sleep(std::time::Duration::from_millis(500));
-    database::delete_user(pool, id).await
+    database::learn::get_owner_cards(pool, owner_id).await
+}
+
+/// Imports every deck of an Anki package sent in the `package` field, along with the review
+/// history of their cards.
+#[server(input = Multipart)]
+pub async fn import_anki_package(data: MultipartBody) -> Result<Vec<Deck>, ServerError> {
+    let (pool, auth) = context();
+    let current_user = auth?.current_user.unwrap_or_default();
+    let actor_id = current_user.id;
+    let owner_id = current_user.id;
+    require_deck_access(pool, current_user, owner_id).await?;
+
+    let package = data.read_field("package", anki::MAX_PACKAGE_SIZE).await?;
+    let imported_decks = tokio::task::spawn_blocking(move || anki::parse_package(&package))
+        .await
+        .map_err(|error| ServerError::Import(error.to_string()))??;
+    let decks = audited(
+        pool,
+        actor_id,
+        owner_id,
+        AuditAction::ImportDeck,
+        |_| AuditTarget::User(owner_id),
+        None::<()>,
+        async |conn| {
+            // Either every deck of the package is imported or none is
+            let mut tx = conn.begin().await?;
+            let mut decks = Vec::with_capacity(imported_decks.len());
+            for imported_deck in imported_decks {
+                decks.push(database::learn::import_deck(&mut *tx, owner_id, imported_deck).await?);
+            }
+            tx.commit().await?;
+            Ok(decks)
+        },
+    )
+    .await?;
+    for deck in &decks {
+        events::publish(ServerEvent::deck(
+            owner_id,
+            ItemEvent::Created(deck.clone()),
+        ));
+    }
+    Ok(decks)
+}
+
+multipart_args!(ImportAnkiPackage);
+
+/// Imports a deck from a delimited text file sent in the `file` field, with the column mapping and
+/// deck name sent as JSON in the `import` field.
+#[server(input = Multipart)]
+pub async fn import_delimited_deck(data: MultipartBody) -> Result<Deck, ServerError> {
+    let (pool, auth) = context();
+    let current_user = auth?.current_user.unwrap_or_default();
+    let actor_id = current_user.id;
+    let owner_id = current_user.id;
+    require_deck_access(pool, current_user, owner_id).await?;
+
+    let mut fields = data
+        .read_fields(
+            delimited::MAX_FILE_SIZE,
+            delimited::MAX_FILE_SIZE + delimited::MAX_IMPORT_SIZE,
+        )
+        .await?;
+    let (Some(file), Some(import)) = (fields.remove("file"), fields.remove("import")) else {
+        return Err(ServerError::API(ServerFnErrorErr::MissingArg(
+            "file or import".to_string(),
+        )));
+    };
+    let import: DelimitedImport = serde_json::from_slice(&import)
+        .map_err(|error| ServerError::API(ServerFnErrorErr::Deserialization(error.to_string())))?;
+    let imported_deck = delimited::parse_cards(&file, &import)?;
+    let deck = audited(
+        pool,
+        actor_id,
+        owner_id,
+        AuditAction::ImportDeck,
+        |deck: &Deck| AuditTarget::Deck(deck.id),
+        None::<()>,
+        async |conn| database::learn::import_deck(conn, owner_id, imported_deck).await,
+    )
+    .await?;
+    events::publish(ServerEvent::deck(
+        owner_id,
+        ItemEvent::Created(deck.clone()),
+    ));
+    Ok(deck)
+}
+
+multipart_args!(ImportDelimitedDeck);
+
+#[server]
+pub async fn export_deck(
+    id: i64,
+    delimiter: Delimiter,
+    include_scheduling: bool,
+) -> Result<String, ServerError> {
+    let (pool, auth) = context();
+    let current_user = auth?.current_user.unwrap_or_default();
+    let owner_id = database::learn::get_deck_owner(pool, id).await?;
+    require_deck_item_access(pool, current_user, owner_id).await?;
+
+    let cards = database::learn::get_cards(pool, id).await?;
+    Ok(delimited::export_cards(
+        &cards,
+        delimiter,
+        include_scheduling,
+    )?)
 }
@@ -0,0 +1,319 @@
+use std::env;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use axum_session::{SameSite, SessionConfig};
+use kreqo_core::users::passwords::PasswordParams;
+use serde::Deserialize;
+use sqlx::PgPool;
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+use thiserror::Error;
+
+use crate::SERVER_ADDRESS;
+
+/// Config file read when `KREQO_CONFIG` isn't set.
+const DEFAULT_CONFIG_PATH: &str = "kreqo-server.toml";
+/// Longest time a deleted user is kept, which keeps the purge date computable.
+const MAX_DELETION_RETENTION: i64 = 3650;
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("failed to read config file {}: {source}", path.display())]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("failed to parse config file {}: {source}", path.display())]
+    Parse {
+        path: PathBuf,
+        source: Box<toml::de::Error>,
+    },
+    #[error("invalid value `{value}` for environment variable {name}")]
+    Env { name: &'static str, value: String },
+    #[error("invalid config: {0}")]
+    Invalid(String),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    /// Address to listen on, e.g. `0.0.0.0:8080`.
+    pub listen_address: String,
+    pub tls: Option<TlsConfig>,
+    pub database: DatabaseConfig,
+    pub session: SessionSettings,
+    pub users: UserSettings,
+    /// Filter directives of the logs, with the same syntax as `RUST_LOG`.
+    pub log_filter: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TlsConfig {
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    pub url: String,
+    pub max_connections: u32,
+    pub min_connections: u32,
+    /// Seconds to wait for a connection before failing a request.
+    pub acquire_timeout: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SessionSettings {
+    /// Hours during which a session is kept without being used.
+    pub lifetime: i64,
+    pub cookie_secure: bool,
+    pub cookie_same_site: CookieSameSite,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UserSettings {
+    /// Days during which a deleted user can be restored before being purged.
+    pub deletion_retention: i64,
+    pub passwords: PasswordParams,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum CookieSameSite {
+    Strict,
+    Lax,
+    None,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            listen_address: SERVER_ADDRESS.to_string(),
+            tls: None,
+            database: DatabaseConfig::default(),
+            session: SessionSettings::default(),
+            users: UserSettings::default(),
+            log_filter: format!("{}=debug", env!("CARGO_CRATE_NAME")),
+        }
+    }
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            url: "postgres://postgres@localhost/kreqo".to_string(),
+            max_connections: 20,
+            min_connections: 0,
+            acquire_timeout: 3,
+        }
+    }
+}
+
+impl Default for SessionSettings {
+    fn default() -> Self {
+        Self {
+            lifetime: 24 * 30,
+            cookie_secure: true,
+            cookie_same_site: CookieSameSite::Strict,
+        }
+    }
+}
+
+impl Default for UserSettings {
+    fn default() -> Self {
+        Self {
+            deletion_retention: 30,
+            passwords: PasswordParams::default(),
+        }
+    }
+}
+
+impl DatabaseConfig {
+    /// Pool connecting lazily, so that an unreachable database doesn't prevent starting up.
+    pub fn connect_lazy(&self) -> Result<PgPool, ConfigError> {
+        let connect_options = PgConnectOptions::from_str(&self.url)
+            .map_err(|error| ConfigError::Invalid(format!("database URL: {error}")))?;
+        Ok(PgPoolOptions::new()
+            .max_connections(self.max_connections)
+            .min_connections(self.min_connections)
+            .acquire_timeout(Duration::from_secs(self.acquire_timeout))
+            .connect_lazy_with(connect_options))
+    }
+}
+
+impl FromStr for CookieSameSite {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Strict" => Ok(CookieSameSite::Strict),
+            "Lax" => Ok(CookieSameSite::Lax),
+            "None" => Ok(CookieSameSite::None),
+            _ => Err(()),
+        }
+    }
+}
+
+impl From<CookieSameSite> for SameSite {
+    fn from(value: CookieSameSite) -> Self {
+        match value {
+            CookieSameSite::Strict => SameSite::Strict,
+            CookieSameSite::Lax => SameSite::Lax,
+            CookieSameSite::None => SameSite::None,
+        }
+    }
+}
+
+/// Overrides `value` with the environment variable `name` when it is set.
+fn env_override<T: FromStr>(name: &'static str, value: &mut T) -> Result<(), ConfigError> {
+    if let Ok(env_value) = env::var(name) {
+        *value = env_value.parse().map_err(|_| ConfigError::Env {
+            name,
+            value: env_value,
+        })?;
+    }
+    Ok(())
+}
+
+impl ServerConfig {
+    /// Reads the config file at `KREQO_CONFIG`, or `kreqo-server.toml` when it exists, then
+    /// applies the `KREQO_*` environment overrides and validates the result.
+    pub fn load() -> Result<Self, ConfigError> {
+        let mut config = match env::var("KREQO_CONFIG") {
+            Ok(path) => Self::from_file(Path::new(&path))?,
+            Err(_) if Path::new(DEFAULT_CONFIG_PATH).exists() => {
+                Self::from_file(Path::new(DEFAULT_CONFIG_PATH))?
+            }
+            Err(_) => Self::default(),
+        };
+        config.apply_env()?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let content = std::fs::read_to_string(path).map_err(|source| ConfigError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        toml::from_str(&content).map_err(|source| ConfigError::Parse {
+            path: path.to_path_buf(),
+            source: Box::new(source),
+        })
+    }
+
+    fn apply_env(&mut self) -> Result<(), ConfigError> {
+        env_override("KREQO_LISTEN_ADDRESS", &mut self.listen_address)?;
+        match (env::var("KREQO_TLS_CERT"), env::var("KREQO_TLS_KEY")) {
+            (Ok(cert_path), Ok(key_path)) => {
+                self.tls = Some(TlsConfig {
+                    cert_path: cert_path.into(),
+                    key_path: key_path.into(),
+                });
+            }
+            (Err(_), Err(_)) => (),
+            _ => {
+                return Err(ConfigError::Invalid(
+                    "KREQO_TLS_CERT and KREQO_TLS_KEY have to be set together".to_string(),
+                ));
+            }
+        }
+        env_override("DATABASE_URL", &mut self.database.url)?;
+        env_override(
+            "KREQO_DATABASE_MAX_CONNECTIONS",
+            &mut self.database.max_connections,
+        )?;
+        env_override(
+            "KREQO_DATABASE_MIN_CONNECTIONS",
+            &mut self.database.min_connections,
+        )?;
+        env_override(
+            "KREQO_DATABASE_ACQUIRE_TIMEOUT",
+            &mut self.database.acquire_timeout,
+        )?;
+        env_override("KREQO_SESSION_LIFETIME", &mut self.session.lifetime)?;
+        env_override("KREQO_COOKIE_SECURE", &mut self.session.cookie_secure)?;
+        env_override("KREQO_COOKIE_SAME_SITE", &mut self.session.cookie_same_site)?;
+        env_override(
+            "KREQO_DELETION_RETENTION",
+            &mut self.users.deletion_retention,
+        )?;
+        env_override(
+            "KREQO_PASSWORD_MEMORY_COST",
+            &mut self.users.passwords.memory_cost,
+        )?;
+        env_override(
+            "KREQO_PASSWORD_TIME_COST",
+            &mut self.users.passwords.time_cost,
+        )?;
+        env_override(
+            "KREQO_PASSWORD_PARALLELISM",
+            &mut self.users.passwords.parallelism,
+        )?;
+        env_override("RUST_LOG", &mut self.log_filter)?;
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |message: String| Err(ConfigError::Invalid(message));
+        match self.listen_address.rsplit_once(':') {
+            Some((host, port)) if !host.is_empty() && port.parse::<u16>().is_ok() => (),
+            _ => {
+                return invalid(format!(
+                    "listen address `{}` should be `host:port`",
+                    self.listen_address
+                ));
+            }
+        }
+        if let Some(tls) = &self.tls {
+            for path in [&tls.cert_path, &tls.key_path] {
+                if !path.is_file() {
+                    return invalid(format!("TLS file {} doesn't exist", path.display()));
+                }
+            }
+        }
+        if let Err(error) = PgConnectOptions::from_str(&self.database.url) {
+            return invalid(format!("database URL: {error}"));
+        }
+        if self.database.max_connections == 0 {
+            return invalid("database max connections should be at least 1".to_string());
+        }
+        if self.database.min_connections > self.database.max_connections {
+            return invalid(
+                "database min connections should not exceed max connections".to_string(),
+            );
+        }
+        if self.session.lifetime <= 0 {
+            return invalid("session lifetime should be at least 1 hour".to_string());
+        }
+        if matches!(self.session.cookie_same_site, CookieSameSite::None)
+            && !self.session.cookie_secure
+        {
+            return invalid("cookies with SameSite=None have to be secure".to_string());
+        }
+        if !(1..=MAX_DELETION_RETENTION).contains(&self.users.deletion_retention) {
+            return invalid(format!(
+                "deletion retention should be between 1 and {MAX_DELETION_RETENTION} days"
+            ));
+        }
+        if let Err(error) = self.users.passwords.hasher() {
+            return invalid(format!("password parameters: {error}"));
+        }
+        if let Err(error) = tracing_subscriber::EnvFilter::try_new(&self.log_filter) {
+            return invalid(format!("log filter: {error}"));
+        }
+        Ok(())
+    }
+
+    pub fn session_config(&self) -> SessionConfig {
+        SessionConfig::default()
+            .with_table_name("axum_sessions")
+            .with_lifetime(chrono::Duration::hours(self.session.lifetime))
+            .with_secure(self.session.cookie_secure)
+            .with_cookie_same_site(self.session.cookie_same_site.into())
+    }
+}
@@ -1,39 +1,66 @@
-use std::env;
-use std::sync::LazyLock;
-use std::time::Duration;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::OnceLock;
 
-use axum::extract::Request;
+use axum::extract::{ConnectInfo, Request};
+use axum::http::header::USER_AGENT;
 use axum::middleware::Next;
 use axum::response::Response;
+use kreqo_core::database;
 use kreqo_core::errors::ServerError;
 use sqlx::PgPool;
-use sqlx::postgres::PgPoolOptions;
 use tokio::task_local;
 
 use crate::KreqoAuth;
+use crate::config::{ConfigError, DatabaseConfig};
 
-static POOL_CONTEXT: LazyLock<PgPool> = LazyLock::new(|| {
-    let db_connection_str = env::var("DATABASE_URL")
-        .unwrap_or_else(|_| "postgres://postgres@localhost/kreqo".to_string());
-
-    PgPoolOptions::new()
-        .max_connections(20)
-        .acquire_timeout(Duration::from_secs(3))
-        .connect_lazy(&db_connection_str)
-        .expect("can't connect to database")
-});
+static POOL_CONTEXT: OnceLock<PgPool> = OnceLock::new();
 
 task_local! {
     static AUTH_CONTEXT: KreqoAuth;
+    static CLIENT_ADDRESS: Option<IpAddr>;
+}
+
+pub async fn auth_context_middleware(
+    mut auth: KreqoAuth,
+    request: Request,
+    next: Next,
+) -> Response {
+    let client_address = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(address)| address.ip());
+    let client_label = request
+        .headers()
+        .get(USER_AGENT)
+        .and_then(|user_agent| user_agent.to_str().ok())
+        .unwrap_or("Unknown client");
+    match track_session(&auth, client_label, client_address).await {
+        Ok(true) => (),
+        Ok(false) => {
+            auth.logout_user();
+            auth.current_user = None;
+        }
+        Err(error) => tracing::warn!("failed to track session: {error}"),
+    }
+    AUTH_CONTEXT
+        .scope(
+            auth,
+            CLIENT_ADDRESS.scope(client_address, next.run(request)),
+        )
+        .await
 }
 
-pub async fn auth_context_middleware(auth: KreqoAuth, request: Request, next: Next) -> Response {
-    AUTH_CONTEXT.scope(auth, next.run(request)).await
+/// Sets up the pool used by [`pool`], which has to be called once before serving requests.
+pub fn init_pool(config: &DatabaseConfig) -> Result<&'static PgPool, ConfigError> {
+    let pool = config.connect_lazy()?;
+    Ok(POOL_CONTEXT.get_or_init(|| pool))
 }
 
 #[inline]
 pub fn pool() -> &'static PgPool {
-    &POOL_CONTEXT
+    POOL_CONTEXT
+        .get()
+        .expect("database pool should be initialized at startup")
 }
 
 #[inline]
@@ -44,6 +71,38 @@ pub fn auth() -> Result<KreqoAuth, ServerError> {
     Ok(auth)
 }
 
+/// Records the request of a logged in session, returning `false` when the session was revoked
+/// and has to be logged out.
+async fn track_session(
+    auth: &KreqoAuth,
+    client_label: &str,
+    client_address: Option<IpAddr>,
+) -> Result<bool, ServerError> {
+    let Some(user) = auth.current_user.as_ref().filter(|user| !user.anonymous) else {
+        return Ok(true);
+    };
+    let session_id = auth.session.get_session_id().to_string();
+    let active = database::sessions::touch_session(
+        pool(),
+        &session_id,
+        user.id,
+        client_label,
+        client_address.map(|address| address.to_string()),
+    )
+    .await?;
+    if !active {
+        database::sessions::delete_sessions(pool(), &[session_id]).await?;
+    }
+    Ok(active)
+}
+
+/// Address of the client that sent the current request, when the server was started with
+/// connection info.
+#[inline]
+pub fn client_address() -> Option<IpAddr> {
+    CLIENT_ADDRESS.try_with(|address| *address).ok().flatten()
+}
+
 #[inline]
 pub fn context() -> (&'static PgPool, Result<KreqoAuth, ServerError>) {
     (pool(), auth())
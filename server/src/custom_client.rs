@@ -1,3 +1,4 @@
 pub mod client;
+pub mod multipart;
 pub mod request;
 pub mod response;
@@ -4,8 +4,11 @@ use bytes::Bytes;
 use futures::{FutureExt, SinkExt, StreamExt, TryFutureExt};
 use server_fn::client::{Client, get_server_url};
 use server_fn::error::{FromServerFnError, IntoAppError, ServerFnErrorErr};
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+use tokio_tungstenite::tungstenite::http::header::{COOKIE, USER_AGENT};
 
-use crate::custom_client::request::{CLIENT, CustomRequest};
+use crate::custom_client::request::{CLIENT, CustomRequest, Url, cookie_header, user_agent};
 use crate::custom_client::response::CustomResponse;
 
 /// Implements [`Client`] for a request made by [`reqwest`].
@@ -36,14 +39,27 @@ impl<
         ),
         Error,
     > {
-        let mut websocket_server_url = get_server_url().to_string();
+        let server_url = get_server_url();
+        let mut websocket_server_url = server_url.to_string();
         if let Some(postfix) = websocket_server_url.strip_prefix("http://") {
             websocket_server_url = format!("ws://{postfix}");
         } else if let Some(postfix) = websocket_server_url.strip_prefix("https://") {
             websocket_server_url = format!("wss://{postfix}");
         }
         let url = format!("{websocket_server_url}{path}");
-        let (ws_stream, _) = tokio_tungstenite::connect_async(url)
+        let mut request = url
+            .into_client_request()
+            .map_err(|e| Error::from_server_fn_error(ServerFnErrorErr::Request(e.to_string())))?;
+        // The session cookie authenticates the websocket like the other server functions
+        let cookies = Url::parse(&format!("{server_url}{path}"))
+            .ok()
+            .and_then(|url| cookie_header(&url));
+        for (name, value) in [(COOKIE, cookies), (USER_AGENT, Some(user_agent()))] {
+            if let Some(value) = value.and_then(|value| HeaderValue::from_str(&value).ok()) {
+                request.headers_mut().insert(name, value);
+            }
+        }
+        let (ws_stream, _) = tokio_tungstenite::connect_async(request)
             .await
             .map_err(|e| Error::from_server_fn_error(ServerFnErrorErr::Request(e.to_string())))?;
 
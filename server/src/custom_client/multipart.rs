@@ -0,0 +1,160 @@
+#[cfg(feature = "ssr")]
+use std::collections::HashMap;
+
+#[cfg(feature = "ssr")]
+use kreqo_core::errors::ServerError;
+use reqwest::Method;
+pub use reqwest::multipart::{Form, Part};
+use server_fn::ContentType;
+use server_fn::codec::{Encoding, FromReq, MultipartData, MultipartFormData};
+use server_fn::error::{FromServerFnError, ServerFnErrorErr};
+use server_fn::request::{ClientReq, Req};
+
+use crate::custom_client::request::CustomRequest;
+
+/// Multipart encoding for server functions called through [`CustomRequest`]. The
+/// `MultipartFormData` encoding of `server_fn` only accepts the browser `FormData`, so this one
+/// sends a [`Form`] instead and decodes it the same way on the server.
+pub struct Multipart;
+
+impl ContentType for Multipart {
+    const CONTENT_TYPE: &'static str = "multipart/form-data";
+}
+
+impl Encoding for Multipart {
+    const METHOD: Method = Method::POST;
+}
+
+/// Argument of a server function using the [`Multipart`] encoding. See [`multipart_args`] to
+/// implement the encoding for the arguments of such a function.
+pub enum MultipartBody {
+    Client(Form),
+    Server(MultipartData),
+}
+
+impl From<Form> for MultipartBody {
+    fn from(value: Form) -> Self {
+        Self::Client(value)
+    }
+}
+
+impl MultipartBody {
+    pub fn into_request<E>(self, path: &str, accepts: &str) -> Result<CustomRequest, E>
+    where
+        E: FromServerFnError,
+    {
+        let Self::Client(form) = self else {
+            return Err(E::from_server_fn_error(ServerFnErrorErr::Request(
+                "multipart data can only be sent by the client".to_string(),
+            )));
+        };
+        <CustomRequest as ClientReq<E>>::try_new_req_multipart(path, accepts, form, Method::POST)
+    }
+
+    pub async fn from_request<E, Request>(req: Request) -> Result<Self, E>
+    where
+        E: FromServerFnError,
+        Request: Req<E> + Send + 'static,
+    {
+        let data = <MultipartData as FromReq<MultipartFormData, Request, E>>::from_req(req).await?;
+        Ok(Self::Server(data))
+    }
+
+    /// Reads the whole content of every field, by name, failing as soon as a field is larger
+    /// than `max_field_size` bytes or all of them together than `max_size` bytes.
+    #[cfg(feature = "ssr")]
+    pub async fn read_fields(
+        self,
+        max_field_size: usize,
+        max_size: usize,
+    ) -> Result<HashMap<String, Vec<u8>>, ServerError> {
+        let deserialization_error =
+            |error: String| ServerError::API(ServerFnErrorErr::Deserialization(error));
+        let Some(mut multipart) = (match self {
+            Self::Server(data) => data.into_inner(),
+            Self::Client(_) => None,
+        }) else {
+            return Err(deserialization_error("expected multipart data".to_string()));
+        };
+        let mut fields = HashMap::new();
+        let mut size = 0;
+        while let Some(mut field) = multipart
+            .next_field()
+            .await
+            .map_err(|error| deserialization_error(error.to_string()))?
+        {
+            let name = field.name().unwrap_or_default().to_string();
+            let mut bytes = Vec::new();
+            while let Some(chunk) = field
+                .chunk()
+                .await
+                .map_err(|error| deserialization_error(error.to_string()))?
+            {
+                size += chunk.len();
+                if size > max_size {
+                    return Err(ServerError::RequestTooLarge { limit: max_size });
+                }
+                if bytes.len() + chunk.len() > max_field_size {
+                    return Err(ServerError::FieldTooLarge {
+                        name,
+                        limit: max_field_size,
+                    });
+                }
+                bytes.extend_from_slice(&chunk);
+            }
+            fields.insert(name, bytes);
+        }
+        Ok(fields)
+    }
+
+    /// Reads the whole content of the field `name`, ignoring the others, with at most `max_size`
+    /// bytes read in total.
+    #[cfg(feature = "ssr")]
+    pub async fn read_field(self, name: &str, max_size: usize) -> Result<Vec<u8>, ServerError> {
+        self.read_fields(max_size, max_size)
+            .await?
+            .remove(name)
+            .ok_or_else(|| ServerError::API(ServerFnErrorErr::MissingArg(name.to_string())))
+    }
+}
+
+/// Implements the [`Multipart`] encoding for the arguments of a server function taking a single
+/// [`MultipartBody`].
+macro_rules! multipart_args {
+    ($args:ty) => {
+        impl<E>
+            ::server_fn::codec::IntoReq<
+                $crate::custom_client::multipart::Multipart,
+                $crate::custom_client::request::CustomRequest,
+                E,
+            > for $args
+        where
+            E: ::server_fn::error::FromServerFnError,
+        {
+            fn into_req(
+                self,
+                path: &str,
+                accepts: &str,
+            ) -> Result<$crate::custom_client::request::CustomRequest, E> {
+                $crate::custom_client::multipart::MultipartBody::from(self)
+                    .into_request(path, accepts)
+            }
+        }
+
+        impl<E, Request>
+            ::server_fn::codec::FromReq<$crate::custom_client::multipart::Multipart, Request, E>
+            for $args
+        where
+            E: ::server_fn::error::FromServerFnError,
+            Request: ::server_fn::request::Req<E> + Send + 'static,
+        {
+            async fn from_req(req: Request) -> Result<Self, E> {
+                $crate::custom_client::multipart::MultipartBody::from_request(req)
+                    .await
+                    .map(Self::from)
+            }
+        }
+    };
+}
+
+pub(crate) use multipart_args;
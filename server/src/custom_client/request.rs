@@ -30,10 +30,26 @@ pub(crate) static COOKIE_STORE: LazyLock<Arc<CookieStoreMutex>> = LazyLock::new(
 pub(crate) static CLIENT: LazyLock<Client> = LazyLock::new(|| {
     Client::builder()
         .cookie_provider(Arc::clone(&COOKIE_STORE))
+        .user_agent(user_agent())
         .build()
         .unwrap()
 });
 
+pub(crate) fn user_agent() -> String {
+    format!("Kreqo on {}", std::env::consts::OS)
+}
+
+/// Value of the `Cookie` header for a request to `url` that isn't sent by [`CLIENT`], e.g. to open
+/// a websocket.
+pub(crate) fn cookie_header(url: &Url) -> Option<String> {
+    let cookie_store = COOKIE_STORE.lock().ok()?;
+    let cookies = cookie_store
+        .get_request_values(url)
+        .map(|(name, value)| format!("{name}={value}"))
+        .collect::<Vec<_>>();
+    (!cookies.is_empty()).then(|| cookies.join("; "))
+}
+
 pub fn save_cookies() -> anyhow::Result<()> {
     if let Ok(cookie_store) = COOKIE_STORE.lock() {
         let path = cookies_path();
@@ -150,10 +166,11 @@ where
         body: Self::FormData,
         method: Method,
     ) -> Result<Self, E> {
+        let url = format!("{}{}", get_server_url(), path);
         let req = match method {
-            Method::POST => CLIENT.post(path),
-            Method::PUT => CLIENT.put(path),
-            Method::PATCH => CLIENT.patch(path),
+            Method::POST => CLIENT.post(url),
+            Method::PUT => CLIENT.put(url),
+            Method::PATCH => CLIENT.patch(url),
             m => {
                 return Err(E::from_server_fn_error(
                     ServerFnErrorErr::UnsupportedRequestMethod(m.to_string()),
@@ -174,10 +191,11 @@ where
         body: Self::FormData,
         method: Method,
     ) -> Result<Self, E> {
+        let url = format!("{}{}", get_server_url(), path);
         let req = match method {
-            Method::POST => CLIENT.post(path),
-            Method::PATCH => CLIENT.patch(path),
-            Method::PUT => CLIENT.put(path),
+            Method::POST => CLIENT.post(url),
+            Method::PATCH => CLIENT.patch(url),
+            Method::PUT => CLIENT.put(url),
             m => {
                 return Err(E::from_server_fn_error(
                     ServerFnErrorErr::UnsupportedRequestMethod(m.to_string()),
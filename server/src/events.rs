@@ -0,0 +1,104 @@
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
+
+use futures::{Stream, stream};
+use kreqo_core::database;
+use kreqo_core::errors::ServerError;
+use kreqo_core::events::{ItemEvent, ServerEvent};
+use kreqo_core::users::User;
+use sqlx::PgPool;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+
+/// Events kept for subscribers that are behind before they are told they lagged.
+const EVENT_CAPACITY: usize = 256;
+/// Longest time the user of a subscription is trusted before being loaded again, bounding how long
+/// a revoked session keeps receiving events.
+const USER_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+static EVENTS: LazyLock<broadcast::Sender<ServerEvent>> =
+    LazyLock::new(|| broadcast::channel(EVENT_CAPACITY).0);
+
+/// Sends an event to the current subscribers allowed to see it. It should only be published once
+/// the change it describes is committed.
+pub fn publish(event: ServerEvent) {
+    // Fails when nobody is subscribed
+    let _ = EVENTS.send(event);
+}
+
+/// User logged in with `session_id`, or `None` once the session was logged out or revoked, which
+/// deleting the user also does.
+async fn session_user(
+    pool: &PgPool,
+    user_id: i64,
+    session_id: &str,
+) -> Result<Option<User>, ServerError> {
+    if !database::sessions::is_session_active(pool, session_id).await? {
+        return Ok(None);
+    }
+    Ok(Some(database::get_user(pool, user_id).await?))
+}
+
+struct Subscription {
+    receiver: broadcast::Receiver<ServerEvent>,
+    session_id: String,
+    user: User,
+    loaded_at: Instant,
+}
+
+impl Subscription {
+    /// Whether the user has to be loaded again before telling if they can see `event`.
+    fn is_stale(&self, event: &ServerEvent) -> bool {
+        let changes_user = match event {
+            ServerEvent::User(ItemEvent::Created(user) | ItemEvent::Updated(user)) => {
+                user.id == self.user.id
+            }
+            ServerEvent::User(ItemEvent::Deleted(id)) => *id == self.user.id,
+            // Changes to the user may be among the missed events
+            ServerEvent::Lagged => true,
+            ServerEvent::Deck { .. } => false,
+        };
+        changes_user || self.loaded_at.elapsed() >= USER_REFRESH_INTERVAL
+    }
+}
+
+/// Events published from now on that `user`, logged in with `session_id`, is allowed to see. The
+/// user is loaded again when an event changes them, and at least every
+/// [`USER_REFRESH_INTERVAL`] so that revoked sessions stop receiving events. The stream ends
+/// once the session is no longer valid, or after telling that it couldn't be checked.
+pub fn subscribe(
+    pool: &'static PgPool,
+    user: User,
+    session_id: String,
+) -> impl Stream<Item = Result<ServerEvent, ServerError>> + Send {
+    let subscription = Subscription {
+        receiver: EVENTS.subscribe(),
+        session_id,
+        user,
+        loaded_at: Instant::now(),
+    };
+    stream::unfold(Some(subscription), move |subscription| async move {
+        let mut subscription = subscription?;
+        loop {
+            let event = match subscription.receiver.recv().await {
+                Ok(event) => event,
+                Err(RecvError::Lagged(_)) => ServerEvent::Lagged,
+                Err(RecvError::Closed) => return None,
+            };
+            if subscription.is_stale(&event) {
+                let user_id = subscription.user.id;
+                match session_user(pool, user_id, &subscription.session_id).await {
+                    Ok(Some(user)) => {
+                        subscription.user = user;
+                        subscription.loaded_at = Instant::now();
+                    }
+                    Ok(None) => return None,
+                    Err(error) => return Some((Err(error), None)),
+                }
+            }
+            if event.is_visible_to(&subscription.user) {
+                return Some((Ok(event), Some(subscription)));
+            }
+        }
+    })
+}
@@ -5,9 +5,20 @@ use sqlx::PgPool;
 
 pub mod api;
 #[cfg(feature = "ssr")]
+pub mod config;
+#[cfg(feature = "ssr")]
 pub mod context;
 pub mod custom_client;
+#[cfg(feature = "ssr")]
+pub mod events;
+#[cfg(feature = "ssr")]
+pub mod optimization;
+#[cfg(feature = "ssr")]
+pub mod purge;
+#[cfg(feature = "ssr")]
+pub mod tls;
 
+/// Address the server listens on by default, and that clients connect to.
 pub const SERVER_ADDRESS: &str = "localhost:8080";
 
 pub type KreqoAuth = AuthSession<User, i64, SessionPgPool, PgPool>;
@@ -1,43 +1,44 @@
-use std::env;
+use std::net::SocketAddr;
 
+use anyhow::Context;
 use axum::Router;
 use axum::middleware::from_fn;
-use axum::routing::{get, post};
-use axum_session::{SameSite, SessionConfig, SessionLayer, SessionStore};
+use axum::routing::get;
+use axum_session::{SessionLayer, SessionStore};
 use axum_session_auth::{AuthConfig, AuthSessionLayer};
 use axum_session_sqlx::SessionPgPool;
 use kreqo_core::users::User;
-use kreqo_server::SERVER_ADDRESS;
-use kreqo_server::context::{auth_context_middleware, pool};
+use kreqo_server::config::ServerConfig;
+use kreqo_server::context::{auth_context_middleware, init_pool};
+use kreqo_server::purge::purge_deleted_users;
+use kreqo_server::tls::{load_rustls_config, reload_on_sighup};
 use server_fn::axum::handle_server_fn;
 use sqlx::PgPool;
-use tokio::net::TcpListener;
+use tokio::net::{TcpListener, lookup_host};
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    dotenvy::dotenv()?;
+    // The environment file is only used during development
+    let _ = dotenvy::dotenv();
+    let config = ServerConfig::load().context("failed to load the server config")?;
 
     tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| format!("{}=debug", env!("CARGO_CRATE_NAME")).into()),
-        )
+        .with(tracing_subscriber::EnvFilter::new(&config.log_filter))
         .with(tracing_subscriber::fmt::layer())
         .init();
 
-    let pool = pool();
+    config.users.passwords.set_current()?;
+    let pool = init_pool(&config.database)?;
 
     sqlx::migrate!()
         .run(pool)
         .await
-        .expect("database migrations failed");
+        .context("failed to run the database migrations")?;
+    purge_deleted_users(pool.clone(), config.users.deletion_retention);
 
-    let session_config = SessionConfig::default()
-        .with_table_name("axum_sessions")
-        .with_secure(true)
-        .with_cookie_same_site(SameSite::Strict);
+    let session_config = config.session_config();
     let auth_config = AuthConfig::<i64>::default().with_anonymous_user_id(Some(1));
 
     let session_store =
@@ -45,7 +46,11 @@ async fn main() -> anyhow::Result<()> {
 
     let router = Router::new()
         .route("/", get(|| async { "kreqo-server is running" }))
-        .route("/api/{*wildcard}", post(handle_server_fn))
+        // Websocket server functions are opened with GET requests
+        .route(
+            "/api/{*wildcard}",
+            get(handle_server_fn).post(handle_server_fn),
+        )
         .layer(from_fn(auth_context_middleware))
         .layer(
             AuthSessionLayer::<User, i64, SessionPgPool, PgPool>::new(Some(pool.clone()))
@@ -53,9 +58,25 @@ async fn main() -> anyhow::Result<()> {
         )
         .layer(SessionLayer::new(session_store));
 
-    let listener = TcpListener::bind(SERVER_ADDRESS).await?;
-    tracing::debug!("listening on {}", listener.local_addr()?);
-    axum::serve(listener, router).await?;
+    let service = router.into_make_service_with_connect_info::<SocketAddr>();
+    if let Some(tls) = &config.tls {
+        let address = lookup_host(&config.listen_address)
+            .await?
+            .next()
+            .with_context(|| format!("failed to resolve {}", config.listen_address))?;
+        let rustls_config = load_rustls_config(tls).await?;
+        reload_on_sighup(rustls_config.clone(), tls.clone())?;
+        tracing::debug!("listening on https://{address}");
+        axum_server::bind_rustls(address, rustls_config)
+            .serve(service)
+            .await?;
+    } else {
+        let listener = TcpListener::bind(&config.listen_address)
+            .await
+            .with_context(|| format!("failed to listen on {}", config.listen_address))?;
+        tracing::debug!("listening on http://{}", listener.local_addr()?);
+        axum::serve(listener, service).await?;
+    }
 
     Ok(())
 }
@@ -0,0 +1,66 @@
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+
+use chrono::Utc;
+use kreqo_core::database;
+use kreqo_core::errors::ServerError;
+use kreqo_core::learn::optimizer::{self, MAX_REVIEWS, OptimizationResult, OptimizationStatus};
+use sqlx::PgPool;
+use tokio::sync::Semaphore;
+
+/// Optimizations run at the same time, the others waiting for their turn.
+const MAX_RUNNING_JOBS: usize = 2;
+
+/// Latest optimization job of each user since the server started.
+static JOBS: LazyLock<Mutex<HashMap<i64, OptimizationStatus>>> = LazyLock::new(Mutex::default);
+static RUNNING_JOBS: Semaphore = Semaphore::const_new(MAX_RUNNING_JOBS);
+
+fn set_status(user_id: i64, status: OptimizationStatus) {
+    JOBS.lock().unwrap().insert(user_id, status);
+}
+
+pub fn status(user_id: i64) -> OptimizationStatus {
+    JOBS.lock()
+        .unwrap()
+        .get(&user_id)
+        .cloned()
+        .unwrap_or_default()
+}
+
+async fn run(pool: &PgPool, user_id: i64) -> Result<OptimizationResult, ServerError> {
+    let histories = database::scheduler::get_review_histories(pool, user_id, MAX_REVIEWS).await?;
+    let optimization =
+        tokio::task::spawn_blocking(move || optimizer::optimize(&histories, Utc::now()))
+            .await
+            .map_err(|error| ServerError::Optimization(error.to_string()))??;
+    database::scheduler::save_optimization(pool, user_id, &optimization).await?;
+    Ok(optimization)
+}
+
+/// Fits the scheduler weights of a user to their review history in the background, unless it is
+/// already being done. Returns the status of the job.
+pub fn start(pool: PgPool, user_id: i64) -> OptimizationStatus {
+    let mut jobs = JOBS.lock().unwrap();
+    if let Some(status @ OptimizationStatus::Running { .. }) = jobs.get(&user_id) {
+        return status.clone();
+    }
+    let status = OptimizationStatus::Running {
+        started_at: Utc::now(),
+    };
+    jobs.insert(user_id, status.clone());
+    drop(jobs);
+
+    tokio::spawn(async move {
+        // The semaphore is never closed
+        let _permit = RUNNING_JOBS.acquire().await;
+        let status = match run(&pool, user_id).await {
+            Ok(optimization) => OptimizationStatus::Finished(optimization),
+            Err(error) => {
+                tracing::error!("failed to optimize the scheduler of user {user_id}: {error}");
+                OptimizationStatus::Failed(error.to_string())
+            }
+        };
+        set_status(user_id, status);
+    });
+    status
+}
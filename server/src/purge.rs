@@ -0,0 +1,45 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use kreqo_core::audit::{AuditAction, AuditTarget};
+use kreqo_core::database;
+use kreqo_core::errors::ServerError;
+use sqlx::PgPool;
+
+/// Time between two purges of the deleted users.
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Removes the users deleted before `deleted_before`, recording each of them in the audit log.
+async fn purge(pool: &PgPool, deleted_before: DateTime<Utc>) -> Result<Vec<i64>, ServerError> {
+    let mut tx = pool.begin().await?;
+    let ids = database::purge_deleted_users(&mut *tx, deleted_before).await?;
+    for id in &ids {
+        database::audit::record(
+            &mut *tx,
+            None,
+            AuditAction::PurgeUser,
+            AuditTarget::User(*id),
+            None::<()>,
+            None::<()>,
+        )
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(ids)
+}
+
+/// Periodically removes for good the users deleted more than `retention_days` ago.
+pub fn purge_deleted_users(pool: PgPool, retention_days: i64) {
+    let retention = chrono::Duration::days(retention_days);
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(PURGE_INTERVAL);
+        loop {
+            interval.tick().await;
+            match purge(&pool, Utc::now() - retention).await {
+                Ok(ids) if ids.is_empty() => (),
+                Ok(ids) => tracing::info!("purged deleted users {ids:?}"),
+                Err(error) => tracing::error!("failed to purge deleted users: {error}"),
+            }
+        }
+    });
+}
@@ -0,0 +1,43 @@
+use anyhow::Context;
+use axum_server::tls_rustls::RustlsConfig;
+
+use crate::config::TlsConfig;
+
+pub async fn load_rustls_config(tls: &TlsConfig) -> anyhow::Result<RustlsConfig> {
+    RustlsConfig::from_pem_file(&tls.cert_path, &tls.key_path)
+        .await
+        .with_context(|| {
+            format!(
+                "failed to load the TLS certificate {} and key {}",
+                tls.cert_path.display(),
+                tls.key_path.display()
+            )
+        })
+}
+
+/// Reloads the certificate and key from their files whenever the server receives SIGHUP, e.g.
+/// after they were renewed. The previous ones are kept when reloading fails.
+#[cfg(unix)]
+pub fn reload_on_sighup(rustls_config: RustlsConfig, tls: TlsConfig) -> anyhow::Result<()> {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut hangup = signal(SignalKind::hangup()).context("failed to listen for SIGHUP")?;
+    tokio::spawn(async move {
+        while hangup.recv().await.is_some() {
+            match rustls_config
+                .reload_from_pem_file(&tls.cert_path, &tls.key_path)
+                .await
+            {
+                Ok(()) => tracing::info!("reloaded the TLS certificate"),
+                Err(error) => tracing::error!("failed to reload the TLS certificate: {error}"),
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn reload_on_sighup(rustls_config: RustlsConfig, tls: TlsConfig) -> anyhow::Result<()> {
+    let _ = (rustls_config, tls);
+    Ok(())
+}
@@ -0,0 +1,291 @@
+use std::io::{Cursor, Write};
+
+use chrono::DateTime;
+use kreqo_core::learn::anki::{AnkiError, parse_package};
+use kreqo_core::learn::fsrs::MAX_STABILITY;
+use kreqo_core::learn::{CardState, ImportedDeck, MAX_TIME_TAKEN_MS, Rating};
+use rusqlite::{Connection, params};
+use zip::ZipWriter;
+use zip::write::SimpleFileOptions;
+
+/// Timestamp of the first review of the fixtures, in milliseconds.
+const FIRST_REVIEW: i64 = 1_700_000_000_000;
+const DAY: i64 = 24 * 60 * 60 * 1000;
+
+const BASIC_NOTE_TYPE: i64 = 1;
+const CLOZE_NOTE_TYPE: i64 = 2;
+const DECK: i64 = 10;
+const CLOZE_TEXT: &str = "{{c1::Paris}} is the capital of {{c2::France::country}}";
+
+/// Tables shared by every schema, the note types and decks being stored separately.
+const SCHEMA: &str = "
+    CREATE TABLE col (crt INTEGER, models TEXT, decks TEXT);
+    CREATE TABLE notes (id INTEGER, mid INTEGER, flds TEXT, tags TEXT);
+    CREATE TABLE cards (
+        id INTEGER, nid INTEGER, did INTEGER, ord INTEGER, type INTEGER, due INTEGER,
+        ivl INTEGER, odid INTEGER, odue INTEGER
+    );
+    CREATE TABLE revlog (id INTEGER, cid INTEGER, ease INTEGER, type INTEGER, time INTEGER);
+";
+
+fn proto_varint(mut value: u64, bytes: &mut Vec<u8>) {
+    while value >= 0x80 {
+        bytes.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+fn proto_bytes(number: u64, value: &str, bytes: &mut Vec<u8>) {
+    proto_varint((number << 3) | 2, bytes);
+    proto_varint(value.len() as u64, bytes);
+    bytes.extend_from_slice(value.as_bytes());
+}
+
+fn template_config(question: &str, answer: &str) -> Vec<u8> {
+    let mut config = Vec::new();
+    proto_bytes(1, question, &mut config);
+    proto_bytes(2, answer, &mut config);
+    config
+}
+
+/// Writes a basic note with a reviewed card and a cloze note with a card per deletion, the note
+/// types and decks being in the `col` table as JSON for the legacy schema.
+fn write_collection(connection: &Connection, legacy: bool) {
+    connection.execute_batch(SCHEMA).unwrap();
+    let (models, decks) = if legacy {
+        let models = serde_json::json!({
+            "1": {
+                "type": 0,
+                "flds": [{"name": "Back", "ord": 1}, {"name": "Front", "ord": 0}],
+                "tmpls": [{
+                    "ord": 0,
+                    "qfmt": "{{Front}}",
+                    "afmt": "{{FrontSide}}<hr id=answer>{{Back}}",
+                }],
+            },
+            "2": {
+                "type": 1,
+                "flds": [{"name": "Text", "ord": 0}],
+                "tmpls": [{"ord": 0, "qfmt": "{{cloze:Text}}", "afmt": "{{cloze:Text}}"}],
+            },
+        });
+        let decks = serde_json::json!({ "10": {"name": "Geography"} });
+        (models.to_string(), decks.to_string())
+    } else {
+        connection
+            .execute_batch(
+                "CREATE TABLE notetypes (id INTEGER, config BLOB);
+                CREATE TABLE fields (ntid INTEGER, ord INTEGER, name TEXT);
+                CREATE TABLE templates (ntid INTEGER, ord INTEGER, config BLOB);
+                CREATE TABLE decks (id INTEGER, name TEXT);",
+            )
+            .unwrap();
+        let mut cloze_config = Vec::new();
+        proto_varint(1 << 3, &mut cloze_config);
+        proto_varint(1, &mut cloze_config);
+        let note_types = [
+            (BASIC_NOTE_TYPE, Vec::new()),
+            (CLOZE_NOTE_TYPE, cloze_config),
+        ];
+        for (id, config) in note_types {
+            connection
+                .execute("INSERT INTO notetypes VALUES (?1, ?2)", params![id, config])
+                .unwrap();
+        }
+        let fields = [
+            (BASIC_NOTE_TYPE, 0, "Front"),
+            (BASIC_NOTE_TYPE, 1, "Back"),
+            (CLOZE_NOTE_TYPE, 0, "Text"),
+        ];
+        for (id, ord, name) in fields {
+            connection
+                .execute(
+                    "INSERT INTO fields VALUES (?1, ?2, ?3)",
+                    params![id, ord, name],
+                )
+                .unwrap();
+        }
+        let templates = [
+            (
+                BASIC_NOTE_TYPE,
+                template_config("{{Front}}", "{{FrontSide}}<hr id=answer>{{Back}}"),
+            ),
+            (
+                CLOZE_NOTE_TYPE,
+                template_config("{{cloze:Text}}", "{{cloze:Text}}"),
+            ),
+        ];
+        for (id, config) in templates {
+            connection
+                .execute(
+                    "INSERT INTO templates VALUES (?1, 0, ?2)",
+                    params![id, config],
+                )
+                .unwrap();
+        }
+        connection
+            .execute(
+                "INSERT INTO decks VALUES (?1, ?2)",
+                params![DECK, "Geography\x1fEurope"],
+            )
+            .unwrap();
+        (String::new(), String::new())
+    };
+    connection
+        .execute("INSERT INTO col VALUES (0, ?1, ?2)", params![models, decks])
+        .unwrap();
+
+    let notes = [
+        (1, BASIC_NOTE_TYPE, "France\x1fParis", "capitals europe"),
+        (2, CLOZE_NOTE_TYPE, CLOZE_TEXT, ""),
+    ];
+    for (id, note_type, fields, tags) in notes {
+        connection
+            .execute(
+                "INSERT INTO notes VALUES (?1, ?2, ?3, ?4)",
+                params![id, note_type, fields, tags],
+            )
+            .unwrap();
+    }
+    // The cloze cards are review cards without history, one of them being due out of range
+    let cards = [
+        (100, 1, 0, 0, 0, 0),
+        (200, 2, 0, 2, 10, 100_000),
+        (201, 2, 1, 2, i64::MAX, 5),
+    ];
+    for (id, note_id, ord, kind, due, interval) in cards {
+        connection
+            .execute(
+                "INSERT INTO cards VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 0, 0)",
+                params![id, note_id, DECK, ord, kind, due, interval],
+            )
+            .unwrap();
+    }
+    // The rescheduling and the cram review aren't replayed
+    let reviews = [
+        (FIRST_REVIEW, 3, 0, 5_000),
+        (FIRST_REVIEW + DAY, 1, 1, 120_000),
+        (FIRST_REVIEW + 2 * DAY, 0, 4, 0),
+        (FIRST_REVIEW + 3 * DAY, 3, 3, 0),
+        (FIRST_REVIEW + 4 * DAY, 4, 2, 2_000),
+    ];
+    for (id, ease, kind, time) in reviews {
+        connection
+            .execute(
+                "INSERT INTO revlog VALUES (?1, 100, ?2, ?3, ?4)",
+                params![id, ease, kind, time],
+            )
+            .unwrap();
+    }
+}
+
+/// Package holding a fixture collection under `name`, compressed for `anki21b` files.
+fn package(name: &str) -> Vec<u8> {
+    let file = tempfile::NamedTempFile::new().unwrap();
+    let connection = Connection::open(file.path()).unwrap();
+    write_collection(&connection, !name.ends_with("anki21b"));
+    drop(connection);
+    let mut collection = std::fs::read(file.path()).unwrap();
+    if name.ends_with("anki21b") {
+        collection = zstd::encode_all(collection.as_slice(), 0).unwrap();
+    }
+
+    let mut archive = ZipWriter::new(Cursor::new(Vec::new()));
+    archive
+        .start_file(name, SimpleFileOptions::default())
+        .unwrap();
+    archive.write_all(&collection).unwrap();
+    archive.finish().unwrap().into_inner()
+}
+
+fn parse_deck(name: &str) -> ImportedDeck {
+    let mut decks = parse_package(&package(name)).unwrap();
+    assert_eq!(decks.len(), 1);
+    let mut deck = decks.remove(0);
+    deck.cards.sort_by(|a, b| a.card.front.cmp(&b.card.front));
+    deck
+}
+
+#[test]
+fn every_collection_variant_is_imported() {
+    for (name, deck_name) in [
+        ("collection.anki2", "Geography"),
+        ("collection.anki21", "Geography"),
+        ("collection.anki21b", "Geography::Europe"),
+    ] {
+        let deck = parse_deck(name);
+        assert_eq!(deck.name, deck_name, "{name}");
+        let sides = deck
+            .cards
+            .iter()
+            .map(|card| (card.card.front.as_str(), card.card.back.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sides,
+            vec![
+                ("France", "Paris"),
+                (
+                    "Paris is the capital of [country]",
+                    "Paris is the capital of France"
+                ),
+                (
+                    "[...] is the capital of France",
+                    "Paris is the capital of France"
+                ),
+            ],
+            "{name}"
+        );
+        assert_eq!(
+            deck.cards[0].card.tags,
+            vec!["capitals", "europe"],
+            "{name}"
+        );
+    }
+}
+
+#[test]
+fn review_history_is_replayed() {
+    let deck = parse_deck("collection.anki21b");
+    let reviewed = &deck.cards[0];
+    let ratings = reviewed
+        .review_logs
+        .iter()
+        .map(|review_log| review_log.rating)
+        .collect::<Vec<_>>();
+    assert_eq!(ratings, vec![Rating::Good, Rating::Again, Rating::Easy]);
+    assert_eq!(reviewed.review_logs[1].time_taken_ms, MAX_TIME_TAKEN_MS);
+    assert_eq!(reviewed.card.reps, 3);
+    assert_eq!(
+        reviewed.card.last_review,
+        DateTime::from_timestamp_millis(FIRST_REVIEW + 4 * DAY)
+    );
+    assert!(reviewed.card.due > reviewed.card.last_review.unwrap());
+
+    // Cards without history are approximated from their interval, unless their dates are out of
+    // range
+    let approximated = &deck.cards[2].card;
+    assert!(deck.cards[2].review_logs.is_empty());
+    assert_eq!(approximated.state, CardState::Review);
+    assert_eq!(approximated.stability, MAX_STABILITY);
+    assert_eq!(
+        approximated.due,
+        DateTime::from_timestamp(10 * DAY / 1000, 0).unwrap()
+    );
+    assert!(deck.cards[1].review_logs.is_empty());
+    assert_eq!(deck.cards[1].card.state, CardState::New);
+}
+
+#[test]
+fn package_without_collection_is_refused() {
+    let mut archive = ZipWriter::new(Cursor::new(Vec::new()));
+    archive
+        .start_file("media", SimpleFileOptions::default())
+        .unwrap();
+    archive.write_all(b"{}").unwrap();
+    let package = archive.finish().unwrap().into_inner();
+    assert!(matches!(
+        parse_package(&package),
+        Err(AnkiError::MissingCollection)
+    ));
+}
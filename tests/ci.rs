@@ -110,7 +110,12 @@ fn main() {
                 .add_step(Step::checkout())
                 .add_step(Step::toolchain().cache(true))
                 .apply(install_native_dependencies)
-                .add_step(Step::new("Run cargo test").run("cargo +nightly test")),
+                .apply(setup_database)
+                .add_step(
+                    Step::new("Run cargo test")
+                        .run("cargo +nightly test")
+                        .apply(db_env),
+                ),
         );
 
     workflow.generate().expect("workflow should generate");
@@ -0,0 +1,172 @@
+use std::env;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use kreqo_server::config::{ConfigError, CookieSameSite, ServerConfig, TlsConfig};
+use tempfile::NamedTempFile;
+
+fn config_file(content: &str) -> NamedTempFile {
+    let mut file = NamedTempFile::new().unwrap();
+    file.write_all(content.as_bytes()).unwrap();
+    file
+}
+
+fn invalid_message(config: &ServerConfig) -> String {
+    match config.validate() {
+        Err(ConfigError::Invalid(message)) => message,
+        result => panic!("expected an invalid config, got {result:?}"),
+    }
+}
+
+#[test]
+fn config_file_is_parsed_over_the_defaults() {
+    let file = config_file(
+        r#"
+        listen_address = "0.0.0.0:9000"
+
+        [database]
+        max_connections = 5
+
+        [session]
+        cookie_same_site = "Lax"
+
+        [users.passwords]
+        time_cost = 3
+        "#,
+    );
+    let config = ServerConfig::from_file(file.path()).unwrap();
+    let default = ServerConfig::default();
+    assert_eq!(config.listen_address, "0.0.0.0:9000");
+    assert_eq!(config.database.max_connections, 5);
+    assert_eq!(config.database.url, default.database.url);
+    assert_eq!(config.session.lifetime, default.session.lifetime);
+    assert_eq!(config.users.passwords.time_cost, 3);
+    assert_eq!(
+        config.users.passwords.memory_cost,
+        default.users.passwords.memory_cost
+    );
+    assert!(config.tls.is_none());
+    config.validate().unwrap();
+}
+
+#[test]
+fn invalid_config_files_are_refused() {
+    let file = config_file("listen_adress = \"0.0.0.0:9000\"");
+    assert!(matches!(
+        ServerConfig::from_file(file.path()),
+        Err(ConfigError::Parse { .. })
+    ));
+    let file = config_file("[database]\nmax_connections = \"many\"");
+    assert!(matches!(
+        ServerConfig::from_file(file.path()),
+        Err(ConfigError::Parse { .. })
+    ));
+    assert!(matches!(
+        ServerConfig::from_file(Path::new("missing/kreqo-server.toml")),
+        Err(ConfigError::Read { .. })
+    ));
+}
+
+#[test]
+fn environment_overrides_the_config_file() {
+    let file = config_file(
+        r#"
+        listen_address = "0.0.0.0:9000"
+
+        [database]
+        max_connections = 5
+        min_connections = 2
+        "#,
+    );
+    // No other test of this binary reads the environment
+    let set_var = |name: &str, value: &str| unsafe { env::set_var(name, value) };
+    let remove_var = |name: &str| unsafe { env::remove_var(name) };
+    set_var("KREQO_CONFIG", file.path().to_str().unwrap());
+    set_var("KREQO_DATABASE_MAX_CONNECTIONS", "8");
+    set_var("KREQO_COOKIE_SAME_SITE", "Lax");
+
+    let config = ServerConfig::load().unwrap();
+    assert_eq!(config.listen_address, "0.0.0.0:9000");
+    assert_eq!(config.database.max_connections, 8);
+    assert_eq!(config.database.min_connections, 2);
+    assert!(matches!(
+        config.session.cookie_same_site,
+        CookieSameSite::Lax
+    ));
+
+    set_var("KREQO_DATABASE_MAX_CONNECTIONS", "many");
+    assert!(matches!(
+        ServerConfig::load(),
+        Err(ConfigError::Env {
+            name: "KREQO_DATABASE_MAX_CONNECTIONS",
+            ..
+        })
+    ));
+    // Overrides are validated like the config file
+    set_var("KREQO_DATABASE_MAX_CONNECTIONS", "1");
+    assert!(matches!(ServerConfig::load(), Err(ConfigError::Invalid(_))));
+    remove_var("KREQO_DATABASE_MAX_CONNECTIONS");
+
+    set_var("KREQO_TLS_CERT", "cert.pem");
+    assert!(matches!(ServerConfig::load(), Err(ConfigError::Invalid(_))));
+
+    for name in ["KREQO_CONFIG", "KREQO_COOKIE_SAME_SITE", "KREQO_TLS_CERT"] {
+        remove_var(name);
+    }
+}
+
+#[test]
+fn invalid_values_are_reported() {
+    assert!(ServerConfig::default().validate().is_ok());
+
+    let config = ServerConfig {
+        listen_address: "8080".to_string(),
+        ..ServerConfig::default()
+    };
+    assert!(invalid_message(&config).contains("listen address"));
+
+    let mut config = ServerConfig {
+        tls: Some(TlsConfig {
+            cert_path: PathBuf::from("missing/cert.pem"),
+            key_path: PathBuf::from("missing/key.pem"),
+        }),
+        ..ServerConfig::default()
+    };
+    assert!(invalid_message(&config).contains("missing/cert.pem"));
+
+    // Both files have to exist
+    let cert = NamedTempFile::new().unwrap();
+    config.tls = Some(TlsConfig {
+        cert_path: cert.path().to_path_buf(),
+        key_path: PathBuf::from("missing/key.pem"),
+    });
+    assert!(invalid_message(&config).contains("missing/key.pem"));
+
+    let mut config = ServerConfig::default();
+    config.database.max_connections = 0;
+    assert!(invalid_message(&config).contains("max connections"));
+
+    let mut config = ServerConfig::default();
+    config.database.min_connections = config.database.max_connections + 1;
+    assert!(invalid_message(&config).contains("min connections"));
+
+    let mut config = ServerConfig::default();
+    config.database.url = "mysql:/kreqo".to_string();
+    assert!(invalid_message(&config).contains("database URL"));
+
+    let mut config = ServerConfig::default();
+    config.session.lifetime = 0;
+    assert!(invalid_message(&config).contains("session lifetime"));
+
+    let mut config = ServerConfig::default();
+    config.users.deletion_retention = 0;
+    assert!(invalid_message(&config).contains("deletion retention"));
+
+    let mut config = ServerConfig::default();
+    config.users.deletion_retention = i64::MAX;
+    assert!(invalid_message(&config).contains("deletion retention"));
+
+    let mut config = ServerConfig::default();
+    config.users.passwords.memory_cost = 0;
+    assert!(invalid_message(&config).contains("password parameters"));
+}
@@ -0,0 +1,235 @@
+use kreqo_core::learn::content::{
+    Block, CardContent, SpanStyle, cloze_cards, cloze_numbers, image_markdown,
+};
+use kreqo_core::learn::delimited::{
+    ColumnMapping, DelimitedError, DelimitedImport, Delimiter, export_cards, parse_cards,
+};
+use kreqo_core::learn::fsrs::{MAX_DIFFICULTY, MAX_STABILITY};
+use kreqo_core::learn::notes::{NoteError, NoteType, Template};
+use kreqo_core::learn::{Card, CardState};
+
+fn template(name: &str, front: &str, back: &str) -> Template {
+    Template {
+        name: name.into(),
+        front: front.into(),
+        back: back.into(),
+    }
+}
+
+fn basic_note_type() -> NoteType {
+    NoteType {
+        name: "Basic".into(),
+        fields: vec!["Front".into(), "Back".into(), "Answer".into()],
+        templates: vec![
+            template("Forward", "{{Front}}", "{{FrontSide}}\n\n---\n\n{{Back}}"),
+            template("Reverse", "{{#Answer}}{{Back}}{{/Answer}}", "{{Front}}"),
+            template("Typed", "{{Front}} {{type:Answer}}", "{{Answer}}"),
+        ],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn cloze_generates_one_card_per_number() {
+    let text = "{{c1::Paris}} is the capital of {{c2::France::country}}, {{c1::Paris}} again";
+    assert_eq!(cloze_numbers(text), vec![1, 2]);
+    let cards = cloze_cards(text);
+    assert_eq!(cards.len(), 2);
+    assert_eq!(cards[0].0, "[...] is the capital of France, [...] again");
+    assert_eq!(cards[0].1, "Paris is the capital of France, Paris again");
+    assert_eq!(cards[1].0, "Paris is the capital of [country], Paris again");
+}
+
+#[test]
+fn malformed_cloze_is_kept_as_text() {
+    assert!(cloze_numbers("{{cx::a}} {{c1::unclosed").is_empty());
+    assert!(cloze_cards("no deletion").is_empty());
+}
+
+#[test]
+fn markdown_is_parsed_into_blocks() {
+    let markdown = format!(
+        "# Title\n\nSome **bold** text\n\n- first\n- second\n\n{}\n\n![remote](https://example.com/a.png)",
+        image_markdown(42)
+    );
+    let content = CardContent::parse(&markdown);
+    assert!(matches!(&content.blocks[0], Block::Heading(1, text) if text.text == "Title"));
+    let Block::Paragraph(paragraph) = &content.blocks[1] else {
+        panic!("expected a paragraph, got {:?}", content.blocks[1]);
+    };
+    assert_eq!(paragraph.text, "Some bold text");
+    assert_eq!(
+        paragraph.spans,
+        vec![(
+            5..9,
+            SpanStyle {
+                bold: true,
+                ..Default::default()
+            }
+        )]
+    );
+    assert!(matches!(&content.blocks[2], Block::ListItem { marker, .. } if marker == "•"));
+    assert!(matches!(&content.blocks[3], Block::ListItem { text, .. } if text.text == "second"));
+    assert_eq!(content.image_ids().collect::<Vec<_>>(), vec![42]);
+    assert!(matches!(&content.blocks[5], Block::Paragraph(text) if text.text == "remote"));
+}
+
+#[test]
+fn note_templates_generate_cards() {
+    let note_type = basic_note_type();
+    note_type.validate().unwrap();
+    let fields = ["Capital of France?".into(), "Paris".into(), String::new()];
+    let cards = note_type.render_cards(&fields);
+    assert_eq!(cards.len(), 1);
+    assert_eq!(cards[0].ord, 0);
+    assert_eq!(cards[0].back, "Capital of France?\n\n---\n\nParis");
+
+    let fields = ["Capital of France?".into(), "Paris".into(), "paris".into()];
+    let cards = note_type.render_cards(&fields);
+    assert_eq!(cards.len(), 3);
+    assert_eq!((cards[1].ord, cards[1].front.as_str()), (1, "Paris"));
+    assert_eq!(cards[2].front, "Capital of France?");
+    assert_eq!(cards[2].typed_answer.as_deref(), Some("paris"));
+}
+
+#[test]
+fn cloze_note_type_generates_one_card_per_deletion() {
+    let note_type = NoteType {
+        name: "Cloze".into(),
+        cloze: true,
+        fields: vec!["Text".into()],
+        templates: vec![template("Cloze", "{{cloze:Text}}", "{{cloze:Text}}")],
+        ..Default::default()
+    };
+    note_type.validate().unwrap();
+    let cards = note_type.render_cards(&["{{c2::Paris}} is in {{c3::France}}".into()]);
+    assert_eq!(
+        cards.iter().map(|card| card.ord).collect::<Vec<_>>(),
+        vec![2, 3]
+    );
+    assert_eq!(cards[0].front, "[...] is in France");
+    assert_eq!(cards[0].back, "Paris is in France");
+    assert_eq!(
+        note_type.validate_fields(&["no deletion".into()]),
+        Err(NoteError::NoCards)
+    );
+}
+
+#[test]
+fn invalid_note_types_and_fields_are_rejected() {
+    let mut note_type = basic_note_type();
+    note_type.templates[0].back = "{{Missing}}".into();
+    assert!(matches!(
+        note_type.validate(),
+        Err(NoteError::UnknownField { field, .. }) if field == "Missing"
+    ));
+    let mut note_type = basic_note_type();
+    note_type.fields[1] = "Front".into();
+    assert!(matches!(
+        note_type.validate(),
+        Err(NoteError::InvalidFieldName(_))
+    ));
+
+    let note_type = basic_note_type();
+    assert_eq!(
+        note_type.validate_fields(&["front".into()]),
+        Err(NoteError::FieldCount {
+            expected: 3,
+            actual: 1
+        })
+    );
+    assert_eq!(
+        note_type.validate_fields(&[String::new(), "back".into(), String::new()]),
+        Err(NoteError::EmptyFirstField)
+    );
+}
+
+#[test]
+fn delimited_export_is_imported_back() {
+    let now = "2024-03-01T08:30:00.123456789Z".parse().unwrap();
+    let mut reviewed = Card::new(
+        "He said \"hi\", then; left\twith a tab".to_string(),
+        "line one\nline two\r\nline three".to_string(),
+        vec!["quotes".to_string(), "new-lines".to_string()],
+        now,
+    );
+    reviewed.extra = vec!["a,b;c\t\"d\"\ne".to_string()];
+    reviewed.stability = 12.345678901234;
+    reviewed.difficulty = 4.2;
+    reviewed.elapsed_days = 3;
+    reviewed.scheduled_days = 12;
+    reviewed.reps = 5;
+    reviewed.lapses = 1;
+    reviewed.state = CardState::Review;
+    reviewed.last_review = Some(now);
+    let new = Card::new("\"\"".to_string(), String::new(), Vec::new(), now);
+    let cards = vec![reviewed, new];
+
+    for delimiter in [Delimiter::Comma, Delimiter::Semicolon, Delimiter::Tab] {
+        let file = export_cards(&cards, delimiter, true).unwrap();
+        let import = DelimitedImport {
+            deck_name: "Imported".to_string(),
+            delimiter,
+            has_header: true,
+            mapping: ColumnMapping {
+                extra: vec![3],
+                ..ColumnMapping::default()
+            },
+        };
+        let deck = parse_cards(file.as_bytes(), &import).unwrap();
+        assert_eq!(deck.cards.len(), cards.len(), "{delimiter}");
+        for (imported, card) in deck.cards.iter().zip(&cards) {
+            let imported = &imported.card;
+            assert_eq!(imported.front, card.front, "{delimiter}");
+            assert_eq!(imported.back, card.back, "{delimiter}");
+            assert_eq!(imported.tags, card.tags, "{delimiter}");
+            assert_eq!(imported.due, card.due, "{delimiter}");
+            assert_eq!(imported.stability, card.stability, "{delimiter}");
+            assert_eq!(imported.difficulty, card.difficulty, "{delimiter}");
+            assert_eq!(imported.elapsed_days, card.elapsed_days, "{delimiter}");
+            assert_eq!(imported.scheduled_days, card.scheduled_days, "{delimiter}");
+            assert_eq!(imported.reps, card.reps, "{delimiter}");
+            assert_eq!(imported.lapses, card.lapses, "{delimiter}");
+            assert_eq!(imported.state, card.state, "{delimiter}");
+            assert_eq!(imported.last_review, card.last_review, "{delimiter}");
+        }
+        // Cards without extra fields get an empty column
+        assert_eq!(deck.cards[0].card.extra, cards[0].extra, "{delimiter}");
+        assert_eq!(deck.cards[1].card.extra, vec![String::new()], "{delimiter}");
+    }
+}
+
+#[test]
+fn imported_scheduling_is_kept_within_bounds() {
+    let import = DelimitedImport {
+        deck_name: "Imported".to_string(),
+        delimiter: Delimiter::Comma,
+        has_header: true,
+        mapping: ColumnMapping::default(),
+    };
+    let file = |stability: &str, difficulty: &str| {
+        format!(
+            "front,back,tags,due,stability,difficulty,elapsed_days,scheduled_days,reps,lapses,\
+                state,last_review\n\
+                front,back,,2024-03-01T08:30:00Z,{stability},{difficulty},0,1,1,0,2,\n"
+        )
+    };
+
+    let deck = parse_cards(file("100000", "12").as_bytes(), &import).unwrap();
+    let card = &deck.cards[0].card;
+    assert_eq!(card.state, CardState::Review);
+    assert_eq!(card.stability, MAX_STABILITY);
+    assert_eq!(card.difficulty, MAX_DIFFICULTY);
+
+    for (stability, difficulty, column) in [
+        ("NaN", "5", "stability"),
+        ("inf", "5", "stability"),
+        ("10", "-inf", "difficulty"),
+    ] {
+        let result = parse_cards(file(stability, difficulty).as_bytes(), &import);
+        assert!(
+            matches!(result, Err(DelimitedError::InvalidValue { column: invalid, .. }) if invalid == column),
+            "{stability} {difficulty}"
+        );
+    }
+}
@@ -0,0 +1,875 @@
+use chrono::{Duration, NaiveDate, TimeZone, Utc};
+use kreqo_core::audit::{AuditAction, AuditQuery, AuditTarget};
+use kreqo_core::database;
+use kreqo_core::errors::ServerError;
+use kreqo_core::learn::notes::{NoteType, Template};
+use kreqo_core::learn::{MAX_SYNCED_REVIEWS, OfflineReview, Rating};
+use kreqo_core::users::passwords::{PasswordParams, needs_rehash, verify_password};
+use kreqo_core::users::permissions::{Permission, UserPermission};
+use kreqo_core::users::query::{UserQuery, UserSortKey};
+use kreqo_core::users::roles::DEFAULT_ROLE;
+use sqlx::PgPool;
+
+async fn count_users(pool: &PgPool, username: &str) -> i64 {
+    sqlx::query_scalar("SELECT COUNT(*) FROM users WHERE username = $1")
+        .bind(username)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+/// Stores a session the way the session store does, expiring at the given Unix timestamp.
+async fn store_session(pool: &PgPool, session_id: &str, expires: Option<i32>) {
+    sqlx::query("INSERT INTO axum_sessions (id, expires, session) VALUES ($1, $2, '')")
+        .bind(session_id)
+        .bind(expires)
+        .execute(pool)
+        .await
+        .unwrap();
+}
+
+#[sqlx::test(migrations = "../server/migrations")]
+async fn create_user_grants_role(pool: PgPool) {
+    let user = database::create_user(&pool, "alice".into(), "password".into(), DEFAULT_ROLE)
+        .await
+        .unwrap();
+    assert!(user.roles.contains(DEFAULT_ROLE));
+    assert!(user.has_permission(Permission::CurrentUserManage));
+    assert!(!user.has_permission(Permission::ServerManage));
+}
+
+#[sqlx::test(migrations = "../server/migrations")]
+async fn create_user_with_unknown_role_is_rolled_back(pool: PgPool) {
+    let result = database::create_user(&pool, "alice".into(), "password".into(), "Unknown").await;
+    assert!(result.is_err());
+    assert_eq!(count_users(&pool, "alice").await, 0);
+}
+
+#[sqlx::test(migrations = "../server/migrations")]
+async fn create_user_joins_outer_transaction(pool: PgPool) {
+    let mut tx = pool.begin().await.unwrap();
+    let user = database::create_user(&mut *tx, "alice".into(), "password".into(), DEFAULT_ROLE)
+        .await
+        .unwrap();
+    database::roles::grant_permission(&mut *tx, user.id, Permission::DecksManage)
+        .await
+        .unwrap();
+    tx.rollback().await.unwrap();
+    assert_eq!(count_users(&pool, "alice").await, 0);
+}
+
+#[sqlx::test(migrations = "../server/migrations")]
+async fn duplicate_username_is_rejected(pool: PgPool) {
+    database::create_user(&pool, "alice".into(), "password".into(), DEFAULT_ROLE)
+        .await
+        .unwrap();
+    let result = database::create_user(&pool, "alice".into(), "other".into(), DEFAULT_ROLE).await;
+    assert!(matches!(result, Err(ServerError::UsernameTaken)));
+    assert_eq!(count_users(&pool, "alice").await, 1);
+}
+
+#[sqlx::test(migrations = "../server/migrations")]
+async fn add_user_perms_is_idempotent(pool: PgPool) {
+    let user = database::create_user(&pool, "alice".into(), "password".into(), DEFAULT_ROLE)
+        .await
+        .unwrap();
+    let perms: Vec<UserPermission> = vec![
+        Permission::DecksManage.into(),
+        Permission::UsersManage.into(),
+    ];
+    let mut tx = pool.begin().await.unwrap();
+    database::add_user_perms(&mut *tx, user.id, perms.clone())
+        .await
+        .unwrap();
+    database::add_user_perms(&mut *tx, user.id, perms)
+        .await
+        .unwrap();
+    tx.commit().await.unwrap();
+    let user = database::get_user(&pool, user.id).await.unwrap();
+    assert!(user.has_permission(Permission::DecksManage));
+    assert!(user.has_permission(Permission::UsersManage));
+}
+
+#[sqlx::test(migrations = "../server/migrations")]
+async fn password_reset_token_is_single_use(pool: PgPool) {
+    let user = database::create_user(&pool, "alice".into(), "password".into(), DEFAULT_ROLE)
+        .await
+        .unwrap();
+    let token = database::create_password_reset(&pool, user.id, true)
+        .await
+        .unwrap();
+    let sql_user = database::get_sql_user(&pool, user.id).await.unwrap();
+    assert!(sql_user.password_reset_required);
+    database::reset_password(&pool, &token, "new password".into())
+        .await
+        .unwrap();
+    let sql_user = database::get_sql_user(&pool, user.id).await.unwrap();
+    assert!(!sql_user.password_reset_required);
+    assert!(
+        database::reset_password(&pool, &token, "another password".into())
+            .await
+            .is_err()
+    );
+}
+
+#[sqlx::test(migrations = "../server/migrations")]
+async fn weak_password_hash_is_upgraded_on_login(pool: PgPool) {
+    let user = database::create_user(&pool, "alice".into(), "password".into(), DEFAULT_ROLE)
+        .await
+        .unwrap();
+    let weak_params = PasswordParams {
+        memory_cost: 8,
+        time_cost: 1,
+        parallelism: 1,
+    };
+    let weak_hash = weak_params.hash_password("password").unwrap();
+    assert!(needs_rehash(&weak_hash));
+    sqlx::query("UPDATE users SET password = $2 WHERE id = $1")
+        .bind(user.id)
+        .bind(&weak_hash)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    // Wrong passwords leave the hash untouched
+    let login = database::verify_login(&pool, "alice".into(), "wrong").await;
+    assert!(login.unwrap().is_none());
+    let sql_user = database::get_sql_user(&pool, user.id).await.unwrap();
+    assert_eq!(sql_user.password, weak_hash);
+
+    let login = database::verify_login(&pool, "alice".into(), "password").await;
+    assert_eq!(login.unwrap().map(|sql_user| sql_user.id), Some(user.id));
+    let sql_user = database::get_sql_user(&pool, user.id).await.unwrap();
+    assert!(!needs_rehash(&sql_user.password));
+    assert!(verify_password("password", &sql_user.password).unwrap());
+}
+
+#[sqlx::test(migrations = "../server/migrations")]
+async fn audit_log_is_filtered(pool: PgPool) {
+    let admin = database::create_user(&pool, "admin".into(), "password".into(), "Admin")
+        .await
+        .unwrap();
+    let user = database::create_user(&pool, "alice".into(), "password".into(), DEFAULT_ROLE)
+        .await
+        .unwrap();
+    database::audit::record(
+        &pool,
+        admin.id,
+        AuditAction::GrantPermission,
+        AuditTarget::User(user.id),
+        Some(&user),
+        None::<()>,
+    )
+    .await
+    .unwrap();
+    database::audit::record(
+        &pool,
+        admin.id,
+        AuditAction::CleanupSessions,
+        AuditTarget::Server,
+        None::<()>,
+        Some(0),
+    )
+    .await
+    .unwrap();
+
+    let page = database::audit::get_audit_log(&pool, &AuditQuery::default())
+        .await
+        .unwrap();
+    assert_eq!(page.total, 2);
+    assert_eq!(page.entries[0].action, AuditAction::CleanupSessions);
+    assert_eq!(page.entries[0].actor_name.as_deref(), Some("admin"));
+
+    let query = AuditQuery {
+        target: Some(AuditTarget::User(user.id)),
+        ..Default::default()
+    };
+    let page = database::audit::get_audit_log(&pool, &query).await.unwrap();
+    assert_eq!(page.total, 1);
+    assert_eq!(page.entries[0].target, AuditTarget::User(user.id));
+    assert!(page.entries[0].before.is_some());
+    assert!(page.entries[0].after.is_none());
+}
+
+#[sqlx::test(migrations = "../server/migrations")]
+async fn audit_log_records_server_actions(pool: PgPool) {
+    database::audit::record(
+        &pool,
+        None,
+        AuditAction::PurgeUser,
+        AuditTarget::User(2),
+        None::<()>,
+        None::<()>,
+    )
+    .await
+    .unwrap();
+
+    let page = database::audit::get_audit_log(&pool, &AuditQuery::default())
+        .await
+        .unwrap();
+    assert_eq!(page.total, 1);
+    assert_eq!(page.entries[0].actor_id, None);
+    assert_eq!(page.entries[0].actor_name, None);
+}
+
+#[sqlx::test(migrations = "../server/migrations")]
+async fn username_of_deleted_user_is_taken(pool: PgPool) {
+    let alice = database::create_user(&pool, "alice".into(), "password".into(), DEFAULT_ROLE)
+        .await
+        .unwrap();
+    let bob = database::create_user(&pool, "bob".into(), "password".into(), DEFAULT_ROLE)
+        .await
+        .unwrap();
+    database::delete_user(&pool, alice.id).await.unwrap();
+
+    let result =
+        database::create_user(&pool, "alice".into(), "password".into(), DEFAULT_ROLE).await;
+    assert!(matches!(result, Err(ServerError::UsernameTaken)));
+    let result = database::update_user_username(&pool, bob.id, "alice".into()).await;
+    assert!(matches!(result, Err(ServerError::UsernameTaken)));
+    assert_eq!(count_users(&pool, "alice").await, 1);
+}
+
+#[sqlx::test(migrations = "../server/migrations")]
+async fn deleted_user_is_restored_until_purged(pool: PgPool) {
+    let user = database::create_user(&pool, "alice".into(), "password".into(), DEFAULT_ROLE)
+        .await
+        .unwrap();
+    database::delete_user(&pool, user.id).await.unwrap();
+    assert!(database::get_user(&pool, user.id).await.is_err());
+    assert!(
+        !database::has_permission(&pool, user.id, Permission::CurrentUserManage)
+            .await
+            .unwrap()
+    );
+    let query = UserQuery {
+        deleted: true,
+        ..Default::default()
+    };
+    let page = database::get_users(&pool, &query).await.unwrap();
+    assert_eq!(page.total, 1);
+    assert!(page.users[0].deleted_at.is_some());
+
+    let user = database::restore_user(&pool, user.id).await.unwrap();
+    assert!(user.deleted_at.is_none());
+    assert!(database::restore_user(&pool, user.id).await.is_err());
+
+    database::delete_user(&pool, user.id).await.unwrap();
+    let purged = database::purge_deleted_users(&pool, Utc::now() - Duration::days(1))
+        .await
+        .unwrap();
+    assert!(purged.is_empty());
+    let purged = database::purge_deleted_users(&pool, Utc::now() + Duration::seconds(1))
+        .await
+        .unwrap();
+    assert_eq!(purged, vec![user.id]);
+    assert_eq!(count_users(&pool, "alice").await, 0);
+}
+
+#[sqlx::test(migrations = "../server/migrations")]
+async fn users_are_sorted_paged_and_searched(pool: PgPool) {
+    for username in ["bob", "Alice", "carol"] {
+        database::create_user(&pool, username.into(), "password".into(), DEFAULT_ROLE)
+            .await
+            .unwrap();
+    }
+    let usernames = async |query: UserQuery| {
+        let sql_users = database::get_sql_users(&pool, &query).await.unwrap();
+        let count = database::count_sql_users(&pool, &query).await.unwrap();
+        let usernames = sql_users
+            .into_iter()
+            .map(|sql_user| sql_user.username)
+            .collect::<Vec<_>>();
+        (usernames, count)
+    };
+
+    // Usernames are sorted regardless of their case, the count ignoring the paging
+    let (by_id, count) = usernames(UserQuery::default()).await;
+    assert_eq!(by_id, ["bob", "Alice", "carol"]);
+    assert_eq!(count, 3);
+    let query = UserQuery {
+        sort_by: UserSortKey::Username,
+        ..Default::default()
+    };
+    let (by_username, _) = usernames(query.clone()).await;
+    assert_eq!(by_username, ["Alice", "bob", "carol"]);
+    let query = UserQuery {
+        descending: true,
+        offset: 1,
+        limit: 1,
+        ..query
+    };
+    let (page, count) = usernames(query).await;
+    assert_eq!(page, ["bob"]);
+    assert_eq!(count, 3);
+    let query = UserQuery {
+        sort_by: UserSortKey::CreatedAt,
+        descending: true,
+        ..Default::default()
+    };
+    let (by_creation, _) = usernames(query).await;
+    assert_eq!(by_creation, ["carol", "Alice", "bob"]);
+
+    // Wildcards in the search are matched literally
+    for username in ["dave_x", "dave%x", "davexx", "ca\\rl"] {
+        database::create_user(&pool, username.into(), "password".into(), DEFAULT_ROLE)
+            .await
+            .unwrap();
+    }
+    for (search, username) in [
+        ("_x", "dave_x"),
+        ("%", "dave%x"),
+        ("\\", "ca\\rl"),
+        ("ALI", "Alice"),
+    ] {
+        let query = UserQuery {
+            search: search.to_string(),
+            ..Default::default()
+        };
+        let (found, count) = usernames(query).await;
+        assert_eq!(found, [username], "{search}");
+        assert_eq!(count, 1, "{search}");
+    }
+}
+
+#[sqlx::test(migrations = "../server/migrations")]
+async fn review_is_persisted(pool: PgPool) {
+    let alice = database::create_user(&pool, "alice".into(), "password".into(), DEFAULT_ROLE)
+        .await
+        .unwrap();
+    let deck = database::learn::create_deck(&pool, alice.id, "Deck".into(), String::new())
+        .await
+        .unwrap();
+    let card = database::learn::create_card(&pool, deck.id, "front".into(), "back".into())
+        .await
+        .unwrap();
+    let now = Utc.with_ymd_and_hms(2026, 1, 1, 12, 0, 0).unwrap();
+
+    let reviewed = database::learn::review_card(&pool, card.id, Rating::Good, 1_500, now)
+        .await
+        .unwrap();
+    let stored = database::learn::get_card(&pool, card.id).await.unwrap();
+    assert!(stored.stability > 0.);
+    assert_eq!(stored.stability, reviewed.stability);
+    assert_eq!(stored.difficulty, reviewed.difficulty);
+    assert_eq!(stored.due, reviewed.due);
+    assert!(stored.due > now);
+    assert_eq!(stored.reps, 1);
+    assert_eq!(stored.last_review, Some(now));
+
+    let review_logs = database::learn::get_review_logs(&pool, card.id)
+        .await
+        .unwrap();
+    assert_eq!(review_logs.len(), 1);
+    assert_eq!(review_logs[0].rating, Rating::Good);
+    assert_eq!(review_logs[0].reviewed_at, now);
+    assert_eq!(review_logs[0].time_taken_ms, 1_500);
+}
+
+#[sqlx::test(migrations = "../server/migrations")]
+async fn offline_reviews_are_skipped_or_clamped(pool: PgPool) {
+    let alice = database::create_user(&pool, "alice".into(), "password".into(), DEFAULT_ROLE)
+        .await
+        .unwrap();
+    let bob = database::create_user(&pool, "bob".into(), "password".into(), DEFAULT_ROLE)
+        .await
+        .unwrap();
+    let deck = database::learn::create_deck(&pool, alice.id, "Deck".into(), String::new())
+        .await
+        .unwrap();
+    let card = database::learn::create_card(&pool, deck.id, "front".into(), "back".into())
+        .await
+        .unwrap();
+    let now = Utc::now();
+    let later = now + Duration::hours(2);
+    database::learn::review_card(&pool, card.id, Rating::Good, 0, now)
+        .await
+        .unwrap();
+
+    let review = |reviewed_at| OfflineReview {
+        card_id: card.id,
+        rating: Rating::Again,
+        reviewed_at,
+        time_taken_ms: 0,
+    };
+    let cards =
+        database::learn::sync_reviews(&pool, bob.id, vec![review(now + Duration::hours(1))], later)
+            .await
+            .unwrap();
+    assert!(cards.is_empty());
+    let cards = database::learn::sync_reviews(
+        &pool,
+        alice.id,
+        vec![
+            review(now + Duration::hours(1)),
+            review(now - Duration::hours(1)),
+        ],
+        later,
+    )
+    .await
+    .unwrap();
+    assert_eq!(cards.len(), 1);
+    assert!(cards[0].last_review.unwrap() > now);
+
+    // Reviews can't be made in the future, nor before the card existed
+    let cards = database::learn::sync_reviews(
+        &pool,
+        alice.id,
+        vec![review(later + Duration::days(30))],
+        later + Duration::hours(1),
+    )
+    .await
+    .unwrap();
+    let last_review = cards[0].last_review.unwrap();
+    assert!(last_review > later && last_review <= later + Duration::hours(1));
+    let new_card = database::learn::create_card(&pool, deck.id, "new".into(), "card".into())
+        .await
+        .unwrap();
+    let cards = database::learn::sync_reviews(
+        &pool,
+        alice.id,
+        vec![OfflineReview {
+            card_id: new_card.id,
+            reviewed_at: now - Duration::days(30),
+            ..review(now)
+        }],
+        later + Duration::hours(1),
+    )
+    .await
+    .unwrap();
+    assert_eq!(cards[0].last_review, new_card.created_at);
+
+    let error = database::learn::sync_reviews(
+        &pool,
+        alice.id,
+        vec![review(now); MAX_SYNCED_REVIEWS + 1],
+        later,
+    )
+    .await
+    .unwrap_err();
+    assert!(matches!(error, ServerError::TooManyReviews { .. }));
+}
+
+#[sqlx::test(migrations = "../server/migrations")]
+async fn item_owners_are_looked_up_without_revealing_items(pool: PgPool) {
+    let alice = database::create_user(&pool, "alice".into(), "password".into(), DEFAULT_ROLE)
+        .await
+        .unwrap();
+    let deck = database::learn::create_deck(&pool, alice.id, "Deck".into(), String::new())
+        .await
+        .unwrap();
+    let card = database::learn::create_card(&pool, deck.id, "front".into(), "back".into())
+        .await
+        .unwrap();
+    let deck_owner = database::learn::get_deck_owner(&pool, deck.id)
+        .await
+        .unwrap();
+    let card_owner = database::learn::get_card_owner(&pool, card.id)
+        .await
+        .unwrap();
+    assert_eq!((deck_owner, card_owner), (Some(alice.id), Some(alice.id)));
+
+    // Missing items have no owner rather than failing
+    database::learn::delete_deck(&pool, deck.id).await.unwrap();
+    let deck_owner = database::learn::get_deck_owner(&pool, deck.id)
+        .await
+        .unwrap();
+    let card_owner = database::learn::get_card_owner(&pool, card.id)
+        .await
+        .unwrap();
+    let note_owner = database::notes::get_note_owner(&pool, -1).await.unwrap();
+    assert_eq!((deck_owner, card_owner, note_owner), (None, None, None));
+}
+
+#[sqlx::test(migrations = "../server/migrations")]
+async fn editing_notes_and_templates_keeps_card_history(pool: PgPool) {
+    let alice = database::create_user(&pool, "alice".into(), "password".into(), DEFAULT_ROLE)
+        .await
+        .unwrap();
+    let deck = database::learn::create_deck(&pool, alice.id, "Deck".into(), String::new())
+        .await
+        .unwrap();
+    let template = |name: &str, front: &str, back: &str| Template {
+        name: name.into(),
+        front: front.into(),
+        back: back.into(),
+    };
+    let note_type = NoteType {
+        name: "Basic".into(),
+        fields: vec!["Front".into(), "Back".into()],
+        templates: vec![
+            template("Forward", "{{Front}}", "{{Back}}"),
+            template("Reverse", "{{Back}}", "{{Front}}"),
+        ],
+        ..Default::default()
+    };
+    let note_type = database::notes::create_note_type(&pool, alice.id, &note_type)
+        .await
+        .unwrap();
+    let (note, cards) = database::notes::create_note(
+        &pool,
+        deck.id,
+        &note_type,
+        vec!["Paris".into(), "France".into()],
+        Vec::new(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(cards.len(), 2);
+    let owner_id = database::notes::get_note_owner(&pool, note.id)
+        .await
+        .unwrap();
+    assert_eq!(owner_id, Some(alice.id));
+    let reviewed = database::learn::review_card(&pool, cards[0].id, Rating::Good, 0, Utc::now())
+        .await
+        .unwrap();
+
+    let (_, updated_cards) = database::notes::update_note(
+        &pool,
+        note.id,
+        &note_type,
+        vec!["Paris".into(), "France".into()],
+        vec!["geography".into()],
+    )
+    .await
+    .unwrap();
+    assert_eq!(updated_cards[0].id, reviewed.id);
+    assert_eq!(updated_cards[0].reps, reviewed.reps);
+    assert_eq!(updated_cards[0].tags, vec!["geography".to_string()]);
+    assert_eq!(updated_cards[1].id, cards[1].id);
+
+    let mut edited_note_type = note_type.clone();
+    edited_note_type.templates[0].back = "{{Back}}!".into();
+    let edited_note_type =
+        database::notes::update_note_type(&pool, note_type.id, &edited_note_type)
+            .await
+            .unwrap();
+    let cards_after_edit = database::learn::get_cards(&pool, deck.id).await.unwrap();
+    assert_eq!(cards_after_edit.len(), 2);
+    let edited_card = cards_after_edit
+        .iter()
+        .find(|card| card.id == reviewed.id)
+        .unwrap();
+    assert_eq!(edited_card.back, "France!");
+    assert_eq!(edited_card.reps, reviewed.reps);
+    let review_logs = database::learn::get_review_logs(&pool, reviewed.id)
+        .await
+        .unwrap();
+    assert_eq!(review_logs.len(), 1);
+
+    let (_, updated_cards) = database::notes::update_note(
+        &pool,
+        note.id,
+        &edited_note_type,
+        vec!["Lyon".into(), String::new()],
+        Vec::new(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(updated_cards.len(), 1);
+    assert_eq!(updated_cards[0].id, reviewed.id);
+    assert_eq!(updated_cards[0].front, "Lyon");
+    let cards_after_edit = database::learn::get_cards(&pool, deck.id).await.unwrap();
+    assert_eq!(cards_after_edit.len(), 1);
+
+    let cloze_note_type = NoteType {
+        cloze: true,
+        ..edited_note_type
+    };
+    assert!(
+        database::notes::update_note_type(&pool, note_type.id, &cloze_note_type)
+            .await
+            .is_err()
+    );
+    assert!(
+        database::notes::delete_note_type(&pool, note_type.id)
+            .await
+            .is_err()
+    );
+}
+
+#[sqlx::test(migrations = "../server/migrations")]
+async fn study_stats_follow_the_day_boundary(pool: PgPool) {
+    let alice = database::create_user(&pool, "alice".into(), "password".into(), DEFAULT_ROLE)
+        .await
+        .unwrap();
+    database::scheduler::update_day_boundary(&pool, alice.id, "America/New_York", 4)
+        .await
+        .unwrap();
+    let deck = database::learn::create_deck(&pool, alice.id, "Deck".into(), String::new())
+        .await
+        .unwrap();
+    let mut cards = Vec::new();
+    for front in ["one", "two"] {
+        let card = database::learn::create_card(&pool, deck.id, front.into(), "back".into())
+            .await
+            .unwrap();
+        cards.push(card);
+    }
+    // 02:00 in New York, still counted for the day before, then 05:00
+    let late = Utc.with_ymd_and_hms(2026, 3, 10, 6, 0, 0).unwrap();
+    let early = Utc.with_ymd_and_hms(2026, 3, 10, 9, 0, 0).unwrap();
+    database::learn::review_card(&pool, cards[0].id, Rating::Good, 90_000, late)
+        .await
+        .unwrap();
+    database::learn::review_card(&pool, cards[1].id, Rating::Good, 5_000, early)
+        .await
+        .unwrap();
+
+    let now = Utc.with_ymd_and_hms(2026, 3, 10, 12, 0, 0).unwrap();
+    let stats = database::stats::get_study_stats(&pool, alice.id, now, 7)
+        .await
+        .unwrap();
+    assert_eq!(stats.days.len(), 7);
+    let today = stats.days[6];
+    let yesterday = stats.days[5];
+    assert_eq!(today.date, NaiveDate::from_ymd_opt(2026, 3, 10).unwrap());
+    assert_eq!((today.reviews, today.time_taken_ms), (1, 5_000));
+    assert_eq!((yesterday.reviews, yesterday.time_taken_ms), (1, 60_000));
+    assert_eq!(stats.reviews(), 2);
+    assert_eq!(stats.retention(), None);
+    assert_eq!(stats.maturity.learning, 2);
+
+    // Both cards are overdue, so they count for today, while new cards aren't counted
+    database::learn::create_card(&pool, deck.id, "new".into(), "back".into())
+        .await
+        .unwrap();
+    let forecast = database::stats::get_due_forecast(&pool, alice.id, now, 3)
+        .await
+        .unwrap();
+    let due = forecast.iter().map(|day| day.due).collect::<Vec<_>>();
+    assert_eq!(due, vec![2, 0, 0]);
+}
+
+#[sqlx::test(migrations = "../server/migrations")]
+async fn session_requests_are_recorded_once_per_interval(pool: PgPool) {
+    let alice = database::create_user(&pool, "alice".into(), "password".into(), DEFAULT_ROLE)
+        .await
+        .unwrap();
+    let touch = async |address: &str| {
+        database::sessions::touch_session(
+            &pool,
+            "session",
+            alice.id,
+            "Client",
+            Some(address.to_string()),
+        )
+        .await
+        .unwrap()
+    };
+    store_session(&pool, "session", None).await;
+    let last_seen_at = async || {
+        let sessions = database::sessions::get_user_sessions(&pool, alice.id, "session")
+            .await
+            .unwrap();
+        sessions[0].last_seen_at
+    };
+    assert!(touch("127.0.0.1").await);
+    let first_seen_at = last_seen_at().await;
+    assert!(touch("127.0.0.1").await);
+    assert_eq!(last_seen_at().await, first_seen_at);
+
+    // A new address is recorded right away
+    assert!(touch("127.0.0.2").await);
+    let sessions = database::sessions::get_user_sessions(&pool, alice.id, "session")
+        .await
+        .unwrap();
+    assert_eq!(sessions[0].client_address.as_deref(), Some("127.0.0.2"));
+    assert!(sessions[0].last_seen_at > first_seen_at);
+
+    // Revoked sessions are reported even when they were seen recently
+    database::sessions::revoke_session(&pool, alice.id, sessions[0].id)
+        .await
+        .unwrap();
+    assert!(!touch("127.0.0.2").await);
+}
+
+#[sqlx::test(migrations = "../server/migrations")]
+async fn password_changes_revoke_sessions(pool: PgPool) {
+    let alice = database::create_user(&pool, "alice".into(), "password".into(), DEFAULT_ROLE)
+        .await
+        .unwrap();
+    for session_id in ["laptop", "phone"] {
+        store_session(&pool, session_id, None).await;
+    }
+    let open_sessions = async || {
+        for session_id in ["laptop", "phone"] {
+            database::sessions::touch_session(&pool, session_id, alice.id, "Client", None)
+                .await
+                .unwrap();
+        }
+    };
+    let is_active = async |session_id: &str| {
+        database::sessions::is_session_active(&pool, session_id)
+            .await
+            .unwrap()
+    };
+
+    // Changing the password keeps the current session only
+    open_sessions().await;
+    let mut tx = pool.begin().await.unwrap();
+    database::update_user_password(&mut *tx, alice.id, "new password".into())
+        .await
+        .unwrap();
+    database::sessions::revoke_other_sessions(&mut *tx, alice.id, "laptop")
+        .await
+        .unwrap();
+    tx.commit().await.unwrap();
+    assert!(is_active("laptop").await);
+    assert!(!is_active("phone").await);
+    database::sessions::delete_sessions(&pool, &["laptop".into(), "phone".into()])
+        .await
+        .unwrap();
+
+    // Forcing a reset revokes every session, as does using the token
+    open_sessions().await;
+    let token = database::create_password_reset(&pool, alice.id, true)
+        .await
+        .unwrap();
+    assert!(!is_active("laptop").await);
+    assert!(!is_active("phone").await);
+    database::sessions::delete_sessions(&pool, &["laptop".into(), "phone".into()])
+        .await
+        .unwrap();
+
+    open_sessions().await;
+    database::reset_password(&pool, &token, "another password".into())
+        .await
+        .unwrap();
+    assert!(!is_active("laptop").await);
+    assert!(!is_active("phone").await);
+}
+
+#[sqlx::test(migrations = "../server/migrations")]
+async fn stale_sessions_are_hidden_and_cleaned_up(pool: PgPool) {
+    let alice = database::create_user(&pool, "alice".into(), "password".into(), DEFAULT_ROLE)
+        .await
+        .unwrap();
+    let now = Utc::now().timestamp() as i32;
+    store_session(&pool, "active", Some(now + 3600)).await;
+    store_session(&pool, "expired", Some(now - 3600)).await;
+    store_session(&pool, "revoked", None).await;
+    for session_id in ["active", "expired", "revoked", "unknown"] {
+        database::sessions::touch_session(&pool, session_id, alice.id, "Client", None)
+            .await
+            .unwrap();
+    }
+    let sessions = database::sessions::get_user_sessions(&pool, alice.id, "active")
+        .await
+        .unwrap();
+    let revoked = sessions.iter().find(|session| !session.current).unwrap();
+    database::sessions::revoke_session(&pool, alice.id, revoked.id)
+        .await
+        .unwrap();
+
+    // Sessions unknown to the session store are never recorded, expired ones aren't listed
+    let sessions = database::sessions::get_user_sessions(&pool, alice.id, "active")
+        .await
+        .unwrap();
+    assert_eq!(sessions.len(), 1);
+    assert!(sessions[0].current);
+
+    let mut removed = database::sessions::delete_stale_sessions(&pool)
+        .await
+        .unwrap();
+    removed.sort();
+    assert_eq!(removed, vec!["expired".to_string(), "revoked".to_string()]);
+    let stored: Vec<String> = sqlx::query_scalar("SELECT id FROM axum_sessions ORDER BY id")
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+    assert_eq!(stored, vec!["active".to_string(), "expired".to_string()]);
+    assert!(
+        database::sessions::is_session_active(&pool, "active")
+            .await
+            .unwrap()
+    );
+}
+
+#[sqlx::test(migrations = "../server/migrations")]
+async fn login_attempts_back_off_until_locked(pool: PgPool) {
+    let keys = vec![
+        "username:alice".to_string(),
+        "address:127.0.0.1".to_string(),
+    ];
+    let reserve = async || database::login_attempts::reserve_login_attempt(&pool, &keys).await;
+    let unlock = async || {
+        sqlx::query("UPDATE login_attempts SET locked_until = NOW() - INTERVAL '1 second'")
+            .execute(&pool)
+            .await
+            .unwrap();
+    };
+    let failures = async |key: &str| -> i32 {
+        sqlx::query_scalar("SELECT failures FROM login_attempts WHERE key = $1")
+            .bind(key)
+            .fetch_one(&pool)
+            .await
+            .unwrap()
+    };
+
+    // A few attempts are free, the next ones wait for a delay doubling each time
+    for _ in 0..3 {
+        reserve().await.unwrap();
+    }
+    assert!(matches!(
+        reserve().await,
+        Err(ServerError::LoginLocked { retry_after: 1..=2 })
+    ));
+    // Locked attempts aren't counted
+    assert_eq!(failures("username:alice").await, 3);
+    unlock().await;
+    reserve().await.unwrap();
+    assert!(matches!(
+        reserve().await,
+        Err(ServerError::LoginLocked { retry_after: 2..=3 })
+    ));
+
+    // Until the login is locked for an hour
+    for _ in 4..10 {
+        unlock().await;
+        reserve().await.unwrap();
+    }
+    assert!(matches!(
+        reserve().await,
+        Err(ServerError::LoginLocked {
+            retry_after: 3500..
+        })
+    ));
+
+    // A success clears the username, and only decays the address
+    unlock().await;
+    database::login_attempts::clear_login_failures(&pool, "username:alice")
+        .await
+        .unwrap();
+    database::login_attempts::decay_login_failures(&pool, "address:127.0.0.1")
+        .await
+        .unwrap();
+    assert_eq!(failures("address:127.0.0.1").await, 8);
+    database::login_attempts::reserve_login_attempt(&pool, &keys[..1])
+        .await
+        .unwrap();
+    assert_eq!(failures("username:alice").await, 1);
+
+    // Old failures are forgotten
+    sqlx::query("UPDATE login_attempts SET last_failure_at = NOW() - INTERVAL '25 hours'")
+        .execute(&pool)
+        .await
+        .unwrap();
+    reserve().await.unwrap();
+    assert_eq!(failures("address:127.0.0.1").await, 1);
+}
+
+#[sqlx::test(migrations = "../server/migrations")]
+async fn role_permissions_are_listed_for_grants(pool: PgPool) {
+    let roles = database::roles::get_roles(&pool).await.unwrap();
+    let admin = roles.iter().find(|role| role.name == "Admin").unwrap();
+    let permissions = database::roles::get_role_permissions(&pool, admin.id)
+        .await
+        .unwrap();
+    assert_eq!(permissions.len(), admin.permissions.len());
+    assert!(permissions.contains(&Permission::ServerManage));
+    let permissions = database::roles::get_role_permissions(&pool, -1)
+        .await
+        .unwrap();
+    assert!(permissions.is_empty());
+}
@@ -0,0 +1,62 @@
+use std::pin::pin;
+
+use futures::StreamExt;
+use kreqo_core::database;
+use kreqo_core::events::{ItemEvent, ServerEvent};
+use kreqo_core::learn::Deck;
+use kreqo_core::users::User;
+use kreqo_core::users::permissions::Permission;
+use kreqo_core::users::roles::DEFAULT_ROLE;
+use kreqo_server::events;
+use sqlx::PgPool;
+
+#[test]
+fn events_are_only_visible_to_allowed_users() {
+    let owner = User {
+        id: 2,
+        ..Default::default()
+    };
+    let mut admin = User {
+        id: 3,
+        ..Default::default()
+    };
+    admin.permissions.insert(Permission::UsersView);
+    admin.permissions.insert(Permission::DecksManage);
+
+    let user_event = ServerEvent::User(ItemEvent::Deleted(owner.id));
+    assert!(user_event.is_visible_to(&admin));
+    assert!(!user_event.is_visible_to(&owner));
+
+    let deck_event = ServerEvent::deck(owner.id, ItemEvent::Updated(Deck::default()));
+    assert!(deck_event.is_visible_to(&owner));
+    assert!(!deck_event.is_visible_to(&admin));
+}
+
+#[sqlx::test(migrations = "../server/migrations")]
+async fn subscriptions_end_with_their_session(pool: PgPool) {
+    // Subscriptions outlive the request, so they borrow the pool for good
+    let pool: &'static PgPool = Box::leak(Box::new(pool));
+    let alice = database::create_user(pool, "alice".into(), "password".into(), DEFAULT_ROLE)
+        .await
+        .unwrap();
+    sqlx::query("INSERT INTO axum_sessions (id, expires, session) VALUES ('session', NULL, '')")
+        .execute(pool)
+        .await
+        .unwrap();
+    database::sessions::touch_session(pool, "session", alice.id, "Client", None)
+        .await
+        .unwrap();
+    let mut subscription = pin!(events::subscribe(pool, alice.clone(), "session".into()));
+
+    events::publish(ServerEvent::deck(alice.id, ItemEvent::Deleted(1)));
+    let event = subscription.next().await;
+    assert!(matches!(event, Some(Ok(ServerEvent::Deck { .. }))));
+
+    // The revoked session is noticed once the user changes
+    database::sessions::revoke_user_sessions(pool, alice.id)
+        .await
+        .unwrap();
+    events::publish(ServerEvent::User(ItemEvent::Updated(alice)));
+    assert!(subscription.next().await.is_none());
+    pool.close().await;
+}
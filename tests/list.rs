@@ -0,0 +1,155 @@
+use std::time::Duration;
+
+use kreqo_core::errors::ServerError;
+use kreqo_core::learn::Deck;
+use kreqo_core::users::User;
+use kreqo_ui::component::list::storage::{ListPage, RetryClass, Retryable};
+use kreqo_ui::component::list::{AsyncList, ListMessage, ListRequest, RetryPolicy};
+use kreqo_ui::deck_list::DeckStorage;
+use kreqo_ui::pending::Pending;
+use kreqo_ui::user_list::UserStorage;
+use xilem::tokio::sync::mpsc::{UnboundedReceiver, unbounded_channel};
+
+struct Error(RetryClass);
+
+impl Retryable for Error {
+    fn retry_class(&self) -> RetryClass {
+        self.0
+    }
+}
+
+const TRANSIENT: Error = Error(RetryClass::Transient);
+
+fn policy(jitter: f32) -> RetryPolicy {
+    RetryPolicy {
+        initial_delay: Duration::from_secs(1),
+        max_delay: Duration::from_secs(10),
+        multiplier: 2.,
+        jitter,
+        max_attempts: 10,
+    }
+}
+
+#[test]
+fn retry_delay_grows_until_capped() {
+    let policy = policy(0.);
+    let delays = (1..=6)
+        .map(|attempts| policy.delay(attempts, &TRANSIENT).unwrap().as_secs_f32())
+        .collect::<Vec<_>>();
+    assert_eq!(delays, vec![1., 2., 4., 8., 10., 10.]);
+}
+
+#[test]
+fn retry_delay_jitter_stays_within_bounds() {
+    let jittered = policy(0.2);
+    for attempts in 1..=6 {
+        let backoff = policy(0.)
+            .delay(attempts, &TRANSIENT)
+            .unwrap()
+            .as_secs_f32();
+        for _ in 0..100 {
+            let delay = jittered.delay(attempts, &TRANSIENT).unwrap().as_secs_f32();
+            assert!(delay >= backoff * 0.8 - 1e-3 && delay <= backoff * 1.2 + 1e-3);
+        }
+    }
+}
+
+#[test]
+fn retries_stop_at_the_last_attempt_or_permanent_errors() {
+    let policy = policy(0.2);
+    assert!(policy.delay(10, &TRANSIENT).is_none());
+    assert!(policy.delay(1, &Error(RetryClass::Permanent)).is_none());
+    let throttled = Error(RetryClass::Throttled(Duration::from_secs(42)));
+    assert_eq!(policy.delay(1, &throttled), Some(Duration::from_secs(42)));
+}
+
+fn deck(id: i64, name: &str) -> Deck {
+    Deck {
+        id,
+        name: name.to_string(),
+        ..Deck::default()
+    }
+}
+
+fn deck_names(list: &AsyncList<Deck, DeckStorage>) -> Vec<&str> {
+    list.items().iter().map(|deck| deck.name.as_str()).collect()
+}
+
+#[test]
+fn optimistic_creation_is_replaced_or_rolled_back() {
+    let (sender, mut receiver) = unbounded_channel();
+    let mut list = AsyncList::<Deck, DeckStorage>::new(false, false).optimistic();
+    list.set_sender(sender);
+    let fetch = receiver.try_recv().unwrap();
+    let fetched = ListMessage::FetchedAll(vec![deck(1, "French")]);
+    list.handle_message(Pending::from((fetch.request_id, fetched)));
+
+    // The deck is shown with a placeholder id before being created
+    list.create(("Spanish".to_string(), String::new()));
+    let failed = receiver.try_recv().unwrap();
+    assert_eq!(deck_names(&list), ["French", "Spanish"]);
+    assert!(list.items()[1].id < 0);
+
+    // Permanent errors aren't retried, so the creation is undone right away
+    let error = ListMessage::Error(ServerError::Unauthorized);
+    list.handle_message(Pending::from((failed.request_id, error)));
+    assert_eq!(deck_names(&list), ["French"]);
+    assert!(receiver.try_recv().is_err());
+
+    list.create(("German".to_string(), String::new()));
+    let created = receiver.try_recv().unwrap();
+    let answer = ListMessage::Created(deck(2, "German"));
+    list.handle_message(Pending::from((created.request_id, answer)));
+    let ids = list.items().iter().map(|deck| deck.id).collect::<Vec<_>>();
+    assert_eq!(ids, [1, 2]);
+}
+
+fn user(id: i64) -> User {
+    User {
+        id,
+        username: format!("user{id}"),
+        ..User::default()
+    }
+}
+
+/// Answers the page the list asked for with `users`, returning its offset.
+fn answer_page(
+    list: &mut AsyncList<User, UserStorage>,
+    receiver: &mut UnboundedReceiver<Pending<ListRequest<User>>>,
+    users: Vec<User>,
+) -> usize {
+    let fetch = receiver.try_recv().unwrap();
+    let ListRequest::FetchPage(query, offset) = fetch.data else {
+        panic!("expected a page to be fetched");
+    };
+    let page = ListPage {
+        items: users,
+        has_more: true,
+    };
+    let fetched = ListMessage::FetchedPage(query, offset, page);
+    list.handle_message(Pending::from((fetch.request_id, fetched)));
+    offset
+}
+
+#[test]
+fn pages_follow_the_offset_of_the_server() {
+    let (sender, mut receiver) = unbounded_channel();
+    let mut list = AsyncList::<User, UserStorage>::new(false, false);
+    list.set_sender(sender);
+    let offset = answer_page(&mut list, &mut receiver, vec![user(2), user(3)]);
+    assert_eq!(offset, 0);
+
+    // Users pushed by the storage don't move the next page
+    list.handle_message(Pending::new(ListMessage::Created(user(4))));
+    list.load_more();
+    let offset = answer_page(&mut list, &mut receiver, vec![user(4), user(5)]);
+    assert_eq!(offset, 2);
+    let ids = list.items().iter().map(|user| user.id).collect::<Vec<_>>();
+    assert_eq!(ids, [2, 3, 4, 5]);
+
+    // While deleted ones move it back, a page without new users fetching the next one
+    list.handle_message(Pending::new(ListMessage::Deleted(2)));
+    list.load_more();
+    assert_eq!(answer_page(&mut list, &mut receiver, vec![user(5)]), 3);
+    assert_eq!(answer_page(&mut list, &mut receiver, Vec::new()), 4);
+}
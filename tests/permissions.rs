@@ -0,0 +1,33 @@
+use kreqo_core::users::permissions::Permission;
+
+#[test]
+fn every_permission_is_listed() {
+    let tokens = [
+        "Server::Manage",
+        "Users::View",
+        "Users::Manage",
+        "CurrentUser::Manage",
+        "Decks::Manage",
+    ];
+    assert_eq!(
+        Permission::ALL.map(|permission| permission.to_string()),
+        tokens
+    );
+    for permission in Permission::ALL {
+        // Fails to compile when a permission is added without being listed here
+        match permission {
+            Permission::ServerManage
+            | Permission::UsersView
+            | Permission::UsersManage
+            | Permission::CurrentUserManage
+            | Permission::DecksManage => {}
+        }
+        assert_eq!(permission.to_string().parse(), Ok(permission));
+    }
+}
+
+#[test]
+fn unknown_permission_is_rejected() {
+    assert!("Users::Delete".parse::<Permission>().is_err());
+    assert!("users::view".parse::<Permission>().is_err());
+}
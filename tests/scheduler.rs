@@ -0,0 +1,248 @@
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use hashbrown::{HashMap, HashSet};
+use kreqo_core::learn::optimizer::{MIN_REVIEWS, OptimizationError, optimize};
+use kreqo_core::learn::presets::{DeckPreset, LEECH_TAG, QueueLimits, StudyPlan};
+use kreqo_core::learn::{Card, CardState, Rating, ReviewLog, ReviewQueue, SchedulerSettings};
+
+fn review_log(
+    card_id: i64,
+    rating: Rating,
+    state: CardState,
+    reviewed_at: DateTime<Utc>,
+) -> ReviewLog {
+    ReviewLog {
+        id: 0,
+        card_id,
+        rating,
+        state,
+        elapsed_days: 0,
+        scheduled_days: 0,
+        reviewed_at,
+        time_taken_ms: 0,
+    }
+}
+
+/// Cards learned on the same day then reviewed at growing intervals, forgotten once in a while.
+fn histories(card_count: i64) -> Vec<Vec<ReviewLog>> {
+    let start = Utc::now() - Duration::days(365);
+    (0..card_count)
+        .map(|card_id| {
+            let mut reviewed_at = start;
+            let mut history = vec![review_log(
+                card_id,
+                Rating::Good,
+                CardState::New,
+                reviewed_at,
+            )];
+            for (index, interval) in [2, 6, 15, 40, 90].into_iter().enumerate() {
+                reviewed_at += Duration::days(interval);
+                let rating = if (card_id + index as i64) % 7 == 0 {
+                    Rating::Again
+                } else {
+                    Rating::Good
+                };
+                history.push(review_log(card_id, rating, CardState::Review, reviewed_at));
+            }
+            history
+        })
+        .collect()
+}
+
+#[test]
+fn optimization_needs_enough_reviews() {
+    let mut histories = histories(10);
+    // Histories missing their first reviews are ignored
+    histories.push(vec![review_log(
+        99,
+        Rating::Good,
+        CardState::Review,
+        Utc::now(),
+    )]);
+    assert_eq!(
+        optimize(&histories, Utc::now()),
+        Err(OptimizationError::NotEnoughReviews {
+            required: MIN_REVIEWS,
+            actual: 50,
+        })
+    );
+}
+
+#[test]
+fn optimization_doesnt_make_predictions_worse() {
+    let now = Utc::now();
+    let optimization = optimize(&histories(40), now).unwrap();
+    assert_eq!(optimization.review_count, 200);
+    assert_eq!(optimization.weights.len(), 19);
+    assert!(optimization.log_loss_after <= optimization.log_loss_before);
+
+    let settings = SchedulerSettings {
+        optimization: Some(optimization),
+        ..Default::default()
+    };
+    let mut card = Card::new(String::new(), String::new(), Vec::new(), now);
+    card.review_with(&settings.scheduler(), Rating::Good, now);
+    assert!(card.stability > 0.);
+}
+
+#[test]
+fn higher_desired_retention_schedules_reviews_sooner() {
+    let now = Utc::now();
+    let due_after_reviews = |desired_retention| {
+        let scheduler = SchedulerSettings {
+            desired_retention,
+            ..Default::default()
+        }
+        .scheduler();
+        let mut card = Card::new(String::new(), String::new(), Vec::new(), now);
+        let mut reviewed_at = now;
+        for _ in 0..3 {
+            card.review_with(&scheduler, Rating::Good, reviewed_at);
+            reviewed_at = card.due;
+        }
+        card.due
+    };
+    assert!(due_after_reviews(0.95) < due_after_reviews(0.8));
+}
+
+#[test]
+fn learning_steps_are_followed_until_graduation() {
+    let preset = DeckPreset::default();
+    let scheduler = SchedulerSettings::default().scheduler();
+    let now = Utc::now();
+    let mut card = Card::new(String::new(), String::new(), Vec::new(), now);
+    let review = |card: &mut Card, rating, now| {
+        let previous_state = card.state;
+        card.review_with(&scheduler, rating, now);
+        preset.apply_steps(card, previous_state, rating, now);
+    };
+
+    review(&mut card, Rating::Again, now);
+    assert_eq!(
+        (card.state, card.due),
+        (CardState::Learning, now + Duration::minutes(1))
+    );
+    review(&mut card, Rating::Good, now);
+    assert_eq!(
+        (card.state, card.due),
+        (CardState::Learning, now + Duration::minutes(10))
+    );
+    review(&mut card, Rating::Good, now);
+    assert_eq!(card.state, CardState::Review);
+    assert!(card.due >= now + Duration::days(1));
+
+    card.lapses = preset.leech_threshold;
+    review(&mut card, Rating::Again, card.due);
+    assert_eq!(card.state, CardState::Relearning);
+    assert_eq!(card.tags, vec![LEECH_TAG.to_string()]);
+}
+
+#[test]
+fn day_starts_at_the_users_hour_in_their_time_zone() {
+    let settings = SchedulerSettings {
+        timezone: "America/New_York".to_string(),
+        day_start_hour: 4,
+        ..Default::default()
+    };
+    // 02:00 in New York, still the study day that started the day before
+    let now = Utc.with_ymd_and_hms(2026, 3, 10, 6, 0, 0).unwrap();
+    assert_eq!(
+        settings.day_start(now),
+        Utc.with_ymd_and_hms(2026, 3, 9, 8, 0, 0).unwrap()
+    );
+    let now = Utc.with_ymd_and_hms(2026, 3, 10, 9, 0, 0).unwrap();
+    assert_eq!(
+        settings.day_start(now),
+        Utc.with_ymd_and_hms(2026, 3, 10, 8, 0, 0).unwrap()
+    );
+}
+
+#[test]
+fn queue_respects_daily_limits_and_buries_siblings() {
+    let now = Utc::now();
+    let card = |id, deck_id, state, note_id| Card {
+        id,
+        deck_id,
+        state,
+        note_id,
+        due: now - Duration::minutes(id),
+        ..Card::new(String::new(), String::new(), Vec::new(), now)
+    };
+    let cards = vec![
+        card(6, 1, CardState::New, None),
+        card(5, 1, CardState::New, None),
+        card(4, 1, CardState::Review, Some(1)),
+        card(3, 1, CardState::Review, Some(1)),
+        card(2, 1, CardState::Learning, Some(2)),
+        card(1, 2, CardState::New, None),
+    ];
+    let limits = HashMap::from([(
+        1,
+        QueueLimits {
+            new_cards: 1,
+            reviews: 5,
+            bury_siblings: true,
+        },
+    )]);
+    let studied_notes = HashSet::from([2]);
+    let next_day = now + Duration::hours(5);
+
+    let queue = ReviewQueue::limited(cards.clone(), &limits, &studied_notes, None, next_day);
+    let ids = queue.cards.iter().map(|card| card.id).collect::<Vec<_>>();
+    assert_eq!(ids, vec![6, 4, 2, 1]);
+
+    // every new card of the deck was already studied today
+    let limits = HashMap::from([(1, QueueLimits::remaining(&DeckPreset::default(), 20, 0))]);
+    let new_cards = cards
+        .into_iter()
+        .filter(|card| card.deck_id == 1 && card.state == CardState::New)
+        .collect();
+    let queue = ReviewQueue::limited(new_cards, &limits, &studied_notes, None, next_day);
+    assert!(queue.cards.is_empty());
+    assert_eq!(queue.next_due, Some(next_day));
+}
+
+#[test]
+fn offline_plan_follows_limits_and_steps_until_the_next_day() {
+    let now = Utc::now();
+    let card = |id| Card {
+        id,
+        deck_id: 1,
+        due: now - Duration::minutes(id),
+        ..Card::new(String::new(), String::new(), Vec::new(), now)
+    };
+    let cards = vec![card(1), card(2), card(3)];
+    let preset = DeckPreset {
+        new_per_day: 2,
+        ..DeckPreset::default()
+    };
+    let mut plan = StudyPlan {
+        limits: HashMap::from([(1, QueueLimits::remaining(&preset, 0, 0))]),
+        presets: HashMap::from([(1, preset.clone())]),
+        next_day: now + Duration::hours(5),
+        ..StudyPlan::default()
+    };
+
+    let mut queue = plan.queue(&cards, now);
+    assert_eq!(queue.cards.len(), 2);
+    let mut reviewed = queue.cards.remove(0);
+    plan.review(&mut reviewed, Rating::Good, now);
+    assert_eq!(reviewed.state, CardState::Learning);
+    assert_eq!(reviewed.due, now + Duration::minutes(10));
+    assert_eq!(plan.limits[&1].new_cards, 1);
+
+    let cards = cards
+        .into_iter()
+        .map(|card| {
+            if card.id == reviewed.id {
+                reviewed.clone()
+            } else {
+                card
+            }
+        })
+        .collect::<Vec<_>>();
+    let queue = plan.queue(&cards, now);
+    assert_eq!(queue.new_count, 1);
+    let queue = plan.queue(&cards, now + Duration::hours(6));
+    assert_eq!(queue.new_count, 2);
+    assert_eq!(queue.learning_count, 1);
+}
@@ -0,0 +1,186 @@
+use chrono::Local;
+use kreqo_core::audit::{AuditAction, AuditEntry, AuditPage, AuditQuery};
+use kreqo_core::errors::ServerError;
+use kreqo_server::api::get_audit_log;
+use xilem::core::map_state;
+use xilem::masonry::layout::AsUnit;
+use xilem::palette::css::GRAY;
+use xilem::style::Style;
+use xilem::tokio::sync::mpsc::UnboundedSender;
+use xilem::view::{FlexExt, flex_col, flex_row, label, prose, spinner, text_button, text_input};
+use xilem::{TextAlign, WidgetView};
+
+use crate::component::{ErrorView, header};
+use crate::theme::{ApplyClass, BORDERED_ROW, CONTAINER};
+
+pub struct AuditLogRequest(pub AuditQuery);
+
+#[derive(Debug)]
+pub enum AuditLogMessage {
+    Fetched(AuditQuery, AuditPage),
+    Error(ServerError),
+}
+
+pub async fn handle_audit_log_request(AuditLogRequest(query): AuditLogRequest) -> AuditLogMessage {
+    match get_audit_log(query.clone()).await {
+        Ok(page) => AuditLogMessage::Fetched(query, page),
+        Err(error) => AuditLogMessage::Error(error),
+    }
+}
+
+/// Privileged actions of every user, newest first, browsed one page at a time.
+#[derive(Default)]
+pub struct AuditLog {
+    query: AuditQuery,
+    actor_id: String,
+    page: AuditPage,
+    pending: bool,
+    sender: Option<UnboundedSender<AuditLogRequest>>,
+    last_error: Option<ServerError>,
+}
+
+impl AuditLog {
+    pub fn set_sender(&mut self, sender: UnboundedSender<AuditLogRequest>) {
+        self.sender = Some(sender);
+        self.fetch();
+    }
+
+    fn fetch(&mut self) {
+        if let Some(sender) = &self.sender {
+            self.pending = true;
+            let _ = sender.send(AuditLogRequest(self.query.clone()));
+        }
+    }
+
+    /// Applies changed filters, starting over from the newest entries.
+    fn filter(&mut self) {
+        self.query.actor_id = self.actor_id.trim().parse().ok();
+        self.query.offset = 0;
+        self.fetch();
+    }
+
+    fn next_action(&mut self) {
+        self.query.action = match self.query.action {
+            None => AuditAction::ALL.first().copied(),
+            Some(action) => AuditAction::ALL
+                .iter()
+                .position(|other| *other == action)
+                .and_then(|index| AuditAction::ALL.get(index + 1))
+                .copied(),
+        };
+        self.filter();
+    }
+
+    pub fn handle_message(&mut self, message: AuditLogMessage) {
+        match message {
+            // Pages of a previous query are outdated
+            AuditLogMessage::Fetched(query, page) if query == self.query => {
+                self.pending = false;
+                self.page = page;
+                self.last_error = None;
+            }
+            AuditLogMessage::Fetched(..) => (),
+            AuditLogMessage::Error(error) => {
+                self.pending = false;
+                self.last_error = Some(error);
+            }
+        }
+    }
+
+    fn entry_view(entry: &AuditEntry) -> impl WidgetView<Self> + use<> {
+        let actor = match (&entry.actor_name, entry.action) {
+            (Some(actor_name), _) => actor_name.clone(),
+            (None, AuditAction::PurgeUser) => "Server".to_string(),
+            (None, _) => "Deleted user".to_string(),
+        };
+        let summary = prose(format!("{actor}: {} on {}", entry.action, entry.target));
+        let date = prose(
+            entry
+                .created_at
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string(),
+        )
+        .text_color(GRAY);
+        let changes = [("Before", &entry.before), ("After", &entry.after)]
+            .into_iter()
+            .filter_map(|(name, value)| {
+                value
+                    .as_ref()
+                    .map(|value| prose(format!("{name}: {value}")).text_color(GRAY))
+            })
+            .collect::<Vec<_>>();
+        flex_col((summary, date, changes)).class(BORDERED_ROW)
+    }
+
+    pub fn view(&mut self) -> impl WidgetView<Self> + use<> {
+        let action = self
+            .query
+            .action
+            .map_or("All".to_string(), |action| action.to_string());
+        let filters = flex_row((
+            label("Action"),
+            text_button(action, |state: &mut Self| state.next_action()),
+            text_input(self.actor_id.clone(), |state: &mut Self, input| {
+                state.actor_id = input;
+            })
+            .on_enter(|state: &mut Self, _| state.filter())
+            .placeholder("Actor ID")
+            .flex(1.),
+        ));
+        let entries = self
+            .page
+            .entries
+            .iter()
+            .map(Self::entry_view)
+            .collect::<Vec<_>>();
+        let empty = (self.page.entries.is_empty() && !self.pending).then(|| {
+            prose("No audited actions")
+                .text_color(GRAY)
+                .text_alignment(TextAlign::Center)
+        });
+        let first = self.query.offset + 1;
+        let last = self.query.offset + self.page.entries.len() as i64;
+        let newer = (self.query.offset > 0).then(|| {
+            text_button("Newer", |state: &mut Self| {
+                state.query.offset = (state.query.offset - state.query.limit).max(0);
+                state.fetch();
+            })
+        });
+        let older = self.page.has_more(&self.query).then(|| {
+            text_button("Older", |state: &mut Self| {
+                state.query.offset += state.query.limit;
+                state.fetch();
+            })
+        });
+        let pages = (!self.page.entries.is_empty()).then(|| {
+            flex_row((
+                newer,
+                prose(format!("{first}-{last} of {}", self.page.total))
+                    .text_alignment(TextAlign::Center)
+                    .flex(1.),
+                older,
+            ))
+        });
+        let status = self.pending.then(spinner);
+        flex_col((
+            header("Audit log"),
+            filters,
+            entries,
+            empty,
+            pages,
+            status,
+            self.error_view(),
+        ))
+        .class(CONTAINER)
+        .gap(15.px())
+    }
+
+    pub fn error_view(&mut self) -> Option<impl WidgetView<Self> + use<>> {
+        self.last_error.as_ref().map(|error| {
+            map_state(error.view(), move |state: &mut Self| {
+                state.last_error.as_mut().unwrap()
+            })
+        })
+    }
+}
@@ -1,11 +1,14 @@
+use kreqo_core::errors::ServerError;
 use kreqo_core::users::User;
 use thiserror::Error;
 use xilem::masonry::layout::AsUnit;
 use xilem::palette::css::GRAY;
 use xilem::style::{Padding, Style};
 use xilem::tokio::sync::mpsc::UnboundedSender;
-use xilem::view::{CrossAxisAlignment, flex_col, flex_row, inline_prose, text_input, zstack};
-use xilem::{Color, WidgetView};
+use xilem::view::{
+    CrossAxisAlignment, flex_col, flex_row, inline_prose, prose, text_input, zstack,
+};
+use xilem::{Color, TextAlign, WidgetView};
 use zxcvbn::feedback::Feedback;
 use zxcvbn::time_estimates::CrackTimes;
 use zxcvbn::{Score, zxcvbn};
@@ -21,12 +24,18 @@ use crate::theme::{
 pub enum UserError {
     #[error("username is required")]
     EmptyUsername,
+    #[error("current password is required")]
+    EmptyCurrentPassword,
     #[error("password is required")]
     EmptyPassword,
+    #[error("reset token is required")]
+    EmptyToken,
     #[error("password confirmation doesn't match")]
     PasswordConfirmationMismatch,
     #[error("password is too weak")]
     WeakPassword,
+    #[error(transparent)]
+    Server(#[from] ServerError),
 }
 
 impl UserError {
@@ -34,6 +43,14 @@ impl UserError {
         matches!(self, UserError::EmptyUsername).then_some(DANGER_COLOR)
     }
 
+    pub fn current_password_color(&self) -> Option<Color> {
+        matches!(self, UserError::EmptyCurrentPassword).then_some(DANGER_COLOR)
+    }
+
+    pub fn token_color(&self) -> Option<Color> {
+        matches!(self, UserError::EmptyToken).then_some(DANGER_COLOR)
+    }
+
     pub fn password_color(&self) -> Option<Color> {
         matches!(self, UserError::EmptyPassword).then_some(DANGER_COLOR)
     }
@@ -48,12 +65,17 @@ pub enum AuthRequest {
     Login(String, String),
     Logout,
     RefreshUser,
+    ChangePassword(String, String),
+    ResetPassword(String, String),
 }
 
 #[derive(Debug)]
 pub enum AuthMessage {
     SessionsCleanedUp,
     UserRefreshed(Option<User>),
+    LoginError(ServerError),
+    PasswordChanged,
+    PasswordError(ServerError),
 }
 
 #[derive(Debug, Default)]
@@ -146,33 +168,111 @@ impl UserLoginForm {
             }
         }
     }
+
+    pub fn handle_message(&mut self, message: AuthMessage) {
+        if let AuthMessage::LoginError(error) = message {
+            self.last_error = Some(error.into());
+        }
+    }
 }
 
+/// zxcvbn estimation of the strength of a password, shown below the password inputs.
 #[derive(Debug)]
-pub struct UserSignupForm {
-    username: String,
-    password: String,
-    password_confirmation: String,
+pub struct PasswordStrength {
     score: Score,
     feedback: Option<Feedback>,
     crack_time: CrackTimes,
-    last_error: Option<UserError>,
 }
 
-impl Default for UserSignupForm {
+impl Default for PasswordStrength {
     fn default() -> Self {
         Self {
-            username: String::new(),
-            password: String::new(),
-            password_confirmation: String::new(),
             score: Score::Zero,
             feedback: None,
             crack_time: CrackTimes::new(0),
-            last_error: None,
         }
     }
 }
 
+impl PasswordStrength {
+    pub fn check(&mut self, password: &str, user_inputs: &[&str]) {
+        let entropy = zxcvbn(password, user_inputs);
+        self.score = entropy.score();
+        self.feedback = entropy.feedback().cloned();
+        self.crack_time = entropy.crack_times();
+    }
+
+    pub fn is_strong(&self) -> bool {
+        self.score >= Score::Four
+    }
+
+    pub fn view<State: 'static>(&self) -> impl WidgetView<State> + use<State> {
+        let (color, text) = match self.score {
+            Score::Zero | Score::One => (DANGER_COLOR, "Very weak"),
+            Score::Two => (DANGER_COLOR, "Weak"),
+            Score::Three => (WARNING_COLOR, "Medium"),
+            _ => (SUCCESS_COLOR, "Strong"),
+        };
+        let password_strength = flex_row((
+            inline_prose("Password strength:").text_color(GRAY),
+            inline_prose(text).text_color(color),
+        ))
+        .padding(3.);
+        let crack_time = (self.score >= Score::Three).then_some(
+            flex_row((
+                inline_prose(if self.score >= Score::Four {
+                    " ✓ Time to crack:"
+                } else {
+                    " ❌ Time to crack:"
+                })
+                .text_size(13.)
+                .text_color(GRAY),
+                inline_prose(
+                    self.crack_time
+                        .offline_slow_hashing_1e4_per_second()
+                        .to_string(),
+                )
+                .text_size(13.)
+                .text_color(color),
+            ))
+            .padding(3.),
+        );
+        let warning = self.feedback.as_ref().map(|feedback| {
+            feedback.warning().map(|warning| {
+                inline_prose(format!(" ❌ {}", warning))
+                    .text_size(13.)
+                    .text_color(GRAY)
+                    .padding(3.)
+            })
+        });
+        let suggestions = self.feedback.as_ref().map(|feedback| {
+            feedback
+                .suggestions()
+                .iter()
+                .map(|suggestion| {
+                    inline_prose(format!(" ✓ {}", suggestion))
+                        .text_size(13.)
+                        .text_color(GRAY)
+                        .padding(3.)
+                })
+                .collect::<Vec<_>>()
+        });
+        flex_col((password_strength, crack_time, warning, suggestions))
+            .cross_axis_alignment(CrossAxisAlignment::Start)
+            .gap(0.px())
+            .padding(Padding::horizontal(19.))
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct UserSignupForm {
+    username: String,
+    password: String,
+    password_confirmation: String,
+    strength: PasswordStrength,
+    last_error: Option<UserError>,
+}
+
 impl Form for UserSignupForm {
     type Output = (String, String);
     type Error = UserError;
@@ -214,62 +314,7 @@ impl Form for UserSignupForm {
                 ),
                 form_input_label("Password"),
             )),
-            (!self.password.is_empty()).then(|| {
-                let (color, text) = match self.score {
-                    Score::Zero | Score::One => (DANGER_COLOR, "Very weak"),
-                    Score::Two => (DANGER_COLOR, "Weak"),
-                    Score::Three => (WARNING_COLOR, "Medium"),
-                    _ => (SUCCESS_COLOR, "Strong"),
-                };
-                let password_strength = flex_row((
-                    inline_prose("Password strength:").text_color(GRAY),
-                    inline_prose(text).text_color(color),
-                ))
-                .padding(3.);
-                let crack_time = (self.score >= Score::Three).then_some(
-                    flex_row((
-                        inline_prose(if self.score >= Score::Four {
-                            " ✓ Time to crack:"
-                        } else {
-                            " ❌ Time to crack:"
-                        })
-                        .text_size(13.)
-                        .text_color(GRAY),
-                        inline_prose(
-                            self.crack_time
-                                .offline_slow_hashing_1e4_per_second()
-                                .to_string(),
-                        )
-                        .text_size(13.)
-                        .text_color(color),
-                    ))
-                    .padding(3.),
-                );
-                let warning = self.feedback.as_ref().map(|feedback| {
-                    feedback.warning().map(|warning| {
-                        inline_prose(format!(" ❌ {}", warning))
-                            .text_size(13.)
-                            .text_color(GRAY)
-                            .padding(3.)
-                    })
-                });
-                let suggestions = self.feedback.as_ref().map(|feedback| {
-                    feedback
-                        .suggestions()
-                        .iter()
-                        .map(|suggestion| {
-                            inline_prose(format!(" ✓ {}", suggestion))
-                                .text_size(13.)
-                                .text_color(GRAY)
-                                .padding(3.)
-                        })
-                        .collect::<Vec<_>>()
-                });
-                flex_col((password_strength, crack_time, warning, suggestions))
-                    .cross_axis_alignment(CrossAxisAlignment::Start)
-                    .gap(0.px())
-                    .padding(Padding::horizontal(19.))
-            }),
+            (!self.password.is_empty()).then(|| self.strength.view()),
         ));
         let password_confirmation = zstack((
             text_input(
@@ -307,10 +352,8 @@ impl Form for UserSignupForm {
     }
 
     fn check(&mut self) -> Result<(), UserError> {
-        let entropy = zxcvbn(self.password.as_str(), &[self.username.as_str()]);
-        self.score = entropy.score();
-        self.feedback = entropy.feedback().cloned();
-        self.crack_time = entropy.crack_times();
+        self.strength
+            .check(&self.password, &[self.username.as_str()]);
 
         if self.username.is_empty() {
             return Err(UserError::EmptyUsername);
@@ -318,7 +361,7 @@ impl Form for UserSignupForm {
         if self.password.is_empty() {
             return Err(UserError::EmptyPassword);
         }
-        if self.score < Score::Four {
+        if !self.strength.is_strong() {
             return Err(UserError::WeakPassword);
         }
         if self.password != self.password_confirmation {
@@ -337,3 +380,308 @@ impl Form for UserSignupForm {
         ))
     }
 }
+
+#[derive(Debug, Default)]
+pub struct ChangePasswordForm {
+    current_password: String,
+    password: String,
+    password_confirmation: String,
+    strength: PasswordStrength,
+    changed: bool,
+    last_error: Option<UserError>,
+}
+
+impl Form for ChangePasswordForm {
+    type Output = (String, String);
+    type Error = UserError;
+
+    fn last_error(&mut self) -> &mut Option<UserError> {
+        &mut self.last_error
+    }
+
+    fn view(&mut self) -> impl WidgetView<Self, Submit> + use<> {
+        let header = header("Change your password");
+        let current_password = zstack((
+            text_input(self.current_password.clone(), |state: &mut Self, input| {
+                state.current_password = input;
+                state.last_error = state.check().err();
+                Submit::No
+            })
+            .placeholder("current password")
+            .text_color(ACCENT_COLOR)
+            .class(FORM_INPUT)
+            .apply(
+                form_border_color,
+                self.last_error
+                    .as_ref()
+                    .and_then(UserError::current_password_color),
+            ),
+            form_input_label("Current Password"),
+        ));
+        let password = flex_col((
+            zstack((
+                text_input(self.password.clone(), |state: &mut Self, input| {
+                    state.password = input;
+                    state.last_error = state.check().err();
+                    Submit::No
+                })
+                .placeholder("new password")
+                .text_color(ACCENT_COLOR)
+                .class(FORM_INPUT)
+                .apply(
+                    form_border_color,
+                    self.last_error.as_ref().and_then(UserError::password_color),
+                ),
+                form_input_label("New Password"),
+            )),
+            (!self.password.is_empty()).then(|| self.strength.view()),
+        ));
+        let password_confirmation = zstack((
+            text_input(
+                self.password_confirmation.clone(),
+                |state: &mut Self, input| {
+                    state.password_confirmation = input;
+                    state.last_error = state.check().err();
+                    Submit::No
+                },
+            )
+            .on_enter(|_, _| Submit::Yes)
+            .placeholder("confirm new password")
+            .text_color(ACCENT_COLOR)
+            .class(FORM_INPUT)
+            .apply(
+                form_border_color,
+                self.last_error
+                    .as_ref()
+                    .and_then(UserError::confirmation_color),
+            ),
+            form_input_label("Password Confirmation"),
+        ));
+        let change_button = action_button("Change Password", |_| Submit::Yes);
+        let changed = self.changed.then(|| {
+            prose("Your password has been changed")
+                .text_color(SUCCESS_COLOR)
+                .text_alignment(TextAlign::Center)
+        });
+        let error = self.error_view();
+        flex_col((
+            header,
+            current_password,
+            password,
+            password_confirmation,
+            change_button,
+            changed,
+            error,
+        ))
+        .class(CONTAINER)
+        .gap(30.px())
+    }
+
+    fn check(&mut self) -> Result<(), UserError> {
+        self.strength
+            .check(&self.password, &[self.current_password.as_str()]);
+
+        if self.current_password.is_empty() {
+            return Err(UserError::EmptyCurrentPassword);
+        }
+        if self.password.is_empty() {
+            return Err(UserError::EmptyPassword);
+        }
+        if !self.strength.is_strong() {
+            return Err(UserError::WeakPassword);
+        }
+        if self.password != self.password_confirmation {
+            return Err(UserError::PasswordConfirmationMismatch);
+        }
+
+        Ok(())
+    }
+
+    fn validate(&mut self) -> Result<(String, String), UserError> {
+        self.check()?;
+        self.password_confirmation = String::default();
+        self.changed = false;
+        Ok((
+            std::mem::take(&mut self.current_password),
+            std::mem::take(&mut self.password),
+        ))
+    }
+}
+
+impl ChangePasswordForm {
+    pub fn handle_submit(&mut self, submit: Submit, sender: Option<&UnboundedSender<AuthRequest>>) {
+        match submit {
+            Submit::No => (),
+            Submit::Cancel => {
+                self.reset();
+            }
+            Submit::Yes => {
+                let output = self.submit();
+                if let (Some((current_password, password)), Some(sender)) = (output, sender) {
+                    let _ = sender.send(AuthRequest::ChangePassword(current_password, password));
+                }
+            }
+        }
+    }
+
+    pub fn handle_message(&mut self, message: AuthMessage) {
+        match message {
+            AuthMessage::PasswordChanged => {
+                self.reset();
+                self.changed = true;
+            }
+            AuthMessage::PasswordError(error) => {
+                self.last_error = Some(error.into());
+            }
+            _ => (),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ResetPasswordForm {
+    token: String,
+    password: String,
+    password_confirmation: String,
+    strength: PasswordStrength,
+    last_error: Option<UserError>,
+}
+
+impl Form for ResetPasswordForm {
+    type Output = (String, String);
+    type Error = UserError;
+
+    fn last_error(&mut self) -> &mut Option<UserError> {
+        &mut self.last_error
+    }
+
+    fn view(&mut self) -> impl WidgetView<Self, Submit> + use<> {
+        let header = header("Reset your password");
+        let token = zstack((
+            text_input(self.token.clone(), |state: &mut Self, input| {
+                state.token = input;
+                state.last_error = state.check().err();
+                Submit::No
+            })
+            .placeholder("token given by an administrator")
+            .text_color(ACCENT_COLOR)
+            .class(FORM_INPUT)
+            .apply(
+                form_border_color,
+                self.last_error.as_ref().and_then(UserError::token_color),
+            ),
+            form_input_label("Reset Token"),
+        ));
+        let password = flex_col((
+            zstack((
+                text_input(self.password.clone(), |state: &mut Self, input| {
+                    state.password = input;
+                    state.last_error = state.check().err();
+                    Submit::No
+                })
+                .placeholder("new password")
+                .text_color(ACCENT_COLOR)
+                .class(FORM_INPUT)
+                .apply(
+                    form_border_color,
+                    self.last_error.as_ref().and_then(UserError::password_color),
+                ),
+                form_input_label("New Password"),
+            )),
+            (!self.password.is_empty()).then(|| self.strength.view()),
+        ));
+        let password_confirmation = zstack((
+            text_input(
+                self.password_confirmation.clone(),
+                |state: &mut Self, input| {
+                    state.password_confirmation = input;
+                    state.last_error = state.check().err();
+                    Submit::No
+                },
+            )
+            .on_enter(|_, _| Submit::Yes)
+            .placeholder("confirm new password")
+            .text_color(ACCENT_COLOR)
+            .class(FORM_INPUT)
+            .apply(
+                form_border_color,
+                self.last_error
+                    .as_ref()
+                    .and_then(UserError::confirmation_color),
+            ),
+            form_input_label("Password Confirmation"),
+        ));
+        let reset_button = action_button("Reset Password", |_| Submit::Yes);
+        let error = self.error_view();
+        flex_col((
+            header,
+            token,
+            password,
+            password_confirmation,
+            reset_button,
+            error,
+        ))
+        .class(CONTAINER)
+        .gap(30.px())
+    }
+
+    fn check(&mut self) -> Result<(), UserError> {
+        self.strength.check(&self.password, &[]);
+
+        if self.token.trim().is_empty() {
+            return Err(UserError::EmptyToken);
+        }
+        if self.password.is_empty() {
+            return Err(UserError::EmptyPassword);
+        }
+        if !self.strength.is_strong() {
+            return Err(UserError::WeakPassword);
+        }
+        if self.password != self.password_confirmation {
+            return Err(UserError::PasswordConfirmationMismatch);
+        }
+
+        Ok(())
+    }
+
+    fn validate(&mut self) -> Result<(String, String), UserError> {
+        self.check()?;
+        self.password_confirmation = String::default();
+        Ok((
+            self.token.trim().to_string(),
+            std::mem::take(&mut self.password),
+        ))
+    }
+}
+
+impl ResetPasswordForm {
+    pub fn handle_submit(&mut self, submit: Submit, sender: Option<&UnboundedSender<AuthRequest>>) {
+        match submit {
+            Submit::No => (),
+            Submit::Cancel => {
+                self.reset();
+            }
+            Submit::Yes => {
+                let output = self.submit();
+                if let (Some((token, password)), Some(sender)) = (output, sender) {
+                    let _ = sender.send(AuthRequest::ResetPassword(token, password));
+                }
+            }
+        }
+    }
+
+    /// Returns whether the password has been reset.
+    pub fn handle_message(&mut self, message: AuthMessage) -> bool {
+        match message {
+            AuthMessage::PasswordChanged => {
+                self.reset();
+                true
+            }
+            AuthMessage::PasswordError(error) => {
+                self.last_error = Some(error.into());
+                false
+            }
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use kreqo_core::learn::content::{Block, CardContent, StyledText};
+use parley::layout::{Alignment, AlignmentOptions};
+use parley::{FontContext, FontStyle, Layout, LayoutContext};
+use xilem::core::one_of::{Either, OneOf3};
+use xilem::masonry::core::{BrushIndex, render_text};
+use xilem::masonry::layout::AsUnit;
+use xilem::masonry::parley::{FontFamily, FontStack, GenericFamily, StyleProperty};
+use xilem::palette::css::{GRAY, WHITE};
+use xilem::vello::kurbo::{Affine, Line, Stroke};
+use xilem::vello::peniko::{Blob, ImageAlphaType, ImageBrush, ImageData, ImageFormat};
+use xilem::view::{canvas, flex_col, image, prose, sized_box};
+use xilem::{Color, FontWeight, TextAlign, WidgetView};
+
+/// Width available to card content on the review page.
+pub const CONTENT_WIDTH: f64 = 550.;
+
+thread_local! {
+    // Card text is laid out while building views to know its height, so it doesn't use the
+    // contexts of the render root
+    static TEXT_CONTEXTS: RefCell<(FontContext, LayoutContext<BrushIndex>)> =
+        RefCell::new((FontContext::new(), LayoutContext::new()));
+}
+
+/// Decodes an image stored by the server, or `None` when it isn't a valid image.
+pub fn decode_image(data: &[u8]) -> Option<ImageBrush> {
+    let image = image::load_from_memory(data).ok()?.into_rgba8();
+    let (width, height) = image.dimensions();
+    Some(ImageBrush::new(ImageData {
+        data: Blob::new(Arc::new(image.into_raw())),
+        format: ImageFormat::Rgba8,
+        alpha_type: ImageAlphaType::Alpha,
+        width,
+        height,
+    }))
+}
+
+struct TextStyle {
+    size: f32,
+    bold: bool,
+    monospace: bool,
+    alignment: Alignment,
+}
+
+fn font_stack(monospace: bool) -> StyleProperty<'static, BrushIndex> {
+    let family = if monospace {
+        GenericFamily::Monospace
+    } else {
+        GenericFamily::SansSerif
+    };
+    StyleProperty::FontStack(FontStack::Single(FontFamily::Generic(family)))
+}
+
+fn layout_text(text: &StyledText, style: &TextStyle) -> Layout<BrushIndex> {
+    TEXT_CONTEXTS.with_borrow_mut(|(font_cx, layout_cx)| {
+        let mut builder = layout_cx.ranged_builder(font_cx, &text.text, 1., true);
+        builder.push_default(font_stack(style.monospace));
+        builder.push_default(StyleProperty::FontSize(style.size));
+        if style.bold {
+            builder.push_default(StyleProperty::FontWeight(FontWeight::BOLD));
+        }
+        for (range, span_style) in &text.spans {
+            if span_style.bold {
+                builder.push(StyleProperty::FontWeight(FontWeight::BOLD), range.clone());
+            }
+            if span_style.italic {
+                builder.push(StyleProperty::FontStyle(FontStyle::Italic), range.clone());
+            }
+            if span_style.strikethrough {
+                builder.push(StyleProperty::Strikethrough(true), range.clone());
+            }
+            if span_style.code {
+                builder.push(font_stack(true), range.clone());
+            }
+        }
+        let mut layout = builder.build(&text.text);
+        layout.break_all_lines(Some(CONTENT_WIDTH as f32));
+        layout.align(
+            Some(CONTENT_WIDTH as f32),
+            style.alignment,
+            AlignmentOptions::default(),
+        );
+        layout
+    })
+}
+
+fn text_view<State: 'static>(
+    layout: &Arc<Layout<BrushIndex>>,
+    color: Color,
+) -> impl WidgetView<State> + use<State> {
+    let layout = layout.clone();
+    let height = f64::from(layout.height());
+    sized_box(canvas(move |_: &mut State, _, scene, _| {
+        render_text(scene, Affine::IDENTITY, &layout, &[color.into()], true);
+    }))
+    .dims((CONTENT_WIDTH.px(), height.px()))
+}
+
+fn image_view<State: 'static>(
+    brush: Option<&ImageBrush>,
+    alt: &str,
+) -> impl WidgetView<State> + use<State> {
+    match brush {
+        Some(brush) => {
+            let (width, height) = (f64::from(brush.image.width), f64::from(brush.image.height));
+            let scale = (CONTENT_WIDTH / width.max(1.)).min(1.);
+            Either::A(
+                sized_box(image(brush.clone())).dims(((width * scale).px(), (height * scale).px())),
+            )
+        }
+        None => Either::B(
+            prose(alt.to_string())
+                .text_color(GRAY)
+                .text_alignment(TextAlign::Center),
+        ),
+    }
+}
+
+fn rule_view<State: 'static>() -> impl WidgetView<State> + use<State> {
+    sized_box(canvas(|_: &mut State, _, scene, size| {
+        let line = Line::new((0., size.height / 2.), (size.width, size.height / 2.));
+        scene.stroke(&Stroke::new(1.), Affine::IDENTITY, GRAY, None, &line);
+    }))
+    .dims((CONTENT_WIDTH.px(), 9.px()))
+}
+
+enum LaidOutBlock {
+    Text(Arc<Layout<BrushIndex>>, Color),
+    Image { id: i64, alt: String },
+    Rule,
+}
+
+/// Card content with its text laid out, kept while the card is shown since laying out text for
+/// every view would be too slow.
+pub struct LaidOutContent {
+    blocks: Vec<LaidOutBlock>,
+}
+
+impl LaidOutContent {
+    /// Lays out `content` centered on the review page, with text of `text_size`.
+    pub fn new(content: &CardContent, text_size: f32) -> Self {
+        let text_style = |size, bold, monospace, alignment| TextStyle {
+            size,
+            bold,
+            monospace,
+            alignment,
+        };
+        let laid_out = |text: &StyledText, style: TextStyle, color: Color| {
+            LaidOutBlock::Text(Arc::new(layout_text(text, &style)), color)
+        };
+        let blocks = content
+            .blocks
+            .iter()
+            .map(|block| match block {
+                Block::Heading(level, text) => {
+                    let size = text_size * (1. + 0.4 / f32::from(*level));
+                    let style = text_style(size, true, false, Alignment::Center);
+                    laid_out(text, style, WHITE)
+                }
+                Block::Paragraph(text) => {
+                    let style = text_style(text_size, false, false, Alignment::Center);
+                    laid_out(text, style, WHITE)
+                }
+                Block::ListItem {
+                    marker,
+                    depth,
+                    text,
+                } => {
+                    let prefix = format!("{}{marker} ", "    ".repeat(*depth));
+                    let item = StyledText {
+                        text: format!("{prefix}{}", text.text),
+                        spans: text
+                            .spans
+                            .iter()
+                            .map(|(range, style)| {
+                                (range.start + prefix.len()..range.end + prefix.len(), *style)
+                            })
+                            .collect(),
+                    };
+                    let style = text_style(text_size, false, false, Alignment::Start);
+                    laid_out(&item, style, WHITE)
+                }
+                Block::Quote(text) => {
+                    let style = text_style(text_size, false, false, Alignment::Center);
+                    laid_out(text, style, GRAY)
+                }
+                Block::Code(code) => {
+                    let text = StyledText {
+                        text: code.clone(),
+                        spans: Vec::new(),
+                    };
+                    let style = text_style(text_size * 0.8, false, true, Alignment::Start);
+                    laid_out(&text, style, WHITE)
+                }
+                Block::Image { id, alt } => LaidOutBlock::Image {
+                    id: *id,
+                    alt: alt.clone(),
+                },
+                Block::Rule => LaidOutBlock::Rule,
+            })
+            .collect();
+        Self { blocks }
+    }
+
+    /// Ids of the images stored by the server shown in this content.
+    pub fn image_ids(&self) -> impl Iterator<Item = i64> + '_ {
+        self.blocks.iter().filter_map(|block| match block {
+            LaidOutBlock::Image { id, .. } => Some(*id),
+            _ => None,
+        })
+    }
+}
+
+/// Shows laid out card content. Images are taken from `images` once fetched, showing their
+/// alternative text in the meantime.
+pub fn card_content_view<State: 'static>(
+    content: &LaidOutContent,
+    images: &HashMap<i64, Option<ImageBrush>>,
+) -> impl WidgetView<State> + use<State> {
+    let blocks = content
+        .blocks
+        .iter()
+        .map(|block| match block {
+            LaidOutBlock::Text(layout, color) => OneOf3::A(text_view(layout, *color)),
+            LaidOutBlock::Image { id, alt } => {
+                OneOf3::B(image_view(images.get(id).and_then(Option::as_ref), alt))
+            }
+            LaidOutBlock::Rule => OneOf3::C(rule_view()),
+        })
+        .collect::<Vec<_>>();
+    flex_col(blocks)
+}
@@ -0,0 +1,111 @@
+use xilem::masonry::layout::{AsUnit, Dim};
+use xilem::vello::Scene;
+use xilem::vello::kurbo::{Affine, BezPath, Circle, Line, Rect, Size, Stroke};
+use xilem::vello::peniko::Fill;
+use xilem::view::{canvas, sized_box};
+use xilem::{Color, WidgetView};
+
+use crate::theme::SURFACE_BORDER_COLOR;
+
+/// Height of the plotting area of bar and line charts.
+const CHART_HEIGHT: f64 = 120.;
+const STACKED_BAR_HEIGHT: f64 = 20.;
+const POINT_RADIUS: f64 = 2.5;
+
+fn draw_baseline(scene: &mut Scene, size: Size) {
+    let baseline = Line::new((0., size.height), (size.width, size.height));
+    scene.stroke(
+        &Stroke::new(1.),
+        Affine::IDENTITY,
+        SURFACE_BORDER_COLOR,
+        None,
+        &baseline,
+    );
+}
+
+/// Draws one bar per value, scaled so that the highest reaches the top of the chart.
+pub fn bar_chart<State: 'static>(
+    values: Vec<f64>,
+    color: Color,
+) -> impl WidgetView<State> + use<State> {
+    let max = values.iter().copied().fold(0., f64::max);
+    sized_box(canvas(move |_: &mut State, _, scene, size| {
+        draw_baseline(scene, size);
+        if max <= 0. {
+            return;
+        }
+        let slot = size.width / values.len() as f64;
+        let gap = (slot * 0.2).min(4.);
+        for (index, value) in values.iter().enumerate() {
+            let x = index as f64 * slot;
+            let top = size.height * (1. - value / max);
+            let bar = Rect::new(x + gap / 2., top, x + slot - gap / 2., size.height);
+            scene.fill(Fill::NonZero, Affine::IDENTITY, color, None, &bar);
+        }
+    }))
+    .dims((Dim::Stretch, CHART_HEIGHT.px()))
+}
+
+/// Draws a line through the values between `min` and `max`, leaving a gap for missing values.
+pub fn line_chart<State: 'static>(
+    values: Vec<Option<f64>>,
+    min: f64,
+    max: f64,
+    color: Color,
+) -> impl WidgetView<State> + use<State> {
+    sized_box(canvas(move |_: &mut State, _, scene, size| {
+        draw_baseline(scene, size);
+        let slot = size.width / values.len() as f64;
+        let position = |index: usize, value: f64| {
+            let ratio = ((value - min) / (max - min)).clamp(0., 1.);
+            (slot * (index as f64 + 0.5), size.height * (1. - ratio))
+        };
+        let mut path = BezPath::new();
+        let mut drawing = false;
+        for (index, value) in values.iter().enumerate() {
+            let Some(value) = value else {
+                drawing = false;
+                continue;
+            };
+            let point = position(index, *value);
+            if drawing {
+                path.line_to(point);
+            } else {
+                path.move_to(point);
+            }
+            drawing = true;
+            let dot = Circle::new(point, POINT_RADIUS);
+            scene.fill(Fill::NonZero, Affine::IDENTITY, color, None, &dot);
+        }
+        scene.stroke(&Stroke::new(2.), Affine::IDENTITY, color, None, &path);
+    }))
+    .dims((Dim::Stretch, CHART_HEIGHT.px()))
+}
+
+/// Draws a single bar split into segments as wide as their share of the total.
+pub fn stacked_bar<State: 'static>(
+    segments: Vec<(f64, Color)>,
+) -> impl WidgetView<State> + use<State> {
+    let total = segments.iter().map(|(value, _)| value).sum::<f64>();
+    sized_box(canvas(move |_: &mut State, _, scene, size| {
+        let background = Rect::from_origin_size((0., 0.), size);
+        scene.fill(
+            Fill::NonZero,
+            Affine::IDENTITY,
+            SURFACE_BORDER_COLOR,
+            None,
+            &background,
+        );
+        if total <= 0. {
+            return;
+        }
+        let mut x = 0.;
+        for (value, color) in &segments {
+            let width = size.width * value / total;
+            let segment = Rect::new(x, 0., x + width, size.height);
+            scene.fill(Fill::NonZero, Affine::IDENTITY, *color, None, &segment);
+            x += width;
+        }
+    }))
+    .dims((Dim::Stretch, STACKED_BAR_HEIGHT.px()))
+}
@@ -1,23 +1,47 @@
 pub mod filter;
+pub mod retry;
 pub mod sorter;
 pub mod storage;
 
+use std::pin::pin;
+use std::time::Duration;
+
+use futures::stream::FuturesUnordered;
+use futures::{FutureExt, StreamExt, future, select};
 use uuid::Uuid;
 use xilem::WidgetView;
 use xilem::core::one_of::Either;
 use xilem::core::{MessageProxy, fork, lens, map_action, map_state};
+use xilem::masonry::layout::AsUnit;
 use xilem::masonry::theme::BASIC_WIDGET_HEIGHT;
 use xilem::style::Style;
+use xilem::tokio::sync::Notify;
 use xilem::tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
-use xilem::view::{flex_col, spinner, worker};
+use xilem::tokio::time::sleep;
+use xilem::view::{
+    FlexExt, button, flex_col, flex_row, label, prose, sized_box, spinner, task, virtual_scroll,
+    worker,
+};
 
 use crate::component::form::Submit;
 pub use crate::component::list::filter::ListFilter;
+pub use crate::component::list::retry::RetryPolicy;
 pub use crate::component::list::sorter::ListSorter;
 pub use crate::component::list::storage::ListStorage;
-use crate::component::list::storage::Retryable;
+use crate::component::list::storage::{ListChange, ListPage, Retryable};
 use crate::component::{ErrorView, Form};
 use crate::pending::Pending;
+use crate::theme::{ApplyClass, DANGER_COLOR, TOAST};
+
+/// Time to wait before subscribing again to the changes of the storage after losing the
+/// subscription.
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(5);
+/// Time the filter and sorter of a paged list have to stay unchanged before fetching its items
+/// again.
+const QUERY_DEBOUNCE: Duration = Duration::from_millis(300);
+/// Number of items left to scroll through before fetching the next page.
+const LOAD_AHEAD: usize = 10;
+const PAGED_LIST_HEIGHT: f64 = 500.;
 
 pub trait ListItem
 where
@@ -28,12 +52,39 @@ where
     type UpdateForm: Form<Output: Clone + Send> + From<Self>;
     type Filter: ListFilter<Item = Self>;
     type Sorter: ListSorter<Item = Self>;
+    /// Filtering and sorting done by the storage when it is paged.
+    type Query: Clone + Default + PartialEq + std::fmt::Debug + Send + 'static;
 
     fn id(&self) -> Self::Id;
+    /// Whether the storage would list the item for the query, checked on the items it pushes.
+    fn matches(&self, query: &Self::Query) -> bool {
+        let _ = query;
+        true
+    }
+    fn query(filter: Option<&Self::Filter>, sorter: Option<&Self::Sorter>) -> Self::Query {
+        let _ = (filter, sorter);
+        Self::Query::default()
+    }
     fn view(
         &self,
         pending_item_operation: PendingItemOperation,
     ) -> impl WidgetView<Self, ItemAction<Self>> + use<Self>;
+    /// The item with an update applied, shown by optimistic lists until the storage answers, or
+    /// `None` when the update can only be shown once it is done.
+    fn updated(&self, update_output: &<Self::UpdateForm as Form>::Output) -> Option<Self> {
+        let _ = update_output;
+        None
+    }
+    /// The item about to be created, shown by optimistic lists until the storage answers, or
+    /// `None` to show the pending view instead. `placeholder` numbers the creations of the list,
+    /// so that the item can get an id no stored item has, e.g. a negative one.
+    fn created(
+        create_output: &<Self::CreateForm as Form>::Output,
+        placeholder: usize,
+    ) -> Option<Self> {
+        let _ = (create_output, placeholder);
+        None
+    }
     fn pending_view(
         create_output: &mut <Self::CreateForm as Form>::Output,
     ) -> impl WidgetView<<Self::CreateForm as Form>::Output> + use<Self> {
@@ -56,6 +107,8 @@ where
 pub enum PendingItemOperation {
     #[default]
     None,
+    /// The item is shown before being created, so it can't be changed yet.
+    PendingCreate,
     PendingUpdate,
     PendingDelete,
 }
@@ -65,9 +118,14 @@ where
     T: ListItem,
 {
     FetchAll,
+    FetchPage(T::Query, usize),
     Create(<T::CreateForm as Form>::Output),
     Update(T::Id, <T::UpdateForm as Form>::Output),
     Delete(T::Id),
+    /// Subscribes again to the changes of the storage, e.g. once logged in.
+    Subscribe,
+    /// Answered right away, so that sending it with a delay debounces query changes.
+    UpdateQuery,
 }
 
 impl<T> Clone for ListRequest<T>
@@ -77,9 +135,12 @@ where
     fn clone(&self) -> Self {
         match self {
             Self::FetchAll => Self::FetchAll,
+            Self::FetchPage(arg0, arg1) => Self::FetchPage(arg0.clone(), *arg1),
             Self::Create(arg0) => Self::Create(arg0.clone()),
             Self::Update(arg0, arg1) => Self::Update(*arg0, arg1.clone()),
             Self::Delete(arg0) => Self::Delete(*arg0),
+            Self::Subscribe => Self::Subscribe,
+            Self::UpdateQuery => Self::UpdateQuery,
         }
     }
 }
@@ -91,12 +152,43 @@ where
     S: ListStorage,
 {
     FetchedAll(Vec<T>),
+    FetchedPage(T::Query, usize, ListPage<T>),
     Created(T),
     Updated(T::Id, T),
     Deleted(T::Id),
+    /// Changes were missed and the items have to be fetched again.
+    Outdated,
+    /// The filter or sorter stopped changing for a while.
+    QueryDebounced,
     Error(S::Error),
 }
 
+impl<T, S> From<ListChange<T>> for ListMessage<T, S>
+where
+    T: ListItem,
+    S: ListStorage,
+{
+    fn from(change: ListChange<T>) -> Self {
+        match change {
+            ListChange::Created(item) => ListMessage::Created(item),
+            ListChange::Updated(item) => ListMessage::Updated(item.id(), item),
+            ListChange::Deleted(id) => ListMessage::Deleted(id),
+            ListChange::Outdated => ListMessage::Outdated,
+        }
+    }
+}
+
+/// Item as it was before an optimistic change, restored when the change fails.
+enum Snapshot<T>
+where
+    T: ListItem,
+{
+    /// Id of the item shown until the created one replaces it.
+    Created(T::Id),
+    Updated(T),
+    Deleted(usize, T),
+}
+
 #[derive(Default)]
 pub struct AsyncList<T, S>
 where
@@ -110,8 +202,19 @@ where
     editing: Option<T::Id>,
     items: Vec<T>,
     processed_items: Vec<(T, f32)>,
+    query: T::Query,
+    query_debounce: Option<Uuid>,
+    has_more: bool,
+    /// Offset of the next page, which differs from the number of items once the storage pushed
+    /// some.
+    next_offset: usize,
     sender: Option<UnboundedSender<Pending<ListRequest<T>>>>,
     pending_requests: Vec<Pending<ListRequest<T>>>,
+    optimistic: bool,
+    snapshots: Vec<(Uuid, Snapshot<T>)>,
+    placeholders: usize,
+    rollback_error: Option<S::Error>,
+    retry_policy: RetryPolicy,
     storage: S,
 }
 
@@ -123,6 +226,10 @@ where
     where
         S: ListStorage<Item = T>,
     {
+        // Items waiting to be created don't exist in the storage yet
+        if state.placeholder_request(id).is_some() {
+            return;
+        }
         match self {
             ItemAction::None => (),
             ItemAction::Edit => {
@@ -157,6 +264,14 @@ where
                     Err(error) => ListMessage::Error(error),
                 }
             }
+            ListRequest::FetchPage(query, offset) => {
+                let limit = S::PAGE_SIZE.unwrap_or_default();
+                let result = S::fetch_page(query.clone(), offset, limit).await;
+                match result {
+                    Ok(page) => ListMessage::FetchedPage(query, offset, page),
+                    Err(error) => ListMessage::Error(error),
+                }
+            }
             ListRequest::Create(create_output) => {
                 let result = S::create(create_output).await;
                 match result {
@@ -178,6 +293,8 @@ where
                     Err(error) => ListMessage::Error(error),
                 }
             }
+            ListRequest::UpdateQuery => ListMessage::QueryDebounced,
+            ListRequest::Subscribe => unreachable!("subscriptions are handled by the worker"),
         });
         let _ = proxy.message(pending_message.await);
     }
@@ -189,31 +306,88 @@ where
     S: ListStorage<Item = T>,
 {
     fn handle(self, state: &mut AsyncList<T, S>) -> Option<ListRequest<T>> {
+        let mut refetch = false;
         match self.data {
             ListMessage::FetchedAll(items) => state.items = items,
+            ListMessage::FetchedPage(query, offset, page) => {
+                // Pages of a previous query are outdated
+                if query == state.query {
+                    if offset == 0 {
+                        state.items.clear();
+                    }
+                    state.next_offset = offset + page.items.len();
+                    let item_count = state.items.len();
+                    for item in page.items {
+                        if state.get(item.id()).is_none() {
+                            state.items.push(item);
+                        }
+                    }
+                    state.has_more = page.has_more;
+                    // Nothing new to scroll to would leave the next page unrequested
+                    refetch = state.has_more && state.items.len() == item_count;
+                }
+            }
+            // Changes pushed by the storage can repeat the responses to our own requests
             ListMessage::Created(item) => {
-                state.items.push(item);
+                let placeholder = state.take_placeholder(self.request_id);
+                if let Some(existing_item) = state.get_mut(item.id()) {
+                    *existing_item = item;
+                    if let Some(index) = placeholder {
+                        state.items.remove(index);
+                    }
+                } else if let Some(index) = placeholder {
+                    state.items[index] = item;
+                } else if !AsyncList::<T, S>::paged() || item.matches(&state.query) {
+                    state.items.push(item);
+                }
             }
             ListMessage::Updated(id, new_item) => {
                 if let Some(item) = state.get_mut(id) {
                     *item = new_item;
                 }
             }
+            // Following items may move up on the server, so the next page starts one item
+            // earlier, fetching an item twice only skipping it
             ListMessage::Deleted(id) => {
+                state.next_offset = state.next_offset.saturating_sub(1);
                 state.remove(id);
             }
+            ListMessage::Outdated => state.refresh(),
+            // Only the last change of the query is applied
+            ListMessage::QueryDebounced => {
+                if state.query_debounce == Some(self.request_id) {
+                    state.query_debounce = None;
+                    state.update_query();
+                }
+                return None;
+            }
             ListMessage::Error(error) => {
-                if error.should_retry() {
-                    state.retry_request(self.request_id);
+                if state.retry_request(self.request_id, &error) {
+                    *state.storage.last_error() = Some(error);
                 } else {
-                    state.resolve_pending_request(self.request_id);
+                    let resolved = state.resolve_pending_request(self.request_id);
+                    let created = matches!(resolved, Some(ListRequest::Create(_)));
+                    if state.rollback(self.request_id) || (state.optimistic && created) {
+                        state.rollback_error = Some(error);
+                    } else {
+                        *state.storage.last_error() = Some(error);
+                    }
                 }
-                *state.storage.last_error() = Some(error);
                 return None;
             }
         }
-        *state.storage.last_error() = None;
-        state.resolve_pending_request(self.request_id)
+        state
+            .snapshots
+            .retain(|(request_id, _)| *request_id != self.request_id);
+        let resolved = state.resolve_pending_request(self.request_id);
+        // Changes pushed by the storage don't answer any request
+        if resolved.is_some() {
+            *state.storage.last_error() = None;
+        }
+        if refetch {
+            state.load_more();
+        }
+        resolved
     }
 }
 
@@ -223,21 +397,124 @@ where
     S: ListStorage<Item = T>,
 {
     pub fn new(filter: bool, sorter: bool) -> Self {
+        let filter = filter.then_some(T::Filter::default());
+        let sorter = sorter.then_some(T::Sorter::default());
         Self {
             create_form: T::CreateForm::default(),
             update_form: T::UpdateForm::default(),
-            filter: filter.then_some(T::Filter::default()),
-            sorter: sorter.then_some(T::Sorter::default()),
+            query: T::query(filter.as_ref(), sorter.as_ref()),
+            filter,
+            sorter,
             editing: None,
             items: Vec::new(),
             processed_items: Vec::new(),
+            query_debounce: None,
+            has_more: false,
+            next_offset: 0,
             pending_requests: Vec::new(),
             sender: None,
+            optimistic: false,
+            snapshots: Vec::new(),
+            placeholders: 0,
+            rollback_error: None,
+            retry_policy: RetryPolicy::default(),
             storage: S::default(),
         }
     }
 
+    /// Applies changes to the items right away instead of waiting for the storage, undoing them
+    /// when they fail.
+    pub fn optimistic(mut self) -> Self {
+        self.optimistic = true;
+        self
+    }
+
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    pub fn items(&self) -> &[T] {
+        &self.items
+    }
+
+    /// Fetches all items again, e.g. after they were changed outside of this list.
+    pub fn refresh(&mut self) {
+        if Self::paged() {
+            self.send_request(ListRequest::FetchPage(self.query.clone(), 0));
+        } else {
+            self.send_request(ListRequest::FetchAll);
+        }
+    }
+
+    /// Fetches the page following the loaded items.
+    pub fn load_more(&mut self) {
+        if self.has_more && !self.fetching_page() {
+            self.send_request(ListRequest::FetchPage(self.query.clone(), self.next_offset));
+        }
+    }
+
+    /// Sends the requests of the list to `sender`, starting by fetching the items. The worker
+    /// handles them once started.
+    pub fn set_sender(&mut self, sender: UnboundedSender<Pending<ListRequest<T>>>) {
+        self.sender = Some(sender);
+        self.refresh();
+    }
+
+    /// Applies the answer to a request or a change pushed by the storage, returning the request
+    /// it answered.
+    pub fn handle_message(
+        &mut self,
+        pending_message: Pending<ListMessage<T, S>>,
+    ) -> Option<ListRequest<T>> {
+        pending_message.handle(self)
+    }
+
+    /// Subscribes again to the changes of the storage when the subscription was refused, e.g.
+    /// after logging in.
+    pub fn resubscribe(&self) {
+        if let Some(sender) = &self.sender {
+            let _ = sender.send(Pending::new(ListRequest::Subscribe));
+        }
+    }
+
+    fn paged() -> bool {
+        S::PAGE_SIZE.is_some()
+    }
+
+    fn fetching_page(&self) -> bool {
+        self.pending_requests
+            .iter()
+            .any(|pending_request| matches!(pending_request.data, ListRequest::FetchPage(..)))
+    }
+
+    /// Updates the query of a paged list once the filter and sorter stopped changing for
+    /// [`QUERY_DEBOUNCE`], so that typing a search doesn't fetch a page per key.
+    fn query_changed(&mut self) {
+        if !Self::paged() {
+            return;
+        }
+        if let Some(sender) = &self.sender {
+            let pending_request = Pending::new(ListRequest::UpdateQuery).with_delay(QUERY_DEBOUNCE);
+            self.query_debounce = Some(pending_request.request_id);
+            let _ = sender.send(pending_request);
+        }
+    }
+
+    /// Starts over from the first page when the filter or sorter changed the query.
+    fn update_query(&mut self) {
+        let query = T::query(self.filter.as_ref(), self.sorter.as_ref());
+        if query != self.query {
+            self.query = query;
+            self.has_more = false;
+            self.refresh();
+        }
+    }
+
     fn filter(&self, item: &T) -> (bool, f32) {
+        if Self::paged() {
+            return (true, 0.);
+        }
         self.filter
             .as_ref()
             .map(|filter| filter.filter(item))
@@ -245,8 +522,18 @@ where
     }
 
     fn pending_item_operation(&self, id: T::Id) -> PendingItemOperation {
+        if self.placeholder_request(id).is_some() {
+            return PendingItemOperation::PendingCreate;
+        }
         self.pending_requests
             .iter()
+            // Optimistic changes are already shown
+            .filter(|pending_request| {
+                !self
+                    .snapshots
+                    .iter()
+                    .any(|(request_id, _)| *request_id == pending_request.request_id)
+            })
             .find_map(|pending_request| match pending_request {
                 Pending {
                     data: ListRequest::Update(pending_id, _),
@@ -262,22 +549,121 @@ where
     }
 
     fn send_request(&mut self, request: ListRequest<T>) {
-        if let Some(sender) = &self.sender {
+        if let Some(sender) = self.sender.clone() {
             let pending_request = Pending::new(request.clone());
+            if self.optimistic {
+                self.apply_optimistically(pending_request.request_id, &request);
+            }
             self.pending_requests
                 .push(Pending::from((pending_request.request_id, request)));
             let _ = sender.send(pending_request);
         }
     }
 
-    fn retry_request(&mut self, request_id: Uuid) {
-        let pending_request = self
-            .pending_requests
+    /// Created items are shown with a placeholder id until the storage answers, or with the
+    /// pending view when the item can't tell how it will look.
+    fn apply_optimistically(&mut self, request_id: Uuid, request: &ListRequest<T>) {
+        let snapshot = match request {
+            ListRequest::Create(create_output) => {
+                self.placeholders += 1;
+                let Some(item) = T::created(create_output, self.placeholders) else {
+                    return;
+                };
+                if Self::paged() && !item.matches(&self.query) {
+                    return;
+                }
+                let id = item.id();
+                self.items.push(item);
+                Snapshot::Created(id)
+            }
+            ListRequest::Update(id, update_output) => {
+                let Some(item) = self.get_mut(*id) else {
+                    return;
+                };
+                let Some(updated_item) = item.updated(update_output) else {
+                    return;
+                };
+                Snapshot::Updated(std::mem::replace(item, updated_item))
+            }
+            ListRequest::Delete(id) => {
+                let Some(index) = self.items.iter().position(|item| item.id() == *id) else {
+                    return;
+                };
+                Snapshot::Deleted(index, self.items.remove(index))
+            }
+            _ => return,
+        };
+        self.snapshots.push((request_id, snapshot));
+    }
+
+    /// Undoes the optimistic change of a request, returning whether there was one.
+    fn rollback(&mut self, request_id: Uuid) -> bool {
+        let Some(index) = self
+            .snapshots
             .iter()
-            .find(|pending_request| pending_request.request_id == request_id);
-        if let (Some(sender), Some(pending_request)) = (&self.sender, pending_request) {
-            let _ = sender.send(pending_request.clone().with_delay(0.5));
+            .position(|(snapshot_request_id, _)| *snapshot_request_id == request_id)
+        else {
+            return false;
+        };
+        match self.snapshots.remove(index).1 {
+            Snapshot::Created(id) => self.remove(id),
+            Snapshot::Updated(previous_item) => {
+                if let Some(item) = self.get_mut(previous_item.id()) {
+                    *item = previous_item;
+                }
+            }
+            // The item may have been fetched again in the meantime
+            Snapshot::Deleted(index, previous_item) => {
+                if self.get(previous_item.id()).is_none() {
+                    let index = index.min(self.items.len());
+                    self.items.insert(index, previous_item);
+                }
+            }
         }
+        true
+    }
+
+    /// Request creating the item with the placeholder id `id`, if it is one.
+    fn placeholder_request(&self, id: T::Id) -> Option<Uuid> {
+        self.snapshots
+            .iter()
+            .find_map(|(request_id, snapshot)| match snapshot {
+                Snapshot::Created(placeholder_id) if *placeholder_id == id => Some(*request_id),
+                _ => None,
+            })
+    }
+
+    /// Forgets the placeholder shown for a creation, returning its index in the items.
+    fn take_placeholder(&mut self, request_id: Uuid) -> Option<usize> {
+        let index = self
+            .snapshots
+            .iter()
+            .position(|(snapshot_request_id, snapshot)| {
+                *snapshot_request_id == request_id && matches!(snapshot, Snapshot::Created(_))
+            })?;
+        let Snapshot::Created(id) = self.snapshots.remove(index).1 else {
+            unreachable!();
+        };
+        self.items.iter().position(|item| item.id() == id)
+    }
+
+    /// Sends a failed request again as allowed by the retry policy, returning whether it will be
+    /// retried.
+    fn retry_request(&mut self, request_id: Uuid, error: &S::Error) -> bool {
+        let Some(sender) = &self.sender else {
+            return false;
+        };
+        let Some(pending_request) = self
+            .pending_requests
+            .iter_mut()
+            .find(|pending_request| pending_request.request_id == request_id)
+        else {
+            return false;
+        };
+        let Some(delay) = self.retry_policy.delay(pending_request.attempts(), error) else {
+            return false;
+        };
+        sender.send(pending_request.retry(delay)).is_ok()
     }
 
     fn resolve_pending_request(&mut self, request_id: Uuid) -> Option<ListRequest<T>> {
@@ -311,6 +697,11 @@ where
         }
     }
 
+    /// Asks the storage to create an item, as the create form does when submitted.
+    pub fn create(&mut self, create_output: <T::CreateForm as Form>::Output) {
+        self.send_request(ListRequest::Create(create_output));
+    }
+
     fn handle_create_submit(&mut self, submit: Submit) {
         match submit {
             Submit::No => (),
@@ -319,7 +710,7 @@ where
             }
             Submit::Yes => {
                 if let Some(output) = self.create_form.submit() {
-                    self.send_request(ListRequest::Create(output));
+                    self.create(output);
                 }
             }
         }
@@ -379,7 +770,7 @@ where
                 _ => None,
             })
             .collect::<Vec<_>>();
-        if let Some(sorter) = &self.sorter {
+        if let Some(sorter) = self.sorter.as_ref().filter(|_| !Self::paged()) {
             self.processed_items
                 .sort_by(|(a, sa), (b, sb)| sorter.sort(a, b, *sa, *sb));
         }
@@ -392,9 +783,16 @@ where
     }
 
     fn process_pending_items(&mut self) -> impl Iterator<Item = impl WidgetView<Self> + use<T, S>> {
+        let snapshots = &self.snapshots;
         self.pending_requests
             .iter_mut()
             .enumerate()
+            // Creations shown optimistically already have their item
+            .filter(|(_, pending_request)| {
+                !snapshots
+                    .iter()
+                    .any(|(request_id, _)| *request_id == pending_request.request_id)
+            })
             .filter_map(|(i, pending_request)| {
                 matches!(pending_request.data, ListRequest::Create(_)).then_some(lens(
                     T::pending_view,
@@ -425,18 +823,75 @@ where
     // TODO: refactor into list view layout options
     pub fn view(&mut self) -> impl WidgetView<Self> + use<T, S> {
         let filter = self.filter.as_mut().map(|filter| {
-            map_state(filter.view(), move |state: &mut Self| {
-                state.filter.as_mut().unwrap()
-            })
+            map_action(
+                map_state(filter.view(), move |state: &mut Self| {
+                    state.filter.as_mut().unwrap()
+                }),
+                |state: &mut Self, _| state.query_changed(),
+            )
         });
         let sorter = self.sorter.as_mut().map(|sorter| {
-            map_state(sorter.view(), move |state: &mut Self| {
-                state.sorter.as_mut().unwrap()
-            })
+            map_action(
+                map_state(sorter.view(), move |state: &mut Self| {
+                    state.sorter.as_mut().unwrap()
+                }),
+                |state: &mut Self, _| state.query_changed(),
+            )
         });
-        let items = self.process_items().collect::<Vec<_>>();
+        let items = if Self::paged() {
+            Either::A(Self::paged_items_view(
+                self.items.len(),
+                self.fetching_page(),
+            ))
+        } else {
+            Either::B(self.process_items().collect::<Vec<_>>())
+        };
         let pending_items = self.process_pending_items().collect::<Vec<_>>();
-        flex_col((filter, sorter, items, pending_items))
+        flex_col((self.toast_view(), filter, sorter, items, pending_items))
+    }
+
+    /// Items of a paged list, fetching the next page as the scroll nears the last loaded items.
+    fn paged_items_view(
+        item_count: usize,
+        fetching_page: bool,
+    ) -> impl WidgetView<Self> + use<T, S> {
+        let row_count = item_count + usize::from(fetching_page);
+        let rows = virtual_scroll(0..row_count as i64, |state: &mut Self, index| {
+            let index = usize::try_from(index).unwrap_or_default();
+            // The task runs once the row is shown, rather than whenever the rows are built
+            let load_more = (index == state.items.len().saturating_sub(LOAD_AHEAD)).then(|| {
+                task(
+                    |proxy| async move { drop(proxy.message(())) },
+                    |state: &mut Self, ()| state.load_more(),
+                )
+            });
+            let row = match state.items.get(index) {
+                Some(item) => {
+                    let id = item.id();
+                    let editing = state.editing == Some(id);
+                    let pending_item_operation = state.pending_item_operation(id);
+                    Either::A(Self::item_view(editing, pending_item_operation, id, item))
+                }
+                None => Either::B(spinner().height(BASIC_WIDGET_HEIGHT)),
+            };
+            fork(row, load_more)
+        });
+        sized_box(rows).height(PAGED_LIST_HEIGHT.px())
+    }
+
+    /// Tells that an optimistic change failed and was undone, until dismissed.
+    fn toast_view(&mut self) -> Option<impl WidgetView<Self> + use<T, S>> {
+        self.rollback_error.as_ref().map(|error| {
+            let message =
+                prose("Your change couldn't be saved and was undone").text_color(DANGER_COLOR);
+            let error = map_state(error.view(), |state: &mut Self| {
+                state.rollback_error.as_mut().unwrap()
+            });
+            let dismiss_button = button(label("✖").color(DANGER_COLOR), |state: &mut Self| {
+                state.rollback_error = None;
+            });
+            flex_row((flex_col((message, error)).flex(1.), dismiss_button)).class(TOAST)
+        })
     }
 
     // TODO: refactor into error display from context
@@ -448,6 +903,44 @@ where
         })
     }
 
+    /// Applies the changes pushed by the storage for as long as the list is shown, subscribing
+    /// again when the subscription is lost, or once asked to when it was refused.
+    async fn subscribe(proxy: &MessageProxy<Pending<ListMessage<T, S>>>, resubscribe: &Notify) {
+        let mut missed_changes = false;
+        loop {
+            let refused = match S::subscribe().await {
+                Ok(None) => return,
+                Ok(Some(changes)) => {
+                    if missed_changes && proxy.message(Pending::new(ListMessage::Outdated)).is_err()
+                    {
+                        return;
+                    }
+                    let mut changes = pin!(changes);
+                    loop {
+                        match changes.next().await {
+                            Some(Ok(change)) => {
+                                if proxy.message(Pending::new(change.into())).is_err() {
+                                    return;
+                                }
+                            }
+                            // The stream also ends with the session, subscribing again is then
+                            // refused until logging in
+                            None => break false,
+                            Some(Err(error)) => break !error.should_retry(),
+                        }
+                    }
+                }
+                Err(error) => !error.should_retry(),
+            };
+            missed_changes = true;
+            if refused {
+                resubscribe.notified().await;
+            } else {
+                future::select(pin!(sleep(RESUBSCRIBE_DELAY)), pin!(resubscribe.notified())).await;
+            }
+        }
+    }
+
     // TODO: refactor storage to be more general and ergonomic
     pub fn worker<Child>(
         child: Child,
@@ -459,17 +952,33 @@ where
             map_action(child, |_, _| None),
             worker(
                 |proxy, mut rx: UnboundedReceiver<Pending<ListRequest<T>>>| async move {
-                    while let Some(pending_request) = rx.recv().await {
-                        pending_request.handle(&proxy).await;
-                    }
-                },
-                |state: &mut Self, sender| {
-                    state.sender = Some(sender);
-                    state.send_request(ListRequest::FetchAll);
-                },
-                |state: &mut Self, pending_message: Pending<ListMessage<T, S>>| {
-                    pending_message.handle(state)
+                    let resubscribe = Notify::new();
+                    let requests = async {
+                        // Retries wait for their delay aside so that other requests go on
+                        let mut delayed_requests = FuturesUnordered::new();
+                        loop {
+                            let pending_request = select! {
+                                pending_request = rx.recv().fuse() => match pending_request {
+                                    Some(pending_request) => pending_request,
+                                    None => break,
+                                },
+                                pending_request = delayed_requests.select_next_some() => {
+                                    pending_request
+                                }
+                            };
+                            if pending_request.is_delayed() {
+                                delayed_requests.push(pending_request.delayed());
+                            } else if matches!(pending_request.data, ListRequest::Subscribe) {
+                                resubscribe.notify_one();
+                            } else {
+                                pending_request.handle(&proxy).await;
+                            }
+                        }
+                    };
+                    futures::join!(requests, Self::subscribe(&proxy, &resubscribe));
                 },
+                |state: &mut Self, sender| state.set_sender(sender),
+                |state: &mut Self, pending_message| state.handle_message(pending_message),
             ),
         )
     }
@@ -0,0 +1,51 @@
+use std::time::Duration;
+
+use crate::component::list::storage::{RetryClass, Retryable};
+
+/// How failed requests are sent again: after an exponentially growing delay, randomized so that
+/// clients losing the server at the same time don't retry in lockstep, until giving up.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    pub multiplier: f32,
+    /// Fraction of the delay randomly added or removed, between 0 and 1.
+    pub jitter: f32,
+    /// Number of times a request is sent, including the first one, before its error is shown as
+    /// final.
+    pub max_attempts: u32,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            multiplier: 2.,
+            jitter: 0.2,
+            max_attempts: 5,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before sending a request again after it failed on its `attempts`-th attempt, or
+    /// `None` to give up.
+    pub fn delay(&self, attempts: u32, error: &impl Retryable) -> Option<Duration> {
+        if attempts >= self.max_attempts {
+            return None;
+        }
+        match error.retry_class() {
+            RetryClass::Permanent => None,
+            RetryClass::Throttled(delay) => Some(delay),
+            RetryClass::Transient => {
+                let exponent = attempts.saturating_sub(1).min(i32::MAX as u32) as i32;
+                let backoff = (self.initial_delay.as_secs_f32() * self.multiplier.powi(exponent))
+                    .min(self.max_delay.as_secs_f32());
+                let jitter = self.jitter.clamp(0., 1.);
+                let factor = 1. + jitter * (fastrand::f32() * 2. - 1.);
+                Some(Duration::from_secs_f32(backoff * factor))
+            }
+        }
+    }
+}
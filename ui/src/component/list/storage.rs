@@ -1,3 +1,8 @@
+use std::time::Duration;
+
+use futures::{Stream, stream};
+use kreqo_core::events::ItemEvent;
+
 use crate::component::list::ListItem;
 use crate::component::{ErrorView, Form};
 
@@ -6,11 +11,27 @@ where
     Self: Default + std::fmt::Debug + 'static,
 {
     type Item: ListItem;
-    type Error: ErrorView + Retryable + std::fmt::Debug + Send;
+    type Error: ErrorView + Retryable + std::fmt::Debug + Send + 'static;
 
     fn last_error(&mut self) -> &mut Option<Self::Error>;
 
+    /// Number of items fetched per page, or `None` to fetch every item at once.
+    const PAGE_SIZE: Option<usize> = None;
+
     fn fetch_all() -> impl Future<Output = Result<Vec<Self::Item>, Self::Error>> + Send;
+    fn fetch_page(
+        query: <Self::Item as ListItem>::Query,
+        offset: usize,
+        limit: usize,
+    ) -> impl Future<Output = Result<ListPage<Self::Item>, Self::Error>> + Send {
+        let _ = (query, offset, limit);
+        async {
+            Self::fetch_all().await.map(|items| ListPage {
+                items,
+                has_more: false,
+            })
+        }
+    }
     fn create(
         create_form: <<Self::Item as ListItem>::CreateForm as Form>::Output,
     ) -> impl Future<Output = Result<Self::Item, Self::Error>> + Send;
@@ -21,8 +42,67 @@ where
     fn delete(
         id: <Self::Item as ListItem>::Id,
     ) -> impl Future<Output = Result<<Self::Item as ListItem>::Id, Self::Error>> + Send;
+    /// Changes of the items made by any client, pushed as they happen, or `None` when the
+    /// storage doesn't push its changes.
+    #[allow(clippy::type_complexity)]
+    fn subscribe() -> impl Future<
+        Output = Result<
+            Option<
+                impl Stream<Item = Result<ListChange<Self::Item>, Self::Error>> + Send + 'static,
+            >,
+            Self::Error,
+        >,
+    > + Send {
+        async { Ok(None::<stream::Empty<_>>) }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ListChange<T>
+where
+    T: ListItem,
+{
+    Created(T),
+    Updated(T),
+    Deleted(T::Id),
+    /// Some changes were missed, so the items have to be fetched again.
+    Outdated,
+}
+
+impl<T> From<ItemEvent<T>> for ListChange<T>
+where
+    T: ListItem<Id = i64>,
+{
+    fn from(event: ItemEvent<T>) -> Self {
+        match event {
+            ItemEvent::Created(item) => ListChange::Created(item),
+            ItemEvent::Updated(item) => ListChange::Updated(item),
+            ItemEvent::Deleted(id) => ListChange::Deleted(id),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ListPage<T> {
+    pub items: Vec<T>,
+    pub has_more: bool,
+}
+
+/// How a failed request can be sent again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetryClass {
+    /// Sending the request again would fail the same way.
+    Permanent,
+    /// The request may succeed later, e.g. once the server is reachable again.
+    Transient,
+    /// The request may only succeed after the given delay.
+    Throttled(Duration),
 }
 
 pub trait Retryable {
-    fn should_retry(&self) -> bool;
+    fn retry_class(&self) -> RetryClass;
+
+    fn should_retry(&self) -> bool {
+        self.retry_class() != RetryClass::Permanent
+    }
 }
@@ -0,0 +1,180 @@
+use std::path::PathBuf;
+
+use kreqo_core::errors::ServerError;
+use kreqo_core::learn::Deck;
+use kreqo_core::learn::delimited::Delimiter;
+use kreqo_server::api::export_deck;
+use thiserror::Error;
+use xilem::core::map_state;
+use xilem::masonry::layout::AsUnit;
+use xilem::style::Style;
+use xilem::tokio::sync::mpsc::UnboundedSender;
+use xilem::view::{
+    FlexExt, checkbox, flex_col, flex_row, label, prose, spinner, text_button, text_input,
+};
+use xilem::{Color, TextAlign, WidgetView};
+
+use crate::component::{ErrorView, action_button, header};
+use crate::theme::{ApplyClass, CONTAINER, DANGER_COLOR, SUCCESS_COLOR, form_border_color};
+
+#[derive(Debug, Error)]
+pub enum ExportError {
+    #[error("a deck must be selected")]
+    NoDeck,
+    #[error("file path is required")]
+    EmptyPath,
+    #[error("failed to write file: {0}")]
+    Io(String),
+    #[error(transparent)]
+    Server(#[from] ServerError),
+}
+
+impl ExportError {
+    pub fn path_color(&self) -> Option<Color> {
+        matches!(self, ExportError::EmptyPath | ExportError::Io(_)).then_some(DANGER_COLOR)
+    }
+}
+
+pub struct ExportRequest {
+    pub deck_id: i64,
+    pub path: PathBuf,
+    pub delimiter: Delimiter,
+    pub include_scheduling: bool,
+}
+
+#[derive(Debug)]
+pub enum ExportMessage {
+    Exported(PathBuf),
+    Error(ExportError),
+}
+
+/// Downloads a deck as delimited text and writes it to disk.
+pub async fn export_deck_file(request: ExportRequest) -> Result<PathBuf, ExportError> {
+    let data = export_deck(
+        request.deck_id,
+        request.delimiter,
+        request.include_scheduling,
+    )
+    .await?;
+    std::fs::write(&request.path, data).map_err(|error| ExportError::Io(error.to_string()))?;
+    Ok(request.path)
+}
+
+#[derive(Default)]
+pub struct DeckExport {
+    deck_id: Option<i64>,
+    path: String,
+    delimiter: Delimiter,
+    include_scheduling: bool,
+    pending: bool,
+    exported: Option<PathBuf>,
+    sender: Option<UnboundedSender<ExportRequest>>,
+    last_error: Option<ExportError>,
+}
+
+impl DeckExport {
+    pub fn set_sender(&mut self, sender: UnboundedSender<ExportRequest>) {
+        self.sender = Some(sender);
+    }
+
+    fn export(&mut self) {
+        if self.pending {
+            return;
+        }
+        let Some(deck_id) = self.deck_id else {
+            self.last_error = Some(ExportError::NoDeck);
+            return;
+        };
+        if self.path.trim().is_empty() {
+            self.last_error = Some(ExportError::EmptyPath);
+            return;
+        }
+        if let Some(sender) = &self.sender {
+            self.pending = true;
+            let _ = sender.send(ExportRequest {
+                deck_id,
+                path: PathBuf::from(self.path.trim()),
+                delimiter: self.delimiter,
+                include_scheduling: self.include_scheduling,
+            });
+        }
+    }
+
+    pub fn handle_message(&mut self, message: ExportMessage) {
+        self.pending = false;
+        match message {
+            ExportMessage::Exported(path) => {
+                self.exported = Some(path);
+                self.last_error = None;
+            }
+            ExportMessage::Error(error) => {
+                self.exported = None;
+                self.last_error = Some(error);
+            }
+        }
+    }
+
+    /// Shows the export options for one of `decks`, which can be cycled through.
+    pub fn view(&mut self, decks: &[Deck]) -> impl WidgetView<Self> + use<> {
+        let deck_ids = decks.iter().map(|deck| deck.id).collect::<Vec<_>>();
+        let deck_name = self
+            .deck_id
+            .and_then(|id| decks.iter().find(|deck| deck.id == id))
+            .map_or("None".to_string(), |deck| deck.name.clone());
+        let deck = text_button(deck_name, move |state: &mut Self| {
+            let index = state
+                .deck_id
+                .and_then(|id| deck_ids.iter().position(|deck_id| *deck_id == id))
+                .map_or(0, |index| index + 1);
+            state.deck_id = deck_ids.get(index % deck_ids.len().max(1)).copied();
+        });
+        let delimiter = text_button(self.delimiter.to_string(), |state: &mut Self| {
+            state.delimiter = state.delimiter.next();
+        });
+        let include_scheduling = checkbox(
+            "Include scheduling state",
+            self.include_scheduling,
+            |state: &mut Self, checked| {
+                state.include_scheduling = checked;
+            },
+        );
+        let path = text_input(self.path.clone(), |state: &mut Self, input| {
+            if let Some(extension) = std::path::Path::new(&input).extension() {
+                state.delimiter = Delimiter::from_extension(&extension.to_string_lossy());
+            }
+            state.path = input;
+        })
+        .on_enter(|state: &mut Self, _| state.export())
+        .placeholder("Path of the .csv or .tsv file to write")
+        .apply(
+            form_border_color,
+            self.last_error.as_ref().and_then(ExportError::path_color),
+        );
+        let status = self.pending.then(spinner);
+        let exported = self.exported.as_ref().map(|path| {
+            prose(format!("Successfully exported to {}", path.display()))
+                .text_color(SUCCESS_COLOR)
+                .text_alignment(TextAlign::Center)
+        });
+        flex_col((
+            header("Export to CSV or TSV"),
+            flex_row((label("Deck"), deck)),
+            flex_row((label("Delimiter"), delimiter, include_scheduling)),
+            flex_row(path.flex(1.)),
+            action_button("Export", |state: &mut Self| state.export()),
+            status,
+            exported,
+            self.error_view(),
+        ))
+        .class(CONTAINER)
+        .gap(15.px())
+    }
+
+    pub fn error_view(&mut self) -> Option<impl WidgetView<Self> + use<>> {
+        self.last_error.as_ref().map(|error| {
+            map_state(error.view(), move |state: &mut Self| {
+                state.last_error.as_mut().unwrap()
+            })
+        })
+    }
+}
@@ -0,0 +1,397 @@
+use std::path::PathBuf;
+
+use kreqo_core::errors::ServerError;
+use kreqo_core::learn::Deck;
+use kreqo_core::learn::delimited::{ColumnMapping, DelimitedImport, Delimiter};
+use kreqo_server::api::{import_anki_package, import_delimited_deck};
+use kreqo_server::custom_client::multipart::{Form as MultipartForm, Part};
+use thiserror::Error;
+use xilem::core::{lens, map_action, map_state};
+use xilem::masonry::layout::AsUnit;
+use xilem::palette::css::GRAY;
+use xilem::style::Style;
+use xilem::tokio::sync::mpsc::UnboundedSender;
+use xilem::view::{
+    FlexExt, checkbox, flex_col, flex_row, label, prose, spinner, text_button, text_input,
+};
+use xilem::{Color, TextAlign, WidgetView};
+
+use crate::component::form::Submit;
+use crate::component::{ErrorView, Form, action_button, header};
+use crate::theme::{ApplyClass, CONTAINER, DANGER_COLOR, SUCCESS_COLOR, form_border_color};
+
+#[derive(Debug, Error)]
+pub enum ImportError {
+    #[error("file path is required")]
+    EmptyPath,
+    #[error("columns must be numbers starting from 1")]
+    InvalidColumn,
+    #[error("failed to read file: {0}")]
+    Io(String),
+    #[error(transparent)]
+    Server(#[from] ServerError),
+}
+
+impl ImportError {
+    pub fn path_color(&self) -> Option<Color> {
+        matches!(self, ImportError::EmptyPath | ImportError::Io(_)).then_some(DANGER_COLOR)
+    }
+
+    pub fn column_color(&self) -> Option<Color> {
+        matches!(self, ImportError::InvalidColumn).then_some(DANGER_COLOR)
+    }
+}
+
+pub enum ImportRequest {
+    Anki(PathBuf),
+    Delimited(PathBuf, DelimitedImport),
+}
+
+#[derive(Debug)]
+pub enum ImportMessage {
+    Imported(Vec<Deck>),
+    Error(ImportError),
+}
+
+/// Reads an Anki package from disk and uploads it to be imported.
+pub async fn import_anki_file(path: PathBuf) -> Result<Vec<Deck>, ImportError> {
+    let package = std::fs::read(&path).map_err(|error| ImportError::Io(error.to_string()))?;
+    let file_name = path
+        .file_name()
+        .map(|file_name| file_name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let form = MultipartForm::new().part("package", Part::bytes(package).file_name(file_name));
+    Ok(import_anki_package(form.into()).await?)
+}
+
+/// Reads a delimited text file from disk and uploads it along with its column mapping.
+pub async fn import_delimited_file(
+    path: PathBuf,
+    import: DelimitedImport,
+) -> Result<Deck, ImportError> {
+    let file = std::fs::read(&path).map_err(|error| ImportError::Io(error.to_string()))?;
+    let import =
+        serde_json::to_string(&import).map_err(|error| ImportError::Io(error.to_string()))?;
+    let form = MultipartForm::new()
+        .part("file", Part::bytes(file))
+        .part("import", Part::text(import));
+    Ok(import_delimited_deck(form.into()).await?)
+}
+
+#[derive(Debug, Default)]
+pub struct AnkiImportForm {
+    path: String,
+    last_error: Option<ImportError>,
+}
+
+impl Form for AnkiImportForm {
+    type Output = PathBuf;
+    type Error = ImportError;
+
+    fn last_error(&mut self) -> &mut Option<ImportError> {
+        &mut self.last_error
+    }
+
+    fn view(&mut self) -> impl WidgetView<Self, Submit> + use<> {
+        let path = text_input(self.path.clone(), |state: &mut Self, input| {
+            state.path = input;
+            state.last_error = state.check().err();
+            Submit::No
+        })
+        .on_enter(|_, _| Submit::Yes)
+        .placeholder("Path to an .apkg or .colpkg file")
+        .apply(
+            form_border_color,
+            self.last_error.as_ref().and_then(ImportError::path_color),
+        );
+        let error = self.error_view();
+        flex_col((flex_row(path.flex(1.)), error))
+    }
+
+    fn check(&mut self) -> Result<(), ImportError> {
+        if self.path.trim().is_empty() {
+            return Err(ImportError::EmptyPath);
+        }
+        Ok(())
+    }
+
+    fn validate(&mut self) -> Result<PathBuf, ImportError> {
+        self.check()?;
+        Ok(PathBuf::from(std::mem::take(&mut self.path).trim()))
+    }
+}
+
+/// Form to import a delimited text file, where columns are picked by their number starting from 1.
+#[derive(Debug)]
+pub struct DelimitedImportForm {
+    path: String,
+    deck_name: String,
+    delimiter: Delimiter,
+    has_header: bool,
+    front: String,
+    back: String,
+    tags: String,
+    extra: String,
+    last_error: Option<ImportError>,
+}
+
+impl Default for DelimitedImportForm {
+    fn default() -> Self {
+        Self {
+            path: String::new(),
+            deck_name: String::new(),
+            delimiter: Delimiter::default(),
+            has_header: true,
+            front: "1".to_string(),
+            back: "2".to_string(),
+            tags: "3".to_string(),
+            extra: String::new(),
+            last_error: None,
+        }
+    }
+}
+
+impl Form for DelimitedImportForm {
+    type Output = (PathBuf, DelimitedImport);
+    type Error = ImportError;
+
+    fn last_error(&mut self) -> &mut Option<ImportError> {
+        &mut self.last_error
+    }
+
+    fn view(&mut self) -> impl WidgetView<Self, Submit> + use<> {
+        let column_color = self.last_error.as_ref().and_then(ImportError::column_color);
+        let path = text_input(self.path.clone(), |state: &mut Self, input| {
+            if let Some(extension) = std::path::Path::new(&input).extension() {
+                state.delimiter = Delimiter::from_extension(&extension.to_string_lossy());
+            }
+            state.path = input;
+            state.last_error = state.check().err();
+            Submit::No
+        })
+        .placeholder("Path to a .csv or .tsv file")
+        .apply(
+            form_border_color,
+            self.last_error.as_ref().and_then(ImportError::path_color),
+        );
+        let deck_name = text_input(self.deck_name.clone(), |state: &mut Self, input| {
+            state.deck_name = input;
+            Submit::No
+        })
+        .placeholder("Deck name (defaults to the file name)");
+        let delimiter = text_button(self.delimiter.to_string(), |state: &mut Self| {
+            state.delimiter = state.delimiter.next();
+            Submit::No
+        });
+        let has_header = checkbox(
+            "Header row",
+            self.has_header,
+            |state: &mut Self, checked| {
+                state.has_header = checked;
+                Submit::No
+            },
+        );
+        let column =
+            |value: &String, placeholder: &'static str, field: fn(&mut Self) -> &mut String| {
+                text_input(value.clone(), move |state: &mut Self, input| {
+                    *field(state) = input;
+                    state.last_error = state.check().err();
+                    Submit::No
+                })
+                .placeholder(placeholder)
+                .apply(form_border_color, column_color)
+            };
+        let columns = flex_row((
+            column(&self.front, "Front", |state| &mut state.front).flex(1.),
+            column(&self.back, "Back", |state| &mut state.back).flex(1.),
+            column(&self.tags, "Tags", |state| &mut state.tags).flex(1.),
+            column(&self.extra, "Extra, e.g. 4, 5", |state| &mut state.extra).flex(2.),
+        ));
+        let error = self.error_view();
+        flex_col((
+            flex_row(path.flex(1.)),
+            flex_row(deck_name.flex(1.)),
+            flex_row((label("Delimiter"), delimiter, has_header)),
+            columns,
+            error,
+        ))
+    }
+
+    fn check(&mut self) -> Result<(), ImportError> {
+        if self.path.trim().is_empty() {
+            return Err(ImportError::EmptyPath);
+        }
+        self.mapping().map(|_| ())
+    }
+
+    fn validate(&mut self) -> Result<(PathBuf, DelimitedImport), ImportError> {
+        self.check()?;
+        let mapping = self.mapping()?;
+        let path = PathBuf::from(self.path.trim());
+        let deck_name = match self.deck_name.trim() {
+            "" => path
+                .file_stem()
+                .map(|file_stem| file_stem.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            deck_name => deck_name.to_string(),
+        };
+        let import = DelimitedImport {
+            deck_name,
+            delimiter: self.delimiter,
+            has_header: self.has_header,
+            mapping,
+        };
+        self.reset();
+        Ok((path, import))
+    }
+}
+
+impl DelimitedImportForm {
+    fn mapping(&self) -> Result<ColumnMapping, ImportError> {
+        let parse_column = |column: &str| match column.trim().parse::<usize>() {
+            Ok(column) if column > 0 => Ok(column - 1),
+            _ => Err(ImportError::InvalidColumn),
+        };
+        let tags = match self.tags.trim() {
+            "" => None,
+            tags => Some(parse_column(tags)?),
+        };
+        let extra = self
+            .extra
+            .split(',')
+            .filter(|column| !column.trim().is_empty())
+            .map(parse_column)
+            .collect::<Result<_, _>>()?;
+        Ok(ColumnMapping {
+            front: parse_column(&self.front)?,
+            back: parse_column(&self.back)?,
+            tags,
+            extra,
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct DeckImport {
+    anki_form: AnkiImportForm,
+    delimited_form: DelimitedImportForm,
+    pending: bool,
+    imported_decks: Option<usize>,
+    sender: Option<UnboundedSender<ImportRequest>>,
+    last_error: Option<ImportError>,
+}
+
+impl DeckImport {
+    pub fn set_sender(&mut self, sender: UnboundedSender<ImportRequest>) {
+        self.sender = Some(sender);
+    }
+
+    fn send_request(&mut self, request: ImportRequest) {
+        if let Some(sender) = &self.sender {
+            self.pending = true;
+            let _ = sender.send(request);
+        }
+    }
+
+    fn handle_anki_submit(&mut self, submit: Submit) {
+        match submit {
+            Submit::No => (),
+            Submit::Cancel => {
+                self.anki_form.reset();
+            }
+            Submit::Yes => {
+                if self.pending {
+                    return;
+                }
+                if let Some(path) = self.anki_form.submit() {
+                    self.send_request(ImportRequest::Anki(path));
+                }
+            }
+        }
+    }
+
+    fn handle_delimited_submit(&mut self, submit: Submit) {
+        match submit {
+            Submit::No => (),
+            Submit::Cancel => {
+                self.delimited_form.reset();
+            }
+            Submit::Yes => {
+                if self.pending {
+                    return;
+                }
+                if let Some((path, import)) = self.delimited_form.submit() {
+                    self.send_request(ImportRequest::Delimited(path, import));
+                }
+            }
+        }
+    }
+
+    /// Returns whether new decks were imported, in which case deck lists should be refreshed.
+    pub fn handle_message(&mut self, message: ImportMessage) -> bool {
+        self.pending = false;
+        match message {
+            ImportMessage::Imported(decks) => {
+                self.imported_decks = Some(decks.len());
+                self.last_error = None;
+                true
+            }
+            ImportMessage::Error(error) => {
+                self.imported_decks = None;
+                self.last_error = Some(error);
+                false
+            }
+        }
+    }
+
+    pub fn view(&mut self) -> impl WidgetView<Self> + use<> {
+        let anki_form = map_action(
+            lens(Form::view, move |state: &mut Self| &mut state.anki_form),
+            |state: &mut Self, submit| {
+                state.handle_anki_submit(submit);
+            },
+        );
+        let delimited_form = map_action(
+            lens(Form::view, move |state: &mut Self| {
+                &mut state.delimited_form
+            }),
+            |state: &mut Self, submit| {
+                state.handle_delimited_submit(submit);
+            },
+        );
+        let status = self.pending.then(spinner);
+        let imported = self.imported_decks.map(|count| {
+            prose(format!("Successfully imported {count} deck(s)"))
+                .text_color(SUCCESS_COLOR)
+                .text_alignment(TextAlign::Center)
+        });
+        flex_col((
+            header("Import from Anki"),
+            prose("Cards keep their review history and are rescheduled with FSRS.")
+                .text_color(GRAY)
+                .text_alignment(TextAlign::Center),
+            anki_form,
+            action_button("Import", |state: &mut Self| {
+                state.handle_anki_submit(Submit::Yes);
+            }),
+            header("Import from CSV or TSV"),
+            delimited_form,
+            action_button("Import", |state: &mut Self| {
+                state.handle_delimited_submit(Submit::Yes);
+            }),
+            status,
+            imported,
+            self.error_view(),
+        ))
+        .class(CONTAINER)
+        .gap(15.px())
+    }
+
+    pub fn error_view(&mut self) -> Option<impl WidgetView<Self> + use<>> {
+        self.last_error.as_ref().map(|error| {
+            map_state(error.view(), move |state: &mut Self| {
+                state.last_error.as_mut().unwrap()
+            })
+        })
+    }
+}
@@ -0,0 +1,218 @@
+use futures::{Stream, StreamExt, future, stream};
+use kreqo_core::errors::ServerError;
+use kreqo_core::events::ServerEvent;
+use kreqo_core::learn::Deck;
+use kreqo_server::api::subscribe_events;
+use thiserror::Error;
+use xilem::core::one_of::Either;
+use xilem::palette::css::GRAY;
+use xilem::style::Style;
+use xilem::view::{
+    FlexExt, button, flex_col, flex_row, label, prose, spinner, text_button, text_input,
+};
+use xilem::{Color, WidgetView};
+
+use crate::component::Form;
+use crate::component::form::Submit;
+use crate::component::list::filter::NoFilter;
+use crate::component::list::sorter::NoSorter;
+use crate::component::list::storage::ListChange;
+use crate::component::list::{ItemAction, ListItem, ListStorage, PendingItemOperation};
+use crate::offline;
+use crate::theme::{ApplyClass, BORDERED_ROW, DANGER_COLOR, SUCCESS_COLOR, form_border_color};
+
+#[derive(Debug, Error)]
+pub enum DeckError {
+    #[error("deck name is required")]
+    EmptyName,
+}
+
+impl DeckError {
+    pub fn name_color(&self) -> Option<Color> {
+        matches!(self, DeckError::EmptyName).then_some(DANGER_COLOR)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct DeckForm {
+    name: String,
+    description: String,
+    last_error: Option<DeckError>,
+}
+
+impl Form for DeckForm {
+    type Output = (String, String);
+    type Error = DeckError;
+
+    fn last_error(&mut self) -> &mut Option<DeckError> {
+        &mut self.last_error
+    }
+
+    fn view(&mut self) -> impl WidgetView<Self, Submit> + use<> {
+        let name = text_input(self.name.clone(), |state: &mut DeckForm, input| {
+            state.name = input;
+            state.last_error = state.check().err();
+            Submit::No
+        })
+        .on_enter(|_, _| Submit::Yes)
+        .placeholder("Deck name")
+        .apply(
+            form_border_color,
+            self.last_error.as_ref().and_then(DeckError::name_color),
+        );
+        let description = text_input(self.description.clone(), |state: &mut DeckForm, input| {
+            state.description = input;
+            Submit::No
+        })
+        .on_enter(|_, _| Submit::Yes)
+        .placeholder("Description");
+        let ok_button = button(label("Ok").color(SUCCESS_COLOR), |_| Submit::Yes);
+        let cancel_button = text_button("Cancel", |_| Submit::Cancel);
+        let error = self.error_view();
+        flex_col((
+            flex_row((
+                name.flex(1.),
+                description.flex(2.),
+                ok_button,
+                cancel_button,
+            )),
+            error,
+        ))
+        .class(BORDERED_ROW)
+    }
+
+    fn check(&mut self) -> Result<(), DeckError> {
+        if self.name.trim().is_empty() {
+            return Err(DeckError::EmptyName);
+        }
+        Ok(())
+    }
+
+    fn validate(&mut self) -> Result<(String, String), DeckError> {
+        self.check()?;
+        Ok((
+            std::mem::take(&mut self.name),
+            std::mem::take(&mut self.description),
+        ))
+    }
+}
+
+impl From<Deck> for DeckForm {
+    fn from(value: Deck) -> Self {
+        Self {
+            name: value.name,
+            description: value.description,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct DeckStorage {
+    last_error: Option<ServerError>,
+}
+
+impl ListStorage for DeckStorage {
+    type Item = Deck;
+    type Error = ServerError;
+
+    fn last_error(&mut self) -> &mut Option<ServerError> {
+        &mut self.last_error
+    }
+
+    #[inline(always)]
+    async fn fetch_all() -> Result<Vec<Deck>, ServerError> {
+        offline::fetch_decks().await
+    }
+
+    #[inline(always)]
+    async fn create((name, description): (String, String)) -> Result<Deck, ServerError> {
+        offline::create_deck(name, description).await
+    }
+
+    #[inline(always)]
+    async fn update(id: i64, (name, description): (String, String)) -> Result<Deck, ServerError> {
+        offline::update_deck(id, name, description).await
+    }
+
+    #[inline(always)]
+    async fn delete(id: i64) -> Result<i64, ServerError> {
+        offline::delete_deck(id).await
+    }
+
+    async fn subscribe() -> Result<
+        Option<impl Stream<Item = Result<ListChange<Deck>, ServerError>> + Send + 'static>,
+        ServerError,
+    > {
+        let events = subscribe_events(stream::pending().into()).await?;
+        Ok(Some(events.filter_map(|event| {
+            future::ready(match event {
+                Ok(ServerEvent::Deck { event, .. }) => Some(Ok(event.into())),
+                Ok(ServerEvent::Lagged) => Some(Ok(ListChange::Outdated)),
+                Ok(_) => None,
+                Err(error) => Some(Err(error)),
+            })
+        })))
+    }
+}
+
+impl ListItem for Deck {
+    type Id = i64;
+    type CreateForm = DeckForm;
+    type UpdateForm = DeckForm;
+    type Filter = NoFilter<Deck>;
+    type Sorter = NoSorter<Deck>;
+    type Query = ();
+
+    fn id(&self) -> i64 {
+        self.id
+    }
+
+    fn created((name, description): &(String, String), placeholder: usize) -> Option<Deck> {
+        Some(Deck {
+            id: -i64::try_from(placeholder).unwrap_or(i64::MAX),
+            name: name.clone(),
+            description: description.clone(),
+            ..Deck::default()
+        })
+    }
+
+    fn updated(&self, (name, description): &(String, String)) -> Option<Deck> {
+        Some(Deck {
+            name: name.clone(),
+            description: description.clone(),
+            ..self.clone()
+        })
+    }
+
+    fn view(
+        &self,
+        pending_item_operation: PendingItemOperation,
+    ) -> impl WidgetView<Self, ItemAction<Self>> + use<> {
+        let name = prose(self.name.to_string());
+        let description = (!self.description.is_empty())
+            .then(|| prose(self.description.to_string()).text_color(GRAY));
+        let creating = matches!(pending_item_operation, PendingItemOperation::PendingCreate);
+        let edit_button =
+            if creating || matches!(pending_item_operation, PendingItemOperation::PendingUpdate) {
+                Either::A(button(spinner(), |_| ItemAction::None))
+            } else {
+                Either::B(text_button("Edit", |_| ItemAction::Edit))
+            };
+        let delete_button = (!creating).then(|| {
+            if matches!(pending_item_operation, PendingItemOperation::PendingDelete) {
+                Either::A(button(spinner().color(DANGER_COLOR), |_| ItemAction::None))
+            } else {
+                Either::B(button(label("Delete").color(DANGER_COLOR), |_| {
+                    ItemAction::Delete
+                }))
+            }
+        });
+        flex_row((
+            flex_col((name, description)).flex(1.),
+            edit_button,
+            delete_button,
+        ))
+        .class(BORDERED_ROW)
+    }
+}
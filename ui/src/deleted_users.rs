@@ -0,0 +1,154 @@
+use chrono::Local;
+use kreqo_core::errors::ServerError;
+use kreqo_core::users::User;
+use kreqo_core::users::query::{UserPage, UserQuery};
+use kreqo_server::api::{get_users, restore_user};
+use xilem::core::map_state;
+use xilem::masonry::layout::AsUnit;
+use xilem::palette::css::GRAY;
+use xilem::style::Style;
+use xilem::tokio::sync::mpsc::UnboundedSender;
+use xilem::view::{FlexExt, button, flex_col, flex_row, label, prose, spinner, text_button};
+use xilem::{TextAlign, WidgetView};
+
+use crate::component::{ErrorView, header};
+use crate::theme::{ApplyClass, BORDERED_ROW, CONTAINER, SUCCESS_COLOR};
+
+pub enum DeletedUserRequest {
+    Fetch,
+    Restore(i64),
+}
+
+#[derive(Debug)]
+pub enum DeletedUserMessage {
+    Fetched(UserPage),
+    Restored(User),
+    Error(ServerError),
+}
+
+pub async fn handle_deleted_user_request(request: DeletedUserRequest) -> DeletedUserMessage {
+    let result = match request {
+        DeletedUserRequest::Fetch => {
+            let query = UserQuery {
+                deleted: true,
+                ..Default::default()
+            };
+            get_users(query).await.map(DeletedUserMessage::Fetched)
+        }
+        DeletedUserRequest::Restore(id) => restore_user(id).await.map(DeletedUserMessage::Restored),
+    };
+    result.unwrap_or_else(DeletedUserMessage::Error)
+}
+
+/// Deleted users that can still be restored, until the server purges them.
+#[derive(Default)]
+pub struct DeletedUsers {
+    page: UserPage,
+    pending: bool,
+    sender: Option<UnboundedSender<DeletedUserRequest>>,
+    last_error: Option<ServerError>,
+}
+
+impl DeletedUsers {
+    pub fn set_sender(&mut self, sender: UnboundedSender<DeletedUserRequest>) {
+        self.sender = Some(sender);
+        self.refresh();
+    }
+
+    pub fn refresh(&mut self) {
+        self.send_request(DeletedUserRequest::Fetch);
+    }
+
+    fn send_request(&mut self, request: DeletedUserRequest) {
+        if let Some(sender) = &self.sender {
+            self.pending = true;
+            let _ = sender.send(request);
+        }
+    }
+
+    /// Handles a message from the worker, returning the user that was restored if any.
+    pub fn handle_message(&mut self, message: DeletedUserMessage) -> Option<User> {
+        self.pending = false;
+        match message {
+            DeletedUserMessage::Fetched(page) => {
+                self.page = page;
+                self.last_error = None;
+                None
+            }
+            DeletedUserMessage::Restored(user) => {
+                self.page
+                    .users
+                    .retain(|deleted_user| deleted_user.id != user.id);
+                self.page.total -= 1;
+                self.last_error = None;
+                Some(user)
+            }
+            DeletedUserMessage::Error(error) => {
+                self.last_error = Some(error);
+                None
+            }
+        }
+    }
+
+    fn user_view(user: &User) -> impl WidgetView<Self> + use<> {
+        let id = user.id;
+        let username = prose(user.username.clone());
+        let deleted_at = prose(user.deleted_at.map_or(String::new(), |deleted_at| {
+            format!(
+                "Deleted {}",
+                deleted_at.with_timezone(&Local).format("%Y-%m-%d %H:%M")
+            )
+        }))
+        .text_color(GRAY);
+        let restore_button = button(
+            label("Restore").color(SUCCESS_COLOR),
+            move |state: &mut Self| {
+                state.send_request(DeletedUserRequest::Restore(id));
+            },
+        );
+        flex_row((flex_col((username, deleted_at)).flex(1.), restore_button)).class(BORDERED_ROW)
+    }
+
+    pub fn view(&mut self) -> impl WidgetView<Self> + use<> {
+        let users = self
+            .page
+            .users
+            .iter()
+            .map(Self::user_view)
+            .collect::<Vec<_>>();
+        let empty = (self.page.users.is_empty() && !self.pending).then(|| {
+            prose("No deleted users")
+                .text_color(GRAY)
+                .text_alignment(TextAlign::Center)
+        });
+        let more = (self.page.total > self.page.users.len() as i64).then(|| {
+            prose(format!(
+                "{} more deleted users",
+                self.page.total - self.page.users.len() as i64
+            ))
+            .text_color(GRAY)
+            .text_alignment(TextAlign::Center)
+        });
+        let refresh_button = text_button("Refresh", |state: &mut Self| state.refresh());
+        let status = self.pending.then(spinner);
+        flex_col((
+            header("Deleted users"),
+            users,
+            empty,
+            more,
+            refresh_button,
+            status,
+            self.error_view(),
+        ))
+        .class(CONTAINER)
+        .gap(15.px())
+    }
+
+    pub fn error_view(&mut self) -> Option<impl WidgetView<Self> + use<>> {
+        self.last_error.as_ref().map(|error| {
+            map_state(error.view(), move |state: &mut Self| {
+                state.last_error.as_mut().unwrap()
+            })
+        })
+    }
+}
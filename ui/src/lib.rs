@@ -1,6 +1,17 @@
+pub mod audit_log;
 pub mod auth_forms;
+pub mod card_content;
 pub mod class;
 pub mod component;
+pub mod deck_export;
+pub mod deck_import;
+pub mod deck_list;
+pub mod deleted_users;
+pub mod offline;
 pub mod pending;
+pub mod review;
+pub mod scheduler_settings;
+pub mod session_list;
+pub mod stats;
 pub mod theme;
 pub mod user_list;
@@ -0,0 +1,429 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{LazyLock, Mutex, PoisonError};
+use std::time::Duration;
+use std::{fs, thread};
+
+use chrono::Utc;
+use kreqo_core::errors::ServerError;
+use kreqo_core::learn::presets::StudyPlan;
+use kreqo_core::learn::{Card, Deck, MAX_SYNCED_REVIEWS, OfflineReview, Rating, ReviewQueue};
+use kreqo_core::offline_store_path;
+use kreqo_server::api;
+use serde::{Deserialize, Serialize};
+
+use crate::component::list::storage::{RetryClass, Retryable};
+
+/// Change of a deck made while offline. Decks created offline have a negative temporary id until
+/// they are synced.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum DeckChange {
+    Create {
+        id: i64,
+        name: String,
+        description: String,
+    },
+    Update {
+        id: i64,
+        name: String,
+        description: String,
+    },
+    Delete {
+        id: i64,
+    },
+}
+
+impl DeckChange {
+    fn deck_id(&self) -> i64 {
+        match self {
+            DeckChange::Create { id, .. }
+            | DeckChange::Update { id, .. }
+            | DeckChange::Delete { id } => *id,
+        }
+    }
+}
+
+/// Copy of the decks and cards of the user along with the changes they made while the server was
+/// unreachable, saved to [`offline_store_path`].
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct OfflineStore {
+    user_id: Option<i64>,
+    decks: Vec<Deck>,
+    cards: Vec<Card>,
+    deck_changes: Vec<DeckChange>,
+    reviews: Vec<OfflineReview>,
+    /// Options and daily limits of the decks, updated by the reviews made offline.
+    #[serde(default)]
+    plan: StudyPlan,
+    /// Temporary ids of the decks created offline along with their id once synced.
+    synced_ids: Vec<(i64, i64)>,
+}
+
+/// Time the copy is left unsaved after a change, so that a burst of changes such as a review
+/// session is written at once.
+const SAVE_DELAY: Duration = Duration::from_secs(1);
+
+static OFFLINE_STORE: LazyLock<Mutex<OfflineStore>> =
+    LazyLock::new(|| Mutex::new(OfflineStore::load(&offline_store_path()).unwrap_or_default()));
+
+/// Asks the thread saving the copy to save it once no change was made for [`SAVE_DELAY`].
+static SAVE_REQUESTS: LazyLock<Sender<()>> = LazyLock::new(|| {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        while receiver.recv().is_ok() {
+            while receiver.recv_timeout(SAVE_DELAY).is_ok() {}
+            // The copy is only a fallback, the next change saves it again
+            let _ = read_store(|store| serde_json::to_vec(store))
+                .map_err(std::io::Error::from)
+                .and_then(|content| OfflineStore::save(&offline_store_path(), &content));
+        }
+    });
+    sender
+});
+
+impl OfflineStore {
+    fn load(path: &Path) -> Option<Self> {
+        fs::read(path)
+            .ok()
+            .and_then(|content| serde_json::from_slice(&content).ok())
+    }
+
+    /// Writes the copy aside before replacing the previous one, so that it is never left partly
+    /// written.
+    fn save(path: &Path, content: &[u8]) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let temporary_path = path.with_extension("json.tmp");
+        fs::write(&temporary_path, content)?;
+        fs::rename(temporary_path, path)
+    }
+
+    fn temporary_id(&self) -> i64 {
+        self.decks
+            .iter()
+            .map(|deck| deck.id)
+            .min()
+            .unwrap_or(0)
+            .min(0)
+            - 1
+    }
+
+    fn synced_id(&self, id: i64) -> i64 {
+        self.synced_ids
+            .iter()
+            .find_map(|(temporary_id, synced_id)| (*temporary_id == id).then_some(*synced_id))
+            .unwrap_or(id)
+    }
+
+    fn replace_deck(&mut self, id: i64, new_deck: Deck) {
+        match self.decks.iter_mut().find(|deck| deck.id == id) {
+            Some(deck) => *deck = new_deck,
+            None => self.decks.push(new_deck),
+        }
+    }
+
+    fn replace_card(&mut self, new_card: Card) {
+        match self.cards.iter_mut().find(|card| card.id == new_card.id) {
+            Some(card) => *card = new_card,
+            None => self.cards.push(new_card),
+        }
+    }
+}
+
+fn read_store<R>(f: impl FnOnce(&OfflineStore) -> R) -> R {
+    f(&OFFLINE_STORE.lock().unwrap_or_else(PoisonError::into_inner))
+}
+
+fn write_store<R>(f: impl FnOnce(&mut OfflineStore) -> R) -> R {
+    let mut store = OFFLINE_STORE.lock().unwrap_or_else(PoisonError::into_inner);
+    let result = f(&mut store);
+    drop(store);
+    let _ = SAVE_REQUESTS.send(());
+    result
+}
+
+/// Requests failing to reach the server may succeed later on.
+fn is_offline(error: &ServerError) -> bool {
+    error.retry_class() == RetryClass::Transient
+}
+
+/// File keeping the copy of a user while another one is logged in.
+fn parked_store_path(user_id: i64) -> PathBuf {
+    offline_store_path().with_file_name(format!("offline-{user_id}.json"))
+}
+
+/// Switches to the copy of another user, e.g. after logging in with another account. The copy of
+/// the previous user is kept aside until they log in again, so that the changes they made offline
+/// are synced rather than lost or sent for the wrong account.
+pub fn set_user(user_id: i64) {
+    write_store(|store| {
+        if store.user_id == Some(user_id) {
+            return;
+        }
+        if let Some(previous_user_id) = store.user_id {
+            let _ = serde_json::to_vec(&*store)
+                .map_err(std::io::Error::from)
+                .and_then(|content| {
+                    OfflineStore::save(&parked_store_path(previous_user_id), &content)
+                });
+        }
+        *store = OfflineStore::load(&parked_store_path(user_id)).unwrap_or(OfflineStore {
+            user_id: Some(user_id),
+            ..Default::default()
+        });
+    });
+}
+
+/// Sends the deck changes made while offline in the order they were made. A change refused by
+/// the server, e.g. the update of a deck deleted from another device, is dropped so that it
+/// doesn't block the next ones, and the first refusal is returned once the others are synced.
+async fn sync_decks() -> Result<(), ServerError> {
+    let mut refused = None;
+    while let Some(change) = read_store(|store| store.deck_changes.first().cloned()) {
+        let result = match change {
+            DeckChange::Create {
+                id,
+                name,
+                description,
+            } => api::create_deck(name, description).await.map(|deck| {
+                write_store(|store| {
+                    store.synced_ids.push((id, deck.id));
+                    store.replace_deck(id, deck);
+                });
+            }),
+            DeckChange::Update {
+                id,
+                name,
+                description,
+            } => {
+                let id = read_store(|store| store.synced_id(id));
+                api::update_deck(id, name, description)
+                    .await
+                    .map(|deck| write_store(|store| store.replace_deck(id, deck)))
+            }
+            DeckChange::Delete { id } => {
+                let id = read_store(|store| store.synced_id(id));
+                api::delete_deck(id).await.map(|_| ())
+            }
+        };
+        match result {
+            Err(error) if is_offline(&error) => return Err(error),
+            Err(error) => {
+                refused.get_or_insert(error);
+            }
+            Ok(()) => (),
+        }
+        write_store(|store| store.deck_changes.remove(0));
+    }
+    refused.map_or(Ok(()), Err)
+}
+
+/// Decks of the user, or their offline copy when the server is unreachable.
+pub async fn fetch_decks() -> Result<Vec<Deck>, ServerError> {
+    let result = async {
+        sync_decks().await?;
+        api::get_decks().await
+    }
+    .await;
+    match result {
+        Ok(decks) => {
+            write_store(|store| store.decks = decks.clone());
+            Ok(decks)
+        }
+        Err(error) if is_offline(&error) => Ok(read_store(|store| store.decks.clone())),
+        Err(error) => Err(error),
+    }
+}
+
+/// Sends a deck change, or queues it until the server is reachable again. The changes queued
+/// before are sent first to keep them in order.
+async fn change_deck(change: DeckChange) -> Result<Option<Deck>, ServerError> {
+    // Changes of a deck created offline wait for its creation to be synced
+    if read_store(|store| store.synced_id(change.deck_id()) < 0) {
+        return Ok(write_store(|store| queue_deck_change(store, change)));
+    }
+    let result = async {
+        sync_decks().await?;
+        match change.clone() {
+            DeckChange::Create {
+                name, description, ..
+            } => api::create_deck(name, description).await.map(Some),
+            DeckChange::Update {
+                id,
+                name,
+                description,
+            } => {
+                let id = read_store(|store| store.synced_id(id));
+                api::update_deck(id, name, description).await.map(Some)
+            }
+            DeckChange::Delete { id } => {
+                let id = read_store(|store| store.synced_id(id));
+                api::delete_deck(id).await.map(|_| None)
+            }
+        }
+    }
+    .await;
+    match result {
+        Ok(deck) => {
+            write_store(|store| match &deck {
+                Some(deck) => store.replace_deck(deck.id, deck.clone()),
+                None => store.decks.retain(|deck| deck.id != change.deck_id()),
+            });
+            Ok(deck)
+        }
+        Err(error) if is_offline(&error) => {
+            Ok(write_store(|store| queue_deck_change(store, change)))
+        }
+        Err(error) => Err(error),
+    }
+}
+
+/// Applies a change to the offline copy and queues it, returning the changed deck.
+fn queue_deck_change(store: &mut OfflineStore, change: DeckChange) -> Option<Deck> {
+    let deck = match &change {
+        DeckChange::Create {
+            name, description, ..
+        } => {
+            let deck = Deck {
+                id: store.temporary_id(),
+                owner_id: store.user_id.unwrap_or_default(),
+                name: name.clone(),
+                description: description.clone(),
+                created_at: None,
+                preset_id: None,
+            };
+            store.decks.push(deck.clone());
+            store.deck_changes.push(DeckChange::Create {
+                id: deck.id,
+                name: name.clone(),
+                description: description.clone(),
+            });
+            return Some(deck);
+        }
+        DeckChange::Update {
+            id,
+            name,
+            description,
+        } => store
+            .decks
+            .iter_mut()
+            .find(|deck| deck.id == *id)
+            .map(|deck| {
+                deck.name = name.clone();
+                deck.description = description.clone();
+                deck.clone()
+            }),
+        DeckChange::Delete { id } => {
+            store.decks.retain(|deck| deck.id != *id);
+            // A deck that was never synced only has to be forgotten
+            if store.deck_changes.iter().any(
+                |queued_change| matches!(queued_change, DeckChange::Create { id: created_id, .. } if created_id == id),
+            ) {
+                store.deck_changes.retain(|queued_change| queued_change.deck_id() != *id);
+                return None;
+            }
+            None
+        }
+    };
+    store.deck_changes.push(change);
+    deck
+}
+
+pub async fn create_deck(name: String, description: String) -> Result<Deck, ServerError> {
+    let change = DeckChange::Create {
+        id: 0,
+        name,
+        description,
+    };
+    Ok(change_deck(change).await?.unwrap_or_default())
+}
+
+pub async fn update_deck(id: i64, name: String, description: String) -> Result<Deck, ServerError> {
+    let change = DeckChange::Update {
+        id,
+        name,
+        description,
+    };
+    Ok(change_deck(change).await?.unwrap_or_default())
+}
+
+pub async fn delete_deck(id: i64) -> Result<i64, ServerError> {
+    change_deck(DeckChange::Delete { id }).await?;
+    Ok(id)
+}
+
+/// Sends the reviews made while offline in batches the server accepts, which are kept when the
+/// server is unreachable.
+async fn sync_reviews() -> Result<(), ServerError> {
+    loop {
+        let reviews = read_store(|store| {
+            let count = store.reviews.len().min(MAX_SYNCED_REVIEWS);
+            store.reviews[..count].to_vec()
+        });
+        if reviews.is_empty() {
+            return Ok(());
+        }
+        let cards = api::sync_reviews(reviews.clone()).await?;
+        write_store(|store| {
+            store.reviews.drain(..reviews.len());
+            for card in cards {
+                store.replace_card(card);
+            }
+        });
+    }
+}
+
+/// Cards to review now, built from the offline copy of the cards when the server is unreachable.
+pub async fn fetch_review_queue() -> Result<ReviewQueue, ServerError> {
+    let result = async {
+        sync_reviews().await?;
+        let queue = api::get_review_queue().await?;
+        let cards = api::get_all_cards().await?;
+        let plan = api::get_study_plan().await?;
+        write_store(|store| {
+            store.cards = cards;
+            store.plan = plan;
+        });
+        Ok(queue)
+    }
+    .await;
+    match result {
+        Err(error) if is_offline(&error) => Ok(write_store(|store| {
+            store.plan.queue(&store.cards, Utc::now())
+        })),
+        result => result,
+    }
+}
+
+/// Reviews a card, or schedules it locally and queues the review when the server is
+/// unreachable.
+pub async fn review_card(id: i64, rating: Rating, time_taken_ms: i64) -> Result<Card, ServerError> {
+    let result = async {
+        sync_reviews().await?;
+        api::review_card(id, rating, time_taken_ms).await
+    }
+    .await;
+    match result {
+        Ok(card) => {
+            write_store(|store| store.replace_card(card.clone()));
+            Ok(card)
+        }
+        Err(error) if is_offline(&error) => write_store(|store| {
+            let Some(card) = store.cards.iter_mut().find(|card| card.id == id) else {
+                return Err(error);
+            };
+            let reviewed_at = Utc::now();
+            store.plan.review(card, rating, reviewed_at);
+            let card = card.clone();
+            store.reviews.push(OfflineReview {
+                card_id: id,
+                rating,
+                reviewed_at,
+                time_taken_ms,
+            });
+            Ok(card)
+        }),
+        Err(error) => Err(error),
+    }
+}
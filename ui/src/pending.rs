@@ -1,12 +1,15 @@
 use std::time::Duration;
 
 use uuid::Uuid;
+use xilem::tokio::time::sleep;
 
 #[derive(Clone, Debug)]
 pub struct Pending<T> {
     pub request_id: Uuid,
     pub data: T,
-    delay: f32,
+    /// Number of times the request was sent.
+    attempts: u32,
+    delay: Duration,
 }
 
 impl<T> Pending<T> {
@@ -14,12 +17,43 @@ impl<T> Pending<T> {
         Pending {
             request_id: Uuid::new_v4(),
             data,
-            delay: 0.,
+            attempts: 1,
+            delay: Duration::ZERO,
         }
     }
 
-    pub fn with_delay(self, delay: f32) -> Self {
-        Pending { delay, ..self }
+    /// The request sent after `delay`.
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    /// Whether the request is a retry waiting for its delay before being sent.
+    pub fn is_delayed(&self) -> bool {
+        !self.delay.is_zero()
+    }
+
+    /// Waits for the delay of a retried request, returning it once it can be sent.
+    pub async fn delayed(mut self) -> Self {
+        sleep(self.delay).await;
+        self.delay = Duration::ZERO;
+        self
+    }
+
+    /// Counts one more attempt, returning the request to send again after `delay`.
+    pub fn retry(&mut self, delay: Duration) -> Self
+    where
+        T: Clone,
+    {
+        self.attempts += 1;
+        Pending {
+            delay,
+            ..self.clone()
+        }
     }
 
     pub async fn fetch_map<U, F>(self, f: F) -> Pending<U>
@@ -27,11 +61,11 @@ impl<T> Pending<T> {
         F: AsyncFnOnce(T) -> U,
     {
         let data = f(self.data).await;
-        std::thread::sleep(Duration::from_secs_f32(self.delay));
         Pending {
             request_id: self.request_id,
             data,
-            delay: self.delay,
+            attempts: self.attempts,
+            delay: Duration::ZERO,
         }
     }
 }
@@ -50,7 +84,8 @@ impl<T> From<(Uuid, T)> for Pending<T> {
         Pending {
             request_id,
             data,
-            delay: 0.,
+            attempts: 1,
+            delay: Duration::ZERO,
         }
     }
 }
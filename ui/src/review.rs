@@ -0,0 +1,314 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use chrono::Local;
+use kreqo_core::errors::ServerError;
+use kreqo_core::learn::content::CardContent;
+use kreqo_core::learn::{Card, Rating, ReviewQueue};
+use xilem::core::map_state;
+use xilem::core::one_of::{Either, OneOf3};
+use xilem::masonry::layout::AsUnit;
+use xilem::palette::css::GRAY;
+use xilem::style::Style;
+use xilem::tokio::sync::mpsc::UnboundedSender;
+use xilem::vello::peniko::ImageBrush;
+use xilem::view::{
+    FlexExt, MainAxisAlignment, button, flex_col, flex_row, label, prose, spinner, text_input,
+};
+use xilem::{Color, FontWeight, TextAlign, WidgetView};
+
+use crate::card_content::{LaidOutContent, card_content_view};
+use crate::component::{ErrorView, action_button, header};
+use crate::theme::{
+    ACCENT_COLOR, ApplyClass, CONTAINER, DANGER_COLOR, SUCCESS_COLOR, WARNING_COLOR,
+};
+
+/// Time after which an image that couldn't be fetched is fetched again when a card shows it.
+const IMAGE_RETRY_DELAY: Duration = Duration::from_secs(10);
+
+pub enum ReviewRequest {
+    FetchQueue,
+    /// Rates a card, along with the time spent on it in milliseconds.
+    Review(i64, Rating, i64),
+    FetchImage(i64),
+}
+
+#[derive(Debug)]
+pub enum ReviewMessage {
+    QueueFetched(ReviewQueue),
+    Reviewed(Card),
+    /// Image shown by a card, or `None` when it couldn't be fetched.
+    ImageFetched(i64, Option<ImageBrush>),
+    Error(ServerError),
+}
+
+/// Content of the card being shown, laid out once for as long as it is shown.
+struct ShownContent {
+    card_id: i64,
+    front_text: String,
+    back_text: String,
+    front: LaidOutContent,
+    back: LaidOutContent,
+}
+
+impl ShownContent {
+    fn new(card: &Card) -> Self {
+        Self {
+            card_id: card.id,
+            front_text: card.front.clone(),
+            back_text: card.back.clone(),
+            front: LaidOutContent::new(&CardContent::parse(&card.front), 22.),
+            back: LaidOutContent::new(&CardContent::parse(&card.back), 18.),
+        }
+    }
+
+    fn is_of(&self, card: &Card) -> bool {
+        self.card_id == card.id && self.front_text == card.front && self.back_text == card.back
+    }
+}
+
+#[derive(Default)]
+pub struct ReviewSession {
+    queue: Option<ReviewQueue>,
+    show_back: bool,
+    /// Answer typed for cards asking for one, compared with the expected answer once shown.
+    typed_answer: String,
+    pending_review: bool,
+    /// When the current card was first shown, to know how long it took to answer.
+    shown_at: Option<Instant>,
+    content: Option<ShownContent>,
+    images: HashMap<i64, Option<ImageBrush>>,
+    /// When images last failed to be fetched, so that they are fetched again later.
+    failed_images: HashMap<i64, Instant>,
+    sender: Option<UnboundedSender<ReviewRequest>>,
+    last_error: Option<ServerError>,
+}
+
+impl ReviewSession {
+    pub fn set_sender(&mut self, sender: UnboundedSender<ReviewRequest>) {
+        self.sender = Some(sender);
+        self.send_request(ReviewRequest::FetchQueue);
+    }
+
+    fn send_request(&mut self, request: ReviewRequest) {
+        if let Some(sender) = &self.sender {
+            self.pending_review = matches!(request, ReviewRequest::Review(..));
+            let _ = sender.send(request);
+        }
+    }
+
+    /// Lays out the content of the current card unless it already is, then fetches its images
+    /// that weren't fetched yet.
+    fn show_current(&mut self) {
+        let Some(card) = self.queue.as_ref().and_then(ReviewQueue::current) else {
+            self.content = None;
+            return;
+        };
+        if !self
+            .content
+            .as_ref()
+            .is_some_and(|content| content.is_of(card))
+        {
+            self.content = Some(ShownContent::new(card));
+        }
+        let (Some(content), Some(sender)) = (&self.content, &self.sender) else {
+            return;
+        };
+        for id in content.front.image_ids().chain(content.back.image_ids()) {
+            let retry = self
+                .failed_images
+                .get(&id)
+                .is_some_and(|failed_at| failed_at.elapsed() >= IMAGE_RETRY_DELAY);
+            if !self.images.contains_key(&id) || retry {
+                self.images.insert(id, None);
+                self.failed_images.remove(&id);
+                let _ = sender.send(ReviewRequest::FetchImage(id));
+            }
+        }
+    }
+
+    fn rate(&mut self, rating: Rating) {
+        if self.pending_review {
+            return;
+        }
+        let current = self.queue.as_ref().and_then(ReviewQueue::current);
+        if let Some(id) = current.map(|card| card.id) {
+            let time_taken = self.shown_at.map(|shown_at| shown_at.elapsed());
+            let time_taken_ms = time_taken.map_or(0, |time_taken| {
+                i64::try_from(time_taken.as_millis()).unwrap_or(i64::MAX)
+            });
+            self.send_request(ReviewRequest::Review(id, rating, time_taken_ms));
+        }
+    }
+
+    pub fn handle_message(&mut self, message: ReviewMessage) {
+        match message {
+            ReviewMessage::QueueFetched(queue) => {
+                self.queue = Some(queue);
+                self.show_back = false;
+                self.typed_answer.clear();
+                self.shown_at = Some(Instant::now());
+                self.show_current();
+            }
+            ReviewMessage::Reviewed(card) => {
+                self.pending_review = false;
+                self.show_back = false;
+                self.typed_answer.clear();
+                let finished = self.queue.as_mut().is_some_and(|queue| {
+                    queue.remove(card.id);
+                    queue.cards.is_empty()
+                });
+                // Cards in (re)learning come back within minutes, so the queue is refreshed once
+                // empty to pick them up and to learn when the next card is due.
+                if finished {
+                    self.send_request(ReviewRequest::FetchQueue);
+                }
+                self.shown_at = Some(Instant::now());
+                self.show_current();
+            }
+            ReviewMessage::ImageFetched(id, image) => {
+                if image.is_none() {
+                    self.failed_images.insert(id, Instant::now());
+                }
+                self.images.insert(id, image);
+                return;
+            }
+            ReviewMessage::Error(error) => {
+                self.pending_review = false;
+                self.last_error = Some(error);
+                return;
+            }
+        }
+        self.last_error = None;
+    }
+
+    fn progress_view(queue: &ReviewQueue) -> impl WidgetView<Self> + use<> {
+        flex_row((
+            label(format!("New: {}", queue.new_count)).color(ACCENT_COLOR),
+            label(format!("Learning: {}", queue.learning_count)).color(DANGER_COLOR),
+            label(format!("Review: {}", queue.review_count)).color(SUCCESS_COLOR),
+        ))
+        .main_axis_alignment(MainAxisAlignment::Center)
+        .gap(20.px())
+    }
+
+    fn rating_button(
+        text: &'static str,
+        color: Color,
+        rating: Rating,
+    ) -> impl WidgetView<Self> + use<> {
+        button(
+            label(text).weight(FontWeight::BOLD).color(color),
+            move |state: &mut Self| state.rate(rating),
+        )
+    }
+
+    fn typed_answer_view(&self, expected: &str) -> impl WidgetView<Self> + use<> {
+        if !self.show_back {
+            return Either::A(
+                text_input(self.typed_answer.clone(), |state: &mut Self, input| {
+                    state.typed_answer = input;
+                })
+                .on_enter(|state: &mut Self, _| state.show_back = true)
+                .placeholder("Type the answer"),
+            );
+        }
+        let typed_answer = self.typed_answer.trim();
+        let result = if typed_answer.eq_ignore_ascii_case(expected.trim()) {
+            label(format!("Correct: {typed_answer}")).color(SUCCESS_COLOR)
+        } else if typed_answer.is_empty() {
+            label(format!("Expected: {expected}")).color(DANGER_COLOR)
+        } else {
+            label(format!("{typed_answer} (expected: {expected})")).color(DANGER_COLOR)
+        };
+        Either::B(flex_row(result).main_axis_alignment(MainAxisAlignment::Center))
+    }
+
+    fn card_view(&self, card: &Card) -> impl WidgetView<Self> + use<> {
+        let content = self.content.as_ref().filter(|content| content.is_of(card));
+        let front = content.map(|content| card_content_view(&content.front, &self.images));
+        let back = content
+            .filter(|_| self.show_back)
+            .map(|content| card_content_view(&content.back, &self.images));
+        let typed_answer = card
+            .typed_answer
+            .as_deref()
+            .map(|expected| self.typed_answer_view(expected));
+        let extra = self.show_back.then(|| {
+            card.extra
+                .iter()
+                .filter(|extra| !extra.is_empty())
+                .map(|extra| {
+                    prose(extra.clone())
+                        .text_color(GRAY)
+                        .text_alignment(TextAlign::Center)
+                })
+                .collect::<Vec<_>>()
+        });
+        let actions = if self.pending_review {
+            OneOf3::A(spinner())
+        } else if self.show_back {
+            OneOf3::B(
+                flex_row((
+                    Self::rating_button("Again", DANGER_COLOR, Rating::Again).flex(1.),
+                    Self::rating_button("Hard", WARNING_COLOR, Rating::Hard).flex(1.),
+                    Self::rating_button("Good", SUCCESS_COLOR, Rating::Good).flex(1.),
+                    Self::rating_button("Easy", ACCENT_COLOR, Rating::Easy).flex(1.),
+                ))
+                .gap(10.px()),
+            )
+        } else {
+            OneOf3::C(action_button("Show answer", |state: &mut Self| {
+                state.show_back = true;
+            }))
+        };
+        flex_col((front, typed_answer, back, extra, actions))
+            .class(CONTAINER)
+            .gap(30.px())
+    }
+
+    fn finished_view(queue: &ReviewQueue) -> impl WidgetView<Self> + use<> {
+        let next_due = match queue.next_due {
+            Some(next_due) => format!(
+                "Next card is due on {}",
+                next_due.with_timezone(&Local).format("%Y-%m-%d at %H:%M")
+            ),
+            None => "There are no cards to study yet".to_string(),
+        };
+        flex_col((
+            header("Congratulations!"),
+            prose("You are done studying for now.").text_alignment(TextAlign::Center),
+            prose(next_due)
+                .text_alignment(TextAlign::Center)
+                .text_color(GRAY),
+            action_button("Refresh", |state: &mut Self| {
+                state.send_request(ReviewRequest::FetchQueue);
+            }),
+        ))
+        .class(CONTAINER)
+        .gap(20.px())
+    }
+
+    pub fn view(&mut self) -> impl WidgetView<Self> + use<> {
+        let content = match &self.queue {
+            None => Either::A(spinner()),
+            Some(queue) => {
+                let progress = Self::progress_view(queue);
+                let current = match queue.current() {
+                    Some(card) => Either::A(self.card_view(card)),
+                    None => Either::B(Self::finished_view(queue)),
+                };
+                Either::B(flex_col((progress, current)).gap(20.px()))
+            }
+        };
+        flex_col((content, self.error_view()))
+    }
+
+    pub fn error_view(&mut self) -> Option<impl WidgetView<Self> + use<>> {
+        self.last_error.as_ref().map(|error| {
+            map_state(error.view(), move |state: &mut Self| {
+                state.last_error.as_mut().unwrap()
+            })
+        })
+    }
+}
@@ -0,0 +1,273 @@
+use std::time::Duration;
+
+use chrono::Local;
+use kreqo_core::errors::ServerError;
+use kreqo_core::learn::optimizer::{MIN_REVIEWS, OptimizationStatus};
+use kreqo_core::learn::{MAX_DESIRED_RETENTION, MIN_DESIRED_RETENTION, SchedulerSettings};
+use kreqo_server::api::{
+    get_optimization_status, get_scheduler_settings, optimize_scheduler, update_day_boundary,
+    update_desired_retention,
+};
+use xilem::core::map_state;
+use xilem::masonry::layout::AsUnit;
+use xilem::palette::css::GRAY;
+use xilem::style::Style;
+use xilem::tokio::sync::mpsc::UnboundedSender;
+use xilem::tokio::time::sleep;
+use xilem::view::{FlexExt, flex_col, flex_row, label, prose, spinner, text_button, text_input};
+use xilem::{FontWeight, WidgetView};
+
+use crate::component::{ErrorView, action_button, header};
+use crate::theme::{ApplyClass, CONTAINER, DANGER_COLOR, SUCCESS_COLOR};
+
+/// Time between two checks of a running optimization.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+const RETENTION_STEP: f64 = 0.01;
+
+pub enum SchedulerRequest {
+    Fetch,
+    SetRetention(f64),
+    SetDayBoundary(String, u32),
+    Optimize,
+    PollStatus,
+}
+
+#[derive(Debug)]
+pub enum SchedulerMessage {
+    Fetched(SchedulerSettings, OptimizationStatus),
+    Updated(SchedulerSettings),
+    Status(OptimizationStatus),
+    Error(ServerError),
+}
+
+pub async fn handle_scheduler_request(request: SchedulerRequest) -> SchedulerMessage {
+    let result = match request {
+        SchedulerRequest::Fetch => {
+            let settings = get_scheduler_settings().await;
+            let status = get_optimization_status().await;
+            return match settings.and_then(|settings| Ok((settings, status?))) {
+                Ok((settings, status)) => SchedulerMessage::Fetched(settings, status),
+                Err(error) => SchedulerMessage::Error(error),
+            };
+        }
+        SchedulerRequest::SetRetention(desired_retention) => {
+            return match update_desired_retention(desired_retention).await {
+                Ok(settings) => SchedulerMessage::Updated(settings),
+                Err(error) => SchedulerMessage::Error(error),
+            };
+        }
+        SchedulerRequest::SetDayBoundary(timezone, day_start_hour) => {
+            return match update_day_boundary(timezone, day_start_hour).await {
+                Ok(settings) => SchedulerMessage::Updated(settings),
+                Err(error) => SchedulerMessage::Error(error),
+            };
+        }
+        SchedulerRequest::Optimize => optimize_scheduler().await,
+        SchedulerRequest::PollStatus => {
+            sleep(POLL_INTERVAL).await;
+            get_optimization_status().await
+        }
+    };
+    match result {
+        Ok(status) => SchedulerMessage::Status(status),
+        Err(error) => SchedulerMessage::Error(error),
+    }
+}
+
+/// Desired retention and day boundary of the current user, and the fitting of their scheduler weights to their
+/// review history, polled while it runs.
+#[derive(Default)]
+pub struct SchedulerSettingsForm {
+    settings: Option<SchedulerSettings>,
+    desired_retention: f64,
+    timezone: String,
+    day_start_hour: u32,
+    status: OptimizationStatus,
+    pending: bool,
+    sender: Option<UnboundedSender<SchedulerRequest>>,
+    last_error: Option<ServerError>,
+}
+
+impl SchedulerSettingsForm {
+    pub fn set_sender(&mut self, sender: UnboundedSender<SchedulerRequest>) {
+        self.sender = Some(sender);
+        self.send_request(SchedulerRequest::Fetch);
+    }
+
+    fn send_request(&mut self, request: SchedulerRequest) {
+        if let Some(sender) = &self.sender {
+            self.pending = !matches!(request, SchedulerRequest::PollStatus);
+            let _ = sender.send(request);
+        }
+    }
+
+    fn set_status(&mut self, status: OptimizationStatus) {
+        if let OptimizationStatus::Finished(optimization) = &status
+            && let Some(settings) = &mut self.settings
+        {
+            settings.optimization = Some(optimization.clone());
+        }
+        let running = matches!(status, OptimizationStatus::Running { .. });
+        self.status = status;
+        if running {
+            self.send_request(SchedulerRequest::PollStatus);
+        }
+    }
+
+    pub fn handle_message(&mut self, message: SchedulerMessage) {
+        self.pending = false;
+        match message {
+            SchedulerMessage::Fetched(settings, status) => {
+                self.set_settings(settings);
+                self.set_status(status);
+            }
+            SchedulerMessage::Updated(settings) => {
+                self.set_settings(settings);
+            }
+            SchedulerMessage::Status(status) => self.set_status(status),
+            SchedulerMessage::Error(error) => {
+                self.last_error = Some(error);
+                return;
+            }
+        }
+        self.last_error = None;
+    }
+
+    fn set_settings(&mut self, settings: SchedulerSettings) {
+        self.desired_retention = settings.desired_retention;
+        self.timezone = settings.timezone.clone();
+        self.day_start_hour = settings.day_start_hour;
+        self.settings = Some(settings);
+    }
+
+    fn change_retention(&mut self, delta: f64) {
+        let desired_retention = ((self.desired_retention + delta) * 100.).round() / 100.;
+        self.desired_retention =
+            desired_retention.clamp(MIN_DESIRED_RETENTION, MAX_DESIRED_RETENTION);
+    }
+
+    fn optimization_view(&self) -> impl WidgetView<Self> + use<> {
+        let last_optimization = self
+            .settings
+            .as_ref()
+            .and_then(|settings| settings.optimization.as_ref());
+        let summary = match last_optimization {
+            Some(optimization) => format!(
+                "Fitted to {} reviews on {}, log loss {:.4} with the default weights and {:.4} now",
+                optimization.review_count,
+                optimization
+                    .optimized_at
+                    .with_timezone(&Local)
+                    .format("%Y-%m-%d %H:%M"),
+                optimization.log_loss_before,
+                optimization.log_loss_after,
+            ),
+            None => format!(
+                "Using the default weights, they can be fitted once you have made {MIN_REVIEWS} reviews"
+            ),
+        };
+        let status = match &self.status {
+            OptimizationStatus::Running { .. } => {
+                Some(flex_row((spinner(), label("Optimizing...").color(GRAY))).gap(10.px()))
+            }
+            _ => None,
+        };
+        let failure = match &self.status {
+            OptimizationStatus::Failed(error) => {
+                Some(prose(error.clone()).text_color(DANGER_COLOR))
+            }
+            _ => None,
+        };
+        let finished = matches!(self.status, OptimizationStatus::Finished(_))
+            .then(|| label("Optimization finished").color(SUCCESS_COLOR));
+        let optimize = (!matches!(self.status, OptimizationStatus::Running { .. })).then(|| {
+            action_button("Optimize", |state: &mut Self| {
+                state.send_request(SchedulerRequest::Optimize);
+            })
+        });
+        flex_col((
+            prose(summary).text_color(GRAY),
+            status,
+            failure,
+            finished,
+            optimize,
+        ))
+        .gap(10.px())
+    }
+
+    pub fn view(&mut self) -> impl WidgetView<Self> + use<> {
+        let saved_retention = self
+            .settings
+            .as_ref()
+            .map(|settings| settings.desired_retention);
+        let retention = flex_row((
+            label("Desired retention").flex(1.),
+            text_button("-", |state: &mut Self| {
+                state.change_retention(-RETENTION_STEP)
+            }),
+            label(format!("{:.0}%", self.desired_retention * 100.)).weight(FontWeight::BOLD),
+            text_button("+", |state: &mut Self| {
+                state.change_retention(RETENTION_STEP)
+            }),
+        ))
+        .gap(10.px());
+        let save =
+            (saved_retention.is_some_and(|saved| saved != self.desired_retention)).then(|| {
+                action_button("Save", |state: &mut Self| {
+                    let desired_retention = state.desired_retention;
+                    state.send_request(SchedulerRequest::SetRetention(desired_retention));
+                })
+            });
+        let day_boundary_changed = self.settings.as_ref().is_some_and(|settings| {
+            settings.timezone != self.timezone || settings.day_start_hour != self.day_start_hour
+        });
+        let day_boundary = flex_row((
+            text_input(self.timezone.clone(), |state: &mut Self, input| {
+                state.timezone = input;
+            })
+            .placeholder("Time zone, e.g. Europe/Paris")
+            .flex(1.),
+            label("Day starts at"),
+            text_button("-", |state: &mut Self| {
+                state.day_start_hour = (state.day_start_hour + 23) % 24;
+            }),
+            label(format!("{:02}:00", self.day_start_hour)).weight(FontWeight::BOLD),
+            text_button("+", |state: &mut Self| {
+                state.day_start_hour = (state.day_start_hour + 1) % 24;
+            }),
+        ))
+        .gap(10.px());
+        let save_day_boundary = day_boundary_changed.then(|| {
+            action_button("Save", |state: &mut Self| {
+                let request =
+                    SchedulerRequest::SetDayBoundary(state.timezone.clone(), state.day_start_hour);
+                state.send_request(request);
+            })
+        });
+        let status = self.pending.then(spinner);
+        flex_col((
+            header("Scheduling"),
+            retention,
+            prose("Share of due cards you expect to recall, higher means more reviews.")
+                .text_color(GRAY),
+            save,
+            day_boundary,
+            prose("Daily limits of your decks reset when a new day starts in your time zone.")
+                .text_color(GRAY),
+            save_day_boundary,
+            self.optimization_view(),
+            status,
+            self.error_view(),
+        ))
+        .class(CONTAINER)
+        .gap(15.px())
+    }
+
+    pub fn error_view(&mut self) -> Option<impl WidgetView<Self> + use<>> {
+        self.last_error.as_ref().map(|error| {
+            map_state(error.view(), move |state: &mut Self| {
+                state.last_error.as_mut().unwrap()
+            })
+        })
+    }
+}
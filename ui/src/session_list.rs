@@ -0,0 +1,189 @@
+use chrono::Local;
+use kreqo_core::errors::ServerError;
+use kreqo_core::users::User;
+use kreqo_core::users::sessions::UserSession;
+use kreqo_server::api::{get_sessions, revoke_other_sessions, revoke_session};
+use xilem::core::map_state;
+use xilem::core::one_of::Either;
+use xilem::masonry::layout::AsUnit;
+use xilem::palette::css::GRAY;
+use xilem::style::Style;
+use xilem::tokio::sync::mpsc::UnboundedSender;
+use xilem::view::{FlexExt, button, flex_col, flex_row, label, prose, spinner, text_button};
+use xilem::{TextAlign, WidgetView};
+
+use crate::component::{ErrorView, action_button, header};
+use crate::theme::{ApplyClass, BORDERED_ROW, CONTAINER, DANGER_COLOR, SUCCESS_COLOR};
+
+pub enum SessionRequest {
+    Fetch(i64),
+    Revoke(i64, i64),
+    RevokeOthers(i64),
+}
+
+#[derive(Debug)]
+pub enum SessionMessage {
+    Fetched(Vec<UserSession>),
+    Revoked(Vec<i64>),
+    Error(ServerError),
+}
+
+pub async fn handle_session_request(request: SessionRequest) -> SessionMessage {
+    let result = match request {
+        SessionRequest::Fetch(user_id) => {
+            return match get_sessions(user_id).await {
+                Ok(sessions) => SessionMessage::Fetched(sessions),
+                Err(error) => SessionMessage::Error(error),
+            };
+        }
+        SessionRequest::Revoke(user_id, id) => revoke_session(user_id, id).await.map(|id| vec![id]),
+        SessionRequest::RevokeOthers(user_id) => revoke_other_sessions(user_id).await,
+    };
+    match result {
+        Ok(ids) => SessionMessage::Revoked(ids),
+        Err(error) => SessionMessage::Error(error),
+    }
+}
+
+/// Active sessions of a user, which can be revoked one by one or all at once except the current
+/// one.
+#[derive(Default)]
+pub struct SessionList {
+    user_id: Option<i64>,
+    sessions: Vec<UserSession>,
+    pending: bool,
+    sender: Option<UnboundedSender<SessionRequest>>,
+    last_error: Option<ServerError>,
+}
+
+impl SessionList {
+    pub fn set_sender(&mut self, sender: UnboundedSender<SessionRequest>) {
+        self.sender = Some(sender);
+        if let Some(user_id) = self.user_id {
+            self.send_request(SessionRequest::Fetch(user_id));
+        }
+    }
+
+    /// Shows the sessions of another user, fetching them if needed.
+    pub fn select(&mut self, user_id: i64) {
+        if self.user_id != Some(user_id) {
+            self.user_id = Some(user_id);
+            self.sessions.clear();
+            self.send_request(SessionRequest::Fetch(user_id));
+        }
+    }
+
+    fn send_request(&mut self, request: SessionRequest) {
+        if let Some(sender) = &self.sender {
+            self.pending = true;
+            let _ = sender.send(request);
+        }
+    }
+
+    pub fn handle_message(&mut self, message: SessionMessage) {
+        self.pending = false;
+        match message {
+            SessionMessage::Fetched(sessions) => {
+                self.sessions = sessions;
+                self.last_error = None;
+            }
+            SessionMessage::Revoked(ids) => {
+                self.sessions.retain(|session| !ids.contains(&session.id));
+                self.last_error = None;
+            }
+            SessionMessage::Error(error) => {
+                self.last_error = Some(error);
+            }
+        }
+    }
+
+    fn session_view(user_id: i64, session: &UserSession) -> impl WidgetView<Self> + use<> {
+        let id = session.id;
+        let client = prose(match &session.client_address {
+            Some(address) => format!("{} ({address})", session.client_label),
+            None => session.client_label.clone(),
+        });
+        let dates = prose(format!(
+            "Logged in {}, last seen {}",
+            session
+                .created_at
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M"),
+            session
+                .last_seen_at
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M"),
+        ))
+        .text_color(GRAY);
+        let action = if session.current {
+            Either::A(label("Current").color(SUCCESS_COLOR))
+        } else {
+            Either::B(button(
+                label("Revoke").color(DANGER_COLOR),
+                move |state: &mut Self| {
+                    state.send_request(SessionRequest::Revoke(user_id, id));
+                },
+            ))
+        };
+        flex_row((flex_col((client, dates)).flex(1.), action)).class(BORDERED_ROW)
+    }
+
+    /// Shows the selected sessions. When `users` isn't empty, any of them can be selected.
+    pub fn view(&mut self, users: &[User]) -> impl WidgetView<Self> + use<> {
+        let picker = (!users.is_empty()).then(|| {
+            let user_ids = users.iter().map(|user| user.id).collect::<Vec<_>>();
+            let username = self
+                .user_id
+                .and_then(|id| users.iter().find(|user| user.id == id))
+                .map_or("None".to_string(), |user| user.username.clone());
+            flex_row((
+                label("User"),
+                text_button(username, move |state: &mut Self| {
+                    let index = state
+                        .user_id
+                        .and_then(|id| user_ids.iter().position(|user_id| *user_id == id))
+                        .map_or(0, |index| index + 1);
+                    if let Some(user_id) = user_ids.get(index % user_ids.len()) {
+                        state.select(*user_id);
+                    }
+                }),
+            ))
+        });
+        let sessions = self.user_id.map(|user_id| {
+            let revoke_others = action_button("Revoke other sessions", move |state: &mut Self| {
+                state.send_request(SessionRequest::RevokeOthers(user_id));
+            });
+            let sessions = self
+                .sessions
+                .iter()
+                .map(|session| Self::session_view(user_id, session))
+                .collect::<Vec<_>>();
+            flex_col((sessions, revoke_others)).gap(10.px())
+        });
+        let empty =
+            (self.user_id.is_some() && self.sessions.is_empty() && !self.pending).then(|| {
+                prose("No active sessions")
+                    .text_color(GRAY)
+                    .text_alignment(TextAlign::Center)
+            });
+        let status = self.pending.then(spinner);
+        flex_col((
+            header("Sessions"),
+            picker,
+            sessions,
+            empty,
+            status,
+            self.error_view(),
+        ))
+        .class(CONTAINER)
+        .gap(15.px())
+    }
+
+    pub fn error_view(&mut self) -> Option<impl WidgetView<Self> + use<>> {
+        self.last_error.as_ref().map(|error| {
+            map_state(error.view(), move |state: &mut Self| {
+                state.last_error.as_mut().unwrap()
+            })
+        })
+    }
+}
@@ -0,0 +1,273 @@
+use chrono::NaiveDate;
+use kreqo_core::errors::ServerError;
+use kreqo_core::learn::stats::{DueForecast, MATURE_INTERVAL, StudyStats};
+use kreqo_server::api::{get_due_forecast, get_study_stats};
+use xilem::core::map_state;
+use xilem::masonry::layout::AsUnit;
+use xilem::palette::css::GRAY;
+use xilem::style::Style;
+use xilem::tokio::sync::mpsc::UnboundedSender;
+use xilem::view::{FlexExt, button, flex_col, flex_row, label, prose, spinner};
+use xilem::{Color, FontWeight, WidgetView};
+
+use crate::component::chart::{bar_chart, line_chart, stacked_bar};
+use crate::component::{ErrorView, header};
+use crate::theme::{ACCENT_COLOR, ApplyClass, CONTAINER, SUCCESS_COLOR, WARNING_COLOR};
+
+/// Numbers of days stats can be shown over, looking back for reviews and ahead for the forecast.
+const PERIODS: [u32; 4] = [7, 30, 90, 365];
+const DEFAULT_PERIOD: u32 = 30;
+
+pub enum StatsRequest {
+    Fetch(u32),
+}
+
+#[derive(Debug)]
+pub enum StatsMessage {
+    Fetched(StudyStats, Vec<DueForecast>),
+    Error(ServerError),
+}
+
+pub async fn handle_stats_request(request: StatsRequest) -> StatsMessage {
+    match request {
+        StatsRequest::Fetch(days) => {
+            let (stats, forecast) = futures::join!(get_study_stats(days), get_due_forecast(days));
+            match stats.and_then(|stats| Ok((stats, forecast?))) {
+                Ok((stats, forecast)) => StatsMessage::Fetched(stats, forecast),
+                Err(error) => StatsMessage::Error(error),
+            }
+        }
+    }
+}
+
+fn format_time(ms: i64) -> String {
+    let minutes = ms / 60_000;
+    match minutes {
+        0 => format!("{}s", ms / 1000),
+        1..60 => format!("{minutes}m"),
+        _ => format!("{}h {:02}m", minutes / 60, minutes % 60),
+    }
+}
+
+fn format_date(date: NaiveDate) -> String {
+    date.format("%b %d").to_string()
+}
+
+/// Reviews, retention and time spent by the current user over a period, along with the maturity
+/// of their cards and how many will be due over the same period ahead.
+pub struct StudyStatsView {
+    days: u32,
+    stats: Option<StudyStats>,
+    forecast: Vec<DueForecast>,
+    pending: bool,
+    sender: Option<UnboundedSender<StatsRequest>>,
+    last_error: Option<ServerError>,
+}
+
+impl Default for StudyStatsView {
+    fn default() -> Self {
+        Self {
+            days: DEFAULT_PERIOD,
+            stats: None,
+            forecast: Vec::new(),
+            pending: false,
+            sender: None,
+            last_error: None,
+        }
+    }
+}
+
+impl StudyStatsView {
+    pub fn set_sender(&mut self, sender: UnboundedSender<StatsRequest>) {
+        self.sender = Some(sender);
+        self.send_request(StatsRequest::Fetch(self.days));
+    }
+
+    fn send_request(&mut self, request: StatsRequest) {
+        if let Some(sender) = &self.sender {
+            self.pending = true;
+            let _ = sender.send(request);
+        }
+    }
+
+    fn select_period(&mut self, days: u32) {
+        if days != self.days {
+            self.days = days;
+            self.send_request(StatsRequest::Fetch(days));
+        }
+    }
+
+    pub fn handle_message(&mut self, message: StatsMessage) {
+        self.pending = false;
+        match message {
+            StatsMessage::Fetched(stats, forecast) => {
+                self.stats = Some(stats);
+                self.forecast = forecast;
+                self.last_error = None;
+            }
+            StatsMessage::Error(error) => self.last_error = Some(error),
+        }
+    }
+
+    fn summary_item(value: String, caption: &'static str) -> impl WidgetView<Self> + use<> {
+        flex_col((
+            label(value).weight(FontWeight::BOLD).text_size(22.),
+            label(caption).color(GRAY),
+        ))
+        .gap(2.px())
+    }
+
+    /// Chart with a title, the highest value it shows and the dates of its first and last entry.
+    fn chart_section(
+        title: &'static str,
+        scale: String,
+        dates: Option<(NaiveDate, NaiveDate)>,
+        chart: impl WidgetView<Self>,
+    ) -> impl WidgetView<Self> {
+        let dates = dates.map(|(first, last)| {
+            flex_row((
+                label(format_date(first)).color(GRAY).flex(1.),
+                label(format_date(last)).color(GRAY),
+            ))
+        });
+        flex_col((
+            flex_row((
+                label(title).weight(FontWeight::BOLD).flex(1.),
+                label(scale).color(GRAY),
+            )),
+            chart,
+            dates,
+        ))
+        .gap(5.px())
+    }
+
+    fn maturity_view(stats: &StudyStats) -> impl WidgetView<Self> + use<> {
+        let maturity = stats.maturity;
+        let segments = [
+            ("New", maturity.new, GRAY),
+            ("Learning", maturity.learning, WARNING_COLOR),
+            ("Young", maturity.young, ACCENT_COLOR),
+            ("Mature", maturity.mature, SUCCESS_COLOR),
+        ];
+        let legend = segments
+            .iter()
+            .map(|(name, count, color)| label(format!("{name} {count}")).color(*color))
+            .collect::<Vec<_>>();
+        flex_col((
+            flex_row((
+                label("Cards").weight(FontWeight::BOLD).flex(1.),
+                label(format!("{} total", maturity.total())).color(GRAY),
+            )),
+            stacked_bar(
+                segments
+                    .iter()
+                    .map(|(_, count, color)| (*count as f64, *color))
+                    .collect::<Vec<(f64, Color)>>(),
+            ),
+            flex_row(legend).gap(15.px()),
+            prose(format!(
+                "Cards in review are mature once their interval reaches {MATURE_INTERVAL} days."
+            ))
+            .text_color(GRAY),
+        ))
+        .gap(5.px())
+    }
+
+    fn stats_view(&self, stats: &StudyStats) -> impl WidgetView<Self> + use<> {
+        let days = stats.days.len().max(1) as i64;
+        let period = stats
+            .days
+            .first()
+            .zip(stats.days.last())
+            .map(|(first, last)| (first.date, last.date));
+        let forecast_period = self
+            .forecast
+            .first()
+            .zip(self.forecast.last())
+            .map(|(first, last)| (first.date, last.date));
+        let retention = stats.retention().map_or("-".to_string(), |retention| {
+            format!("{:.1}%", retention * 100.)
+        });
+        let summary = flex_row((
+            Self::summary_item(stats.reviews().to_string(), "reviews").flex(1.),
+            Self::summary_item((stats.reviews() / days).to_string(), "per day").flex(1.),
+            Self::summary_item(retention, "retention").flex(1.),
+            Self::summary_item(format_time(stats.time_taken_ms()), "time spent").flex(1.),
+        ));
+
+        let reviews = stats.days.iter().map(|day| day.reviews as f64).collect();
+        let most_reviews = stats.days.iter().map(|day| day.reviews).max();
+        let retentions = stats.days.iter().map(|day| day.retention()).collect();
+        let minutes = stats
+            .days
+            .iter()
+            .map(|day| day.time_taken_ms as f64 / 60_000.)
+            .collect();
+        let longest_day = stats.days.iter().map(|day| day.time_taken_ms).max();
+        let due = self.forecast.iter().map(|day| day.due as f64).collect();
+        let most_due = self.forecast.iter().map(|day| day.due).max();
+        flex_col((
+            summary,
+            Self::chart_section(
+                "Reviews per day",
+                format!("max {}", most_reviews.unwrap_or_default()),
+                period,
+                bar_chart(reviews, ACCENT_COLOR),
+            ),
+            Self::chart_section(
+                "Retention",
+                "0 - 100%".to_string(),
+                period,
+                line_chart(retentions, 0., 1., SUCCESS_COLOR),
+            ),
+            Self::chart_section(
+                "Time spent",
+                format!("max {}", format_time(longest_day.unwrap_or_default())),
+                period,
+                bar_chart(minutes, WARNING_COLOR),
+            ),
+            Self::chart_section(
+                "Due forecast",
+                format!("max {}", most_due.unwrap_or_default()),
+                forecast_period,
+                bar_chart(due, ACCENT_COLOR),
+            ),
+            Self::maturity_view(stats),
+        ))
+        .gap(25.px())
+    }
+
+    pub fn view(&mut self) -> impl WidgetView<Self> + use<> {
+        let periods = PERIODS
+            .into_iter()
+            .map(|days| {
+                let weight = if days == self.days {
+                    FontWeight::BOLD
+                } else {
+                    FontWeight::NORMAL
+                };
+                let name = label(format!("{days} days")).weight(weight);
+                button(name, move |state: &mut Self| state.select_period(days))
+            })
+            .collect::<Vec<_>>();
+        let status = self.pending.then(spinner);
+        let stats = self.stats.as_ref().map(|stats| self.stats_view(stats));
+        flex_col((
+            header("Statistics"),
+            flex_row((label("Period").color(GRAY).flex(1.), periods)).gap(10.px()),
+            status,
+            stats,
+            self.error_view(),
+        ))
+        .class(CONTAINER)
+        .gap(15.px())
+    }
+
+    pub fn error_view(&mut self) -> Option<impl WidgetView<Self> + use<>> {
+        self.last_error.as_ref().map(|error| {
+            map_state(error.view(), move |state: &mut Self| {
+                state.last_error.as_mut().unwrap()
+            })
+        })
+    }
+}
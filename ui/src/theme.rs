@@ -60,6 +60,14 @@ pub const ROW_OVERLAY: (Padding, CornerRadius, Background) =
 pub const BORDERED_ROW: (Padding, CornerRadius, Background, BorderWidth, BorderColor) =
     (ROW.0, ROW.1, SURFACE.0, SURFACE.1, SURFACE.2);
 
+pub const TOAST: (Padding, CornerRadius, Background, BorderWidth, BorderColor) = (
+    ROW.0,
+    ROW.1,
+    SURFACE.0,
+    SURFACE.1,
+    BorderColor::new(DANGER_COLOR),
+);
+
 pub const FORM_INPUT: (Padding, CornerRadius, PlaceholderColor, SelectionColor) = (
     Padding::from_vh(15., 25.),
     CornerRadius::all(7.5),
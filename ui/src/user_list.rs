@@ -1,8 +1,12 @@
 use std::cmp::Ordering;
+use std::time::Duration;
 
+use futures::{Stream, StreamExt, future, stream};
 use kreqo_core::errors::ServerError;
+use kreqo_core::events::ServerEvent;
 use kreqo_core::users::User;
-use kreqo_server::api::{delete_user, get_users, signup, update_user_username};
+use kreqo_core::users::query::{UserQuery, UserSortKey};
+use kreqo_server::api::{delete_user, get_users, signup, subscribe_events, update_user_username};
 use rapidfuzz::distance::jaro;
 use server_fn::error::ServerFnErrorErr;
 use xilem::core::one_of::Either;
@@ -17,7 +21,7 @@ use xilem::{TextAlign, WidgetView};
 use crate::auth_forms::{UserError, UserSignupForm};
 use crate::component::Form;
 use crate::component::form::Submit;
-use crate::component::list::storage::Retryable;
+use crate::component::list::storage::{ListChange, ListPage, RetryClass, Retryable};
 use crate::component::list::{
     ItemAction, ListFilter, ListItem, ListSorter, ListStorage, PendingItemOperation,
 };
@@ -87,8 +91,14 @@ impl From<User> for UpdateUserForm {
 }
 
 impl Retryable for ServerError {
-    fn should_retry(&self) -> bool {
-        matches!(self, ServerError::API(ServerFnErrorErr::Request(_)))
+    fn retry_class(&self) -> RetryClass {
+        match self {
+            ServerError::API(ServerFnErrorErr::Request(_)) => RetryClass::Transient,
+            ServerError::LoginLocked { retry_after } => {
+                RetryClass::Throttled(Duration::from_secs(*retry_after))
+            }
+            _ => RetryClass::Permanent,
+        }
     }
 }
 
@@ -105,9 +115,29 @@ impl ListStorage for UserStorage {
         &mut self.last_error
     }
 
+    const PAGE_SIZE: Option<usize> = Some(50);
+
     #[inline(always)]
     async fn fetch_all() -> Result<Vec<User>, ServerError> {
-        get_users().await
+        get_users(UserQuery::default()).await.map(|page| page.users)
+    }
+
+    #[inline(always)]
+    async fn fetch_page(
+        query: UserQuery,
+        offset: usize,
+        limit: usize,
+    ) -> Result<ListPage<User>, ServerError> {
+        let query = UserQuery {
+            offset: offset as i64,
+            limit: limit as i64,
+            ..query
+        };
+        let page = get_users(query.clone()).await?;
+        Ok(ListPage {
+            has_more: page.has_more(&query),
+            items: page.users,
+        })
     }
 
     #[inline(always)]
@@ -124,6 +154,21 @@ impl ListStorage for UserStorage {
     async fn delete(id: i64) -> Result<i64, ServerError> {
         delete_user(id).await
     }
+
+    async fn subscribe() -> Result<
+        Option<impl Stream<Item = Result<ListChange<User>, ServerError>> + Send + 'static>,
+        ServerError,
+    > {
+        let events = subscribe_events(stream::pending().into()).await?;
+        Ok(Some(events.filter_map(|event| {
+            future::ready(match event {
+                Ok(ServerEvent::User(event)) => Some(Ok(event.into())),
+                Ok(ServerEvent::Lagged) => Some(Ok(ListChange::Outdated)),
+                Ok(_) => None,
+                Err(error) => Some(Err(error)),
+            })
+        })))
+    }
 }
 
 #[derive(Default)]
@@ -265,11 +310,39 @@ impl ListItem for User {
     type UpdateForm = UpdateUserForm;
     type Filter = UserFilter;
     type Sorter = UserSorter;
+    type Query = UserQuery;
 
     fn id(&self) -> i64 {
         self.id
     }
 
+    fn matches(&self, query: &UserQuery) -> bool {
+        query.matches(self)
+    }
+
+    fn query(filter: Option<&UserFilter>, sorter: Option<&UserSorter>) -> UserQuery {
+        let mut query = UserQuery {
+            search: filter.map_or(String::new(), |filter| filter.by_username.clone()),
+            ..Default::default()
+        };
+        if let Some(sorter) = sorter.filter(|sorter| sorter.enabled) {
+            query.sort_by = match sorter.sort_by {
+                UserSortBy::Id => UserSortKey::Id,
+                UserSortBy::Username => UserSortKey::Username,
+                UserSortBy::CreatedAt => UserSortKey::CreatedAt,
+            };
+            query.descending = matches!(sorter.option, UserSortOption::Descending);
+        }
+        query
+    }
+
+    fn updated(&self, username: &String) -> Option<User> {
+        Some(User {
+            username: username.clone(),
+            ..self.clone()
+        })
+    }
+
     fn view(
         &self,
         pending_item_operation: PendingItemOperation,